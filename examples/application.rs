@@ -0,0 +1,96 @@
+// Public domain, 2017-02-21, James Bendig.
+
+#[macro_use]
+extern crate fix_rs;
+
+use fix_rs::dictionary::field_types::other::BusinessRejectReason;
+use fix_rs::dictionary::messages::{
+    BusinessMessageReject, Heartbeat, Logon, Logout, NewOrderSingle, Reject, ResendRequest,
+    SequenceReset, TestRequest,
+};
+use fix_rs::fixt::application::{Application, ApplicationRunner, BusinessReject, RejectLogon};
+use fix_rs::fixt::engine::{Connection, ConnectionTerminatedReason, Engine, EngineEvent, Listener};
+
+//List only the messages we need. See examples/server.rs for everything define_dictionary!()
+//creates. The MessageEnum it creates is what the Application receives messages as.
+define_dictionary!(
+    BusinessMessageReject,
+    Heartbeat,
+    Logon,
+    Logout,
+    NewOrderSingle,
+    Reject,
+    ResendRequest,
+    SequenceReset,
+    TestRequest,
+);
+
+//Same as examples/server.rs but only the events we care about are handled. Everything else is
+//taken care of by the ApplicationRunner.
+struct Server;
+
+impl Application for Server {
+    type Message = MessageEnum;
+
+    //Connection sent a Logon message. Returning Ok(()) responds with response.
+    fn on_logon_request(
+        &mut self,
+        _engine: &mut Engine,
+        _listener: Listener,
+        _connection: Connection,
+        logon: &Logon,
+        _response: &mut Logon,
+    ) -> Result<(), RejectLogon> {
+        if logon.username == b"some_user" && logon.password == b"some_password" {
+            Ok(())
+        } else {
+            Err(RejectLogon {
+                text: Some(b"Invalid username and/or password".to_vec()),
+            })
+        }
+    }
+
+    fn on_logon(&mut self, _engine: &mut Engine, connection: Connection) {
+        println!("({})Logged on", connection);
+    }
+
+    fn on_logout(
+        &mut self,
+        _engine: &mut Engine,
+        connection: Connection,
+        reason: &ConnectionTerminatedReason,
+    ) {
+        println!("({})Logged out: {:?}", connection, reason);
+    }
+
+    //Received a business message. Returning an error responds with a BusinessMessageReject.
+    fn from_app(
+        &mut self,
+        _engine: &mut Engine,
+        _connection: Connection,
+        message: MessageEnum,
+    ) -> Result<(), BusinessReject> {
+        match message {
+            MessageEnum::NewOrderSingle(_) => Err(BusinessReject::new(
+                BusinessRejectReason::ApplicationNotAvailable,
+                b"Market is closed".to_vec(),
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    //Everything without a dedicated callback ends up here.
+    fn on_event(&mut self, _engine: &mut Engine, event: EngineEvent) {
+        println!("{:?}", event);
+    }
+}
+
+fn main() {
+    let max_message_size = 4096;
+    let mut engine = Engine::new(build_dictionary(), max_message_size).unwrap();
+    engine.add_listener(b"Server", "127.0.0.1:7001").unwrap();
+
+    //Dispatch events to Server until the Engine stops.
+    let mut runner = ApplicationRunner::new(engine, Server);
+    runner.run();
+}
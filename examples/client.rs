@@ -80,7 +80,7 @@ fn main() {
             }
             //Connection to server was closed either using the Engine::logout() function, logout
             //request by server, or an unrecoverable error.
-            EngineEvent::ConnectionTerminated(connection_id, reason) => {
+            EngineEvent::ConnectionTerminated(connection_id, reason, _) => {
                 println!("({})Connection terminated: {:?}", connection_id, reason);
                 break;
             }
@@ -90,6 +90,14 @@ fn main() {
 
                 //Start sending messages here.
             }
+            //Connection moved to a different SessionState. Only sent after calling
+            //Engine::set_session_state_events(true).
+            EngineEvent::SessionStateChanged(connection_id, old_state, new_state) => {
+                println!(
+                    "({})Session state {} -> {}",
+                    connection_id, old_state, new_state
+                );
+            }
             //Connection received a new message.
             EngineEvent::MessageReceived(connection_id, message) => {
                 //Handle the received message. Must be one of the messages listed in the
@@ -128,6 +136,26 @@ fn main() {
             EngineEvent::MessageRejected(connection_id, message) => {
                 println!("({})Message was rejected", connection_id);
             }
+            //Message that was sent is larger than the remote's MaxMessageSize, has a MsgType the
+            //remote doesn't support, was over the connection's Throttle, or it was vetoed by the
+            //outbound message hook. It never went out on the wire.
+            EngineEvent::MessageRefused(connection_id, message_id, message, reason) => {
+                println!("({})Message was refused: {:?}", connection_id, reason);
+            }
+            //Message returned by send_message() was completely written to the socket. It hasn't
+            //necessarily been received by the remote yet. Not emitted after
+            //Engine::set_send_receipts(false).
+            EngineEvent::MessageSent(connection_id, message_id, msg_seq_num, _) => {
+                println!(
+                    "({})Message {} was sent with MsgSeqNum {}",
+                    connection_id, message_id, msg_seq_num
+                );
+            }
+            //Message returned by send_message() was dropped before it could be sent because the
+            //connection logged out or was terminated.
+            EngineEvent::MessageNotSent(connection_id, message_id, _) => {
+                println!("({})Message {} was not sent", connection_id, message_id);
+            }
             //Connected received a ResendRequest message for the messages in
             //[range.start,range.end).
             EngineEvent::ResendRequested(connection_id, range) => {
@@ -136,6 +164,15 @@ fn main() {
                     connection_id, range.start, range.end
                 );
             }
+            //Messages in [range.start,range.end) are being requested from the remote again
+            //because it answered the last ResendRequest with garbled messages. The connection
+            //logs out once the SessionOptions' retries run out.
+            EngineEvent::ResendRequestRetried(connection_id, range, attempt) => {
+                println!(
+                    "({})Sent ResendRequest attempt {} for messages where {} <= MsgSeqNum < {}",
+                    connection_id, attempt, range.start, range.end
+                );
+            }
             //Connection received a SequenceReset-Reset message where NewSeqNo is set to the same
             //number as the expected MsgSeqNum.
             EngineEvent::SequenceResetResetHasNoEffect(connection_id) => {
@@ -152,13 +189,23 @@ fn main() {
                     connection_id
                 );
             }
+            //Application messages are being held back because they would go over the connection's
+            //Throttle. They are sent automatically once allowed.
+            EngineEvent::Throttled(connection_id, duration) => {
+                println!(
+                    "({})Messages are being throttled for {:?}",
+                    connection_id, duration
+                );
+            }
             //Internal error setting up Engine (before any connections were added).
             EngineEvent::FatalError(_, _) => {
                 println!("Could not setup Engine.");
                 break;
             }
-            //The following events are not used for client connections.
-            EngineEvent::ConnectionDropped(_, _)
+            //The following events are not used for unscheduled client connections.
+            EngineEvent::SessionEnded(_, _, _)
+            | EngineEvent::Reconnecting(_, _, _)
+            | EngineEvent::ConnectionDropped(_, _)
             | EngineEvent::ConnectionAccepted(_, _, _)
             | EngineEvent::ConnectionLoggingOn(_, _, _)
             | EngineEvent::ListenerFailed(_, _)
@@ -64,6 +64,14 @@ fn main() {
             EngineEvent::ConnectionDropped(listener_id, addr) => {
                 println!("({})New connection was dropped: {}", listener_id, addr);
             }
+            //Connection moved to a different SessionState. Only sent after calling
+            //Engine::set_session_state_events(true).
+            EngineEvent::SessionStateChanged(connection_id, old_state, new_state) => {
+                println!(
+                    "({})Session state {} -> {}",
+                    connection_id, old_state, new_state
+                );
+            }
             //Connection sent a Logon message and is awaiting approval or rejection.
             EngineEvent::ConnectionLoggingOn(listener_id, connection_id, logon) => {
                 if logon.username == b"some_user" && logon.password == b"some_password" {
@@ -93,7 +101,7 @@ fn main() {
             }
             //Connection to server was closed either using the Engine::logout() function, logout
             //request by client, or an unrecoverable error.
-            EngineEvent::ConnectionTerminated(connection_id, reason) => {
+            EngineEvent::ConnectionTerminated(connection_id, reason, _) => {
                 println!("({})Connection terminated: {:?}", connection_id, reason);
             }
             //Connection received a new message.
@@ -134,6 +142,26 @@ fn main() {
             EngineEvent::MessageRejected(connection_id, message) => {
                 println!("({})Message was rejected", connection_id);
             }
+            //Message that was sent is larger than the remote's MaxMessageSize, has a MsgType the
+            //remote doesn't support, was over the connection's Throttle, or it was vetoed by the
+            //outbound message hook. It never went out on the wire.
+            EngineEvent::MessageRefused(connection_id, message_id, message, reason) => {
+                println!("({})Message was refused: {:?}", connection_id, reason);
+            }
+            //Message returned by send_message() was completely written to the socket. It hasn't
+            //necessarily been received by the remote yet. Not emitted after
+            //Engine::set_send_receipts(false).
+            EngineEvent::MessageSent(connection_id, message_id, msg_seq_num, _) => {
+                println!(
+                    "({})Message {} was sent with MsgSeqNum {}",
+                    connection_id, message_id, msg_seq_num
+                );
+            }
+            //Message returned by send_message() was dropped before it could be sent because the
+            //connection logged out or was terminated.
+            EngineEvent::MessageNotSent(connection_id, message_id, _) => {
+                println!("({})Message {} was not sent", connection_id, message_id);
+            }
             //Connected received a ResendRequest message for the messages in
             //[range.start,range.end).
             EngineEvent::ResendRequested(connection_id, range) => {
@@ -144,6 +172,15 @@ fn main() {
                 response.push(ResendResponse::Gap(range));
                 server.send_resend_response(connection_id, response);
             }
+            //Messages in [range.start,range.end) are being requested from the remote again
+            //because it answered the last ResendRequest with garbled messages. The connection
+            //logs out once the SessionOptions' retries run out.
+            EngineEvent::ResendRequestRetried(connection_id, range, attempt) => {
+                println!(
+                    "({})Sent ResendRequest attempt {} for messages where {} <= MsgSeqNum < {}",
+                    connection_id, attempt, range.start, range.end
+                );
+            }
             //Connection received a SequenceReset-Reset message where NewSeqNo is set to the same
             //number as the expected MsgSeqNum.
             EngineEvent::SequenceResetResetHasNoEffect(connection_id) => {
@@ -160,6 +197,14 @@ fn main() {
                     connection_id
                 );
             }
+            //Application messages are being held back because they would go over the connection's
+            //Throttle. They are sent automatically once allowed.
+            EngineEvent::Throttled(connection_id, duration) => {
+                println!(
+                    "({})Messages are being throttled for {:?}",
+                    connection_id, duration
+                );
+            }
             //Internal error setting up Engine (before any listeners were added).
             EngineEvent::FatalError(_, _) => {
                 println!("Could not setup Engine.");
@@ -169,7 +214,9 @@ fn main() {
             //Connection was able to open TCP stream to server.
             EngineEvent::ConnectionSucceeded(_)
             | EngineEvent::ConnectionFailed(_, _)
-            | EngineEvent::SessionEstablished(_) => {}
+            | EngineEvent::SessionEstablished(_)
+            | EngineEvent::SessionEnded(_, _, _)
+            | EngineEvent::Reconnecting(_, _, _) => {}
         }
     }
 }
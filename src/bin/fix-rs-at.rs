@@ -0,0 +1,272 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate clap;
+#[macro_use]
+extern crate fix_rs;
+
+use clap::{App, Arg};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use fix_rs::dictionary::field_types::other::EncryptMethod;
+use fix_rs::dictionary::messages::{
+    Heartbeat, Logon, Logout, Reject, ResendRequest, SequenceReset, TestRequest,
+};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{Engine, EngineEvent};
+use fix_rs::fixt::session_config::ConnectionConfig;
+use fix_rs::fixt::transport::LoopbackAddr;
+use fix_rs::testing::definition::{Definition, DefinitionRunner, RunError};
+
+const MAX_MESSAGE_SIZE: u64 = 4096;
+const ENGINE_POLL_MILLIS: u64 = 10;
+const HEART_BT_INT: i64 = 30;
+//fix-rs always sends MaxMessageSize in its Logon but the definitions never expect it, so it's
+//ignored along with any tags given using --ignore-tag.
+const MAX_MESSAGE_SIZE_TAG: u32 = 383;
+
+define_dictionary!(
+    Heartbeat,
+    Logon,
+    Logout,
+    Reject,
+    ResendRequest,
+    SequenceReset,
+    TestRequest,
+);
+
+struct Options {
+    initiator: bool,
+    fix_version: FIXVersion,
+    sender_comp_id: Vec<u8>,
+    target_comp_id: Vec<u8>,
+    timeout: Duration,
+    ignored_tags: Vec<u32>,
+}
+
+//Every `.def` file in path, sorted by name, or just path if it's a file.
+fn definition_paths(path: &Path) -> io::Result<Vec<PathBuf>> {
+    if !path.is_dir() {
+        return Ok(vec![path.to_path_buf()]);
+    }
+
+    let mut paths = Vec::new();
+    for entry in fs::read_dir(path)? {
+        let path = entry?.path();
+        if path
+            .extension()
+            .map_or(false, |extension| extension == "def")
+        {
+            paths.push(path);
+        }
+    }
+    paths.sort();
+
+    Ok(paths)
+}
+
+//Run definition against a new Engine so every definition starts with a fresh session. The Engine
+//approves every Logon it receives and, when it's the initiator, logs on as soon as it connects.
+fn run_definition(definition: Definition, options: &Options) -> Result<(), String> {
+    let mut engine =
+        Engine::new(build_dictionary(), MAX_MESSAGE_SIZE).map_err(|e| e.to_string())?;
+    let address = LoopbackAddr::new();
+    let mut runner = if options.initiator {
+        let runner = DefinitionRunner::initiator(address.clone()).map_err(|e| e.to_string())?;
+        engine.add_connection_with_config(ConnectionConfig::new(
+            options.fix_version,
+            options.fix_version.max_message_version(),
+            &options.sender_comp_id[..],
+            &options.target_comp_id[..],
+            address,
+        ));
+        runner
+    } else {
+        engine
+            .add_listener_with_transport(&options.sender_comp_id[..], address.clone())
+            .map_err(|e| e.to_string())?;
+        DefinitionRunner::acceptor(address)
+    }
+    .with_timeout(options.timeout);
+    for tag in &options.ignored_tags {
+        runner = runner.with_ignored_tag(*tag);
+    }
+
+    let (result_sender, result_receiver) = mpsc::channel::<Result<(), RunError>>();
+    let runner_thread = thread::spawn(move || {
+        let _ = result_sender.send(runner.run(&definition));
+    });
+
+    let result = loop {
+        if let Ok(result) = result_receiver.try_recv() {
+            break result.map_err(|e| e.to_string());
+        }
+
+        match engine.poll(Duration::from_millis(ENGINE_POLL_MILLIS)) {
+            Some(EngineEvent::ConnectionSucceeded(connection)) => {
+                let mut message = Logon::new();
+                message.encrypt_method = EncryptMethod::None;
+                message.heart_bt_int = HEART_BT_INT;
+                message.default_appl_ver_id = options.fix_version.max_message_version();
+                engine.send_message(connection, message);
+            }
+            Some(EngineEvent::ConnectionLoggingOn(_, connection, logon_message)) => {
+                let mut response_message = Logon::new();
+                response_message.encrypt_method = logon_message.encrypt_method.clone();
+                response_message.heart_bt_int = logon_message.heart_bt_int;
+                response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+                engine.approve_new_connection(connection, Box::new(response_message), None);
+            }
+            Some(EngineEvent::FatalError(description, error)) => {
+                break Err(format!("{}: {}", description, error));
+            }
+            _ => {}
+        }
+    };
+    drop(engine);
+    let _ = runner_thread.join();
+
+    result
+}
+
+fn main() {
+    let matches = App::new("fix-rs-at")
+        .version(env!("CARGO_PKG_VERSION"))
+        .author(env!("CARGO_PKG_AUTHORS"))
+        .about("Runs QuickFIX style acceptance test definitions against fix-rs")
+        .arg(
+            Arg::with_name("initiator")
+                .long("initiator")
+                .help("Test fix-rs as the initiator instead of the acceptor"),
+        )
+        .arg(
+            Arg::with_name("begin-string")
+                .long("begin-string")
+                .takes_value(true)
+                .default_value("FIX.4.2")
+                .help("BeginString used by the initiator"),
+        )
+        .arg(
+            Arg::with_name("sender-comp-id")
+                .long("sender-comp-id")
+                .takes_value(true)
+                .default_value("ISLD"),
+        )
+        .arg(
+            Arg::with_name("target-comp-id")
+                .long("target-comp-id")
+                .takes_value(true)
+                .default_value("TW"),
+        )
+        .arg(
+            Arg::with_name("timeout")
+                .long("timeout")
+                .takes_value(true)
+                .default_value("10")
+                .help("Seconds to wait for each expected message"),
+        )
+        .arg(
+            Arg::with_name("ignore-tag")
+                .long("ignore-tag")
+                .takes_value(true)
+                .multiple_occurrences(true)
+                .help("Extra tag to skip when comparing expected messages"),
+        )
+        .arg(
+            Arg::with_name("definitions")
+                .required(true)
+                .multiple_values(true)
+                .help("Definition files or directories containing them"),
+        )
+        .get_matches();
+
+    let begin_string = matches.value_of("begin-string").unwrap();
+    let fix_version = match FIXVersion::all()
+        .into_iter()
+        .find(|fix_version| fix_version.begin_string() == begin_string.as_bytes())
+    {
+        Some(fix_version) => fix_version,
+        None => {
+            eprintln!("Unsupported BeginString: {}", begin_string);
+            process::exit(2);
+        }
+    };
+    let timeout = match matches.value_of("timeout").unwrap().parse::<u64>() {
+        Ok(timeout) => Duration::from_secs(timeout),
+        Err(_) => {
+            eprintln!("Timeout must be a number of seconds");
+            process::exit(2);
+        }
+    };
+    let mut ignored_tags = vec![MAX_MESSAGE_SIZE_TAG];
+    for tag in matches.values_of("ignore-tag").into_iter().flatten() {
+        match tag.parse::<u32>() {
+            Ok(tag) => ignored_tags.push(tag),
+            Err(_) => {
+                eprintln!("Not a valid tag: {}", tag);
+                process::exit(2);
+            }
+        }
+    }
+    let options = Options {
+        initiator: matches.is_present("initiator"),
+        fix_version,
+        sender_comp_id: matches
+            .value_of("sender-comp-id")
+            .unwrap()
+            .as_bytes()
+            .to_vec(),
+        target_comp_id: matches
+            .value_of("target-comp-id")
+            .unwrap()
+            .as_bytes()
+            .to_vec(),
+        timeout,
+        ignored_tags,
+    };
+
+    let mut passed = 0;
+    let mut total = 0;
+    for path in matches.values_of("definitions").unwrap() {
+        let paths = match definition_paths(Path::new(path)) {
+            Ok(paths) => paths,
+            Err(e) => {
+                eprintln!("Could not read {}: {}", path, e);
+                process::exit(2);
+            }
+        };
+
+        for path in paths {
+            total += 1;
+            let result = Definition::load(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|definition| run_definition(definition, &options));
+            match result {
+                Ok(()) => {
+                    passed += 1;
+                    println!("PASS {}", path.display());
+                }
+                Err(e) => println!("FAIL {}: {}", path.display(), e),
+            }
+        }
+    }
+
+    println!("{} of {} definitions passed", passed, total);
+    if passed != total {
+        process::exit(1);
+    }
+}
@@ -32,7 +32,7 @@ use std::thread;
 use std::time::{Duration, Instant};
 
 use fix_rs::byte_buffer::ByteBuffer;
-use fix_rs::dictionary::field_types::generic::StringFieldType;
+use fix_rs::dictionary::field_types::generic::{StringFieldType, UtcTimestampFieldType};
 use fix_rs::dictionary::field_types::other::EncryptMethod;
 use fix_rs::dictionary::fields::{
     ApplVerID, MsgSeqNum, OrigSendingTime, SenderCompID, SendingTime, TargetCompID, TestReqID,
@@ -145,6 +145,7 @@ impl FIXTMessage for Heartbeat {
         _msg_seq_num: Option<<<MsgSeqNum as Field>::Type as FieldType>::Type>,
         _sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
         _target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+        _sending_time: <<SendingTime as Field>::Type as FieldType>::Type,
     ) {
         unimplemented!();
     }
@@ -211,6 +212,7 @@ impl FIXTMessage for TestRequest {
         msg_seq_num: Option<<<MsgSeqNum as Field>::Type as FieldType>::Type>,
         sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
         target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+        _sending_time: <<SendingTime as Field>::Type as FieldType>::Type,
     ) {
         if let Some(msg_seq_num) = msg_seq_num {
             self.msg_seq_num = msg_seq_num;
@@ -281,7 +283,12 @@ impl Connection {
 
         //Logon.
         let mut logon_message = Logon::new();
-        logon_message.setup_fixt_session_header(Some(1), b"fix-rs-lt".to_vec(), b"Server".to_vec());
+        logon_message.setup_fixt_session_header(
+            Some(1),
+            b"fix-rs-lt".to_vec(),
+            b"Server".to_vec(),
+            UtcTimestampFieldType::new_now(),
+        );
         logon_message.encrypt_method = EncryptMethod::None;
         logon_message.heart_bt_int = 60;
         logon_message.default_appl_ver_id = MessageVersion::FIX50SP2;
@@ -491,6 +498,7 @@ impl<'a> TestRequestIter<'a> {
             Some(start_msg_seq_num - 1),
             b"fix-rs-lt".to_vec(),
             b"Server".to_vec(),
+            UtcTimestampFieldType::new_now(),
         );
 
         TestRequestIter {
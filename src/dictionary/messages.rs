@@ -119,6 +119,7 @@ impl FIXTMessage for NullMessage {
         _msg_seq_num: Option<<<MsgSeqNum as Field>::Type as FieldType>::Type>,
         _sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
         _target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+        _sending_time: <<SendingTime as Field>::Type as FieldType>::Type,
     ) {
         unimplemented!();
     }
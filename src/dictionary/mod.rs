@@ -49,6 +49,12 @@ macro_rules! define_dictionary {
 
             panic!("Unsupported message");
         }
+
+        impl $crate::fixt::application::FromFIXTMessage for MessageEnum {
+            fn from_fixt_message(message: Box<$crate::fixt::message::FIXTMessage>) -> MessageEnum {
+                message_to_enum(message)
+            }
+        }
     };
 }
 
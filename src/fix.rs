@@ -279,6 +279,11 @@ pub struct Parser {
     missing_conditional_tag: FieldTag,
     current_message: Box<dyn FIXTMessage + Send>,
     pub messages: Vec<Box<dyn FIXTMessage + Send>>,
+    capture_raw_messages: bool,
+    raw_message_bytes: Vec<u8>, //Part of the current message passed to an earlier parse() call.
+    raw_message_begin: usize,   //Where the current message begins in the bytes passed to parse().
+    pub raw_messages: Vec<Vec<u8>>, //Exact bytes of each message in messages when capturing.
+    pub raw_garbled_message: Vec<u8>, //Exact bytes of the message that caused the last error.
 }
 
 impl Parser {
@@ -345,6 +350,11 @@ impl Parser {
             missing_conditional_tag: FieldTag::empty(),
             current_message: Box::new(NullMessage {}),
             messages: Vec::new(),
+            capture_raw_messages: false,
+            raw_message_bytes: Vec::new(),
+            raw_message_begin: 0,
+            raw_messages: Vec::new(),
+            raw_garbled_message: Vec::new(),
         }
     }
 
@@ -368,6 +378,20 @@ impl Parser {
         self.missing_tag = FieldTag::empty();
         self.missing_conditional_tag = FieldTag::empty();
         self.current_message = Box::new(NullMessage {});
+        self.raw_message_bytes.clear();
+    }
+
+    //Keep a copy of the exact bytes of every parsed message in raw_messages and of every message
+    //that fails to parse in raw_garbled_message. Off by default because it costs a copy of
+    //everything parsed.
+    pub fn set_capture_raw_messages(&mut self, capture_raw_messages: bool) {
+        self.capture_raw_messages = capture_raw_messages;
+    }
+
+    fn raw_message(&self, message_bytes: &[u8], end: usize) -> Vec<u8> {
+        let mut raw_message = self.raw_message_bytes.clone();
+        raw_message.extend_from_slice(&message_bytes[self.raw_message_begin..end]);
+        raw_message
     }
 
     pub fn set_default_message_version(&mut self, message_version: MessageVersion) {
@@ -641,6 +665,17 @@ impl Parser {
         }
 
         if self.found_message == FoundMessage::SecondByte {
+            //The '8' is either right before the '=' or was the last byte passed to the previous
+            //parse() call.
+            if self.capture_raw_messages {
+                if *index > 0 {
+                    self.raw_message_begin = *index - 1;
+                } else {
+                    self.raw_message_bytes.push(BEGINSTR_TAG_BYTES[0]);
+                    self.raw_message_begin = 0;
+                }
+            }
+
             //Act like the BeginStr tag was parsed so we don't duplicate work.
             self.current_tag = BEGINSTR_TAG;
             self.checksum = BEGINSTR_TAG_BYTES[0] + TAG_END;
@@ -1156,6 +1191,10 @@ impl Parser {
 
                 //Save message.
                 let is_logon_message = self.current_message.msg_type() == Logon::msg_type();
+                if self.capture_raw_messages {
+                    let raw_message = self.raw_message(message_bytes, *index + 1);
+                    self.raw_messages.push(raw_message);
+                }
                 self.messages.push(mem::replace(
                     &mut self.current_message,
                     Box::new(NullMessage {}),
@@ -1206,8 +1245,21 @@ impl Parser {
         //parse error is triggered -- whatever happens first.
         let mut index = 0;
         match self.parse_private(&mut index, message_bytes) {
-            Ok(_) => (index, Ok(())),
+            Ok(_) => {
+                //Hold onto the beginning of a message that hasn't been completely received yet.
+                if self.capture_raw_messages && self.found_message == FoundMessage::SecondByte {
+                    let raw_message = self.raw_message(message_bytes, index);
+                    self.raw_message_bytes = raw_message;
+                    self.raw_message_begin = 0;
+                }
+
+                (index, Ok(()))
+            }
             Err(err) => {
+                if self.capture_raw_messages {
+                    self.raw_garbled_message = self.raw_message(message_bytes, index);
+                }
+
                 //Reset automatically so the next parse won't fail immediatelly.
                 self.reset_parser();
 
@@ -15,7 +15,7 @@ use crate::constant::{
 };
 use crate::message_version::MessageVersion;
 
-#[derive(Clone, Copy, Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 #[allow(non_camel_case_types)]
 pub enum FIXVersion {
     FIXT_1_1,
@@ -0,0 +1,86 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::fix_version::FIXVersion;
+use crate::fixt::seq_num_store::SeqNumStore;
+use crate::fixt::session_options::SessionOptions;
+use crate::fixt::session_schedule::SessionSchedule;
+
+//A counterparty that is expected to logon to a listener. Once a listener has at least one
+//AcceptorSession, Logons that don't match any of them are answered with a Logout before the
+//application is asked to approve them. A Logon matches when its BeginString and SenderCompID are
+//the same as fix_version and target_comp_id. SubIDs are only compared when they're set.
+pub struct AcceptorSession {
+    pub(crate) fix_version: FIXVersion,
+    pub(crate) target_comp_id: Vec<u8>,
+    pub(crate) sender_sub_id: Option<Vec<u8>>,
+    pub(crate) target_sub_id: Option<Vec<u8>>,
+    pub(crate) heart_bt_int: Option<i64>,
+    pub(crate) schedule: Option<SessionSchedule>,
+    pub(crate) seq_num_store: Option<Box<dyn SeqNumStore + Send>>,
+    pub(crate) session_options: Option<SessionOptions>,
+}
+
+impl AcceptorSession {
+    pub fn new(fix_version: FIXVersion, target_comp_id: &[u8]) -> AcceptorSession {
+        AcceptorSession {
+            fix_version,
+            target_comp_id: target_comp_id.to_vec(),
+            sender_sub_id: None,
+            target_sub_id: None,
+            heart_bt_int: None,
+            schedule: None,
+            seq_num_store: None,
+            session_options: None,
+        }
+    }
+
+    //Our SenderSubID. Must match the TargetSubID of the remote's Logon.
+    pub fn with_sender_sub_id(mut self, sender_sub_id: &[u8]) -> AcceptorSession {
+        self.sender_sub_id = Some(sender_sub_id.to_vec());
+        self
+    }
+
+    //The remote's SenderSubID. Must match the SenderSubID of the remote's Logon.
+    pub fn with_target_sub_id(mut self, target_sub_id: &[u8]) -> AcceptorSession {
+        self.target_sub_id = Some(target_sub_id.to_vec());
+        self
+    }
+
+    //Reject Logons that ask for any other HeartBtInt.
+    pub fn with_heart_bt_int(mut self, heart_bt_int: i64) -> AcceptorSession {
+        self.heart_bt_int = Some(heart_bt_int);
+        self
+    }
+
+    //Only accept Logons while schedule is active. Works the same as a listener's schedule but
+    //only applies to this session. Both must be active when the listener has one too.
+    pub fn with_schedule(mut self, schedule: SessionSchedule) -> AcceptorSession {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    //Keep this session's MsgSeqNums in seq_num_store instead of the Engine's SeqNumStore.
+    pub fn with_seq_num_store(
+        mut self,
+        seq_num_store: Box<dyn SeqNumStore + Send>,
+    ) -> AcceptorSession {
+        self.seq_num_store = Some(seq_num_store);
+        self
+    }
+
+    //Use session_options instead of the listener's once this session's Logon is received. The
+    //listener's no logon timeout still applies because the session isn't known until then.
+    pub fn with_session_options(mut self, session_options: SessionOptions) -> AcceptorSession {
+        self.session_options = Some(session_options);
+        self
+    }
+}
@@ -0,0 +1,273 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+use crate::dictionary::administrative_msg_types;
+use crate::dictionary::field_types::other::BusinessRejectReason;
+use crate::dictionary::messages::{BusinessMessageReject, Logon};
+use crate::fixt::engine::{Connection, ConnectionTerminatedReason, Engine, EngineEvent, Listener};
+use crate::fixt::message::FIXTMessage;
+
+//Conversion from the messages returned by the Engine into something easier to match against.
+//Implemented for the MessageEnum created by define_dictionary!().
+pub trait FromFIXTMessage {
+    fn from_fixt_message(message: Box<dyn FIXTMessage>) -> Self;
+}
+
+//Returned by Application::to_admin() or Application::to_app() to stop an outbound message from
+//being sent. The message is returned using EngineEvent::MessageRefused instead.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct DoNotSend;
+
+//Returned by Application::on_logon_request() to reject a Logon with an optional reason that is
+//sent in the Logout's Text field.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct RejectLogon {
+    pub text: Option<Vec<u8>>,
+}
+
+//Returned by Application::from_app() to reject a received message using a BusinessMessageReject.
+#[derive(Clone, Debug, PartialEq)]
+pub struct BusinessReject {
+    pub reason: BusinessRejectReason,
+    pub text: Option<Vec<u8>>,
+}
+
+impl BusinessReject {
+    pub fn new<T: Into<Option<Vec<u8>>>>(reason: BusinessRejectReason, text: T) -> BusinessReject {
+        BusinessReject {
+            reason,
+            text: text.into(),
+        }
+    }
+}
+
+//Callbacks for everything that happens to the connections of an Engine driven by an
+//ApplicationRunner. Every callback does nothing (or approves) by default so only the interesting
+//ones need to be implemented.
+//
+//to_admin() and to_app() are called from the Engine's internal thread right before a message is
+//sent while everything else is called from whichever thread calls ApplicationRunner::poll().
+//
+//Callback names follow QuickFIX instead of Rust's from_*() convention.
+#[allow(clippy::wrong_self_convention)]
+pub trait Application: Send + 'static {
+    //Usually the MessageEnum created by define_dictionary!().
+    type Message: FromFIXTMessage;
+
+    //Connection was opened, either by connecting to a server or by being accepted by a listener.
+    //Initiators should send their Logon from here.
+    fn on_create(&mut self, _engine: &mut Engine, _connection: Connection) {}
+
+    //Remote sent a Logon to one of our listeners. response is sent back once approved and
+    //starts out with the same EncryptMethod, HeartBtInt, and DefaultApplVerID as logon.
+    fn on_logon_request(
+        &mut self,
+        _engine: &mut Engine,
+        _listener: Listener,
+        _connection: Connection,
+        _logon: &Logon,
+        _response: &mut Logon,
+    ) -> Result<(), RejectLogon> {
+        Ok(())
+    }
+
+    //Session was established and application messages can be sent.
+    fn on_logon(&mut self, _engine: &mut Engine, _connection: Connection) {}
+
+    //Session that on_logon() was called for has ended.
+    fn on_logout(
+        &mut self,
+        _engine: &mut Engine,
+        _connection: Connection,
+        _reason: &ConnectionTerminatedReason,
+    ) {
+    }
+
+    //Administrative message, including those generated by the Engine, is about to be sent. The
+    //session header has already been filled in.
+    fn to_admin(
+        &mut self,
+        _connection: Connection,
+        _message: &mut dyn FIXTMessage,
+    ) -> Result<(), DoNotSend> {
+        Ok(())
+    }
+
+    fn from_admin(
+        &mut self,
+        _engine: &mut Engine,
+        _connection: Connection,
+        _message: Self::Message,
+    ) {
+    }
+
+    //Application message is about to be sent. The session header has already been filled in.
+    fn to_app(
+        &mut self,
+        _connection: Connection,
+        _message: &mut dyn FIXTMessage,
+    ) -> Result<(), DoNotSend> {
+        Ok(())
+    }
+
+    //Application message was received. Returning an error responds with a BusinessMessageReject.
+    fn from_app(
+        &mut self,
+        _engine: &mut Engine,
+        _connection: Connection,
+        _message: Self::Message,
+    ) -> Result<(), BusinessReject> {
+        Ok(())
+    }
+
+    //Any event that isn't covered by one of the callbacks above.
+    fn on_event(&mut self, _engine: &mut Engine, _event: EngineEvent) {}
+}
+
+//Drives an Engine and dispatches each EngineEvent to an Application.
+pub struct ApplicationRunner<A: Application> {
+    engine: Engine,
+    application: Arc<Mutex<A>>,
+    administrative_msg_types: HashSet<&'static [u8]>,
+    logged_on_connections: HashSet<Connection>,
+}
+
+impl<A: Application> ApplicationRunner<A> {
+    //Take over an Engine. Replaces any outbound message hook already set on engine.
+    pub fn new(mut engine: Engine, application: A) -> ApplicationRunner<A> {
+        let application = Arc::new(Mutex::new(application));
+        let administrative_msg_types: HashSet<&'static [u8]> =
+            administrative_msg_types().into_iter().collect();
+
+        let hook_application = application.clone();
+        let hook_administrative_msg_types = administrative_msg_types.clone();
+        engine.set_outbound_message_hook(Box::new(move |connection, message| {
+            let mut application = hook_application.lock().unwrap();
+            let result = if hook_administrative_msg_types.contains(message.msg_type()) {
+                application.to_admin(connection, message)
+            } else {
+                application.to_app(connection, message)
+            };
+            result.is_ok()
+        }));
+
+        ApplicationRunner {
+            engine,
+            application,
+            administrative_msg_types,
+            logged_on_connections: HashSet::new(),
+        }
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    //Lock the Application for direct access. Outbound messages are held up while the returned
+    //guard exists.
+    pub fn application(&self) -> MutexGuard<'_, A> {
+        self.application.lock().unwrap()
+    }
+
+    //Wait up to duration for a single event and dispatch it to the Application. Returns false if
+    //no event was received.
+    pub fn poll<D: Into<Option<Duration>>>(&mut self, duration: D) -> bool {
+        let event = match self.engine.poll(duration) {
+            Some(event) => event,
+            None => return false,
+        };
+
+        let mut application = self.application.lock().unwrap();
+        let engine = &mut self.engine;
+        match event {
+            EngineEvent::ConnectionSucceeded(connection)
+            | EngineEvent::ConnectionAccepted(_, connection, _) => {
+                application.on_create(engine, connection);
+            }
+            EngineEvent::ConnectionLoggingOn(listener, connection, logon) => {
+                let mut response = Logon::new();
+                response.encrypt_method = logon.encrypt_method.clone();
+                response.heart_bt_int = logon.heart_bt_int;
+                response.default_appl_ver_id = logon.default_appl_ver_id;
+
+                match application.on_logon_request(
+                    engine,
+                    listener,
+                    connection,
+                    &logon,
+                    &mut response,
+                ) {
+                    Ok(()) => {
+                        engine.approve_new_connection(connection, Box::new(response), None);
+
+                        //Acceptors don't receive EngineEvent::SessionEstablished so the session
+                        //is considered established as soon as it's approved.
+                        self.logged_on_connections.insert(connection);
+                        application.on_logon(engine, connection);
+                    }
+                    Err(reject) => engine.reject_new_connection(connection, reject.text),
+                }
+            }
+            EngineEvent::SessionEstablished(connection) => {
+                self.logged_on_connections.insert(connection);
+                application.on_logon(engine, connection);
+            }
+            EngineEvent::ConnectionTerminated(connection, ref reason, _)
+            | EngineEvent::SessionEnded(connection, ref reason, _)
+                if self.logged_on_connections.contains(&connection) =>
+            {
+                self.logged_on_connections.remove(&connection);
+                application.on_logout(engine, connection, reason);
+            }
+            EngineEvent::MessageReceived(connection, message) => {
+                if self.administrative_msg_types.contains(message.msg_type()) {
+                    application.from_admin(
+                        engine,
+                        connection,
+                        A::Message::from_fixt_message(message),
+                    );
+                } else {
+                    let msg_seq_num = message.msg_seq_num();
+                    let msg_type = message.msg_type().to_vec();
+                    let result = application.from_app(
+                        engine,
+                        connection,
+                        A::Message::from_fixt_message(message),
+                    );
+                    if let Err(business_reject) = result {
+                        let mut business_message_reject = BusinessMessageReject::new();
+                        business_message_reject.ref_seq_num = msg_seq_num;
+                        business_message_reject.ref_msg_type = msg_type;
+                        business_message_reject.business_reject_reason = business_reject.reason;
+                        business_message_reject.text = business_reject.text.unwrap_or_default();
+                        engine.send_message(connection, business_message_reject);
+                    }
+                }
+            }
+            event => application.on_event(engine, event),
+        }
+
+        true
+    }
+
+    //Dispatch events until the Engine stops.
+    pub fn run(&mut self) {
+        while self.poll(None) {}
+    }
+}
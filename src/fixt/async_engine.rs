@@ -0,0 +1,577 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#![allow(deprecated)]
+
+use futures::channel::mpsc::{unbounded, UnboundedReceiver};
+use futures::future;
+use futures::sink::Sink;
+use futures::stream::Stream;
+use mio::Token;
+use std::collections::HashMap;
+use std::error::Error;
+use std::fmt;
+use std::io;
+use std::net::ToSocketAddrs;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll, Waker};
+use std::thread;
+
+use crate::fix_version::FIXVersion;
+use crate::fixt::engine::{
+    Connection, Engine, EngineEvent, MessageId, MessageIdGenerator, WorkerSenders,
+};
+use crate::fixt::engine_thread::InternalEngineToThreadEvent;
+use crate::fixt::message::{BuildFIXTMessage, FIXTMessage};
+use crate::message_version::MessageVersion;
+
+//Returned when a message can't be sent because the connection, or the entire engine, has
+//already been closed.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ConnectionClosed;
+
+impl fmt::Display for ConnectionClosed {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Connection was closed.")
+    }
+}
+
+impl Error for ConnectionClosed {}
+
+//Returned when waiting on a message to be sent but it never was.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum DeliveryError {
+    ConnectionClosed, //Connection was closed before the message could be sent.
+    Refused,          //Message was refused instead of being sent. See EngineEvent::MessageRefused.
+}
+
+impl fmt::Display for DeliveryError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            DeliveryError::ConnectionClosed => write!(f, "Connection was closed."),
+            DeliveryError::Refused => write!(f, "Message was refused."),
+        }
+    }
+}
+
+impl Error for DeliveryError {}
+
+impl From<ConnectionClosed> for DeliveryError {
+    fn from(_: ConnectionClosed) -> Self {
+        DeliveryError::ConnectionClosed
+    }
+}
+
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum SessionStatus {
+    Pending,
+    Established,
+    Closed,
+}
+
+enum MessageStatus {
+    Pending(Option<Waker>),
+    Done(Result<(), DeliveryError>),
+}
+
+//Connections are released for reuse while the SessionStatuses lock is held and AsyncConnection
+//handles are only created while holding it too. So each time a Connection is reused it gets a
+//new generation and handles left over from before are closed.
+#[derive(Default)]
+struct SessionStatuses {
+    statuses: HashMap<Connection, (u64, SessionStatus)>,
+    wakers: HashMap<Connection, Vec<Waker>>,
+    messages: HashMap<MessageId, MessageStatus>, //Messages someone is waiting to be sent.
+    forward_send_receipts: bool,
+    engine_stopped: bool,
+}
+
+impl SessionStatuses {
+    fn generation(&self, connection: Connection) -> u64 {
+        self.statuses
+            .get(&connection)
+            .map_or(0, |&(generation, _)| generation)
+    }
+
+    fn get(&self, connection: Connection, generation: u64) -> SessionStatus {
+        if self.engine_stopped {
+            return SessionStatus::Closed;
+        }
+
+        match self.statuses.get(&connection) {
+            Some(&(current_generation, status)) if current_generation == generation => status,
+            _ => SessionStatus::Closed,
+        }
+    }
+
+    //Generation to give a new handle to connection. A connection still in use by the Engine is
+    //Pending until its next event arrives. Anything else, such as a connection that was never
+    //setup or was already released, stays Closed.
+    fn open(&mut self, connection: Connection, in_use: bool) -> u64 {
+        let generation = self.generation(connection);
+        if in_use {
+            if let None | Some(&(_, SessionStatus::Closed)) = self.statuses.get(&connection) {
+                self.statuses
+                    .insert(connection, (generation, SessionStatus::Pending));
+            }
+        }
+        generation
+    }
+
+    fn set(&mut self, connection: Connection, status: SessionStatus) {
+        let generation = self.generation(connection);
+        self.statuses.insert(connection, (generation, status));
+        self.wake(connection);
+    }
+
+    //Close every handle of connection so the Connection can be reused. Handles created before
+    //the Connection is reused are closed too.
+    fn close(&mut self, connection: Connection) {
+        let generation = self.generation(connection) + 1;
+        self.statuses
+            .insert(connection, (generation, SessionStatus::Closed));
+        self.wake(connection);
+    }
+
+    fn wake(&mut self, connection: Connection) {
+        if let Some(wakers) = self.wakers.remove(&connection) {
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+    }
+
+    fn track_message(&mut self, message_id: MessageId) {
+        self.messages
+            .insert(message_id, MessageStatus::Pending(None));
+    }
+
+    fn finish_message(&mut self, message_id: MessageId, result: Result<(), DeliveryError>) {
+        if let Some(message_status) = self.messages.get_mut(&message_id) {
+            if let MessageStatus::Pending(Some(ref waker)) = *message_status {
+                waker.wake_by_ref();
+            }
+            *message_status = MessageStatus::Done(result);
+        }
+    }
+
+    //Messages stop being tracked once this returns Ready.
+    fn poll_message(
+        &mut self,
+        message_id: MessageId,
+        cx: &mut Context,
+    ) -> Poll<Result<(), DeliveryError>> {
+        match self.messages.get_mut(&message_id) {
+            Some(MessageStatus::Pending(ref mut waker)) if !self.engine_stopped => {
+                *waker = Some(cx.waker().clone());
+                Poll::Pending
+            }
+            Some(MessageStatus::Done(result)) => {
+                let result = *result;
+                self.messages.remove(&message_id);
+                Poll::Ready(result)
+            }
+            _ => {
+                self.messages.remove(&message_id);
+                Poll::Ready(Err(DeliveryError::ConnectionClosed))
+            }
+        }
+    }
+
+    fn forget_message(&mut self, message_id: MessageId) {
+        self.messages.remove(&message_id);
+    }
+
+    fn stop(&mut self) {
+        self.engine_stopped = true;
+        for (_, wakers) in self.wakers.drain() {
+            for waker in wakers {
+                waker.wake();
+            }
+        }
+        for (_, message_status) in self.messages.iter_mut() {
+            if let MessageStatus::Pending(Some(ref waker)) = *message_status {
+                waker.wake_by_ref();
+            }
+        }
+    }
+}
+
+//Engine for use with async code. All events are received by treating AsyncEngine as a
+//futures::Stream. Connections are setup using the wrapped Engine and messages are sent using
+//AsyncConnection handles. Events are buffered without limit until they are read from the
+//stream.
+pub struct AsyncEngine {
+    engine: Engine,
+    events: UnboundedReceiver<EngineEvent>,
+    statuses: Arc<Mutex<SessionStatuses>>,
+}
+
+impl AsyncEngine {
+    pub fn new(
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+        max_message_size: u64,
+    ) -> Result<AsyncEngine, io::Error> {
+        Ok(AsyncEngine::from_engine(Engine::new(
+            message_dictionary,
+            max_message_size,
+        )?))
+    }
+
+    //Take over receiving events from an existing Engine. Engine::poll() no longer returns any
+    //events once this is called. Send receipts are always enabled on the Engine because flushing
+    //an AsyncConnection relies on them. Use AsyncEngine::set_send_receipts() instead of
+    //Engine::set_send_receipts() to choose whether they show up on the stream.
+    pub fn from_engine(mut engine: Engine) -> AsyncEngine {
+        let (tx, rx) = unbounded();
+        let statuses = Arc::new(Mutex::new(SessionStatuses {
+            forward_send_receipts: true,
+            ..SessionStatuses::default()
+        }));
+        engine.set_send_receipts(true);
+
+        //Forward events from the internal engine thread onto the stream. The forwarding thread
+        //stops on its own once the Engine is dropped.
+        let mut event_receiver = engine
+            .take_event_receiver()
+            .expect("Engine events were already taken");
+        let thread_statuses = statuses.clone();
+        thread::spawn(move || {
+            while let Some(event) = event_receiver.recv() {
+                let forward_event = {
+                    let mut statuses = thread_statuses.lock().unwrap();
+                    match event {
+                        EngineEvent::ConnectionSucceeded(connection)
+                        | EngineEvent::ConnectionAccepted(_, connection, _)
+                        | EngineEvent::SessionEnded(connection, _, _) => {
+                            statuses.set(connection, SessionStatus::Pending)
+                        }
+                        EngineEvent::SessionEstablished(connection) => {
+                            statuses.set(connection, SessionStatus::Established)
+                        }
+                        EngineEvent::ConnectionFailed(connection, _)
+                        | EngineEvent::ConnectionTerminated(connection, _, _) => {
+                            statuses.close(connection)
+                        }
+                        EngineEvent::MessageSent(_, message_id, _, _) => {
+                            statuses.finish_message(message_id, Ok(()))
+                        }
+                        EngineEvent::MessageNotSent(_, message_id, _) => statuses
+                            .finish_message(message_id, Err(DeliveryError::ConnectionClosed)),
+                        EngineEvent::MessageRefused(_, Some(message_id), _, _) => {
+                            statuses.finish_message(message_id, Err(DeliveryError::Refused))
+                        }
+                        _ => {}
+                    }
+
+                    //Release the Connection while still holding the lock so it can't be reused
+                    //until every handle to it is closed.
+                    event_receiver.release(&event);
+
+                    match event {
+                        EngineEvent::MessageSent(..) | EngineEvent::MessageNotSent(..) => {
+                            statuses.forward_send_receipts
+                        }
+                        _ => true,
+                    }
+                };
+
+                if forward_event && tx.unbounded_send(event).is_err() {
+                    break;
+                }
+            }
+
+            thread_statuses.lock().unwrap().stop();
+        });
+
+        AsyncEngine {
+            engine,
+            events: rx,
+            statuses,
+        }
+    }
+
+    //Emit EngineEvent::MessageSent and EngineEvent::MessageNotSent on the stream. Enabled by
+    //default. AsyncConnection::flush() and delivery tracking keep working when disabled.
+    pub fn set_send_receipts(&mut self, enabled: bool) {
+        self.statuses.lock().unwrap().forward_send_receipts = enabled;
+    }
+
+    pub fn engine(&self) -> &Engine {
+        &self.engine
+    }
+
+    pub fn engine_mut(&mut self) -> &mut Engine {
+        &mut self.engine
+    }
+
+    //Same as Engine::add_connection() but returns a handle for sending messages instead.
+    pub fn add_connection<A: ToSocketAddrs>(
+        &mut self,
+        fix_version: FIXVersion,
+        default_message_version: MessageVersion,
+        sender_comp_id: &[u8],
+        target_comp_id: &[u8],
+        address: A,
+    ) -> Option<AsyncConnection> {
+        //Hold the lock until the handle is created so the connection can't be closed and its
+        //identifier reused in between.
+        let mut statuses = self.statuses.lock().unwrap();
+        let connection = self.engine.add_connection(
+            fix_version,
+            default_message_version,
+            sender_comp_id,
+            target_comp_id,
+            address,
+        )?;
+
+        let generation = statuses.open(connection, true);
+        Some(self.connection_with_generation(connection, generation))
+    }
+
+    //Get a handle for sending messages to a connection that was setup with the wrapped Engine
+    //or accepted by a listener. The handle is closed right away if the connection doesn't exist.
+    pub fn connection(&self, connection: Connection) -> AsyncConnection {
+        //Connections are only released while holding the lock so checking the Engine here can't
+        //race with the connection being closed.
+        let mut statuses = self.statuses.lock().unwrap();
+        let in_use = self.engine.connection_worker(connection).is_some();
+        let generation = statuses.open(connection, in_use);
+        drop(statuses);
+        self.connection_with_generation(connection, generation)
+    }
+
+    fn connection_with_generation(
+        &self,
+        connection: Connection,
+        generation: u64,
+    ) -> AsyncConnection {
+        AsyncConnection {
+            connection,
+            generation,
+            tx: self.engine.sender(),
+            message_ids: self.engine.message_id_generator(),
+            statuses: self.statuses.clone(),
+            unflushed: Vec::new(),
+        }
+    }
+}
+
+impl Stream for AsyncEngine {
+    type Item = EngineEvent;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Option<EngineEvent>> {
+        Pin::new(&mut self.get_mut().events).poll_next(cx)
+    }
+}
+
+//Cloneable handle for sending messages to a single connection from any task. Handles stay
+//closed once the connection is closed even if a new connection reuses the same Connection.
+pub struct AsyncConnection {
+    connection: Connection,
+    generation: u64,
+    tx: WorkerSenders,
+    message_ids: MessageIdGenerator,
+    statuses: Arc<Mutex<SessionStatuses>>,
+    unflushed: Vec<MessageId>, //Messages sent as a Sink that haven't been flushed yet.
+}
+
+impl Clone for AsyncConnection {
+    fn clone(&self) -> Self {
+        AsyncConnection {
+            connection: self.connection,
+            generation: self.generation,
+            tx: self.tx.clone(),
+            message_ids: self.message_ids.clone(),
+            statuses: self.statuses.clone(),
+            unflushed: Vec::new(),
+        }
+    }
+}
+
+impl Drop for AsyncConnection {
+    fn drop(&mut self) {
+        let mut statuses = self.statuses.lock().unwrap();
+        for message_id in self.unflushed.drain(..) {
+            statuses.forget_message(message_id);
+        }
+    }
+}
+
+//Stops tracking a message if the future waiting on it is dropped early.
+struct TrackedMessage<'a> {
+    statuses: &'a Mutex<SessionStatuses>,
+    message_id: MessageId,
+}
+
+impl<'a> Drop for TrackedMessage<'a> {
+    fn drop(&mut self) {
+        self.statuses
+            .lock()
+            .unwrap()
+            .forget_message(self.message_id);
+    }
+}
+
+impl AsyncConnection {
+    pub fn connection(&self) -> Connection {
+        self.connection
+    }
+
+    //Queue message to be sent right away. Use this for the Logon message or anything else that
+    //must go out before the session is established.
+    pub fn send_message<T: 'static + FIXTMessage + Send>(
+        &self,
+        message: T,
+    ) -> Result<MessageId, ConnectionClosed> {
+        self.send_message_box(Box::new(message))
+    }
+
+    pub fn send_message_box(
+        &self,
+        message: Box<dyn FIXTMessage + Send>,
+    ) -> Result<MessageId, ConnectionClosed> {
+        self.send(message, false)
+    }
+
+    //Queue message and, when track is set, keep track of whether it's sent until
+    //SessionStatuses::poll_message() says so.
+    fn send(
+        &self,
+        message: Box<dyn FIXTMessage + Send>,
+        track: bool,
+    ) -> Result<MessageId, ConnectionClosed> {
+        let message_id = self.message_ids.create();
+        {
+            let mut statuses = self.statuses.lock().unwrap();
+            if let SessionStatus::Closed = statuses.get(self.connection, self.generation) {
+                return Err(ConnectionClosed);
+            }
+            if track {
+                statuses.track_message(message_id);
+            }
+        }
+
+        let result = self.tx.send(InternalEngineToThreadEvent::SendMessage(
+            Token(self.connection.0),
+            message_id,
+            None,
+            message,
+        ));
+        if result.is_err() {
+            self.statuses.lock().unwrap().forget_message(message_id);
+            return Err(ConnectionClosed);
+        }
+
+        Ok(message_id)
+    }
+
+    //Resolves once the session has been established or fails if the connection is closed
+    //before then.
+    pub async fn established(&self) -> Result<(), ConnectionClosed> {
+        future::poll_fn(|cx| self.poll_established(cx)).await
+    }
+
+    pub fn poll_established(&self, cx: &mut Context) -> Poll<Result<(), ConnectionClosed>> {
+        let mut statuses = self.statuses.lock().unwrap();
+        match statuses.get(self.connection, self.generation) {
+            SessionStatus::Established => Poll::Ready(Ok(())),
+            SessionStatus::Closed => Poll::Ready(Err(ConnectionClosed)),
+            SessionStatus::Pending => {
+                //Only keep one copy of each waker no matter how many times this is polled.
+                let wakers = statuses.wakers.entry(self.connection).or_default();
+                if !wakers.iter().any(|waker| waker.will_wake(cx.waker())) {
+                    wakers.push(cx.waker().clone());
+                }
+                Poll::Pending
+            }
+        }
+    }
+
+    //Wait until the session is established, queue message to be sent, and then wait until it's
+    //completely written to the socket.
+    pub async fn deliver_message<T: 'static + FIXTMessage + Send>(
+        &self,
+        message: T,
+    ) -> Result<MessageId, DeliveryError> {
+        self.established().await?;
+        let message_id = self.send(Box::new(message), true)?;
+        let tracked_message = TrackedMessage {
+            statuses: &self.statuses,
+            message_id,
+        };
+        future::poll_fn(|cx| {
+            tracked_message
+                .statuses
+                .lock()
+                .unwrap()
+                .poll_message(message_id, cx)
+        })
+        .await?;
+
+        Ok(message_id)
+    }
+
+    pub fn logout(&self) {
+        let _ = self.tx.send(InternalEngineToThreadEvent::Logout(Token(
+            self.connection.0,
+        )));
+    }
+}
+
+impl Sink<Box<dyn FIXTMessage + Send>> for AsyncConnection {
+    type Error = DeliveryError;
+
+    fn poll_ready(self: Pin<&mut Self>, _: &mut Context) -> Poll<Result<(), DeliveryError>> {
+        match self
+            .statuses
+            .lock()
+            .unwrap()
+            .get(self.connection, self.generation)
+        {
+            SessionStatus::Closed => Poll::Ready(Err(DeliveryError::ConnectionClosed)),
+            _ => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn start_send(
+        self: Pin<&mut Self>,
+        message: Box<dyn FIXTMessage + Send>,
+    ) -> Result<(), DeliveryError> {
+        let this = self.get_mut();
+        let message_id = this.send(message, true)?;
+        this.unflushed.push(message_id);
+        Ok(())
+    }
+
+    //Resolves once every message sent using this handle has been written to the socket. Fails
+    //with the first one that wasn't.
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), DeliveryError>> {
+        let this = self.get_mut();
+        let mut statuses = this.statuses.lock().unwrap();
+        while let Some(&message_id) = this.unflushed.first() {
+            match statuses.poll_message(message_id, cx) {
+                Poll::Ready(result) => {
+                    this.unflushed.remove(0);
+                    result?;
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
+
+        Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(self: Pin<&mut Self>, cx: &mut Context) -> Poll<Result<(), DeliveryError>> {
+        self.poll_flush(cx)
+    }
+}
@@ -0,0 +1,149 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use chrono::{DateTime, Duration as ChronoDuration, Utc};
+use mio::{Ready, SetReadiness};
+use std::sync::{Arc, Mutex, Weak};
+use std::time::{Duration, Instant};
+
+//Source of time for every timeout and timestamp an Engine uses. Ie. heartbeats, TestRequests,
+//logout and logon timeouts, throttling, schedules, and SendingTime.
+pub trait Clock: Send + Sync {
+    //Monotonic time used to measure timeouts.
+    fn now(&self) -> Instant;
+
+    //Wall clock time used for timestamps and schedules.
+    fn now_utc(&self) -> DateTime<Utc>;
+
+    //Whether time passes on its own. Clocks that don't must wake every ClockWaker they're given
+    //whenever time moves forward so expired timeouts are noticed.
+    fn is_real_time(&self) -> bool {
+        true
+    }
+
+    fn add_waker(&self, _waker: ClockWaker) {}
+}
+
+//Lets a clock tell an Engine's worker thread that time moved forward. Does nothing once the
+//timer it belongs to is dropped.
+#[derive(Clone)]
+pub struct ClockWaker(Weak<SetReadiness>);
+
+impl ClockWaker {
+    pub(crate) fn new(set_readiness: &Arc<SetReadiness>) -> ClockWaker {
+        ClockWaker(Arc::downgrade(set_readiness))
+    }
+
+    pub fn wake(&self) {
+        if let Some(set_readiness) = self.0.upgrade() {
+            let _ = set_readiness.set_readiness(Ready::readable());
+        }
+    }
+
+    //Whether the timer this belongs to was dropped. Clocks can forget about these wakers.
+    pub fn is_dropped(&self) -> bool {
+        self.0.strong_count() == 0
+    }
+}
+
+//The system's clock. Used by default.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+struct ManualClockState {
+    elapsed: Duration,
+    wakers: Vec<ClockWaker>,
+}
+
+//Clock that only moves when advance() is called. Makes tests involving timeouts run instantly
+//and deterministically. Clones share the same time.
+#[derive(Clone)]
+pub struct ManualClock {
+    start: Instant,
+    start_utc: DateTime<Utc>,
+    state: Arc<Mutex<ManualClockState>>,
+}
+
+impl ManualClock {
+    pub fn new() -> ManualClock {
+        ManualClock::new_at(Utc::now())
+    }
+
+    //Start the wall clock at start_utc instead of the current time.
+    pub fn new_at(start_utc: DateTime<Utc>) -> ManualClock {
+        ManualClock {
+            start: Instant::now(),
+            start_utc,
+            state: Arc::new(Mutex::new(ManualClockState {
+                elapsed: Duration::from_secs(0),
+                wakers: Vec::new(),
+            })),
+        }
+    }
+
+    //Move time forward by duration. Any timeouts that expire as a result fire right away.
+    pub fn advance(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap();
+        state.elapsed += duration;
+        state.wakers.retain(|waker| !waker.is_dropped());
+        for waker in &state.wakers {
+            waker.wake();
+        }
+    }
+
+    pub fn elapsed(&self) -> Duration {
+        self.state.lock().unwrap().elapsed
+    }
+}
+
+impl Default for ManualClock {
+    fn default() -> Self {
+        ManualClock::new()
+    }
+}
+
+impl Clock for ManualClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.start_utc + ChronoDuration::from_std(self.elapsed()).unwrap()
+    }
+
+    fn is_real_time(&self) -> bool {
+        false
+    }
+
+    fn add_waker(&self, waker: ClockWaker) {
+        let mut state = self.state.lock().unwrap();
+        state.wakers.retain(|waker| !waker.is_dropped());
+        state.wakers.push(waker);
+    }
+}
+
+//Current time according to clock as a UtcTimestamp. UtcTimestamp does not support sub-millisecond
+//precision so anything smaller is truncated.
+pub(crate) fn now_utc_timestamp(clock: &dyn Clock) -> DateTime<Utc> {
+    let now = clock.now_utc();
+    let nanos = now.timestamp_subsec_nanos();
+    now - ChronoDuration::nanoseconds(i64::from(nanos % 1_000_000))
+}
@@ -0,0 +1,1247 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//Declarative Engine setup. An EngineConfig is usually loaded from a TOML file like:
+//
+//  [engine]
+//  max_message_size = 4096
+//  seq_num_store = "store"         #Optional directory for FileSeqNumStore.
+//  message_store = "store"         #Optional directory for FileMessageStoreFactory.
+//
+//  [[initiator]]
+//  fix_version = "FIXT.1.1"
+//  default_appl_ver_id = "FIX.5.0SP2" #Optional, defaults to the newest for fix_version.
+//  sender_comp_id = "CLIENT"
+//  target_comp_id = "SERVER"
+//  address = "127.0.0.1:7001"
+//  heart_bt_int = 30               #Optional, defaults to 30.
+//  reset_on_logon = false          #Optional, sends ResetSeqNumFlag=Y with every Logon.
+//  schedule = { start_time = "08:00:00", end_time = "17:00:00", time_zone = "America/New_York" }
+//  reconnect = { initial_delay_ms = 1000, max_delay_ms = 30000, failover_addresses = ["127.0.0.1:7002"] }
+//  tls = { ca_file = "ca.pem", server_name = "server.example.com" }
+//
+//  [[acceptor]]
+//  sender_comp_id = "SERVER"
+//  address = "0.0.0.0:7001"
+//  tls = { cert_file = "server.pem", key_file = "server.key" }
+//
+//  [[acceptor.session]]
+//  fix_version = "FIXT.1.1"
+//  target_comp_id = "CLIENT"
+//  heart_bt_int = 30
+//
+//Schedules may also have start_day and end_day to make them weekly and reset_time (plus
+//reset_day for weekly resets) to reset MsgSeqNums. Everything is validated while loading so
+//build() only fails when something outside of the file, like a certificate or a listener's
+//address, can't be used.
+
+use chrono::{NaiveTime, Weekday};
+use chrono_tz::{Tz, UTC};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::net::{SocketAddr, ToSocketAddrs};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use thiserror::Error;
+
+use crate::dictionary::field_types::other::EncryptMethod;
+use crate::dictionary::messages::Logon;
+use crate::fix_version::FIXVersion;
+use crate::fixt::acceptor_session::AcceptorSession;
+use crate::fixt::engine::{Connection, Engine, Listener};
+use crate::fixt::message::BuildFIXTMessage;
+use crate::fixt::message_store::FileMessageStoreFactory;
+use crate::fixt::reconnect_policy::ReconnectPolicy;
+use crate::fixt::seq_num_store::FileSeqNumStore;
+use crate::fixt::session_config::{ConnectionConfig, ListenerConfig};
+use crate::fixt::session_schedule::{ScheduleTime, SessionSchedule};
+use crate::fixt::stream::StreamConfig;
+#[cfg(feature = "tls")]
+use crate::fixt::tls::{TlsAcceptor, TlsConnector};
+use crate::message_version::MessageVersion;
+
+const DEFAULT_MAX_MESSAGE_SIZE: u64 = 4096;
+const DEFAULT_HEART_BT_INT: i64 = 30;
+const DEFAULT_QUICKFIX_RECONNECT_INTERVAL_SECONDS: u64 = 30;
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("failed to read {}: {source}", path.display())]
+    Io { path: PathBuf, source: io::Error },
+    #[error("failed to parse TOML: {0}")]
+    Toml(#[from] toml::de::Error),
+    //location is where in the file the problem is. Either a key path like
+    //"initiator[0].schedule.start_time" or a line number for QuickFIX settings.
+    #[error("{location}: {message}")]
+    Invalid { location: String, message: String },
+    #[error("{location}: {source}")]
+    Build { location: String, source: io::Error },
+}
+
+fn invalid<L: Into<String>, M: Into<String>>(location: L, message: M) -> ConfigError {
+    ConfigError::Invalid {
+        location: location.into(),
+        message: message.into(),
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct InitiatorTlsConfig {
+    pub ca_file: PathBuf,
+    pub server_name: String,
+    //Certificate chain and private key presented to servers requiring client authentication.
+    pub client_auth: Option<(PathBuf, PathBuf)>,
+}
+
+#[derive(Clone, Debug)]
+pub struct AcceptorTlsConfig {
+    pub cert_file: PathBuf,
+    pub key_file: PathBuf,
+    //When set, clients must present a certificate signed by one of these CAs.
+    pub client_ca_file: Option<PathBuf>,
+}
+
+//Session that's connected using Engine::add_connection_with_config(). Logon is sent
+//automatically.
+#[derive(Clone, Debug)]
+pub struct InitiatorConfig {
+    pub fix_version: FIXVersion,
+    pub default_message_version: MessageVersion,
+    pub sender_comp_id: Vec<u8>,
+    pub target_comp_id: Vec<u8>,
+    pub address: SocketAddr,
+    pub heart_bt_int: i64,
+    pub reset_on_logon: bool,
+    pub schedule: Option<SessionSchedule>,
+    pub reconnect_policy: Option<ReconnectPolicy>,
+    pub tls: Option<InitiatorTlsConfig>,
+}
+
+//Counterparty expected to logon to an acceptor. See AcceptorSession.
+#[derive(Clone, Debug)]
+pub struct AcceptorSessionConfig {
+    pub fix_version: FIXVersion,
+    pub target_comp_id: Vec<u8>,
+    pub sender_sub_id: Option<Vec<u8>>,
+    pub target_sub_id: Option<Vec<u8>>,
+    pub heart_bt_int: Option<i64>,
+    pub schedule: Option<SessionSchedule>,
+    pub seq_num_store: Option<PathBuf>, //Directory for this session's own FileSeqNumStore.
+}
+
+#[derive(Clone, Debug)]
+pub struct AcceptorConfig {
+    pub sender_comp_id: Vec<u8>,
+    pub address: SocketAddr,
+    pub schedule: Option<SessionSchedule>,
+    pub tls: Option<AcceptorTlsConfig>,
+    pub sessions: Vec<AcceptorSessionConfig>,
+}
+
+#[derive(Clone, Debug)]
+pub struct EngineConfig {
+    pub max_message_size: u64,
+    pub seq_num_store: Option<PathBuf>,
+    pub message_store: Option<PathBuf>,
+    pub initiators: Vec<InitiatorConfig>,
+    pub acceptors: Vec<AcceptorConfig>,
+}
+
+//Engine created by EngineConfig::build(). connections and listeners are in the same order as
+//the initiators and acceptors they were created from.
+pub struct ConfiguredEngine {
+    pub engine: Engine,
+    pub connections: Vec<Connection>,
+    pub listeners: Vec<Listener>,
+}
+
+impl EngineConfig {
+    pub fn from_toml_file<P: AsRef<Path>>(path: P) -> Result<EngineConfig, ConfigError> {
+        EngineConfig::from_toml_str(&read_file(path.as_ref())?)
+    }
+
+    pub fn from_toml_str(toml: &str) -> Result<EngineConfig, ConfigError> {
+        let raw_config: RawConfig = toml::from_str(toml)?;
+        raw_config.validate()
+    }
+
+    //Import the sessions from a QuickFIX style settings file. See from_quickfix_str().
+    pub fn from_quickfix_file<P: AsRef<Path>>(path: P) -> Result<EngineConfig, ConfigError> {
+        EngineConfig::from_quickfix_str(&read_file(path.as_ref())?)
+    }
+
+    //Import the sessions from QuickFIX style settings. Every [SESSION] inherits from [DEFAULT]
+    //and ConnectionType decides whether it becomes an initiator or one of an acceptor's
+    //sessions. Acceptor sessions sharing a SocketAcceptPort and SenderCompID share a listener.
+    //Supported settings are BeginString, SenderCompID, TargetCompID, DefaultApplVerID,
+    //HeartBtInt, StartTime, EndTime, StartDay, EndDay, TimeZone and FileStorePath. Initiators
+    //also support ResetOnLogon, ReconnectInterval, SocketConnectHost and SocketConnectPort
+    //(plus numbered failovers like SocketConnectHost1). Acceptors also support SenderSubID,
+    //TargetSubID, SocketAcceptHost and SocketAcceptPort. SSL is supported using QuickFIX's PEM
+    //based CertificationAuthoritiesFile, ServerCertificateFile, ServerCertificateKeyFile,
+    //ClientCertificateFile and ClientCertificateKeyFile. Everything else is ignored.
+    pub fn from_quickfix_str(settings: &str) -> Result<EngineConfig, ConfigError> {
+        let sections = parse_quickfix_sections(settings)?;
+
+        let mut config = EngineConfig {
+            max_message_size: DEFAULT_MAX_MESSAGE_SIZE,
+            seq_num_store: None,
+            message_store: None,
+            initiators: Vec::new(),
+            acceptors: Vec::new(),
+        };
+        for section in sections {
+            section.import(&mut config)?;
+        }
+
+        Ok(config)
+    }
+
+    //Create an Engine with every acceptor and initiator. Listeners are setup first so initiators,
+    //which start connecting right away (or once their schedule becomes active), can reach them.
+    pub fn build(
+        &self,
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+    ) -> Result<ConfiguredEngine, ConfigError> {
+        let build_error =
+            |location: String| move |source: io::Error| ConfigError::Build { location, source };
+
+        let mut engine = Engine::new(message_dictionary, self.max_message_size)
+            .map_err(build_error(String::from("engine")))?;
+        if let Some(ref directory) = self.seq_num_store {
+            let seq_num_store = FileSeqNumStore::new(directory.clone())
+                .map_err(build_error(String::from("engine.seq_num_store")))?;
+            engine.set_seq_num_store(Box::new(seq_num_store));
+        }
+        if let Some(ref directory) = self.message_store {
+            engine.set_message_store_factory(Box::new(FileMessageStoreFactory::new(
+                directory.clone(),
+            )));
+        }
+
+        let mut listeners = Vec::new();
+        for (index, acceptor) in self.acceptors.iter().enumerate() {
+            let location = format!("acceptor[{}]", index);
+            let stream_config = acceptor
+                .tls
+                .as_ref()
+                .map(|tls| tls.stream_config())
+                .unwrap_or(Ok(StreamConfig::Plain))
+                .map_err(build_error(format!("{}.tls", location)))?;
+
+            let mut listener_config =
+                ListenerConfig::new(&acceptor.sender_comp_id[..], acceptor.address)
+                    .with_stream_config(stream_config);
+            if let Some(ref schedule) = acceptor.schedule {
+                listener_config = listener_config.with_schedule(schedule.clone());
+            }
+            let listener = engine
+                .add_listener_with_config(listener_config)
+                .map_err(build_error(format!("{}.address", location)))?
+                .ok_or_else(|| invalid(location.clone(), "too many listeners"))?;
+
+            for (session_index, session_config) in acceptor.sessions.iter().enumerate() {
+                let location = format!("{}.session[{}]", location, session_index);
+                let session = session_config
+                    .acceptor_session()
+                    .map_err(build_error(format!("{}.seq_num_store", location)))?;
+                if !engine.add_acceptor_session(listener, session) {
+                    return Err(invalid(location, "too many sessions"));
+                }
+            }
+
+            listeners.push(listener);
+        }
+
+        let mut connections = Vec::new();
+        for (index, initiator) in self.initiators.iter().enumerate() {
+            let location = format!("initiator[{}]", index);
+            let stream_config = initiator
+                .tls
+                .as_ref()
+                .map(|tls| tls.stream_config())
+                .unwrap_or(Ok(StreamConfig::Plain))
+                .map_err(build_error(format!("{}.tls", location)))?;
+
+            let mut logon = Logon::new();
+            logon.encrypt_method = EncryptMethod::None;
+            logon.heart_bt_int = initiator.heart_bt_int;
+            logon.default_appl_ver_id = initiator.default_message_version;
+            logon.reset_seq_num_flag = initiator.reset_on_logon;
+
+            let mut connection_config = ConnectionConfig::new(
+                initiator.fix_version,
+                initiator.default_message_version,
+                &initiator.sender_comp_id[..],
+                &initiator.target_comp_id[..],
+                initiator.address,
+            )
+            .with_stream_config(stream_config)
+            .with_logon(Box::new(logon));
+            if let Some(ref schedule) = initiator.schedule {
+                connection_config = connection_config.with_schedule(schedule.clone());
+            }
+            if let Some(ref reconnect_policy) = initiator.reconnect_policy {
+                connection_config =
+                    connection_config.with_reconnect_policy(reconnect_policy.clone());
+            }
+            let connection = engine
+                .add_connection_with_config(connection_config)
+                .ok_or_else(|| invalid(location, "too many connections"))?;
+            connections.push(connection);
+        }
+
+        Ok(ConfiguredEngine {
+            engine,
+            connections,
+            listeners,
+        })
+    }
+}
+
+impl InitiatorTlsConfig {
+    #[cfg(feature = "tls")]
+    fn stream_config(&self) -> Result<StreamConfig, io::Error> {
+        let tls_connector = match self.client_auth {
+            Some((ref cert_file, ref key_file)) => TlsConnector::from_pem_files_with_client_auth(
+                &self.ca_file,
+                cert_file,
+                key_file,
+                &self.server_name,
+            )?,
+            None => TlsConnector::from_pem_files(&self.ca_file, &self.server_name)?,
+        };
+        Ok(StreamConfig::TlsClient(tls_connector))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn stream_config(&self) -> Result<StreamConfig, io::Error> {
+        Err(tls_unsupported_error())
+    }
+}
+
+impl AcceptorTlsConfig {
+    #[cfg(feature = "tls")]
+    fn stream_config(&self) -> Result<StreamConfig, io::Error> {
+        let tls_acceptor = match self.client_ca_file {
+            Some(ref client_ca_file) => TlsAcceptor::from_pem_files_with_client_auth(
+                &self.cert_file,
+                &self.key_file,
+                client_ca_file,
+            )?,
+            None => TlsAcceptor::from_pem_files(&self.cert_file, &self.key_file)?,
+        };
+        Ok(StreamConfig::TlsServer(tls_acceptor))
+    }
+
+    #[cfg(not(feature = "tls"))]
+    fn stream_config(&self) -> Result<StreamConfig, io::Error> {
+        Err(tls_unsupported_error())
+    }
+}
+
+#[cfg(not(feature = "tls"))]
+fn tls_unsupported_error() -> io::Error {
+    io::Error::new(
+        io::ErrorKind::Other,
+        "TLS requires fix-rs to be built with the tls feature",
+    )
+}
+
+impl AcceptorSessionConfig {
+    fn acceptor_session(&self) -> Result<AcceptorSession, io::Error> {
+        let mut session = AcceptorSession::new(self.fix_version, &self.target_comp_id[..]);
+        if let Some(ref sender_sub_id) = self.sender_sub_id {
+            session = session.with_sender_sub_id(&sender_sub_id[..]);
+        }
+        if let Some(ref target_sub_id) = self.target_sub_id {
+            session = session.with_target_sub_id(&target_sub_id[..]);
+        }
+        if let Some(heart_bt_int) = self.heart_bt_int {
+            session = session.with_heart_bt_int(heart_bt_int);
+        }
+        if let Some(ref schedule) = self.schedule {
+            session = session.with_schedule(schedule.clone());
+        }
+        if let Some(ref directory) = self.seq_num_store {
+            session =
+                session.with_seq_num_store(Box::new(FileSeqNumStore::new(directory.clone())?));
+        }
+
+        Ok(session)
+    }
+}
+
+fn read_file(path: &Path) -> Result<String, ConfigError> {
+    fs::read_to_string(path).map_err(|source| ConfigError::Io {
+        path: path.to_path_buf(),
+        source,
+    })
+}
+
+fn parse_fix_version(location: &str, value: &str) -> Result<FIXVersion, ConfigError> {
+    FIXVersion::all()
+        .into_iter()
+        .find(|fix_version| fix_version.begin_string() == value.as_bytes())
+        .ok_or_else(|| {
+            invalid(
+                location,
+                format!(
+                    "unknown FIX version \"{}\", expected one of {}",
+                    value,
+                    FIXVersion::all()
+                        .iter()
+                        .map(|fix_version| String::from_utf8_lossy(fix_version.begin_string()))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            )
+        })
+}
+
+fn message_version_name(message_version: MessageVersion) -> &'static str {
+    match message_version {
+        MessageVersion::FIX40 => "FIX.4.0",
+        MessageVersion::FIX41 => "FIX.4.1",
+        MessageVersion::FIX42 => "FIX.4.2",
+        MessageVersion::FIX43 => "FIX.4.3",
+        MessageVersion::FIX44 => "FIX.4.4",
+        MessageVersion::FIX50 => "FIX.5.0",
+        MessageVersion::FIX50SP1 => "FIX.5.0SP1",
+        MessageVersion::FIX50SP2 => "FIX.5.0SP2",
+    }
+}
+
+//Accepts names like "FIX.5.0SP2" or the ApplVerID value itself like "9".
+fn parse_message_version(location: &str, value: &str) -> Result<MessageVersion, ConfigError> {
+    MessageVersion::all()
+        .into_iter()
+        .find(|message_version| {
+            message_version_name(*message_version) == value
+                || message_version.as_bytes() == value.as_bytes()
+        })
+        .ok_or_else(|| {
+            invalid(
+                location,
+                format!(
+                    "unknown ApplVerID \"{}\", expected one of {}",
+                    value,
+                    MessageVersion::all()
+                        .into_iter()
+                        .map(message_version_name)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                ),
+            )
+        })
+}
+
+fn parse_comp_id(location: &str, value: &str) -> Result<Vec<u8>, ConfigError> {
+    if value.is_empty() {
+        return Err(invalid(location, "must not be empty"));
+    }
+
+    Ok(value.as_bytes().to_vec())
+}
+
+fn parse_address(location: &str, value: &str) -> Result<SocketAddr, ConfigError> {
+    value
+        .to_socket_addrs()
+        .map_err(|e| invalid(location, format!("invalid address \"{}\": {}", value, e)))?
+        .next()
+        .ok_or_else(|| {
+            invalid(
+                location,
+                format!("\"{}\" did not resolve to an address", value),
+            )
+        })
+}
+
+fn parse_heart_bt_int(location: &str, value: i64) -> Result<i64, ConfigError> {
+    if value <= 0 {
+        return Err(invalid(
+            location,
+            format!("HeartBtInt must be greater than 0 but is {}", value),
+        ));
+    }
+
+    Ok(value)
+}
+
+fn parse_time(location: &str, value: &str) -> Result<NaiveTime, ConfigError> {
+    NaiveTime::parse_from_str(value, "%H:%M:%S")
+        .or_else(|_| NaiveTime::parse_from_str(value, "%H:%M"))
+        .map_err(|_| {
+            invalid(
+                location,
+                format!("invalid time \"{}\", expected HH:MM:SS", value),
+            )
+        })
+}
+
+fn parse_weekday(location: &str, value: &str) -> Result<Weekday, ConfigError> {
+    value.parse::<Weekday>().map_err(|_| {
+        invalid(
+            location,
+            format!("invalid day \"{}\", expected a day like \"Monday\"", value),
+        )
+    })
+}
+
+fn parse_time_zone(location: &str, value: &str) -> Result<Tz, ConfigError> {
+    value.parse::<Tz>().map_err(|_| {
+        invalid(
+            location,
+            format!(
+                "unknown time zone \"{}\", expected a name like \"America/New_York\"",
+                value
+            ),
+        )
+    })
+}
+
+//Schedule settings shared by the TOML and QuickFIX formats. Each value is paired with the
+//location it came from so problems can be reported precisely.
+#[derive(Default)]
+struct ScheduleSettings<'a> {
+    start_time: Option<(String, &'a str)>,
+    end_time: Option<(String, &'a str)>,
+    start_day: Option<(String, &'a str)>,
+    end_day: Option<(String, &'a str)>,
+    time_zone: Option<(String, &'a str)>,
+    reset_time: Option<(String, &'a str)>,
+    reset_day: Option<(String, &'a str)>,
+}
+
+impl<'a> ScheduleSettings<'a> {
+    //Returns None when no schedule settings are present at all.
+    fn validate(self, location: &str) -> Result<Option<SessionSchedule>, ConfigError> {
+        let (start_time, end_time) = match (self.start_time, self.end_time) {
+            (None, None)
+                if self.start_day.is_none()
+                    && self.end_day.is_none()
+                    && self.reset_time.is_none() =>
+            {
+                return Ok(None)
+            }
+            (Some(start_time), Some(end_time)) => (
+                parse_time(&start_time.0, start_time.1)?,
+                parse_time(&end_time.0, end_time.1)?,
+            ),
+            _ => {
+                return Err(invalid(
+                    location,
+                    "schedule requires both a start time and an end time",
+                ))
+            }
+        };
+        let time_zone = match self.time_zone {
+            Some((location, value)) => parse_time_zone(&location, value)?,
+            None => UTC,
+        };
+
+        let mut schedule = match (self.start_day, self.end_day) {
+            (None, None) => SessionSchedule::daily(start_time, end_time, time_zone),
+            (Some(start_day), Some(end_day)) => SessionSchedule::weekly(
+                parse_weekday(&start_day.0, start_day.1)?,
+                start_time,
+                parse_weekday(&end_day.0, end_day.1)?,
+                end_time,
+                time_zone,
+            ),
+            _ => {
+                return Err(invalid(
+                    location,
+                    "weekly schedule requires both a start day and an end day",
+                ))
+            }
+        };
+
+        match (self.reset_time, self.reset_day) {
+            (None, None) => {}
+            (Some(reset_time), None) => {
+                schedule = schedule.with_reset_time(ScheduleTime::Daily(parse_time(
+                    &reset_time.0,
+                    reset_time.1,
+                )?));
+            }
+            (Some(reset_time), Some(reset_day)) => {
+                schedule = schedule.with_reset_time(ScheduleTime::Weekly(
+                    parse_weekday(&reset_day.0, reset_day.1)?,
+                    parse_time(&reset_time.0, reset_time.1)?,
+                ));
+            }
+            (None, Some(reset_day)) => {
+                return Err(invalid(reset_day.0, "reset day requires a reset time"));
+            }
+        }
+
+        Ok(Some(schedule))
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawConfig {
+    #[serde(default)]
+    engine: RawEngine,
+    #[serde(default, rename = "initiator")]
+    initiators: Vec<RawInitiator>,
+    #[serde(default, rename = "acceptor")]
+    acceptors: Vec<RawAcceptor>,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawEngine {
+    max_message_size: Option<u64>,
+    seq_num_store: Option<PathBuf>,
+    message_store: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawSchedule {
+    start_time: String,
+    end_time: String,
+    start_day: Option<String>,
+    end_day: Option<String>,
+    time_zone: Option<String>,
+    reset_time: Option<String>,
+    reset_day: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawReconnect {
+    initial_delay_ms: u64,
+    max_delay_ms: u64,
+    jitter: Option<f64>,
+    max_attempts: Option<u32>,
+    #[serde(default)]
+    failover_addresses: Vec<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawInitiatorTls {
+    ca_file: PathBuf,
+    server_name: String,
+    cert_file: Option<PathBuf>,
+    key_file: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawInitiator {
+    fix_version: String,
+    default_appl_ver_id: Option<String>,
+    sender_comp_id: String,
+    target_comp_id: String,
+    address: String,
+    heart_bt_int: Option<i64>,
+    #[serde(default)]
+    reset_on_logon: bool,
+    schedule: Option<RawSchedule>,
+    reconnect: Option<RawReconnect>,
+    tls: Option<RawInitiatorTls>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAcceptorTls {
+    cert_file: PathBuf,
+    key_file: PathBuf,
+    client_ca_file: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAcceptorSession {
+    fix_version: String,
+    target_comp_id: String,
+    sender_sub_id: Option<String>,
+    target_sub_id: Option<String>,
+    heart_bt_int: Option<i64>,
+    schedule: Option<RawSchedule>,
+    seq_num_store: Option<PathBuf>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RawAcceptor {
+    sender_comp_id: String,
+    address: String,
+    schedule: Option<RawSchedule>,
+    tls: Option<RawAcceptorTls>,
+    #[serde(default, rename = "session")]
+    sessions: Vec<RawAcceptorSession>,
+}
+
+impl RawConfig {
+    fn validate(self) -> Result<EngineConfig, ConfigError> {
+        let max_message_size = self
+            .engine
+            .max_message_size
+            .unwrap_or(DEFAULT_MAX_MESSAGE_SIZE);
+        if max_message_size == 0 {
+            return Err(invalid("engine.max_message_size", "must be greater than 0"));
+        }
+
+        Ok(EngineConfig {
+            max_message_size,
+            seq_num_store: self.engine.seq_num_store,
+            message_store: self.engine.message_store,
+            initiators: self
+                .initiators
+                .into_iter()
+                .enumerate()
+                .map(|(index, initiator)| initiator.validate(&format!("initiator[{}]", index)))
+                .collect::<Result<_, _>>()?,
+            acceptors: self
+                .acceptors
+                .into_iter()
+                .enumerate()
+                .map(|(index, acceptor)| acceptor.validate(&format!("acceptor[{}]", index)))
+                .collect::<Result<_, _>>()?,
+        })
+    }
+}
+
+impl RawSchedule {
+    fn validate(&self, location: &str) -> Result<SessionSchedule, ConfigError> {
+        fn setting<'a>(
+            location: &str,
+            name: &str,
+            value: &'a Option<String>,
+        ) -> Option<(String, &'a str)> {
+            value
+                .as_ref()
+                .map(|value| (format!("{}.{}", location, name), &value[..]))
+        }
+        let settings = ScheduleSettings {
+            start_time: Some((format!("{}.start_time", location), &self.start_time[..])),
+            end_time: Some((format!("{}.end_time", location), &self.end_time[..])),
+            start_day: setting(location, "start_day", &self.start_day),
+            end_day: setting(location, "end_day", &self.end_day),
+            time_zone: setting(location, "time_zone", &self.time_zone),
+            reset_time: setting(location, "reset_time", &self.reset_time),
+            reset_day: setting(location, "reset_day", &self.reset_day),
+        };
+
+        Ok(settings
+            .validate(location)?
+            .expect("start and end times are always set"))
+    }
+}
+
+fn validate_schedule(
+    location: &str,
+    schedule: &Option<RawSchedule>,
+) -> Result<Option<SessionSchedule>, ConfigError> {
+    schedule
+        .as_ref()
+        .map(|schedule| schedule.validate(&format!("{}.schedule", location)))
+        .transpose()
+}
+
+impl RawInitiator {
+    fn validate(self, location: &str) -> Result<InitiatorConfig, ConfigError> {
+        let key = |name: &str| format!("{}.{}", location, name);
+
+        let fix_version = parse_fix_version(&key("fix_version"), &self.fix_version)?;
+        let default_message_version = match self.default_appl_ver_id {
+            Some(ref value) => parse_message_version(&key("default_appl_ver_id"), value)?,
+            None => fix_version.max_message_version(),
+        };
+
+        let reconnect_policy = match self.reconnect {
+            Some(ref reconnect) => {
+                let location = key("reconnect");
+                let key = |name: &str| format!("{}.{}", location, name);
+                if reconnect.initial_delay_ms == 0 {
+                    return Err(invalid(key("initial_delay_ms"), "must be greater than 0"));
+                }
+                if reconnect.max_delay_ms < reconnect.initial_delay_ms {
+                    return Err(invalid(
+                        key("max_delay_ms"),
+                        "must be greater than or equal to initial_delay_ms",
+                    ));
+                }
+
+                let mut reconnect_policy = ReconnectPolicy::new(
+                    Duration::from_millis(reconnect.initial_delay_ms),
+                    Duration::from_millis(reconnect.max_delay_ms),
+                );
+                if let Some(jitter) = reconnect.jitter {
+                    if !(0.0..=1.0).contains(&jitter) {
+                        return Err(invalid(key("jitter"), "must be between 0.0 and 1.0"));
+                    }
+                    reconnect_policy = reconnect_policy.with_jitter(jitter);
+                }
+                if let Some(max_attempts) = reconnect.max_attempts {
+                    reconnect_policy = reconnect_policy.with_max_attempts(max_attempts);
+                }
+                for (index, address) in reconnect.failover_addresses.iter().enumerate() {
+                    reconnect_policy = reconnect_policy.with_failover_address(parse_address(
+                        &format!("{}.failover_addresses[{}]", location, index),
+                        address,
+                    )?);
+                }
+                Some(reconnect_policy)
+            }
+            None => None,
+        };
+
+        let tls = match self.tls {
+            Some(tls) => {
+                let client_auth = match (tls.cert_file, tls.key_file) {
+                    (Some(cert_file), Some(key_file)) => Some((cert_file, key_file)),
+                    (None, None) => None,
+                    _ => {
+                        return Err(invalid(
+                            key("tls"),
+                            "cert_file and key_file must be set together",
+                        ))
+                    }
+                };
+                Some(InitiatorTlsConfig {
+                    ca_file: tls.ca_file,
+                    server_name: tls.server_name,
+                    client_auth,
+                })
+            }
+            None => None,
+        };
+        check_tls_supported(&key("tls"), tls.is_some())?;
+
+        Ok(InitiatorConfig {
+            fix_version,
+            default_message_version,
+            sender_comp_id: parse_comp_id(&key("sender_comp_id"), &self.sender_comp_id)?,
+            target_comp_id: parse_comp_id(&key("target_comp_id"), &self.target_comp_id)?,
+            address: parse_address(&key("address"), &self.address)?,
+            heart_bt_int: parse_heart_bt_int(
+                &key("heart_bt_int"),
+                self.heart_bt_int.unwrap_or(DEFAULT_HEART_BT_INT),
+            )?,
+            reset_on_logon: self.reset_on_logon,
+            schedule: validate_schedule(location, &self.schedule)?,
+            reconnect_policy,
+            tls,
+        })
+    }
+}
+
+impl RawAcceptor {
+    fn validate(self, location: &str) -> Result<AcceptorConfig, ConfigError> {
+        let key = |name: &str| format!("{}.{}", location, name);
+
+        let tls = self.tls.map(|tls| AcceptorTlsConfig {
+            cert_file: tls.cert_file,
+            key_file: tls.key_file,
+            client_ca_file: tls.client_ca_file,
+        });
+        check_tls_supported(&key("tls"), tls.is_some())?;
+
+        let sessions = self
+            .sessions
+            .into_iter()
+            .enumerate()
+            .map(|(index, session)| {
+                let location = format!("{}.session[{}]", location, index);
+                let key = |name: &str| format!("{}.{}", location, name);
+                Ok(AcceptorSessionConfig {
+                    fix_version: parse_fix_version(&key("fix_version"), &session.fix_version)?,
+                    target_comp_id: parse_comp_id(&key("target_comp_id"), &session.target_comp_id)?,
+                    sender_sub_id: session
+                        .sender_sub_id
+                        .map(|sub_id| parse_comp_id(&key("sender_sub_id"), &sub_id))
+                        .transpose()?,
+                    target_sub_id: session
+                        .target_sub_id
+                        .map(|sub_id| parse_comp_id(&key("target_sub_id"), &sub_id))
+                        .transpose()?,
+                    heart_bt_int: session
+                        .heart_bt_int
+                        .map(|heart_bt_int| parse_heart_bt_int(&key("heart_bt_int"), heart_bt_int))
+                        .transpose()?,
+                    schedule: validate_schedule(&location, &session.schedule)?,
+                    seq_num_store: session.seq_num_store,
+                })
+            })
+            .collect::<Result<_, ConfigError>>()?;
+
+        Ok(AcceptorConfig {
+            sender_comp_id: parse_comp_id(&key("sender_comp_id"), &self.sender_comp_id)?,
+            address: parse_address(&key("address"), &self.address)?,
+            schedule: validate_schedule(location, &self.schedule)?,
+            tls,
+            sessions,
+        })
+    }
+}
+
+#[cfg(feature = "tls")]
+fn check_tls_supported(_location: &str, _uses_tls: bool) -> Result<(), ConfigError> {
+    Ok(())
+}
+
+#[cfg(not(feature = "tls"))]
+fn check_tls_supported(location: &str, uses_tls: bool) -> Result<(), ConfigError> {
+    if uses_tls {
+        return Err(invalid(
+            location,
+            "TLS requires fix-rs to be built with the tls feature",
+        ));
+    }
+
+    Ok(())
+}
+
+//A [SESSION] from a QuickFIX settings file with [DEFAULT] already merged in. Each setting keeps
+//the line it came from.
+struct QuickFIXSession {
+    line: usize,
+    settings: HashMap<String, (usize, String)>,
+}
+
+fn parse_quickfix_sections(settings: &str) -> Result<Vec<QuickFIXSession>, ConfigError> {
+    let mut defaults: HashMap<String, (usize, String)> = HashMap::new();
+    let mut sessions: Vec<QuickFIXSession> = Vec::new();
+    let mut in_default = false;
+    let mut seen_section = false;
+
+    for (index, line) in settings.lines().enumerate() {
+        let line_number = index + 1;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
+
+        if line.starts_with('[') && line.ends_with(']') {
+            let name = line[1..line.len() - 1].trim();
+            if name.eq_ignore_ascii_case("DEFAULT") {
+                in_default = true;
+            } else if name.eq_ignore_ascii_case("SESSION") {
+                in_default = false;
+                sessions.push(QuickFIXSession {
+                    line: line_number,
+                    settings: HashMap::new(),
+                });
+            } else {
+                return Err(invalid(
+                    format!("line {}", line_number),
+                    format!(
+                        "unknown section [{}], expected [DEFAULT] or [SESSION]",
+                        name
+                    ),
+                ));
+            }
+            seen_section = true;
+            continue;
+        }
+
+        let (key, value) = match line.find('=') {
+            Some(position) => (line[..position].trim(), line[position + 1..].trim()),
+            None => {
+                return Err(invalid(
+                    format!("line {}", line_number),
+                    format!("expected Key=Value but found \"{}\"", line),
+                ))
+            }
+        };
+        if !seen_section {
+            return Err(invalid(
+                format!("line {}", line_number),
+                format!("{} appears before [DEFAULT] or [SESSION]", key),
+            ));
+        }
+
+        let setting = (String::from(key), (line_number, String::from(value)));
+        if in_default {
+            defaults.insert(setting.0, setting.1);
+        } else {
+            sessions
+                .last_mut()
+                .unwrap()
+                .settings
+                .insert(setting.0, setting.1);
+        }
+    }
+
+    //Settings in a [DEFAULT] section apply to every session no matter where it appears.
+    for session in &mut sessions {
+        for (key, value) in &defaults {
+            session
+                .settings
+                .entry(key.clone())
+                .or_insert_with(|| value.clone());
+        }
+    }
+
+    Ok(sessions)
+}
+
+impl QuickFIXSession {
+    fn location(&self, key: &str) -> String {
+        match self.settings.get(key) {
+            Some(&(line, _)) => format!("line {}: {}", line, key),
+            None => format!("line {}: [SESSION]", self.line),
+        }
+    }
+
+    fn get(&self, key: &str) -> Option<(String, &str)> {
+        self.settings
+            .get(key)
+            .map(|(_, value)| (self.location(key), &value[..]))
+    }
+
+    fn require(&self, key: &str) -> Result<(String, &str), ConfigError> {
+        self.get(key)
+            .ok_or_else(|| invalid(self.location(key), format!("{} is required", key)))
+    }
+
+    fn get_number<T: std::str::FromStr>(&self, key: &str) -> Result<Option<T>, ConfigError> {
+        self.get(key)
+            .map(|(location, value)| {
+                value.parse::<T>().map_err(|_| {
+                    invalid(
+                        location,
+                        format!("expected a number but found \"{}\"", value),
+                    )
+                })
+            })
+            .transpose()
+    }
+
+    fn get_bool(&self, key: &str) -> Result<bool, ConfigError> {
+        match self.get(key) {
+            Some((_, "Y")) => Ok(true),
+            Some((_, "N")) | None => Ok(false),
+            Some((location, value)) => Err(invalid(
+                location,
+                format!("expected Y or N but found \"{}\"", value),
+            )),
+        }
+    }
+
+    fn get_address(&self, host_key: &str, port_key: &str) -> Result<SocketAddr, ConfigError> {
+        let host = self
+            .get(host_key)
+            .map(|(_, host)| host)
+            .unwrap_or("0.0.0.0");
+        let port = self
+            .get_number::<u16>(port_key)?
+            .ok_or_else(|| invalid(self.location(port_key), format!("{} is required", port_key)))?;
+        let location = match self.get(host_key) {
+            Some((location, _)) => location,
+            None => self.location(port_key),
+        };
+        parse_address(&location, &format!("{}:{}", host, port))
+    }
+
+    fn import(self, config: &mut EngineConfig) -> Result<(), ConfigError> {
+        let (location, begin_string) = self.require("BeginString")?;
+        let fix_version = parse_fix_version(&location, begin_string)?;
+        let (location, sender_comp_id) = self.require("SenderCompID")?;
+        let sender_comp_id = parse_comp_id(&location, sender_comp_id)?;
+        let (location, target_comp_id) = self.require("TargetCompID")?;
+        let target_comp_id = parse_comp_id(&location, target_comp_id)?;
+        let heart_bt_int = match self.get_number::<i64>("HeartBtInt")? {
+            Some(heart_bt_int) => Some(parse_heart_bt_int(
+                &self.location("HeartBtInt"),
+                heart_bt_int,
+            )?),
+            None => None,
+        };
+
+        let schedule = ScheduleSettings {
+            start_time: self.get("StartTime"),
+            end_time: self.get("EndTime"),
+            start_day: self.get("StartDay"),
+            end_day: self.get("EndDay"),
+            time_zone: self.get("TimeZone"),
+            reset_time: None,
+            reset_day: None,
+        }
+        .validate(&format!("line {}: [SESSION]", self.line))?;
+
+        //The first FileStorePath is used by the whole Engine. Acceptor sessions stored somewhere
+        //else get their own SeqNumStore.
+        let file_store_path = self
+            .get("FileStorePath")
+            .map(|(_, path)| PathBuf::from(path));
+        let mut session_seq_num_store = None;
+        match (&config.seq_num_store, file_store_path) {
+            (None, Some(path)) => {
+                config.seq_num_store = Some(path.clone());
+                config.message_store = Some(path);
+            }
+            (Some(engine_path), Some(path)) if *engine_path != path => {
+                session_seq_num_store = Some(path);
+            }
+            _ => {}
+        }
+
+        let (location, connection_type) = self.require("ConnectionType")?;
+        match connection_type {
+            "initiator" => {
+                if session_seq_num_store.is_some() {
+                    return Err(invalid(
+                        self.location("FileStorePath"),
+                        "initiators must all use the same FileStorePath",
+                    ));
+                }
+
+                let default_message_version = match self.get("DefaultApplVerID") {
+                    Some((location, value)) => parse_message_version(&location, value)?,
+                    None => fix_version.max_message_version(),
+                };
+
+                let reconnect_interval = self
+                    .get_number::<u64>("ReconnectInterval")?
+                    .unwrap_or(DEFAULT_QUICKFIX_RECONNECT_INTERVAL_SECONDS);
+                if reconnect_interval == 0 {
+                    return Err(invalid(
+                        self.location("ReconnectInterval"),
+                        "must be greater than 0",
+                    ));
+                }
+                let reconnect_interval = Duration::from_secs(reconnect_interval);
+                let mut reconnect_policy =
+                    ReconnectPolicy::new(reconnect_interval, reconnect_interval);
+                for index in 1.. {
+                    let host_key = format!("SocketConnectHost{}", index);
+                    let port_key = format!("SocketConnectPort{}", index);
+                    if self.get(&host_key).is_none() && self.get(&port_key).is_none() {
+                        break;
+                    }
+                    reconnect_policy = reconnect_policy
+                        .with_failover_address(self.get_address(&host_key, &port_key)?);
+                }
+
+                let tls = match self.get("CertificationAuthoritiesFile") {
+                    Some((_, ca_file)) => {
+                        let client_auth = match (
+                            self.get("ClientCertificateFile"),
+                            self.get("ClientCertificateKeyFile"),
+                        ) {
+                            (Some((_, cert_file)), Some((_, key_file))) => {
+                                Some((PathBuf::from(cert_file), PathBuf::from(key_file)))
+                            }
+                            (None, None) => None,
+                            _ => {
+                                return Err(invalid(
+                                    format!("line {}: [SESSION]", self.line),
+                                    "ClientCertificateFile and ClientCertificateKeyFile must be set together",
+                                ))
+                            }
+                        };
+                        let (_, server_name) = self.require("SocketConnectHost")?;
+                        check_tls_supported(&self.location("CertificationAuthoritiesFile"), true)?;
+                        Some(InitiatorTlsConfig {
+                            ca_file: PathBuf::from(ca_file),
+                            server_name: String::from(server_name),
+                            client_auth,
+                        })
+                    }
+                    None => None,
+                };
+
+                config.initiators.push(InitiatorConfig {
+                    fix_version,
+                    default_message_version,
+                    sender_comp_id,
+                    target_comp_id,
+                    address: self.get_address("SocketConnectHost", "SocketConnectPort")?,
+                    heart_bt_int: heart_bt_int.unwrap_or(DEFAULT_HEART_BT_INT),
+                    reset_on_logon: self.get_bool("ResetOnLogon")?,
+                    schedule,
+                    reconnect_policy: Some(reconnect_policy),
+                    tls,
+                });
+            }
+            "acceptor" => {
+                let address = self.get_address("SocketAcceptHost", "SocketAcceptPort")?;
+                let tls = match (
+                    self.get("ServerCertificateFile"),
+                    self.get("ServerCertificateKeyFile"),
+                ) {
+                    (Some((location, cert_file)), Some((_, key_file))) => {
+                        check_tls_supported(&location, true)?;
+                        Some(AcceptorTlsConfig {
+                            cert_file: PathBuf::from(cert_file),
+                            key_file: PathBuf::from(key_file),
+                            client_ca_file: self
+                                .get("CertificationAuthoritiesFile")
+                                .map(|(_, path)| PathBuf::from(path)),
+                        })
+                    }
+                    (None, None) => None,
+                    _ => return Err(invalid(
+                        format!("line {}: [SESSION]", self.line),
+                        "ServerCertificateFile and ServerCertificateKeyFile must be set together",
+                    )),
+                };
+
+                let session = AcceptorSessionConfig {
+                    fix_version,
+                    target_comp_id,
+                    sender_sub_id: self
+                        .get("SenderSubID")
+                        .map(|(_, id)| id.as_bytes().to_vec()),
+                    target_sub_id: self
+                        .get("TargetSubID")
+                        .map(|(_, id)| id.as_bytes().to_vec()),
+                    heart_bt_int,
+                    schedule,
+                    seq_num_store: session_seq_num_store,
+                };
+
+                let acceptor = config.acceptors.iter_mut().find(|acceptor| {
+                    acceptor.address == address && acceptor.sender_comp_id == sender_comp_id
+                });
+                match acceptor {
+                    Some(acceptor) => {
+                        if acceptor.tls.is_some() != tls.is_some() {
+                            return Err(invalid(
+                                format!("line {}: [SESSION]", self.line),
+                                "sessions sharing a SocketAcceptPort must all use SSL or not",
+                            ));
+                        }
+                        acceptor.sessions.push(session);
+                    }
+                    None => config.acceptors.push(AcceptorConfig {
+                        sender_comp_id,
+                        address,
+                        schedule: None,
+                        tls,
+                        sessions: vec![session],
+                    }),
+                }
+            }
+            _ => {
+                return Err(invalid(
+                    location,
+                    format!(
+                        "expected initiator or acceptor but found \"{}\"",
+                        connection_type
+                    ),
+                ))
+            }
+        }
+
+        Ok(())
+    }
+}
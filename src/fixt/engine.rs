@@ -11,28 +11,46 @@
 
 #![allow(deprecated)]
 
-use mio::channel::{channel, Receiver, Sender};
-use mio::tcp::TcpListener;
+use chrono::{DateTime, Utc};
+use mio::channel::{channel, Receiver, SendError, Sender};
 use mio::{Events, Poll, PollOpt, Ready, Token};
-use std::collections::HashMap;
+use std::cmp;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::io;
-use std::mem;
 use std::net::{SocketAddr, ToSocketAddrs};
 use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering};
 use std::sync::mpsc::TryRecvError;
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::{Duration, Instant};
 
 use crate::dictionary::messages::Logon;
+use crate::dictionary::CloneDictionary;
 use crate::fix::ParseError;
 use crate::fix_version::FIXVersion;
+use crate::fixt::acceptor_session::AcceptorSession;
+use crate::fixt::clock::Clock;
 use crate::fixt::engine_thread::{
     internal_engine_thread, InternalEngineToThreadEvent, BASE_CONNECTION_TOKEN,
     CONNECTION_COUNT_MAX, INTERNAL_ENGINE_EVENT_TOKEN,
 };
+use crate::fixt::logon_auth::{LogonAuthenticator, LogonDecorator};
 use crate::fixt::message::{BuildFIXTMessage, FIXTMessage};
+use crate::fixt::message_log::{MessageDirection, MessageLog};
+use crate::fixt::message_store::{MessageStore, MessageStoreFactory};
+use crate::fixt::reconnect_policy::ReconnectPolicy;
+use crate::fixt::seq_num_store::SeqNumStore;
+use crate::fixt::session_config::{ConnectionConfig, ListenerConfig};
+use crate::fixt::session_info::{SessionInfo, SessionInfoRegistry, SessionState};
+use crate::fixt::session_options::SessionOptions;
+use crate::fixt::session_schedule::SessionSchedule;
+use crate::fixt::stats::{EngineStats, StatsRegistry};
+use crate::fixt::throttle::Throttle;
+#[cfg(feature = "tls")]
+use crate::fixt::tls::{TlsAcceptor, TlsConnector};
+use crate::fixt::transport::{TransportAddr, TransportListener};
 use crate::message_version::MessageVersion;
 use crate::token_generator::TokenGenerator;
 
@@ -47,6 +65,130 @@ impl fmt::Display for Connection {
     }
 }
 
+//Returned when a message is queued to be sent so the EngineEvent::MessageSent,
+//EngineEvent::MessageNotSent, or EngineEvent::MessageRefused about it can be matched up later.
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct MessageId(pub u64);
+
+impl fmt::Display for MessageId {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+//Hands out MessageIds that are unique for the life of an Engine, no matter which handle queued
+//the message.
+#[derive(Clone, Default)]
+pub(crate) struct MessageIdGenerator(Arc<AtomicU64>);
+
+impl MessageIdGenerator {
+    pub fn create(&self) -> MessageId {
+        MessageId(self.0.fetch_add(1, Ordering::Relaxed) + 1)
+    }
+}
+
+//How connections and listeners added to an Engine are spread across its worker threads.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum WorkerPolicy {
+    RoundRobin,    //Each one goes to the next worker thread in turn.
+    Pinned(usize), //Every one goes to this worker thread. Wraps around past the last worker thread.
+}
+
+//Worker thread that owns each connection and listener.
+pub(crate) type WorkerAssignments = Arc<Mutex<HashMap<Token, usize>>>;
+
+//Sends events to whichever worker thread owns the connection or listener they're about.
+#[derive(Clone)]
+pub(crate) struct WorkerSenders {
+    senders: Vec<Sender<InternalEngineToThreadEvent>>,
+    assignments: WorkerAssignments,
+}
+
+impl WorkerSenders {
+    //Events about an unknown connection go to the first worker thread which treats them the same
+    //as any other invalid connection.
+    #[allow(clippy::result_large_err)]
+    pub fn send(
+        &self,
+        event: InternalEngineToThreadEvent,
+    ) -> Result<(), SendError<InternalEngineToThreadEvent>> {
+        let worker = event
+            .token()
+            .and_then(|token| self.worker(token))
+            .unwrap_or(0);
+        self.senders[worker].send(event)
+    }
+
+    //Send an event built by make_event to every worker thread.
+    fn send_all<F: FnMut() -> InternalEngineToThreadEvent>(&self, mut make_event: F) {
+        for sender in &self.senders {
+            sender.send(make_event()).unwrap();
+        }
+    }
+
+    fn worker(&self, token: Token) -> Option<usize> {
+        self.assignments.lock().unwrap().get(&token).cloned()
+    }
+
+    fn assign(&self, token: Token, worker: usize) {
+        self.assignments.lock().unwrap().insert(token, worker);
+    }
+}
+
+//Lets every worker thread use the same MessageStoreFactory, SeqNumStore, MessageLog, or
+//LogonAuthenticator. Only one worker thread can use it at a time so the *_per_worker() setters
+//are preferred when there's more than one worker thread.
+struct Shared<T: ?Sized>(Arc<Mutex<Box<T>>>);
+
+impl<T: ?Sized> Shared<T> {
+    fn new(value: Box<T>) -> Shared<T> {
+        Shared(Arc::new(Mutex::new(value)))
+    }
+}
+
+impl<T: ?Sized> Clone for Shared<T> {
+    fn clone(&self) -> Self {
+        Shared(self.0.clone())
+    }
+}
+
+impl MessageStoreFactory for Shared<dyn MessageStoreFactory + Send> {
+    fn create(&mut self, session_id: &SessionID) -> io::Result<Box<dyn MessageStore + Send>> {
+        self.0.lock().unwrap().create(session_id)
+    }
+}
+
+impl SeqNumStore for Shared<dyn SeqNumStore + Send> {
+    fn load(&mut self, session_id: &SessionID) -> io::Result<Option<MsgSeqNums>> {
+        self.0.lock().unwrap().load(session_id)
+    }
+
+    fn save(&mut self, session_id: &SessionID, msg_seq_nums: MsgSeqNums) -> io::Result<()> {
+        self.0.lock().unwrap().save(session_id, msg_seq_nums)
+    }
+}
+
+impl MessageLog for Shared<dyn MessageLog + Send> {
+    fn log(
+        &mut self,
+        session_id: &SessionID,
+        direction: MessageDirection,
+        timestamp: &DateTime<Utc>,
+        bytes: &[u8],
+    ) -> io::Result<()> {
+        self.0
+            .lock()
+            .unwrap()
+            .log(session_id, direction, timestamp, bytes)
+    }
+}
+
+impl LogonAuthenticator for Shared<dyn LogonAuthenticator + Send> {
+    fn authenticate(&mut self, session_id: &SessionID, logon: &Logon) -> Result<(), Vec<u8>> {
+        self.0.lock().unwrap().authenticate(session_id, logon)
+    }
+}
+
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct Listener(pub usize);
 
@@ -56,6 +198,80 @@ impl fmt::Display for Listener {
     }
 }
 
+//Uniquely identifies a FIX session independent of the Connection currently carrying it. This is
+//what persisted session state is keyed by.
+#[derive(Clone, Debug, Eq, Hash, PartialEq)]
+pub struct SessionID {
+    pub fix_version: FIXVersion,
+    pub sender_comp_id: Vec<u8>,
+    pub target_comp_id: Vec<u8>,
+}
+
+impl SessionID {
+    pub fn new(fix_version: FIXVersion, sender_comp_id: &[u8], target_comp_id: &[u8]) -> SessionID {
+        SessionID {
+            fix_version,
+            sender_comp_id: sender_comp_id.to_vec(),
+            target_comp_id: target_comp_id.to_vec(),
+        }
+    }
+
+    //A file system safe name for the session. Used by the file backed stores.
+    pub fn file_stem(&self) -> String {
+        fn sanitize(bytes: &[u8]) -> String {
+            bytes
+                .iter()
+                .map(|b| {
+                    if b.is_ascii_alphanumeric() || *b == b'.' || *b == b'_' {
+                        *b as char
+                    } else {
+                        '_'
+                    }
+                })
+                .collect()
+        }
+
+        format!(
+            "{}-{}-{}",
+            sanitize(self.fix_version.begin_string()),
+            sanitize(&self.sender_comp_id[..]),
+            sanitize(&self.target_comp_id[..])
+        )
+    }
+}
+
+impl fmt::Display for SessionID {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}->{}",
+            String::from_utf8_lossy(self.fix_version.begin_string()),
+            String::from_utf8_lossy(&self.sender_comp_id[..]),
+            String::from_utf8_lossy(&self.target_comp_id[..])
+        )
+    }
+}
+
+//Next MsgSeqNum expected to be received and next MsgSeqNum to be sent for a session. Both start
+//at 1 for a brand new session. FIXT v1.1, page 5.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MsgSeqNums {
+    pub inbound: u64,
+    pub outbound: u64,
+}
+
+impl MsgSeqNums {
+    pub fn new(inbound: u64, outbound: u64) -> MsgSeqNums {
+        MsgSeqNums { inbound, outbound }
+    }
+}
+
+impl Default for MsgSeqNums {
+    fn default() -> Self {
+        MsgSeqNums::new(1, 1)
+    }
+}
+
 pub enum ConnectionTerminatedReason {
     BeginStrWrongError {
         received: FIXVersion,
@@ -65,21 +281,37 @@ pub enum ConnectionTerminatedReason {
     InboundMsgSeqNumLowerThanExpectedError,
     InboundResendRequestLoopError,
     LocalRequested,
+    LogonAuthenticationError,
+    LogonHeartBtIntMismatchError,
     LogonHeartBtIntNegativeError,
+    LogonHeartBtIntOutOfRangeError,
     LogonParseError(ParseError),
     LogonNeverReceivedError,
+    LogonNextExpectedMsgSeqNumTooHighError,
     LogonNotFirstMessageError,
+    LogonOutsideScheduleError,
     LogonRejectedError,
+    LogonUnknownSessionError,
     LogoutNoHangUpError,
     LogoutNoResponseError,
+    MessageLogError(io::Error),
+    MessageStoreError(io::Error),
     OutboundMsgSeqNumMaxExceededError,
+    OutboundResendRequestLoopError {
+        range: Range<u64>,
+        attempts: u64,
+    },
     RemoteRequested,
     SenderCompIDWrongError,
+    SeqNumStoreError(io::Error),
+    SocketConnectError(io::Error),
     SocketNotWritableTimeoutError,
     SocketReadError(io::Error),
     SocketWriteError(io::Error),
     TargetCompIDWrongError,
     TestRequestNotRespondedError,
+    TlsCertificateError(io::Error),
+    TlsHandshakeError(io::Error),
 }
 
 impl fmt::Debug for ConnectionTerminatedReason {
@@ -94,42 +326,84 @@ impl fmt::Debug for ConnectionTerminatedReason {
             ConnectionTerminatedReason::InboundMsgSeqNumLowerThanExpectedError => write!(f,"Received message with lower MsgSeqNum than expected."),
             ConnectionTerminatedReason::InboundResendRequestLoopError => write!(f,"Received too many ResendRequests with the same BeginSeqNo."),
             ConnectionTerminatedReason::LocalRequested => write!(f,"Local requested logout and it was performed cleanly."),
+            ConnectionTerminatedReason::LogonAuthenticationError => write!(f,"Remote's logon was refused by the LogonAuthenticator."),
+            ConnectionTerminatedReason::LogonHeartBtIntMismatchError => write!(f,"Remote's logon HeartBtInt did not match the session's."),
             ConnectionTerminatedReason::LogonHeartBtIntNegativeError => write!(f,"Response to logon included negative HeartBtInt."),
+            ConnectionTerminatedReason::LogonHeartBtIntOutOfRangeError => write!(f,"Remote's logon HeartBtInt was outside of the range allowed by the session's options."),
             ConnectionTerminatedReason::LogonParseError(_) => write!(f,"Could not parse logon response."), //Did you connect to a server not running a FIX engine?
             ConnectionTerminatedReason::LogonNeverReceivedError => write!(f,"Never received logon from new connection."),
+            ConnectionTerminatedReason::LogonNextExpectedMsgSeqNumTooHighError => write!(f,"Remote's logon expected a higher MsgSeqNum than was ever sent."),
             ConnectionTerminatedReason::LogonNotFirstMessageError => write!(f,"Remote responded to logon with a non-logon message."),
+            ConnectionTerminatedReason::LogonOutsideScheduleError => write!(f,"Remote attempted to logon outside of the session's schedule."),
             ConnectionTerminatedReason::LogonRejectedError => write!(f,"Remote rejected logon for arbitrary reason."),
+            ConnectionTerminatedReason::LogonUnknownSessionError => write!(f,"Remote attempted to logon as a session the listener does not know about."),
             ConnectionTerminatedReason::LogoutNoHangUpError => write!(f,"Remote requested logout but did not close socket after response."),
             ConnectionTerminatedReason::LogoutNoResponseError => write!(f,"Local requested logout but remote did not respond within a reasonable amount of time."),
+            ConnectionTerminatedReason::MessageLogError(ref error) => write!(f,"Message log could not be written to: {}",error),
+            ConnectionTerminatedReason::MessageStoreError(ref error) => write!(f,"Message store could not be opened or written to: {}",error),
             ConnectionTerminatedReason::OutboundMsgSeqNumMaxExceededError => write!(f,"Expected outbound MsgSeqNum exceeded maximum allowed."),
+            ConnectionTerminatedReason::OutboundResendRequestLoopError{ref range,attempts} => write!(f,"Remote answered all {} ResendRequests for MsgSeqNums {:?} with garbled messages.",attempts,range),
             ConnectionTerminatedReason::RemoteRequested => write!(f,"Remote requested logout and it was performed cleanly."),
             ConnectionTerminatedReason::SenderCompIDWrongError => write!(f,"Received message with SenderCompID not matching the expected value."),
+            ConnectionTerminatedReason::SeqNumStoreError(ref error) => write!(f,"Sequence number store could not be read from or written to: {}",error),
+            ConnectionTerminatedReason::SocketConnectError(ref error) => write!(f,"Socket could not connect: {}",error),
             ConnectionTerminatedReason::SocketNotWritableTimeoutError => write!(f,"Socket returned WouldBlock on write for an unreasonable amount of time."),
             ConnectionTerminatedReason::SocketReadError(ref error) => write!(f,"Socket could not be read from: {}",error),
             ConnectionTerminatedReason::SocketWriteError(ref error) => write!(f,"Socket could not be written to: {}",error),
             ConnectionTerminatedReason::TargetCompIDWrongError => write!(f,"Received message with TargetCompID not matching the expected value."),
             ConnectionTerminatedReason::TestRequestNotRespondedError => write!(f,"TestRequest message not responded with Heartbeat message within a reasonable amount of time."),
+            ConnectionTerminatedReason::TlsCertificateError(ref error) => write!(f,"TLS certificate was rejected: {}",error),
+            ConnectionTerminatedReason::TlsHandshakeError(ref error) => write!(f,"TLS handshake failed: {}",error),
         }
     }
 }
 
+//Why a message was never sent. The limits are negotiated by the remote's Logon.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum MessageRefusedReason {
+    MaxMessageSizeExceeded {
+        message_size: u64,
+        max_message_size: u64,
+    },
+    MsgTypeNotSupported,
+    Throttled(Duration), //Throttle with ThrottleAction::Reject was over its limit. Includes how long until it's not.
+    Vetoed,              //Outbound message hook returned false.
+}
+
+//Called by the engine's thread right before every outbound message is serialized. The message can
+//be modified in place or dropped by returning false.
+pub type OutboundMessageHook = Box<dyn FnMut(Connection, &mut dyn FIXTMessage) -> bool + Send>;
+
 pub enum EngineEvent {
     ConnectionFailed(Connection, io::Error), //Could not setup connection.
     ConnectionSucceeded(Connection),         //Connection completed and ready to begin logon.
-    ConnectionTerminated(Connection, ConnectionTerminatedReason), //Connection ended for ConnectionTerminatedReason reason.
+    ConnectionTerminated(Connection, ConnectionTerminatedReason, MsgSeqNums), //Connection ended for ConnectionTerminatedReason reason. MsgSeqNums can be used to resume the session later.
     ConnectionDropped(Listener, SocketAddr), //Connection was dropped by listener because of a lock of resources.
     ConnectionAccepted(Listener, Connection, SocketAddr), //Listener accepted a new connection and is awaiting a Logon message.
     ConnectionLoggingOn(Listener, Connection, Box<Logon>),
     SessionEstablished(Connection), //Connection completed logon process successfully.
-    ListenerFailed(Listener, io::Error), //Could not setup listener.
+    SessionStateChanged(Connection, SessionState, SessionState), //Connection moved from the first SessionState to the second.
+    SessionEnded(Connection, ConnectionTerminatedReason, MsgSeqNums), //Scheduled or reconnecting session ended but Connection remains valid and will logon again when the SessionSchedule or ReconnectPolicy allows.
+    Reconnecting(Connection, u32, Duration), //Connection will try to connect again after delay. Includes the attempt number starting at 1.
+    ListenerFailed(Listener, io::Error),     //Could not setup listener.
     ListenerAcceptFailed(Listener, io::Error), //Could not accept a connection with listener.
     MessageReceived(Connection, Box<dyn FIXTMessage + Send>), //New valid message was received.
     MessageReceivedGarbled(Connection, ParseError), //New message could not be parsed correctly. (If not garbled (FIXT 1.1, page 40), a Reject will be issued first)
     MessageReceivedDuplicate(Connection, Box<dyn FIXTMessage + Send>), //Message with MsgSeqNum already seen was received.
     MessageRejected(Connection, Box<dyn FIXTMessage + Send>), //New message breaks session rules and was rejected.
+    MessageRefused(
+        Connection,
+        Option<MessageId>,
+        Box<dyn FIXTMessage + Send>,
+        MessageRefusedReason,
+    ), //Outbound message breaks MaxMessageSize or NoMsgTypes limits, was over the Throttle's limit, or was vetoed by the OutboundMessageHook, and was never sent. MessageId is only set when a MessageSent or MessageNotSent would have been emitted for it instead.
+    MessageSent(Connection, MessageId, u64, DateTime<Utc>), //Message was completely written to the socket using MsgSeqNum and SendingTime. This does not mean the remote has received it yet.
+    MessageNotSent(Connection, MessageId, Box<dyn FIXTMessage + Send>), //Message was dropped before being written because the connection started logging out, was terminated, or doesn't exist.
     ResendRequested(Connection, Range<u64>), //Range of messages by MsgSeqNum that are requested to be resent. [Range::start,Range::end)
+    ResendRequestRetried(Connection, Range<u64>, u64), //Range of messages by MsgSeqNum that are being requested from the remote again because the last request was answered with garbled messages. Includes the attempt number starting at 2. [Range::start,Range::end)
     SequenceResetResetHasNoEffect(Connection),
     SequenceResetResetInThePast(Connection),
+    Throttled(Connection, Duration), //Application messages are being held back by the Connection's Throttle. Includes how long until the next one is sent.
     FatalError(&'static str, io::Error), //A critical error has occurred. No more events can be received and no more messages will be sent.
 }
 
@@ -144,10 +418,10 @@ impl fmt::Debug for EngineEvent {
             EngineEvent::ConnectionSucceeded(connection) => {
                 write!(f, "EngineEvent::ConnectionSucceeded({:?})", connection)
             }
-            EngineEvent::ConnectionTerminated(connection, ref reason) => write!(
+            EngineEvent::ConnectionTerminated(connection, ref reason, msg_seq_nums) => write!(
                 f,
-                "EngineEvent::ConnectionTerminated({:?},{:?})",
-                connection, reason
+                "EngineEvent::ConnectionTerminated({:?},{:?},{:?})",
+                connection, reason, msg_seq_nums
             ),
             EngineEvent::ConnectionDropped(connection, addr) => write!(
                 f,
@@ -167,6 +441,16 @@ impl fmt::Debug for EngineEvent {
             EngineEvent::SessionEstablished(connection) => {
                 write!(f, "EngineEvent::SessionEstablished({:?})", connection)
             }
+            EngineEvent::SessionEnded(connection, ref reason, msg_seq_nums) => write!(
+                f,
+                "EngineEvent::SessionEnded({:?},{:?},{:?})",
+                connection, reason, msg_seq_nums
+            ),
+            EngineEvent::Reconnecting(connection, attempt, delay) => write!(
+                f,
+                "EngineEvent::Reconnecting({:?},{},{:?})",
+                connection, attempt, delay
+            ),
             EngineEvent::ListenerFailed(listener, ref error) => {
                 write!(f, "EngineEvent::ListenerFailed({:?},{:?})", listener, error)
             }
@@ -195,11 +479,40 @@ impl fmt::Debug for EngineEvent {
                 "EngineEvent::MessageRejected({:?},{:?})",
                 connection, message
             ),
+            EngineEvent::MessageRefused(connection, message_id, ref message, ref reason) => {
+                write!(
+                    f,
+                    "EngineEvent::MessageRefused({:?},{:?},{:?},{:?})",
+                    connection, message_id, message, reason
+                )
+            }
+            EngineEvent::SessionStateChanged(connection, old_state, new_state) => write!(
+                f,
+                "EngineEvent::SessionStateChanged({:?},{:?},{:?})",
+                connection, old_state, new_state
+            ),
+            EngineEvent::MessageSent(connection, message_id, msg_seq_num, ref sending_time) => {
+                write!(
+                    f,
+                    "EngineEvent::MessageSent({:?},{:?},{},{:?})",
+                    connection, message_id, msg_seq_num, sending_time
+                )
+            }
+            EngineEvent::MessageNotSent(connection, message_id, ref message) => write!(
+                f,
+                "EngineEvent::MessageNotSent({:?},{:?},{:?})",
+                connection, message_id, message
+            ),
             EngineEvent::ResendRequested(connection, ref range) => write!(
                 f,
                 "EngineEvent::ResendRequested({:?},{:?})",
                 connection, range
             ),
+            EngineEvent::ResendRequestRetried(connection, ref range, attempt) => write!(
+                f,
+                "EngineEvent::ResendRequestRetried({:?},{:?},{})",
+                connection, range, attempt
+            ),
             EngineEvent::SequenceResetResetHasNoEffect(connection) => write!(
                 f,
                 "EngineEvent:SequenceResetResetHasNoEffect({:?})",
@@ -210,6 +523,9 @@ impl fmt::Debug for EngineEvent {
                 "EngineEvent:SequenceResetResetInThePast({:?})",
                 connection
             ),
+            EngineEvent::Throttled(connection, duration) => {
+                write!(f, "EngineEvent::Throttled({:?},{:?})", connection, duration)
+            }
             EngineEvent::FatalError(description, ref error) => {
                 write!(f, "EngineEvent::FatalError({:?},{:?})", description, error)
             }
@@ -222,6 +538,28 @@ pub enum ResendResponse {
     Gap(Range<u64>),
 }
 
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ResendRequestHandling {
+    Automatic,   //Engine responds to ResendRequests using the connection's MessageStore.
+    Application, //Engine emits EngineEvent::ResendRequested and waits for send_resend_response().
+}
+
+fn force_message_version(
+    fix_version: FIXVersion,
+    default_message_version: MessageVersion,
+) -> MessageVersion {
+    //Force older FIX versions that don't support message versioning to use their respective
+    //message versions.
+    match fix_version {
+        FIXVersion::FIX_4_0 => MessageVersion::FIX40,
+        FIXVersion::FIX_4_1 => MessageVersion::FIX41,
+        FIXVersion::FIX_4_2 => MessageVersion::FIX42,
+        FIXVersion::FIX_4_3 => MessageVersion::FIX43,
+        FIXVersion::FIX_4_4 => MessageVersion::FIX44,
+        FIXVersion::FIXT_1_1 => default_message_version,
+    }
+}
+
 fn to_socket_addr<A: ToSocketAddrs>(address: A) -> Option<SocketAddr> {
     //Use first socket address. This more or less emulates TcpStream::connect.
     match address.to_socket_addrs() {
@@ -230,18 +568,116 @@ fn to_socket_addr<A: ToSocketAddrs>(address: A) -> Option<SocketAddr> {
     }
 }
 
-pub struct Engine {
+//Receiving end of the events sent by the internal engine thread.
+pub(crate) struct EngineEventReceiver {
     token_generator: Arc<Mutex<TokenGenerator>>,
-    tx: Sender<InternalEngineToThreadEvent>,
+    worker_assignments: WorkerAssignments,
     rx: Receiver<EngineEvent>,
     poll: Poll,
-    thread_handle: Option<thread::JoinHandle<()>>,
+}
+
+impl EngineEventReceiver {
+    fn poll(&mut self, duration: Option<Duration>) -> Option<EngineEvent> {
+        if let Ok(event) = self.rx.try_recv() {
+            self.on_event(&event);
+            return Some(event);
+        }
+
+        if let Some(poll_duration) = duration {
+            let now = Instant::now(); //Watch time manually because Mio's poll::poll() can wake immediatelly and we'll have no idea how long has elapsed.
+
+            while let Some(poll_duration) = poll_duration.checked_sub(now.elapsed()) {
+                let mut events = Events::with_capacity(1);
+                if self.poll.poll(&mut events, Some(poll_duration)).is_err() {
+                    return None;
+                }
+
+                let result = self.rx.try_recv();
+                match result {
+                    Ok(event) => {
+                        self.on_event(&event);
+                        return Some(event);
+                    }
+                    Err(e) if e == TryRecvError::Disconnected => return None,
+                    _ => {}
+                }
+            }
+        }
+
+        None
+    }
+
+    //Wait as long as it takes for the next event. Returns None once the internal engine thread
+    //has stopped. Unlike poll(), the Connection of a ConnectionFailed or ConnectionTerminated
+    //event can't be reused until the event is passed to release().
+    #[cfg(feature = "async")]
+    pub fn recv(&mut self) -> Option<EngineEvent> {
+        loop {
+            match self.rx.try_recv() {
+                Ok(event) => return Some(event),
+                Err(TryRecvError::Disconnected) => return None,
+                Err(TryRecvError::Empty) => {}
+            }
+
+            let mut events = Events::with_capacity(1);
+            if self.poll.poll(&mut events, None).is_err() {
+                return None;
+            }
+        }
+    }
+
+    #[cfg(feature = "async")]
+    pub fn release(&mut self, event: &EngineEvent) {
+        self.on_event(event);
+    }
+
+    fn on_event(&mut self, event: &EngineEvent) {
+        match *event {
+            EngineEvent::ConnectionFailed(connection, _)
+            | EngineEvent::ConnectionTerminated(connection, _, _) => {
+                self.token_generator
+                    .lock()
+                    .unwrap()
+                    .remove(Token(connection.0));
+                self.worker_assignments
+                    .lock()
+                    .unwrap()
+                    .remove(&Token(connection.0));
+            }
+            _ => {}
+        }
+    }
+}
+
+pub struct Engine {
+    token_generator: Arc<Mutex<TokenGenerator>>,
+    stats: StatsRegistry,
+    session_infos: SessionInfoRegistry,
+    message_ids: MessageIdGenerator,
+    workers: WorkerSenders,
+    worker_policy: WorkerPolicy,
+    next_worker: usize,
+    listeners: HashSet<Listener>,
+    events: Option<EngineEventReceiver>,
+    thread_handles: Vec<thread::JoinHandle<()>>,
 }
 
 impl Engine {
     pub fn new(
         message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
         max_message_size: u64,
+    ) -> Result<Engine, io::Error> {
+        Engine::new_with_worker_threads(message_dictionary, max_message_size, 1)
+    }
+
+    //Same as new() but connections are spread across worker_threads threads that each read,
+    //parse, and write messages for their own connections. This keeps a busy session from
+    //delaying the others. Everything is still done through this Engine and all events come out
+    //of poll() like usual. See set_worker_policy().
+    pub fn new_with_worker_threads(
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+        max_message_size: u64,
+        worker_threads: usize,
     ) -> Result<Engine, io::Error> {
         let engine_poll = Poll::new()?;
         let (thread_to_engine_tx, thread_to_engine_rx) = channel::<EngineEvent>();
@@ -252,82 +688,347 @@ impl Engine {
             PollOpt::level(),
         )?;
 
-        let poll = Poll::new()?;
-        let (engine_to_thread_tx, engine_to_thread_rx) = channel::<InternalEngineToThreadEvent>();
-        poll.register(
-            &engine_to_thread_rx,
-            INTERNAL_ENGINE_EVENT_TOKEN,
-            Ready::readable(),
-            PollOpt::level(),
-        )?;
+        //Setup everything that can fail before any threads are started.
+        let mut workers = Vec::new();
+        for _ in 0..cmp::max(worker_threads, 1) {
+            let poll = Poll::new()?;
+            let (engine_to_thread_tx, engine_to_thread_rx) =
+                channel::<InternalEngineToThreadEvent>();
+            poll.register(
+                &engine_to_thread_rx,
+                INTERNAL_ENGINE_EVENT_TOKEN,
+                Ready::readable(),
+                PollOpt::level(),
+            )?;
+            workers.push((poll, engine_to_thread_tx, engine_to_thread_rx));
+        }
 
         let token_generator = Arc::new(Mutex::new(TokenGenerator::new(
             BASE_CONNECTION_TOKEN.0,
             Some(CONNECTION_COUNT_MAX - BASE_CONNECTION_TOKEN.0),
         )));
+        let stats = StatsRegistry::default();
+        let session_infos = SessionInfoRegistry::default();
+        let worker_assignments = WorkerAssignments::default();
 
-        Ok(Engine {
-            token_generator: token_generator.clone(),
-            tx: engine_to_thread_tx,
-            rx: thread_to_engine_rx,
-            poll: engine_poll,
-            thread_handle: Some(thread::spawn(move || {
+        let mut senders = Vec::new();
+        let mut thread_handles = Vec::new();
+        for (worker, (poll, engine_to_thread_tx, engine_to_thread_rx)) in
+            workers.into_iter().enumerate()
+        {
+            let token_generator = token_generator.clone();
+            let worker_assignments = worker_assignments.clone();
+            let stats = stats.clone();
+            let session_infos = session_infos.clone();
+            let thread_to_engine_tx = thread_to_engine_tx.clone();
+            let message_dictionary = message_dictionary.clone();
+            thread_handles.push(thread::spawn(move || {
                 internal_engine_thread(
+                    worker,
                     poll,
                     token_generator,
+                    worker_assignments,
+                    stats,
+                    session_infos,
                     thread_to_engine_tx,
                     engine_to_thread_rx,
                     message_dictionary,
                     max_message_size,
                 );
-            })),
+            }));
+            senders.push(engine_to_thread_tx);
+        }
+
+        Ok(Engine {
+            token_generator: token_generator.clone(),
+            stats,
+            session_infos,
+            message_ids: MessageIdGenerator::default(),
+            workers: WorkerSenders {
+                senders,
+                assignments: worker_assignments.clone(),
+            },
+            worker_policy: WorkerPolicy::RoundRobin,
+            next_worker: 0,
+            listeners: HashSet::new(),
+            events: Some(EngineEventReceiver {
+                token_generator,
+                worker_assignments,
+                rx: thread_to_engine_rx,
+                poll: engine_poll,
+            }),
+            thread_handles,
         })
     }
 
+    //Pick which worker thread connections and listeners added from now on are handled by.
+    //Connections accepted by a listener are always handled by the listener's worker thread.
+    //Defaults to WorkerPolicy::RoundRobin.
+    pub fn set_worker_policy(&mut self, worker_policy: WorkerPolicy) {
+        self.worker_policy = worker_policy;
+    }
+
+    pub fn worker_threads(&self) -> usize {
+        self.workers.senders.len()
+    }
+
+    //Number of connections, listeners, and acceptor sessions currently in use. Nothing more can
+    //be added once the engine's limit is reached.
+    pub fn connection_count(&self) -> usize {
+        self.token_generator.lock().unwrap().len()
+    }
+
+    //Worker thread handling connection. None if connection is invalid or was terminated.
+    pub fn connection_worker(&self, connection: Connection) -> Option<usize> {
+        self.workers.worker(Token(connection.0))
+    }
+
+    fn assign_worker(&mut self, token: Token) {
+        let worker_threads = self.worker_threads();
+        let worker = match self.worker_policy {
+            WorkerPolicy::RoundRobin => {
+                let worker = self.next_worker;
+                self.next_worker = (worker + 1) % worker_threads;
+                worker
+            }
+            WorkerPolicy::Pinned(worker) => worker % worker_threads,
+        };
+        self.workers.assign(token, worker);
+    }
+
     pub fn add_connection<A: ToSocketAddrs>(
         &mut self,
         fix_version: FIXVersion,
-        mut default_message_version: MessageVersion,
+        default_message_version: MessageVersion,
         sender_comp_id: &[u8],
         target_comp_id: &[u8],
         address: A,
     ) -> Option<Connection> {
-        let address = match to_socket_addr(address) {
-            Some(address) => address,
-            None => return None,
-        };
+        let address = to_socket_addr(address)?;
+        self.add_connection_with_config(ConnectionConfig::new(
+            fix_version,
+            default_message_version,
+            sender_comp_id,
+            target_comp_id,
+            address,
+        ))
+    }
 
-        //Force older FIX versions that don't support message versioning to use their respective
-        //message versions.
-        default_message_version = match fix_version {
-            FIXVersion::FIX_4_0 => MessageVersion::FIX40,
-            FIXVersion::FIX_4_1 => MessageVersion::FIX41,
-            FIXVersion::FIX_4_2 => MessageVersion::FIX42,
-            FIXVersion::FIX_4_3 => MessageVersion::FIX43,
-            FIXVersion::FIX_4_4 => MessageVersion::FIX44,
-            FIXVersion::FIXT_1_1 => default_message_version,
-        };
+    //Same as add_connection() but the session resumes at msg_seq_nums instead of the values in
+    //the SeqNumStore (or starting over at 1 when there is no SeqNumStore).
+    #[deprecated(note = "Use add_connection_with_config() instead")]
+    pub fn add_connection_with_msg_seq_nums<A: ToSocketAddrs, MSN: Into<Option<MsgSeqNums>>>(
+        &mut self,
+        fix_version: FIXVersion,
+        default_message_version: MessageVersion,
+        sender_comp_id: &[u8],
+        target_comp_id: &[u8],
+        address: A,
+        msg_seq_nums: MSN,
+    ) -> Option<Connection> {
+        let address = to_socket_addr(address)?;
+        let mut config = ConnectionConfig::new(
+            fix_version,
+            default_message_version,
+            sender_comp_id,
+            target_comp_id,
+            address,
+        );
+        if let Some(msg_seq_nums) = msg_seq_nums.into() {
+            config = config.with_msg_seq_nums(msg_seq_nums);
+        }
+        self.add_connection_with_config(config)
+    }
+
+    //Same as add_connection() but session_options replace the engine's default timeouts and
+    //limits for this connection.
+    #[deprecated(note = "Use add_connection_with_config() instead")]
+    pub fn add_connection_with_session_options<A: ToSocketAddrs>(
+        &mut self,
+        fix_version: FIXVersion,
+        default_message_version: MessageVersion,
+        sender_comp_id: &[u8],
+        target_comp_id: &[u8],
+        address: A,
+        session_options: SessionOptions,
+    ) -> Option<Connection> {
+        let address = to_socket_addr(address)?;
+        self.add_connection_with_config(
+            ConnectionConfig::new(
+                fix_version,
+                default_message_version,
+                sender_comp_id,
+                target_comp_id,
+                address,
+            )
+            .with_session_options(session_options),
+        )
+    }
+
+    //Same as add_connection() but the connection is secured using TLS. The TLS handshake is
+    //performed before Logon is sent.
+    #[cfg(feature = "tls")]
+    #[deprecated(note = "Use add_connection_with_config() instead")]
+    pub fn add_connection_with_tls<A: ToSocketAddrs>(
+        &mut self,
+        fix_version: FIXVersion,
+        default_message_version: MessageVersion,
+        sender_comp_id: &[u8],
+        target_comp_id: &[u8],
+        address: A,
+        tls_connector: TlsConnector,
+    ) -> Option<Connection> {
+        let address = to_socket_addr(address)?;
+        self.add_connection_with_config(
+            ConnectionConfig::new(
+                fix_version,
+                default_message_version,
+                sender_comp_id,
+                target_comp_id,
+                address,
+            )
+            .with_tls(tls_connector),
+        )
+    }
+
+    //Same as add_connection() but connects using any transport. Ie. a Unix domain socket to a
+    //gateway on the same machine or a LoopbackAddr that another Engine in this process is
+    //listening on.
+    #[deprecated(note = "Use add_connection_with_config() instead")]
+    pub fn add_connection_with_transport<A: Into<TransportAddr>>(
+        &mut self,
+        fix_version: FIXVersion,
+        default_message_version: MessageVersion,
+        sender_comp_id: &[u8],
+        target_comp_id: &[u8],
+        address: A,
+    ) -> Option<Connection> {
+        self.add_connection_with_config(ConnectionConfig::new(
+            fix_version,
+            default_message_version,
+            sender_comp_id,
+            target_comp_id,
+            address,
+        ))
+    }
+
+    //Add a connection that connects and sends logon automatically whenever schedule says the
+    //session is active and logs out when it's not. The returned Connection remains valid between
+    //sessions (see EngineEvent::SessionEnded) until logout() is called.
+    pub fn add_scheduled_connection<A: ToSocketAddrs>(
+        &mut self,
+        fix_version: FIXVersion,
+        default_message_version: MessageVersion,
+        sender_comp_id: &[u8],
+        target_comp_id: &[u8],
+        address: A,
+        schedule: SessionSchedule,
+        logon: Box<Logon>,
+    ) -> Option<Connection> {
+        let address = to_socket_addr(address)?;
+        self.add_connection_with_config(
+            ConnectionConfig::new(
+                fix_version,
+                default_message_version,
+                sender_comp_id,
+                target_comp_id,
+                address,
+            )
+            .with_schedule(schedule)
+            .with_logon(logon),
+        )
+    }
+
+    //Add a connection that connects and sends logon automatically. Whenever the connection fails
+    //or is disconnected for any reason other than logout() being called, it's reconnected
+    //according to reconnect_policy and logon is sent again. The returned Connection remains valid
+    //between sessions (see EngineEvent::SessionEnded and EngineEvent::Reconnecting) until logout()
+    //is called or reconnect_policy gives up.
+    #[deprecated(note = "Use add_connection_with_config() instead")]
+    pub fn add_connection_with_reconnect_policy<A: ToSocketAddrs>(
+        &mut self,
+        fix_version: FIXVersion,
+        default_message_version: MessageVersion,
+        sender_comp_id: &[u8],
+        target_comp_id: &[u8],
+        address: A,
+        reconnect_policy: ReconnectPolicy,
+        logon: Box<Logon>,
+    ) -> Option<Connection> {
+        let address = to_socket_addr(address)?;
+        self.add_connection_with_config(
+            ConnectionConfig::new(
+                fix_version,
+                default_message_version,
+                sender_comp_id,
+                target_comp_id,
+                address,
+            )
+            .with_reconnect_policy(reconnect_policy)
+            .with_logon(logon),
+        )
+    }
+
+    //Add a connection described by config. Returns None if the Engine can't keep track of any
+    //more connections or if config has a schedule or reconnect policy but no logon to send.
+    pub fn add_connection_with_config(&mut self, config: ConnectionConfig) -> Option<Connection> {
+        let ConnectionConfig {
+            fix_version,
+            default_message_version,
+            sender_comp_id,
+            target_comp_id,
+            address,
+            msg_seq_nums,
+            session_options,
+            stream_config,
+            schedule,
+            reconnect_policy,
+            logon,
+        } = config;
+        let default_message_version = force_message_version(fix_version, default_message_version);
+        if logon.is_none() && (schedule.is_some() || reconnect_policy.is_some()) {
+            return None;
+        }
 
         //Create unique id to refer to connection by.
         let token = match self.token_generator.lock().unwrap().create() {
             Some(token) => token,
             None => return None,
         };
+        self.assign_worker(token);
 
-        //Tell thread to setup this connection by connecting a socket and logging on.
-        self.tx
-            .send(InternalEngineToThreadEvent::NewConnection(
+        //Connections that send their own Logon are managed by the thread from then on.
+        //Otherwise, the thread just connects a socket and waits for the application to logon.
+        let event = match logon {
+            Some(logon) => InternalEngineToThreadEvent::NewInitiatorSession(
                 token,
                 fix_version,
                 default_message_version,
-                sender_comp_id.to_vec(),
-                target_comp_id.to_vec(),
+                sender_comp_id,
+                target_comp_id,
                 address,
-            ))
-            .unwrap();
+                msg_seq_nums,
+                schedule,
+                reconnect_policy,
+                logon,
+                stream_config,
+                session_options,
+            ),
+            None => InternalEngineToThreadEvent::NewConnection(
+                token,
+                fix_version,
+                default_message_version,
+                sender_comp_id,
+                target_comp_id,
+                address,
+                msg_seq_nums,
+                stream_config,
+                session_options,
+            ),
+        };
+        self.workers.send(event).unwrap();
 
-        let connection = Connection(token.0);
-        Some(connection)
+        Some(Connection(token.0))
     }
 
     pub fn add_listener<A: ToSocketAddrs>(
@@ -339,40 +1040,143 @@ impl Engine {
             Some(address) => address,
             None => return Ok(None),
         };
-        let listener = TcpListener::bind(&address)?;
+        self.add_listener_with_config(ListenerConfig::new(sender_comp_id, address))
+    }
+
+    //Same as add_listener() but accepted connections use session_options instead of the engine's
+    //default timeouts and limits. Acceptor sessions can replace these once their Logon is received
+    //using AcceptorSession::with_session_options().
+    pub fn add_listener_with_session_options<A: ToSocketAddrs>(
+        &mut self,
+        sender_comp_id: &[u8],
+        address: A,
+        session_options: SessionOptions,
+    ) -> Result<Option<Listener>, io::Error> {
+        let address = match to_socket_addr(address) {
+            Some(address) => address,
+            None => return Ok(None),
+        };
+        self.add_listener_with_config(
+            ListenerConfig::new(sender_comp_id, address).with_session_options(session_options),
+        )
+    }
+
+    //Same as add_listener() but every accepted connection must complete a TLS handshake before
+    //its Logon is read.
+    #[cfg(feature = "tls")]
+    pub fn add_listener_with_tls<A: ToSocketAddrs>(
+        &mut self,
+        sender_comp_id: &[u8],
+        address: A,
+        tls_acceptor: TlsAcceptor,
+    ) -> Result<Option<Listener>, io::Error> {
+        let address = match to_socket_addr(address) {
+            Some(address) => address,
+            None => return Ok(None),
+        };
+        self.add_listener_with_config(
+            ListenerConfig::new(sender_comp_id, address).with_tls(tls_acceptor),
+        )
+    }
+
+    //Same as add_listener() but accepts connections using any transport. Connections accepted
+    //over a Unix domain socket or LoopbackAddr are reported as coming from 0.0.0.0:0 because
+    //they don't have an IP address. Binding a Unix domain socket fails if its file already
+    //exists. The file is removed once the listener is gone.
+    pub fn add_listener_with_transport<A: Into<TransportAddr>>(
+        &mut self,
+        sender_comp_id: &[u8],
+        address: A,
+    ) -> Result<Option<Listener>, io::Error> {
+        self.add_listener_with_config(ListenerConfig::new(sender_comp_id, address))
+    }
+
+    //Add a listener described by config. Returns None if the Engine can't keep track of any more
+    //listeners.
+    pub fn add_listener_with_config(
+        &mut self,
+        config: ListenerConfig,
+    ) -> Result<Option<Listener>, io::Error> {
+        let listener = TransportListener::bind(&config.address)?;
 
         let token = match self.token_generator.lock().unwrap().create() {
             Some(token) => token,
             None => return Ok(None),
         };
 
-        self.tx
+        self.assign_worker(token);
+        self.workers
             .send(InternalEngineToThreadEvent::NewListener(
                 token,
-                sender_comp_id.to_vec(),
+                config.sender_comp_id,
                 listener,
+                config.stream_config,
+                config.session_options,
             ))
             .unwrap();
 
         let listener = Listener(token.0);
+        self.listeners.insert(listener);
+        if let Some(schedule) = config.schedule {
+            self.set_listener_session_schedule(listener, schedule);
+        }
         Ok(Some(listener))
     }
 
+    //Only accept logons on listener while schedule says sessions are active. Sessions accepted by
+    //listener are logged out when schedule ends and their stored MsgSeqNums are reset at the
+    //schedule's reset time.
+    pub fn set_listener_session_schedule(&mut self, listener: Listener, schedule: SessionSchedule) {
+        self.workers
+            .send(InternalEngineToThreadEvent::SetListenerSessionSchedule(
+                Token(listener.0),
+                schedule,
+            ))
+            .unwrap();
+    }
+
+    //Expect session to logon to listener. Once a listener has any sessions, Logons from
+    //everyone else are rejected without involving the application. Returns false if listener
+    //doesn't exist or the Engine can't keep track of any more sessions.
+    pub fn add_acceptor_session(&mut self, listener: Listener, session: AcceptorSession) -> bool {
+        if !self.listeners.contains(&listener) {
+            return false;
+        }
+
+        let token = match self.token_generator.lock().unwrap().create() {
+            Some(token) => token,
+            None => return false,
+        };
+
+        self.workers
+            .send(InternalEngineToThreadEvent::AddAcceptorSession(
+                Token(listener.0),
+                token,
+                session,
+            ))
+            .unwrap();
+
+        true
+    }
+
+    //Queue message to be sent over connection. Unless send receipts were disabled using
+    //set_send_receipts(false), the returned MessageId is included in the EngineEvent::MessageSent,
+    //EngineEvent::MessageNotSent, or EngineEvent::MessageRefused that eventually follows.
     pub fn send_message<T: 'static + FIXTMessage + Send>(
         &mut self,
         connection: Connection,
         message: T,
-    ) {
+    ) -> MessageId {
         let message = Box::new(message);
-        self.send_message_box(connection, message);
+        self.send_message_box(connection, message)
     }
 
     pub fn send_message_box(
         &mut self,
         connection: Connection,
         message: Box<dyn FIXTMessage + Send>,
-    ) {
-        self.send_message_box_with_message_version(connection, None, message);
+    ) -> MessageId {
+        self.send_message_box_with_message_version(connection, None, message)
     }
 
     pub fn send_message_box_with_message_version<MV: Into<Option<MessageVersion>>>(
@@ -380,14 +1184,18 @@ impl Engine {
         connection: Connection,
         message_version: MV,
         message: Box<dyn FIXTMessage + Send>,
-    ) {
-        self.tx
+    ) -> MessageId {
+        let message_id = self.message_ids.create();
+        self.workers
             .send(InternalEngineToThreadEvent::SendMessage(
                 Token(connection.0),
+                message_id,
                 message_version.into(),
                 message,
             ))
             .unwrap();
+
+        message_id
     }
 
     pub fn send_resend_response(&mut self, connection: Connection, response: Vec<ResendResponse>) {
@@ -417,7 +1225,7 @@ impl Engine {
         }
 
         //Pass response on to actually be sent.
-        self.tx
+        self.workers
             .send(InternalEngineToThreadEvent::ResendMessages(
                 Token(connection.0),
                 response,
@@ -425,87 +1233,308 @@ impl Engine {
             .unwrap();
     }
 
+    //Record every outbound message of sessions added from now on using a MessageStore created by
+    //message_store_factory. Sessions with a MessageStore respond to ResendRequests automatically
+    //unless changed with set_resend_request_handling(). Worker threads take turns using
+    //message_store_factory. See set_message_store_factory_per_worker().
+    pub fn set_message_store_factory(
+        &mut self,
+        message_store_factory: Box<dyn MessageStoreFactory + Send>,
+    ) {
+        let message_store_factory = Shared::new(message_store_factory);
+        self.set_message_store_factory_per_worker(|| Box::new(message_store_factory.clone()));
+    }
+
+    //Same as set_message_store_factory() but new_message_store_factory is called once for each
+    //worker thread so they never wait on each other.
+    pub fn set_message_store_factory_per_worker<F>(&mut self, mut new_message_store_factory: F)
+    where
+        F: FnMut() -> Box<dyn MessageStoreFactory + Send>,
+    {
+        self.workers.send_all(|| {
+            InternalEngineToThreadEvent::SetMessageStoreFactory(new_message_store_factory())
+        });
+    }
+
+    //Load the MsgSeqNums of sessions added from now on from seq_num_store and save them back
+    //whenever they change so the sessions can be resumed after a reconnect or process restart.
+    //Worker threads take turns using seq_num_store. See set_seq_num_store_per_worker().
+    pub fn set_seq_num_store(&mut self, seq_num_store: Box<dyn SeqNumStore + Send>) {
+        let seq_num_store = Shared::new(seq_num_store);
+        self.set_seq_num_store_per_worker(|| Box::new(seq_num_store.clone()));
+    }
+
+    //Same as set_seq_num_store() but new_seq_num_store is called once for each worker thread so
+    //they never wait on each other. A session isn't always handled by the same worker thread each
+    //time it's added, so the stores must save somewhere they can all load from (ie. one
+    //FileSeqNumStore per worker thread using the same directory).
+    pub fn set_seq_num_store_per_worker<F>(&mut self, mut new_seq_num_store: F)
+    where
+        F: FnMut() -> Box<dyn SeqNumStore + Send>,
+    {
+        self.workers
+            .send_all(|| InternalEngineToThreadEvent::SetSeqNumStore(new_seq_num_store()));
+    }
+
+    //Record the exact bytes of every message read from or written to any session's socket,
+    //including those handled automatically by the Engine and garbled ones, using message_log.
+    //Worker threads take turns using message_log. See set_message_log_per_worker().
+    pub fn set_message_log(&mut self, message_log: Box<dyn MessageLog + Send>) {
+        let message_log = Shared::new(message_log);
+        self.set_message_log_per_worker(|| Box::new(message_log.clone()));
+    }
+
+    //Same as set_message_log() but new_message_log is called once for each worker thread so they
+    //never wait on each other.
+    pub fn set_message_log_per_worker<F>(&mut self, mut new_message_log: F)
+    where
+        F: FnMut() -> Box<dyn MessageLog + Send>,
+    {
+        self.workers
+            .send_all(|| InternalEngineToThreadEvent::SetMessageLog(new_message_log()));
+    }
+
+    //Fill in authentication fields of every outbound Logon, such as Username and Password or a
+    //signature in RawData, after its session header is final. Worker threads take turns calling
+    //logon_decorator. See set_logon_decorator_per_worker().
+    pub fn set_logon_decorator(&mut self, logon_decorator: LogonDecorator) {
+        let logon_decorator = Arc::new(Mutex::new(logon_decorator));
+        self.set_logon_decorator_per_worker(|| {
+            let logon_decorator = logon_decorator.clone();
+            Box::new(move |connection, logon| (logon_decorator.lock().unwrap())(connection, logon))
+        });
+    }
+
+    //Same as set_logon_decorator() but new_logon_decorator is called once for each worker thread
+    //so they never wait on each other.
+    pub fn set_logon_decorator_per_worker<F>(&mut self, mut new_logon_decorator: F)
+    where
+        F: FnMut() -> LogonDecorator,
+    {
+        self.workers
+            .send_all(|| InternalEngineToThreadEvent::SetLogonDecorator(new_logon_decorator()));
+    }
+
+    //Check the Logon of every connection accepted by a listener before it's passed along using
+    //EngineEvent::ConnectionLoggingOn. Refused connections are logged out with the returned text
+    //and terminated with ConnectionTerminatedReason::LogonAuthenticationError. Worker threads
+    //take turns using logon_authenticator. See set_logon_authenticator_per_worker().
+    pub fn set_logon_authenticator(
+        &mut self,
+        logon_authenticator: Box<dyn LogonAuthenticator + Send>,
+    ) {
+        let logon_authenticator = Shared::new(logon_authenticator);
+        self.set_logon_authenticator_per_worker(|| Box::new(logon_authenticator.clone()));
+    }
+
+    //Same as set_logon_authenticator() but new_logon_authenticator is called once for each worker
+    //thread so they never wait on each other.
+    pub fn set_logon_authenticator_per_worker<F>(&mut self, mut new_logon_authenticator: F)
+    where
+        F: FnMut() -> Box<dyn LogonAuthenticator + Send>,
+    {
+        self.workers.send_all(|| {
+            InternalEngineToThreadEvent::SetLogonAuthenticator(new_logon_authenticator())
+        });
+    }
+
+    //Measure every timeout and timestamp, including SendingTime, using clock instead of the
+    //system's clock. Timeouts already started keep however much time they had left. Usually used
+    //with a ManualClock so tests involving timeouts don't have to wait.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        self.workers
+            .send_all(|| InternalEngineToThreadEvent::SetClock(clock.clone()));
+    }
+
+    //Emit EngineEvent::MessageSent and EngineEvent::MessageNotSent for messages queued using
+    //send_message(). Enabled by default. Applications that don't need to know when each message
+    //goes out can pass false to skip these events for messages queued after this call.
+    pub fn set_send_receipts(&mut self, enabled: bool) {
+        self.workers
+            .send_all(|| InternalEngineToThreadEvent::SetSendReceipts(enabled));
+    }
+
+    //Emit EngineEvent::SessionStateChanged each time a connection moves to a different
+    //SessionState. Disabled by default.
+    pub fn set_session_state_events(&mut self, enabled: bool) {
+        self.workers
+            .send_all(|| InternalEngineToThreadEvent::SetSessionStateEvents(enabled));
+    }
+
+    //Inspect, modify, or veto every outbound message, including those generated by the engine
+    //like Heartbeat and Logout, just before it's sent. Vetoed messages don't use up a MsgSeqNum
+    //and are returned using EngineEvent::MessageRefused. Worker threads take turns calling
+    //outbound_message_hook. See set_outbound_message_hook_per_worker().
+    pub fn set_outbound_message_hook(&mut self, outbound_message_hook: OutboundMessageHook) {
+        let outbound_message_hook = Arc::new(Mutex::new(outbound_message_hook));
+        self.set_outbound_message_hook_per_worker(|| {
+            let outbound_message_hook = outbound_message_hook.clone();
+            Box::new(move |connection, message| {
+                (outbound_message_hook.lock().unwrap())(connection, message)
+            })
+        });
+    }
+
+    //Same as set_outbound_message_hook() but new_outbound_message_hook is called once for each
+    //worker thread so they never wait on each other.
+    pub fn set_outbound_message_hook_per_worker<F>(&mut self, mut new_outbound_message_hook: F)
+    where
+        F: FnMut() -> OutboundMessageHook,
+    {
+        self.workers.send_all(|| {
+            InternalEngineToThreadEvent::SetOutboundMessageHook(new_outbound_message_hook())
+        });
+    }
+
+    pub fn set_resend_request_handling(
+        &mut self,
+        connection: Connection,
+        resend_request_handling: ResendRequestHandling,
+    ) {
+        self.workers
+            .send(InternalEngineToThreadEvent::SetResendRequestHandling(
+                Token(connection.0),
+                resend_request_handling,
+            ))
+            .unwrap();
+    }
+
+    //Limit how fast application messages are sent on connection. Administrative messages are
+    //never held back. Replaces any existing throttle and None removes it. Connections that
+    //reconnect or follow a schedule keep using it for every new session.
+    pub fn set_throttle<T: Into<Option<Throttle>>>(&mut self, connection: Connection, throttle: T) {
+        self.workers
+            .send(InternalEngineToThreadEvent::SetThrottle(
+                Token(connection.0),
+                throttle.into(),
+            ))
+            .unwrap();
+    }
+
     pub fn approve_new_connection<IMSN: Into<Option<u64>>>(
         &mut self,
         connection: Connection,
         message: Box<Logon>,
         inbound_msg_seq_num: IMSN,
     ) {
-        self.tx
+        self.workers
+            .send(InternalEngineToThreadEvent::ApproveNewConnection(
+                connection,
+                message,
+                inbound_msg_seq_num.into(),
+                None,
+            ))
+            .unwrap();
+    }
+
+    //Same as approve_new_connection() but the session resumes at msg_seq_nums instead of the
+    //values in the SeqNumStore. msg_seq_nums.inbound is the MsgSeqNum the received Logon was
+    //expected to have and msg_seq_nums.outbound is the MsgSeqNum of the Logon response.
+    pub fn approve_new_connection_with_msg_seq_nums(
+        &mut self,
+        connection: Connection,
+        message: Box<Logon>,
+        msg_seq_nums: MsgSeqNums,
+    ) {
+        self.workers
             .send(InternalEngineToThreadEvent::ApproveNewConnection(
                 connection,
                 message,
-                inbound_msg_seq_num.into().unwrap_or(2),
+                None,
+                Some(msg_seq_nums),
             ))
             .unwrap();
     }
 
     pub fn reject_new_connection(&mut self, connection: Connection, reason: Option<Vec<u8>>) {
-        self.tx
+        self.workers
             .send(InternalEngineToThreadEvent::RejectNewConnection(
                 connection, reason,
             ))
             .unwrap();
     }
 
+    //Start both MsgSeqNums over at 1 without disconnecting by sending a Logon with
+    //ResetSeqNumFlag set. The remote acknowledges with its own Logon which is received like any
+    //other message. Only has an effect once the session is established.
+    pub fn reset_sequence_numbers(&mut self, connection: Connection) {
+        self.workers
+            .send(InternalEngineToThreadEvent::ResetSequenceNumbers(Token(
+                connection.0,
+            )))
+            .unwrap();
+    }
+
     pub fn logout(&mut self, connection: Connection) {
-        self.tx
+        self.workers
             .send(InternalEngineToThreadEvent::Logout(Token(connection.0)))
             .unwrap();
     }
 
-    pub fn poll<D: Into<Option<Duration>>>(&mut self, duration: D) -> Option<EngineEvent> {
-        //Perform any book keeping needed to manage engine's state.
-        fn update_engine(engine: &mut Engine, event: &EngineEvent) {
-            match *event {
-                EngineEvent::ConnectionFailed(connection, _)
-                | EngineEvent::ConnectionTerminated(connection, _) => {
-                    engine
-                        .token_generator
-                        .lock()
-                        .unwrap()
-                        .remove(Token(connection.0));
-                }
-                _ => {}
-            }
-        }
+    //Snapshot of where a connection is in its session. Never waits on the engine's thread so
+    //it's safe to call from anywhere. Returns None if the connection is invalid or was
+    //terminated.
+    pub fn session_info(&self, connection: Connection) -> Option<SessionInfo> {
+        self.session_infos.get(connection)
+    }
 
-        if let Ok(event) = self.rx.try_recv() {
-            update_engine(self, &event);
-            return Some(event);
-        }
+    //Block until the engine's thread handling connection is done with everything already asked
+    //of it. Mostly useful for tests that have to move a ManualClock forward without racing the
+    //engine's thread. Never call this from an OutboundMessageHook or anything else run by the
+    //engine's thread because it would wait on itself forever.
+    pub fn sync(&self, connection: Connection) {
+        let (response_tx, response_rx) = std::sync::mpsc::channel();
+        self.workers
+            .send(InternalEngineToThreadEvent::Sync(
+                Token(connection.0),
+                response_tx,
+            ))
+            .unwrap();
+        let _ = response_rx.recv();
+    }
 
-        if let Some(poll_duration) = duration.into() {
-            let now = Instant::now(); //Watch time manually because Mio's poll::poll() can wake immediatelly and we'll have no idea how long has elapsed.
+    //Snapshot of the stats for every open Connection.
+    pub fn stats(&self) -> EngineStats {
+        self.stats.snapshot()
+    }
 
-            while let Some(poll_duration) = poll_duration.checked_sub(now.elapsed()) {
-                let mut events = Events::with_capacity(1);
-                if self.poll.poll(&mut events, Some(poll_duration)).is_err() {
-                    return None;
-                }
+    pub(crate) fn stats_registry(&self) -> StatsRegistry {
+        self.stats.clone()
+    }
 
-                let result = self.rx.try_recv();
-                match result {
-                    Ok(event) => {
-                        update_engine(self, &event);
-                        return Some(event);
-                    }
-                    Err(e) if e == TryRecvError::Disconnected => return None,
-                    _ => {}
-                }
-            }
+    pub fn poll<D: Into<Option<Duration>>>(&mut self, duration: D) -> Option<EngineEvent> {
+        match self.events {
+            Some(ref mut events) => events.poll(duration.into()),
+            None => None,
         }
+    }
 
-        None
+    //Hand events off to something else (ie. AsyncEngine). poll() never returns an event
+    //afterwards.
+    #[cfg(feature = "async")]
+    pub(crate) fn take_event_receiver(&mut self) -> Option<EngineEventReceiver> {
+        self.events.take()
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn sender(&self) -> WorkerSenders {
+        self.workers.clone()
+    }
+
+    #[cfg(feature = "async")]
+    pub(crate) fn message_id_generator(&self) -> MessageIdGenerator {
+        self.message_ids.clone()
     }
 }
 
 impl Drop for Engine {
     fn drop(&mut self) {
-        //Shutdown thread and wait until it completes. No attempt is made to make connections
+        //Shutdown threads and wait until they complete. No attempt is made to make connections
         //logout cleanly.
-        self.tx.send(InternalEngineToThreadEvent::Shutdown).unwrap();
-        let thread_handle = mem::replace(&mut self.thread_handle, None);
-        let _ = thread_handle.unwrap().join();
+        self.workers
+            .send_all(|| InternalEngineToThreadEvent::Shutdown);
+        for thread_handle in self.thread_handles.drain(..) {
+            let _ = thread_handle.join();
+        }
     }
 }
@@ -11,29 +11,29 @@
 
 #![allow(deprecated)]
 
+use chrono::{DateTime, Utc};
 use mio::channel::{Receiver, Sender};
-use mio::tcp::{Shutdown, TcpListener, TcpStream};
-use mio::timer::Builder as TimerBuilder;
-use mio::timer::{Timeout, Timer};
+use mio::tcp::Shutdown;
 use mio::unix::UnixReady;
 use mio::{Event, Events, Poll, PollOpt, Ready, Token};
 use std::cmp;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fmt;
 use std::io::{self, Write};
 use std::mem;
-use std::net::SocketAddr;
+use std::ops::Range;
+use std::sync::mpsc::Sender as StdSender;
 use std::sync::{Arc, Mutex};
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use crate::byte_buffer::ByteBuffer;
 use crate::dictionary::field_types::generic::UtcTimestampFieldType;
 use crate::dictionary::field_types::other::{
-    BusinessRejectReason, MsgDirection, SessionRejectReason,
+    BusinessRejectReason, EncryptMethod, MsgDirection, SessionRejectReason,
 };
 use crate::dictionary::fields::{
-    ApplVerID, MsgSeqNum, OrigSendingTime, SenderCompID, TargetCompID,
+    ApplVerID, MsgSeqNum, MsgTypeGrp, OrigSendingTime, SenderCompID, TargetCompID,
 };
 use crate::dictionary::messages::{
     BusinessMessageReject, Heartbeat, Logon, Logout, Reject, ResendRequest, SequenceReset,
@@ -44,39 +44,51 @@ use crate::field::Field;
 use crate::field_type::FieldType;
 use crate::fix::{ParseError, Parser};
 use crate::fix_version::FIXVersion;
+use crate::fixt::acceptor_session::AcceptorSession;
+use crate::fixt::clock::{Clock, SystemClock};
 use crate::fixt::engine::{
-    Connection, ConnectionTerminatedReason, EngineEvent, Listener, ResendResponse,
+    Connection, ConnectionTerminatedReason, EngineEvent, Listener, MessageId, MessageRefusedReason,
+    MsgSeqNums, OutboundMessageHook, ResendRequestHandling, ResendResponse, SessionID,
+    WorkerAssignments,
 };
+use crate::fixt::logon_auth::{LogonAuthenticator, LogonDecorator};
 use crate::fixt::message::{BuildFIXTMessage, FIXTMessage};
+use crate::fixt::message_log::{MessageDirection, MessageLog};
+use crate::fixt::message_store::{
+    prepare_stored_message_for_resend, MessageStore, MessageStoreFactory,
+};
+use crate::fixt::reconnect_policy::ReconnectPolicy;
+use crate::fixt::seq_num_store::SeqNumStore;
+use crate::fixt::session_info::{SessionInfo, SessionInfoRegistry, SessionState};
+use crate::fixt::session_options::SessionOptions;
+use crate::fixt::session_schedule::SessionSchedule;
+use crate::fixt::stats::{SessionStats, StatsRegistry};
+use crate::fixt::stream::{Stream, StreamConfig};
+use crate::fixt::throttle::{Throttle, ThrottleAction, ThrottleState};
+use crate::fixt::timer::{Timeout, Timer};
+use crate::fixt::transport::{TransportAddr, TransportListener, TransportStream};
 use crate::message_version::MessageVersion;
 use crate::network_read_retry::NetworkReadRetry;
 use crate::token_generator::TokenGenerator;
 
 //TODO: Make sure Logon message is sent automatically instead of waiting on caller. Althought, we
 //might have to support this for testing purposes.
-//TODO: Check for infinite resend loop when other side sends garbled messages, we later send
-//ResendRequest, and the other side continues to send garbled messages.
-//TODO: Implement ConnectionStatus handling using a state machine pattern to reduce chance of
-//mistake.
-//TODO: Need to make inbound and outbound MsgSeqNums adjustable at connection setup and available
-//on connection termination to support persistent sessions.
 //TODO: Stop allowing outgoing messages when performing an emergency logout.
 //TODO: Need to sanitize output strings when serializing.
 
-const NO_INBOUND_TIMEOUT_PADDING_MS: u64 = 250;
-const AUTO_DISCONNECT_AFTER_LOGOUT_RESPONSE_SECS: u64 = 10;
+pub const NO_INBOUND_TIMEOUT_PADDING_MS: u64 = 250;
+pub const AUTO_DISCONNECT_AFTER_LOGOUT_RESPONSE_SECS: u64 = 10;
 const AUTO_DISCONNECT_AFTER_INITIATING_LOGOUT_SECS: u64 = 10;
 const AUTO_CONTINUE_AFTER_LOGOUT_RESEND_REQUEST_SECS: u64 = 10;
 const AUTO_DISCONNECT_AFTER_WRITE_BLOCKS_SECS: u64 = 10;
+const AUTO_RECONNECT_SCHEDULED_SESSION_SECS: u64 = 30;
 pub const AUTO_DISCONNECT_AFTER_INBOUND_RESEND_REQUEST_LOOP_COUNT: u64 = 5;
+pub const AUTO_DISCONNECT_AFTER_GARBLED_RESEND_REQUEST_RETRY_COUNT: u64 = 5;
 pub const AUTO_DISCONNECT_AFTER_NO_LOGON_RECEIVED_SECONDS: u64 = 10;
 const EVENT_POLL_CAPACITY: usize = 1024;
 pub const INBOUND_MESSAGES_BUFFER_LEN_MAX: usize = 10;
 pub const INBOUND_BYTES_BUFFER_CAPACITY: usize = 2048;
-const TIMER_TICK_MS: u64 = 100;
-const TIMER_TIMEOUTS_PER_TICK_MAX: usize = 256;
 pub const CONNECTION_COUNT_MAX: usize = 65536;
-const TIMEOUTS_PER_CONNECTION_MAX: usize = 3;
 
 pub const INTERNAL_ENGINE_EVENT_TOKEN: Token = Token(0);
 const TIMEOUT_TOKEN: Token = Token(1);
@@ -175,38 +187,59 @@ enum TimeoutType {
     NoLogon,
     Logout,
     HangUp,
+    Throttle,
+    Schedule,
+    Reconnect,
 }
 
 type MsgSeqNumType = <<MsgSeqNum as Field>::Type as FieldType>::Type;
 
+enum OutboundMessageBody {
+    Message(Box<dyn FIXTMessage + Send>),
+    Serialized(Vec<u8>), //Already serialized message (ie. from a MessageStore) that is sent as is.
+}
+
 struct OutboundMessage {
-    message: Box<dyn FIXTMessage + Send>,
+    body: OutboundMessageBody,
     message_version: Option<MessageVersion>,
     auto_msg_seq_num: bool,
+    message_id: Option<MessageId>, //Only set for messages queued by the application while send receipts are enabled.
 }
 
 impl OutboundMessage {
     fn new<T: FIXTMessage + Send + Sized + 'static>(message: T, auto_msg_seq_num: bool) -> Self {
         OutboundMessage {
-            message: Box::new(message),
+            body: OutboundMessageBody::Message(Box::new(message)),
             message_version: None,
             auto_msg_seq_num,
+            message_id: None,
         }
     }
 
     fn from<T: FIXTMessage + Send + Sized + 'static>(message: T) -> Self {
         OutboundMessage {
-            message: Box::new(message),
+            body: OutboundMessageBody::Message(Box::new(message)),
             message_version: None,
             auto_msg_seq_num: true,
+            message_id: None,
         }
     }
 
     fn from_box(message: Box<dyn FIXTMessage + Send>) -> Self {
         OutboundMessage {
-            message,
+            body: OutboundMessageBody::Message(message),
             message_version: None,
             auto_msg_seq_num: true,
+            message_id: None,
+        }
+    }
+
+    fn from_serialized(bytes: Vec<u8>) -> Self {
+        OutboundMessage {
+            body: OutboundMessageBody::Serialized(bytes),
+            message_version: None,
+            auto_msg_seq_num: false,
+            message_id: None,
         }
     }
 }
@@ -223,7 +256,7 @@ fn reset_timeout(
     }
 
     *timeout = if let Some(duration) = *timeout_duration {
-        Some(timer.set_timeout(duration, (timeout_type, *token)).unwrap())
+        Some(timer.set_timeout(duration, (timeout_type, *token)))
     } else {
         None
     };
@@ -266,21 +299,95 @@ pub enum InternalEngineToThreadEvent {
         MessageVersion,
         <<SenderCompID as Field>::Type as FieldType>::Type,
         <<TargetCompID as Field>::Type as FieldType>::Type,
-        SocketAddr,
+        TransportAddr,
+        Option<MsgSeqNums>,
+        StreamConfig,
+        SessionOptions,
     ),
     NewListener(
         Token,
         <<SenderCompID as Field>::Type as FieldType>::Type,
-        TcpListener,
+        TransportListener,
+        StreamConfig,
+        SessionOptions,
+    ),
+    NewInitiatorSession(
+        Token,
+        FIXVersion,
+        MessageVersion,
+        <<SenderCompID as Field>::Type as FieldType>::Type,
+        <<TargetCompID as Field>::Type as FieldType>::Type,
+        TransportAddr,
+        Option<MsgSeqNums>,
+        Option<SessionSchedule>,
+        Option<ReconnectPolicy>,
+        Box<Logon>,
+        StreamConfig,
+        SessionOptions,
+    ),
+    SetListenerSessionSchedule(Token, SessionSchedule),
+    AddAcceptorSession(Token, Token, AcceptorSession),
+    SendMessage(
+        Token,
+        MessageId,
+        Option<MessageVersion>,
+        Box<dyn FIXTMessage + Send>,
     ),
-    SendMessage(Token, Option<MessageVersion>, Box<dyn FIXTMessage + Send>),
     ResendMessages(Token, Vec<ResendResponse>),
-    ApproveNewConnection(Connection, Box<Logon>, u64),
+    SetMessageStoreFactory(Box<dyn MessageStoreFactory + Send>),
+    SetResendRequestHandling(Token, ResendRequestHandling),
+    SetThrottle(Token, Option<Throttle>),
+    SetSeqNumStore(Box<dyn SeqNumStore + Send>),
+    SetOutboundMessageHook(OutboundMessageHook),
+    SetMessageLog(Box<dyn MessageLog + Send>),
+    SetSendReceipts(bool),
+    SetSessionStateEvents(bool),
+    Sync(Token, StdSender<()>),
+    SetLogonDecorator(LogonDecorator),
+    SetLogonAuthenticator(Box<dyn LogonAuthenticator + Send>),
+    SetClock(Arc<dyn Clock>),
+    ApproveNewConnection(Connection, Box<Logon>, Option<u64>, Option<MsgSeqNums>),
+    ResetSequenceNumbers(Token),
     RejectNewConnection(Connection, Option<Vec<u8>>),
     Logout(Token),
     Shutdown,
 }
 
+impl InternalEngineToThreadEvent {
+    //Connection, listener, or acceptor session the event is about. Events without one apply to
+    //every worker thread.
+    pub fn token(&self) -> Option<Token> {
+        match *self {
+            InternalEngineToThreadEvent::NewConnection(token, ..)
+            | InternalEngineToThreadEvent::NewListener(token, ..)
+            | InternalEngineToThreadEvent::NewInitiatorSession(token, ..)
+            | InternalEngineToThreadEvent::SetListenerSessionSchedule(token, _)
+            | InternalEngineToThreadEvent::AddAcceptorSession(token, _, _)
+            | InternalEngineToThreadEvent::SendMessage(token, ..)
+            | InternalEngineToThreadEvent::ResendMessages(token, _)
+            | InternalEngineToThreadEvent::SetResendRequestHandling(token, _)
+            | InternalEngineToThreadEvent::SetThrottle(token, _)
+            | InternalEngineToThreadEvent::Sync(token, _)
+            | InternalEngineToThreadEvent::ResetSequenceNumbers(token)
+            | InternalEngineToThreadEvent::Logout(token) => Some(token),
+            InternalEngineToThreadEvent::ApproveNewConnection(connection, ..)
+            | InternalEngineToThreadEvent::RejectNewConnection(connection, _) => {
+                Some(Token(connection.0))
+            }
+            InternalEngineToThreadEvent::SetMessageStoreFactory(_)
+            | InternalEngineToThreadEvent::SetSeqNumStore(_)
+            | InternalEngineToThreadEvent::SetOutboundMessageHook(_)
+            | InternalEngineToThreadEvent::SetMessageLog(_)
+            | InternalEngineToThreadEvent::SetSendReceipts(_)
+            | InternalEngineToThreadEvent::SetSessionStateEvents(_)
+            | InternalEngineToThreadEvent::SetLogonDecorator(_)
+            | InternalEngineToThreadEvent::SetLogonAuthenticator(_)
+            | InternalEngineToThreadEvent::SetClock(_)
+            | InternalEngineToThreadEvent::Shutdown => None,
+        }
+    }
+}
+
 impl fmt::Debug for InternalEngineToThreadEvent {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         //TODO: Actually implement this if its ever used. Write now this exists so some unwrap()
@@ -290,13 +397,21 @@ impl fmt::Debug for InternalEngineToThreadEvent {
 }
 
 enum ConnectionEventError {
-    TerminateConnection(InternalConnection, ConnectionTerminatedReason),
+    TerminateConnection(Box<InternalConnection>, ConnectionTerminatedReason),
     Shutdown,
 }
 
 enum ConnectionReadMessage {
-    Message(Box<dyn FIXTMessage + Send>),
-    Error(ParseError),
+    Message(Box<dyn FIXTMessage + Send>, Vec<u8>), //Raw bytes are only kept for the MessageLog.
+    Error(ParseError, Vec<u8>),
+}
+
+//Application message sitting in the outbound buffer. It's reported as sent once the buffer has
+//been completely written.
+struct OutboundReceipt {
+    message_id: MessageId,
+    msg_seq_num: MsgSeqNumType,
+    message: Box<dyn FIXTMessage + Send>,
 }
 
 struct LastSeenResendRequest {
@@ -304,13 +419,23 @@ struct LastSeenResendRequest {
     count: u64,
 }
 
+//Most recent ResendRequest sent while a message gap is open. Used to notice when the remote keeps
+//answering with garbled messages so the same messages would otherwise be requested forever.
+struct OutboundResendRequest {
+    range: Range<MsgSeqNumType>,
+    attempts: u64,
+    received_garbled: bool,
+}
+
 struct InternalConnection {
     fix_version: FIXVersion,
     default_message_version: MessageVersion,
-    socket: TcpStream,
+    socket: Stream,
     token: Token,
     outbound_messages: Vec<OutboundMessage>,
     outbound_buffer: ByteBuffer,
+    outbound_receipt: Option<OutboundReceipt>,
+    unsent_messages: Vec<(MessageId, Box<dyn FIXTMessage + Send>)>, //Dropped application messages waiting to be reported.
     outbound_msg_seq_num: MsgSeqNumType,
     outbound_heartbeat_timeout: Option<Timeout>,
     outbound_heartbeat_timeout_duration: Option<Duration>,
@@ -320,14 +445,37 @@ struct InternalConnection {
     inbound_testrequest_timeout_duration: Option<Duration>,
     inbound_resend_request_msg_seq_num: Option<MsgSeqNumType>,
     inbound_last_seen_resend_request: LastSeenResendRequest,
+    outbound_resend_request: Option<OutboundResendRequest>,
     inbound_blocked: bool,
     inbound_blocked_timeout: Option<Timeout>,
     logout_timeout: Option<Timeout>,
     parser: Parser,
     is_connected: bool, //TODO: Might belong better as part of ConnectionStatus if the state machine design works well.
-    status: ConnectionStatus,
+    is_peer_closed: bool, //Read reached the end of the stream. Poll doesn't always report this as hup.
+    status: ConnectionStatus, //Only change using set_status() so the SessionState is kept up to date.
+    session_state: SessionState,
+    session_state_changes: Vec<(SessionState, SessionState)>, //Transitions waiting to be reported.
     sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
     target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+    message_store: Option<Box<dyn MessageStore + Send>>,
+    resend_request_handling: ResendRequestHandling,
+    persisted_msg_seq_nums: Option<MsgSeqNums>,
+    listener: Option<Listener>,
+    logon_allowed: bool,
+    expected_sessions: Option<Vec<ExpectedSession>>, //Sessions the listener accepts. None when anyone is accepted.
+    acceptor_session: Option<Token>,                 //Expected session the remote's Logon matched.
+    reset_msg_seq_nums_on_termination: bool,
+    sent_reset_seq_num_flag: bool, //Waiting on remote to respond to our Logon with ResetSeqNumFlag.
+    received_reset_seq_num_flag: bool, //Remote sent Logon with ResetSeqNumFlag and is waiting on us.
+    received_next_expected_msg_seq_num: MsgSeqNumType, //From remote's Logon. 0 when not given.
+    outbound_max_message_size: u64,    //From remote's Logon. 0 when unlimited.
+    outbound_msg_types: Option<HashSet<Vec<u8>>>, //Application MsgTypes allowed to be sent. None when unrestricted.
+    inbound_msg_types: Option<HashSet<Vec<u8>>>, //Application MsgTypes allowed to be received. None when unrestricted.
+    stats: Arc<Mutex<SessionStats>>,
+    published_session_info: Arc<Mutex<SessionInfo>>, //Only change using publish_session_info() or publish_msg_seq_nums().
+    throttle: Option<ThrottleState>,
+    throttle_timeout: Option<Timeout>,
+    options: SessionOptions,
 }
 
 impl InternalConnection {
@@ -336,7 +484,7 @@ impl InternalConnection {
         max_message_size: u64,
         fix_version: FIXVersion,
         default_message_version: MessageVersion,
-        socket: TcpStream,
+        socket: Stream,
         token: Token,
         sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
         target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
@@ -348,6 +496,19 @@ impl InternalConnection {
         for msg_type in administrative_msg_types() {
             parser.set_default_message_type_version(msg_type, fix_version.max_message_version());
         }
+        let stats = SessionStats::new(SessionID::new(
+            fix_version,
+            &sender_comp_id[..],
+            &target_comp_id[..],
+        ));
+        let session_info = SessionInfo {
+            state: SessionState::Connecting,
+            session_id: stats.session_id.clone(),
+            default_appl_ver_id: default_message_version,
+            next_msg_seq_nums: MsgSeqNums::default(),
+            heart_bt_int: None,
+            peer_addr: None,
+        };
 
         InternalConnection {
             fix_version,
@@ -356,6 +517,8 @@ impl InternalConnection {
             socket,
             outbound_messages: Vec::new(),
             outbound_buffer: ByteBuffer::new(),
+            outbound_receipt: None,
+            unsent_messages: Vec::new(),
             outbound_msg_seq_num: 1, //Starts at 1. FIXT v1.1, page 5.
             outbound_heartbeat_timeout: None,
             outbound_heartbeat_timeout_duration: None,
@@ -368,14 +531,37 @@ impl InternalConnection {
                 begin_seq_no: 0,
                 count: 0,
             },
+            outbound_resend_request: None,
             inbound_blocked: false,
             inbound_blocked_timeout: None,
             logout_timeout: None,
             parser,
             is_connected: false,
+            is_peer_closed: false,
             status: ConnectionStatus::SendingLogon,
+            session_state: SessionState::Connecting,
+            session_state_changes: Vec::new(),
             sender_comp_id,
             target_comp_id,
+            message_store: None,
+            resend_request_handling: ResendRequestHandling::Automatic,
+            persisted_msg_seq_nums: None,
+            listener: None,
+            logon_allowed: true,
+            expected_sessions: None,
+            acceptor_session: None,
+            reset_msg_seq_nums_on_termination: false,
+            sent_reset_seq_num_flag: false,
+            received_reset_seq_num_flag: false,
+            received_next_expected_msg_seq_num: 0,
+            outbound_max_message_size: 0,
+            outbound_msg_types: None,
+            inbound_msg_types: None,
+            stats: Arc::new(Mutex::new(stats)),
+            published_session_info: Arc::new(Mutex::new(session_info)),
+            throttle: None,
+            throttle_timeout: None,
+            options: SessionOptions::default(),
         }
     }
 
@@ -383,7 +569,24 @@ impl InternalConnection {
         &mut self,
         timer: &mut Timer<(TimeoutType, Token)>,
         network_read_retry: &mut NetworkReadRetry,
+        tx: &Sender<EngineEvent>,
+        outbound_message_hook: &mut Option<OutboundMessageHook>,
+        message_log: &mut Option<Box<dyn MessageLog + Send>>,
+        logon_decorator: &mut Option<LogonDecorator>,
     ) -> Result<(), ConnectionTerminatedReason> {
+        self.update_outbound_queue_depth();
+        self.report_unsent_messages(tx);
+
+        //Finish sending anything the stream is still holding onto (ie. encrypted TLS records)
+        //before adding more to it.
+        if let Err(e) = self.socket.flush() {
+            if let io::ErrorKind::WouldBlock = e.kind() {
+                return Ok(());
+            }
+
+            return Err(ConnectionTerminatedReason::SocketWriteError(e));
+        }
+
         //Send data until no more messages are available or until the socket returns WouldBlock.
         let mut sent_data = false;
         loop {
@@ -413,46 +616,212 @@ impl InternalConnection {
                     //socket. This is the recommended way to respond to a Logout instead of
                     //disconnecting immediately.
                     else if self.status.is_logging_out_with_responding() {
-                        self.status = ConnectionStatus::LoggingOut(LoggingOutType::Responded);
+                        self.set_status(ConnectionStatus::LoggingOut(LoggingOutType::Responded));
 
-                        self.logout_timeout = Some(
-                            timer
-                                .set_timeout(
-                                    Duration::from_secs(AUTO_DISCONNECT_AFTER_LOGOUT_RESPONSE_SECS),
-                                    (TimeoutType::HangUp, self.token),
-                                )
-                                .unwrap(),
-                        );
+                        self.logout_timeout = Some(timer.set_timeout(
+                            self.options.logout_response_timeout,
+                            (TimeoutType::HangUp, self.token),
+                        ));
                     }
                     break;
                 }
 
                 //Setup message to go out and serialize it.
-                let mut message = self.outbound_messages.remove(0);
-                message.message.setup_fixt_session_header(
-                    if message.auto_msg_seq_num {
-                        let result = Some(self.outbound_msg_seq_num);
-                        self.increment_outbound_msg_seq_num()?;
-                        result
-                    } else {
-                        None
-                    },
+                let message = match self.next_outbound_message(timer, tx) {
+                    Some(message) => message,
+                    None => break, //Everything left is being held back by the throttle.
+                };
+                let mut fixt_message = match message.body {
+                    OutboundMessageBody::Message(fixt_message) => fixt_message,
+                    OutboundMessageBody::Serialized(bytes) => {
+                        self.outbound_buffer
+                            .clear_and_read_all(|buffer| buffer.extend_from_slice(&bytes[..]));
+                        self.record_outbound_buffer(message_log, &timer.now_utc())?;
+                        continue;
+                    }
+                };
+                //Logon with ResetSeqNumFlag starts the outbound MsgSeqNum over at 1. Anything
+                //previously sent can never be resent. See FIXT v1.1, page 36.
+                let is_reset_seq_num_logon = matches!(
+                    fixt_message.as_any().downcast_ref::<Logon>(),
+                    Some(logon) if logon.reset_seq_num_flag
+                );
+
+                //Tell the remote which MsgSeqNum we expect next so it can resend anything we
+                //missed without waiting for a ResendRequest. Only serialized for FIX 4.4 and
+                //later.
+                if let Some(logon) = fixt_message.as_any_mut().downcast_mut::<Logon>() {
+                    if logon.next_expected_msg_seq_num == 0 {
+                        logon.next_expected_msg_seq_num =
+                            if is_reset_seq_num_logon && !self.received_reset_seq_num_flag {
+                                1
+                            } else {
+                                self.inbound_msg_seq_num
+                            };
+                    }
+
+                    //Advertise the largest message we're willing to receive and hold ourselves
+                    //to the MsgTypes we said we support.
+                    if logon.max_message_size == 0 {
+                        logon.max_message_size = self.parser.max_message_size();
+                    }
+                    restrict_msg_types(
+                        &mut self.outbound_msg_types,
+                        &logon.no_msg_types,
+                        MsgDirection::Send,
+                    );
+                    restrict_msg_types(
+                        &mut self.inbound_msg_types,
+                        &logon.no_msg_types,
+                        MsgDirection::Receive,
+                    );
+                }
+
+                //Refuse application messages the remote said it doesn't support instead of
+                //sending them.
+                if !is_msg_type_allowed(&self.outbound_msg_types, fixt_message.msg_type()) {
+                    tx.send(EngineEvent::MessageRefused(
+                        self.as_connection(),
+                        message.message_id,
+                        fixt_message,
+                        MessageRefusedReason::MsgTypeNotSupported,
+                    ))
+                    .unwrap();
+                    continue;
+                }
+
+                //MsgSeqNum to go back to if the message ends up being refused instead of sent.
+                let refused_outbound_msg_seq_num = if message.auto_msg_seq_num {
+                    Some(self.outbound_msg_seq_num)
+                } else {
+                    None
+                };
+                if is_reset_seq_num_logon && message.auto_msg_seq_num {
+                    self.outbound_msg_seq_num = 1;
+                }
+
+                let msg_seq_num = if message.auto_msg_seq_num {
+                    let result = Some(self.outbound_msg_seq_num);
+                    self.increment_outbound_msg_seq_num()?;
+                    result
+                } else {
+                    None
+                };
+                fixt_message.setup_fixt_session_header(
+                    msg_seq_num,
                     self.sender_comp_id.clone(),
                     self.target_comp_id.clone(),
+                    timer.now_utc_timestamp(),
                 );
+
+                //Authentication fields usually depend on the session header so they can only be
+                //filled in now.
+                if let Some(ref mut logon_decorator) = *logon_decorator {
+                    if let Some(logon) = fixt_message.as_any_mut().downcast_mut::<Logon>() {
+                        logon_decorator(self.as_connection(), logon);
+                    }
+                }
+
+                //Give the application a final chance to change or drop the message. Dropped
+                //messages hand their MsgSeqNum back just like refused ones.
+                if let Some(ref mut outbound_message_hook) = *outbound_message_hook {
+                    if !outbound_message_hook(self.as_connection(), &mut *fixt_message) {
+                        if let Some(outbound_msg_seq_num) = refused_outbound_msg_seq_num {
+                            self.outbound_msg_seq_num = outbound_msg_seq_num;
+                            self.publish_msg_seq_nums();
+                        }
+                        tx.send(EngineEvent::MessageRefused(
+                            self.as_connection(),
+                            message.message_id,
+                            fixt_message,
+                            MessageRefusedReason::Vetoed,
+                        ))
+                        .unwrap();
+                        continue;
+                    }
+                }
+
                 let fix_version = self.fix_version;
                 let message_version = if let Some(message_version) = message.message_version {
                     message_version
                 } else {
                     self.default_message_version
                 };
-                message
-                    .message
-                    .read(fix_version, message_version, &mut self.outbound_buffer);
+                fixt_message.read(fix_version, message_version, &mut self.outbound_buffer);
+
+                //Same goes for application messages larger than the remote is willing to receive.
+                //The MsgSeqNum is handed back so the next message doesn't leave a gap.
+                //Administrative messages are sent anyway so the session can always Reject or
+                //Logout.
+                let message_size = self.outbound_buffer.len() as u64;
+                if self.outbound_max_message_size > 0
+                    && message_size > self.outbound_max_message_size
+                    && !administrative_msg_types().contains(&fixt_message.msg_type())
+                {
+                    self.outbound_buffer.clear();
+                    if let Some(outbound_msg_seq_num) = refused_outbound_msg_seq_num {
+                        self.outbound_msg_seq_num = outbound_msg_seq_num;
+                        self.publish_msg_seq_nums();
+                    }
+                    tx.send(EngineEvent::MessageRefused(
+                        self.as_connection(),
+                        message.message_id,
+                        fixt_message,
+                        MessageRefusedReason::MaxMessageSizeExceeded {
+                            message_size,
+                            max_message_size: self.outbound_max_message_size,
+                        },
+                    ))
+                    .unwrap();
+                    continue;
+                }
+
+                //Only forget everything previously sent once the Logon is definitely going out.
+                //Otherwise a refused Logon would still wipe the session.
+                if is_reset_seq_num_logon && message.auto_msg_seq_num {
+                    if let Some(ref mut message_store) = self.message_store {
+                        if let Err(e) = message_store.reset() {
+                            self.shutdown();
+                            return Err(ConnectionTerminatedReason::MessageStoreError(e));
+                        }
+                    }
+
+                    if self.received_reset_seq_num_flag {
+                        self.received_reset_seq_num_flag = false;
+                    } else {
+                        self.sent_reset_seq_num_flag = true;
+                    }
+                }
+
+                //Record every message that consumed a new MsgSeqNum so it can be resent later.
+                if let (Some(msg_seq_num), Some(message_store)) =
+                    (msg_seq_num, self.message_store.as_mut())
+                {
+                    if let Err(e) = message_store.store(
+                        msg_seq_num,
+                        fixt_message.msg_type(),
+                        self.outbound_buffer.bytes(),
+                    ) {
+                        if let Some(message_id) = message.message_id {
+                            self.unsent_messages.push((message_id, fixt_message));
+                        }
+                        self.shutdown();
+                        return Err(ConnectionTerminatedReason::MessageStoreError(e));
+                    }
+                }
 
-                //TODO: Hold onto message and pass it off to the engine or some callback so the
-                //library user knows exactly which messages have been sent -- although not
-                //necessarily acknowledged.
+                //Hold onto application messages until they're completely written so the
+                //application knows exactly which have been sent -- although not necessarily
+                //acknowledged.
+                if let Some(message_id) = message.message_id {
+                    self.outbound_receipt = Some(OutboundReceipt {
+                        message_id,
+                        msg_seq_num: fixt_message.msg_seq_num(),
+                        message: fixt_message,
+                    });
+                }
+
+                self.record_outbound_buffer(message_log, &timer.now_utc())?;
             }
 
             log::debug!(
@@ -468,6 +837,18 @@ impl InternalConnection {
                 Ok(_) => {
                     sent_data = true;
 
+                    if self.outbound_buffer.is_empty() {
+                        if let Some(receipt) = self.outbound_receipt.take() {
+                            tx.send(EngineEvent::MessageSent(
+                                self.as_connection(),
+                                receipt.message_id,
+                                receipt.msg_seq_num,
+                                receipt.message.sending_time(),
+                            ))
+                            .unwrap();
+                        }
+                    }
+
                     //When data has been successfully sent, it's okay to start reading in new data
                     //again.
                     if self.inbound_blocked {
@@ -505,35 +886,242 @@ impl InternalConnection {
             );
         }
 
+        self.update_outbound_queue_depth();
+
+        Ok(())
+    }
+
+    //Log and count a freshly serialized message in the outbound buffer right before it's
+    //written.
+    fn record_outbound_buffer(
+        &mut self,
+        message_log: &mut Option<Box<dyn MessageLog + Send>>,
+        now: &DateTime<Utc>,
+    ) -> Result<(), ConnectionTerminatedReason> {
+        if let Some(ref mut message_log) = *message_log {
+            if let Err(e) = message_log.log(
+                &self.session_id(),
+                MessageDirection::Outbound,
+                now,
+                self.outbound_buffer.bytes(),
+            ) {
+                self.shutdown();
+                return Err(ConnectionTerminatedReason::MessageLogError(e));
+            }
+        }
+
+        let bytes = self.outbound_buffer.bytes();
+        self.stats
+            .lock()
+            .unwrap()
+            .record_outbound(serialized_msg_type(bytes), bytes.len() as u64);
+
         Ok(())
     }
 
+    //Next message in outbound_messages that's allowed to go out. Application messages over the
+    //throttle's limit are either refused or held back, in order, while administrative messages
+    //queued behind them go ahead.
+    fn next_outbound_message(
+        &mut self,
+        timer: &mut Timer<(TimeoutType, Token)>,
+        tx: &Sender<EngineEvent>,
+    ) -> Option<OutboundMessage> {
+        let connection = self.as_connection();
+        let throttle = match self.throttle {
+            Some(ref mut throttle) => throttle,
+            None => return Some(self.outbound_messages.remove(0)),
+        };
+
+        let mut hold_duration = None;
+        let mut index = 0;
+        while index < self.outbound_messages.len() {
+            let msg_type = match self.outbound_messages[index].body {
+                OutboundMessageBody::Message(ref message) => message.msg_type(),
+                OutboundMessageBody::Serialized(_) => {
+                    return Some(self.outbound_messages.remove(index))
+                }
+            };
+            if administrative_msg_types().contains(&msg_type) {
+                return Some(self.outbound_messages.remove(index));
+            }
+            if hold_duration.is_some() {
+                index += 1;
+                continue;
+            }
+
+            match throttle.acquire(msg_type, timer.now()) {
+                Ok(()) => {
+                    throttle.is_holding = false;
+                    return Some(self.outbound_messages.remove(index));
+                }
+                Err(duration) if throttle.action() == ThrottleAction::Reject => {
+                    let outbound_message = self.outbound_messages.remove(index);
+                    if let OutboundMessageBody::Message(message) = outbound_message.body {
+                        tx.send(EngineEvent::MessageRefused(
+                            connection,
+                            outbound_message.message_id,
+                            message,
+                            MessageRefusedReason::Throttled(duration),
+                        ))
+                        .unwrap();
+                    }
+                }
+                Err(duration) => {
+                    hold_duration = Some(duration);
+                    index += 1;
+                }
+            }
+        }
+
+        //Try again once the first held message is allowed out.
+        if let Some(hold_duration) = hold_duration {
+            if self.throttle_timeout.is_none() {
+                self.throttle_timeout =
+                    Some(timer.set_timeout(hold_duration, (TimeoutType::Throttle, self.token)));
+            }
+            if !throttle.is_holding {
+                throttle.is_holding = true;
+                tx.send(EngineEvent::Throttled(connection, hold_duration))
+                    .unwrap();
+            }
+        }
+
+        None
+    }
+
+    //Drop every queued message that hasn't been written yet. Application messages are kept until
+    //they can be reported using EngineEvent::MessageNotSent.
+    fn discard_outbound_messages(&mut self) {
+        for message in self.outbound_messages.drain(..) {
+            if let (Some(message_id), OutboundMessageBody::Message(message)) =
+                (message.message_id, message.body)
+            {
+                self.unsent_messages.push((message_id, message));
+            }
+        }
+    }
+
+    fn report_unsent_messages(&mut self, tx: &Sender<EngineEvent>) {
+        let connection = self.as_connection();
+        for (message_id, message) in self.unsent_messages.drain(..) {
+            tx.send(EngineEvent::MessageNotSent(connection, message_id, message))
+                .unwrap();
+        }
+    }
+
+    fn set_status(&mut self, status: ConnectionStatus) {
+        self.status = status;
+        self.update_session_state();
+    }
+
+    //Record a transition whenever anything the public SessionState is derived from changes.
+    fn update_session_state(&mut self) {
+        let session_state = match self.status {
+            ConnectionStatus::SendingLogon if !self.is_connected => SessionState::Connecting,
+            ConnectionStatus::SendingLogon => SessionState::LoggingOn,
+            ConnectionStatus::ReceivingLogon(_, _) => SessionState::AwaitingLogon,
+            ConnectionStatus::ApprovingLogon => SessionState::ApprovingLogon,
+            ConnectionStatus::Established if self.inbound_resend_request_msg_seq_num.is_some() => {
+                SessionState::Resending
+            }
+            ConnectionStatus::Established => SessionState::Established,
+            ConnectionStatus::LoggingOut(_) => SessionState::LoggingOut,
+        };
+        self.set_session_state(session_state);
+    }
+
+    fn set_session_state(&mut self, session_state: SessionState) {
+        if session_state != self.session_state {
+            self.session_state_changes
+                .push((self.session_state, session_state));
+            self.session_state = session_state;
+            self.publish_session_info();
+        }
+    }
+
+    //Transitions are always drained so they don't pile up but only sent when requested.
+    fn report_session_state_changes(&mut self, tx: &Sender<EngineEvent>, enabled: bool) {
+        let connection = self.as_connection();
+        for (old_state, new_state) in self.session_state_changes.drain(..) {
+            if enabled {
+                tx.send(EngineEvent::SessionStateChanged(
+                    connection, old_state, new_state,
+                ))
+                .unwrap();
+            }
+        }
+    }
+
+    fn session_info(&self) -> SessionInfo {
+        SessionInfo {
+            state: self.session_state,
+            session_id: self.session_id(),
+            default_appl_ver_id: self.default_message_version,
+            next_msg_seq_nums: self.msg_seq_nums(),
+            heart_bt_int: self.outbound_heartbeat_timeout_duration,
+            peer_addr: if self.is_connected {
+                self.socket.socket().peer_addr()
+            } else {
+                None
+            },
+        }
+    }
+
+    //Share the current SessionInfo with the Engine. Must be called whenever anything it's built
+    //from changes.
+    fn publish_session_info(&self) {
+        *self.published_session_info.lock().unwrap() = self.session_info();
+    }
+
+    //Same as publish_session_info() but cheap enough to call for every message.
+    fn publish_msg_seq_nums(&self) {
+        self.published_session_info
+            .lock()
+            .unwrap()
+            .next_msg_seq_nums = self.msg_seq_nums();
+    }
+
+    fn update_outbound_queue_depth(&self) {
+        self.stats.lock().unwrap().outbound_queue_depth = self.outbound_messages.len() as u64;
+    }
+
     fn read(
         &mut self,
         timer: &mut Timer<(TimeoutType, Token)>,
+        capture_raw_messages: bool,
     ) -> Result<Vec<ConnectionReadMessage>, ::std::io::Error> {
         fn parse_bytes(
             connection: &mut InternalConnection,
             messages: &mut Vec<ConnectionReadMessage>,
         ) -> bool {
             while !connection.inbound_buffer.is_empty() {
+                let parse_start = Instant::now();
                 let (bytes_parsed, result) =
                     connection.parser.parse(connection.inbound_buffer.bytes());
+                connection
+                    .stats
+                    .lock()
+                    .unwrap()
+                    .record_parse_time(parse_start.elapsed());
 
                 assert!(bytes_parsed > 0);
                 connection.inbound_buffer.consume(bytes_parsed);
 
                 //Retain order by extracting messages and then the error from parser.
+                let mut raw_messages = connection.parser.raw_messages.drain(..);
                 for message in connection.parser.messages.drain(..) {
-                    messages.push(ConnectionReadMessage::Message(message));
+                    let raw_message = raw_messages.next().unwrap_or_default();
+                    messages.push(ConnectionReadMessage::Message(message, raw_message));
                 }
                 if let Err(e) = result {
-                    messages.push(ConnectionReadMessage::Error(e));
+                    let raw_message = mem::take(&mut connection.parser.raw_garbled_message);
+                    messages.push(ConnectionReadMessage::Error(e, raw_message));
                 }
 
-                //Stop reading once INBOUND_MESSAGES_BUFFER_LEN_MAX messages have been read.
+                //Stop reading once inbound_messages_buffer_len_max messages have been read.
                 //This prevents a flood of messages from completely stalling the thread.
-                if messages.len() >= INBOUND_MESSAGES_BUFFER_LEN_MAX {
+                if messages.len() >= connection.options.inbound_messages_buffer_len_max {
                     return false;
                 }
                 //Stop reading temporarily after receiving the first message (that should be a
@@ -550,6 +1138,8 @@ impl InternalConnection {
             true
         }
 
+        self.parser.set_capture_raw_messages(capture_raw_messages);
+
         let mut messages = Vec::new();
         let mut keep_reading = parse_bytes(self, &mut messages);
 
@@ -568,7 +1158,8 @@ impl InternalConnection {
             match self.inbound_buffer.clear_and_read(&mut self.socket) {
                 Ok(bytes_read) => {
                     if bytes_read == 0 {
-                        //Socket exhausted.
+                        //Socket was closed on the other side.
+                        self.is_peer_closed = true;
                         break;
                     }
 
@@ -601,10 +1192,38 @@ impl InternalConnection {
 
     fn shutdown(&mut self) {
         let _ = self.socket.shutdown(Shutdown::Both);
-        self.outbound_messages.clear();
+        if let Some(receipt) = self.outbound_receipt.take() {
+            self.unsent_messages
+                .push((receipt.message_id, receipt.message));
+        }
+        self.discard_outbound_messages();
         self.outbound_buffer.clear();
     }
 
+    //Logout when the HeartBtInt in the remote's Logon is outside of the range allowed by the
+    //session's options. Returns true when logging out.
+    fn initiate_logout_if_heart_bt_int_out_of_range(
+        &mut self,
+        timer: &mut Timer<(TimeoutType, Token)>,
+        heart_bt_int: i64,
+    ) -> bool {
+        let range = match self.options.heart_bt_int_range {
+            Some(ref range) if !range.contains(&heart_bt_int) => range.clone(),
+            _ => return false,
+        };
+
+        let mut text = b"HeartBtInt must be between ".to_vec();
+        text.extend_from_slice(range.start().to_string().as_bytes());
+        text.extend_from_slice(b" and ");
+        text.extend_from_slice(range.end().to_string().as_bytes());
+        self.initiate_logout(
+            timer,
+            LoggingOutType::Error(ConnectionTerminatedReason::LogonHeartBtIntOutOfRangeError),
+            &text[..],
+        );
+        true
+    }
+
     fn initiate_logout(
         &mut self,
         timer: &mut Timer<(TimeoutType, Token)>,
@@ -626,25 +1245,20 @@ impl InternalConnection {
         let mut logout = Logout::new();
         logout.text = text.to_vec();
 
-        //TODO: The clearing of outbound messages might be optional. Probably need a receipt or
-        //something for those that are left unprocessed.
-        self.outbound_messages.clear(); //TODO: May want to store unprocessed messages so engine knows what didn't go out.
+        //TODO: The clearing of outbound messages might be optional.
+        self.discard_outbound_messages();
         self.outbound_messages.push(OutboundMessage::from(logout));
 
         //If attempting to logout cleanly, setup timer to auto-logout if we don't get a Logout
         //response. LoggingOutType::Error just disconnects immediately.
         if let LoggingOutType::Ok = logging_out_type {
-            self.logout_timeout = Some(
-                timer
-                    .set_timeout(
-                        Duration::from_secs(AUTO_DISCONNECT_AFTER_INITIATING_LOGOUT_SECS),
-                        (TimeoutType::Logout, self.token),
-                    )
-                    .unwrap(),
-            );
+            self.logout_timeout = Some(timer.set_timeout(
+                Duration::from_secs(AUTO_DISCONNECT_AFTER_INITIATING_LOGOUT_SECS),
+                (TimeoutType::Logout, self.token),
+            ));
         }
 
-        self.status = ConnectionStatus::LoggingOut(logging_out_type);
+        self.set_status(ConnectionStatus::LoggingOut(logging_out_type));
     }
 
     fn respond_to_logout(&mut self) {
@@ -658,29 +1272,31 @@ impl InternalConnection {
         let logout = Logout::new();
         self.outbound_messages.push(OutboundMessage::from(logout));
 
-        self.status = ConnectionStatus::LoggingOut(LoggingOutType::Responding);
+        self.set_status(ConnectionStatus::LoggingOut(LoggingOutType::Responding));
     }
 
     fn increment_outbound_msg_seq_num(&mut self) -> Result<(), ConnectionTerminatedReason> {
         //Check for overflow before incrementing. Just force the connection to terminate if this
         //occurs. This number is so large that the only way it can be reached is if the other party
         //issues SequenceReset-Reset with a crazy high NewSeqNo. NewSeqNo values higher than
-        //u64::max_value() are outright rejected as parsing errors.
-        if self.outbound_msg_seq_num == u64::max_value() {
+        //u64::MAX are outright rejected as parsing errors.
+        if self.outbound_msg_seq_num == u64::MAX {
             return Err(ConnectionTerminatedReason::OutboundMsgSeqNumMaxExceededError);
         }
 
         self.outbound_msg_seq_num += 1;
+        self.publish_msg_seq_nums();
         Ok(())
     }
 
     fn increment_inbound_msg_seq_num(&mut self) -> Result<(), ConnectionTerminatedReason> {
         //See increment_outbound_msg_seq_num() for an explanation of this check.
-        if self.inbound_msg_seq_num == u64::max_value() {
+        if self.inbound_msg_seq_num == u64::MAX {
             return Err(ConnectionTerminatedReason::InboundMsgSeqNumMaxExceededError);
         }
 
         self.inbound_msg_seq_num += 1;
+        self.publish_msg_seq_nums();
         Ok(())
     }
 
@@ -689,6 +1305,8 @@ impl InternalConnection {
         timer: &mut Timer<(TimeoutType, Token)>,
     ) {
         self.inbound_resend_request_msg_seq_num = None;
+        self.outbound_resend_request = None;
+        self.update_session_state();
 
         //If remote started a logout, we noticed missing messaged, and have now
         //received all of those messages, finally respond to logout.
@@ -717,14 +1335,10 @@ impl InternalConnection {
 
         //Setup a timer to disconnect if inbound blocking is not stopped shortly. Otherwise, the
         //connection is just sitting there wasting space and is unusable.
-        self.inbound_blocked_timeout = Some(
-            timer
-                .set_timeout(
-                    Duration::from_secs(AUTO_DISCONNECT_AFTER_WRITE_BLOCKS_SECS),
-                    (TimeoutType::InboundBlocked, self.token),
-                )
-                .unwrap(),
-        );
+        self.inbound_blocked_timeout = Some(timer.set_timeout(
+            Duration::from_secs(AUTO_DISCONNECT_AFTER_WRITE_BLOCKS_SECS),
+            (TimeoutType::InboundBlocked, self.token),
+        ));
     }
 
     fn end_blocking_inbound(
@@ -747,109 +1361,710 @@ impl InternalConnection {
         network_read_retry.queue(self.token);
     }
 
-    fn as_connection(&self) -> Connection {
-        Connection(self.token.0)
+    fn is_resending_from_message_store(&self) -> bool {
+        self.message_store.is_some()
+            && self.resend_request_handling == ResendRequestHandling::Automatic
     }
-}
 
-macro_rules! try_write_connection_or_terminate {
-    ( $connection_entry:ident, $internal_thread:ident ) => {
-        if let Err(e) = $connection_entry.get_mut().write(
-            &mut $internal_thread.timer,
-            &mut $internal_thread.network_read_retry,
-        ) {
-            return Err(ConnectionEventError::TerminateConnection(
-                $connection_entry.remove(),
-                e,
-            ));
+    fn push_deferred_resend_request(&mut self) {
+        //If we are still waiting on a response to our own ResendRequest, send a new
+        //ResendRequest. Deferring like this is the correct behavior according to FIXT v1.1, page
+        //13.
+        if self.inbound_resend_request_msg_seq_num.is_some() {
+            let mut resend_request = ResendRequest::new();
+            resend_request.begin_seq_no = self.inbound_msg_seq_num;
+            resend_request.end_seq_no = 0;
+            self.outbound_messages
+                .push(OutboundMessage::from(resend_request));
         }
-    };
-}
+    }
 
-struct InternalListener {
-    socket: TcpListener,
-    token: Token,
-    sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
-}
+    //Send a ResendRequest for range. If the remote answered the previous ResendRequest for some
+    //of the same messages with garbled messages too many times in a row, logout instead and
+    //return false.
+    fn push_resend_request(
+        &mut self,
+        range: Range<MsgSeqNumType>,
+        tx: &Sender<EngineEvent>,
+        timer: &mut Timer<(TimeoutType, Token)>,
+    ) -> bool {
+        let attempts = match self.outbound_resend_request {
+            Some(ref previous) if previous.received_garbled && range.start < previous.range.end => {
+                previous.attempts + 1
+            }
+            _ => 1,
+        };
 
-impl InternalListener {
-    fn as_listener(&self) -> Listener {
-        Listener(self.token.0)
-    }
-}
+        if attempts > self.options.garbled_resend_request_retry_count + 1 {
+            if !self.status.is_logging_out_with_error() {
+                let mut text = b"Detected garbled ResendRequest loop for BeginSeqNo ".to_vec();
+                text.extend_from_slice(range.start.to_string().as_bytes());
+                self.initiate_logout(
+                    timer,
+                    LoggingOutType::Error(
+                        ConnectionTerminatedReason::OutboundResendRequestLoopError {
+                            range,
+                            attempts: attempts - 1,
+                        },
+                    ),
+                    &text[..],
+                );
+            }
+            return false;
+        }
 
-struct InternalThread {
-    poll: Poll,
-    token_generator: Arc<Mutex<TokenGenerator>>,
-    tx: Sender<EngineEvent>,
-    rx: Receiver<InternalEngineToThreadEvent>,
-    message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
-    max_message_size: u64,
-    connections: HashMap<Token, InternalConnection>,
-    listeners: HashMap<Token, InternalListener>,
-    timer: Timer<(TimeoutType, Token)>,
-    network_read_retry: NetworkReadRetry,
-}
+        if attempts > 1 {
+            tx.send(EngineEvent::ResendRequestRetried(
+                self.as_connection(),
+                range.clone(),
+                attempts,
+            ))
+            .unwrap();
+        }
 
-impl InternalThread {
-    fn on_internal_engine_event(&mut self) -> Result<(), ConnectionEventError> {
-        let engine_event = match self.rx.try_recv() {
-            Ok(e) => e,
-            Err(_) => return Ok(()), //Shouldn't be possible but PROBABLY just means no engine events are available.
-        };
+        let mut resend_request = ResendRequest::new();
+        resend_request.begin_seq_no = range.start;
+        resend_request.end_seq_no = 0;
+        self.outbound_messages
+            .push(OutboundMessage::from(resend_request));
+        self.outbound_resend_request = Some(OutboundResendRequest {
+            range,
+            attempts,
+            received_garbled: false,
+        });
 
-        match engine_event {
-            //Engine wants to setup a new connection.
-            InternalEngineToThreadEvent::NewConnection(
+        true
+    }
+
+    fn push_resend_gap_fill(&mut self, range: Range<MsgSeqNumType>, now: &DateTime<Utc>) {
+        let mut sequence_reset = SequenceReset::new();
+        sequence_reset.gap_fill_flag = true;
+        sequence_reset.msg_seq_num = range.start;
+        sequence_reset.new_seq_no = range.end;
+        sequence_reset.poss_dup_flag = true;
+        sequence_reset.orig_sending_time = *now;
+        self.outbound_messages
+            .push(OutboundMessage::new(sequence_reset, false));
+    }
+
+    fn resend_from_message_store(&mut self, range: Range<MsgSeqNumType>, now: &DateTime<Utc>) {
+        //Never resend or gap fill past the last MsgSeqNum actually sent.
+        let range = range.start..cmp::min(range.end, self.outbound_msg_seq_num);
+        if range.start >= range.end {
+            return;
+        }
+
+        let stored_messages = match self.message_store.as_mut().unwrap().fetch(range.clone()) {
+            Ok(stored_messages) => stored_messages,
+            Err(e) => {
+                //Nothing can be resent so the best remaining option is to skip the messages
+                //entirely.
+                log::error!(
+                    "could not fetch messages {:?} from message store: {}",
+                    range,
+                    e
+                );
+                Vec::new()
+            }
+        };
+
+        //Administrative messages, except for Reject, are never resent. They are replaced with a
+        //SequenceReset-GapFill along with any messages that were not recorded. Consecutive gaps
+        //are collapsed into a single SequenceReset-GapFill. See FIXT v1.1, page 13.
+        let mut next_msg_seq_num = range.start;
+        for stored_message in stored_messages {
+            let is_resendable = stored_message.msg_type == Reject::msg_type()
+                || !administrative_msg_types().contains(&&stored_message.msg_type[..]);
+            if !is_resendable {
+                continue;
+            }
+
+            let bytes = match prepare_stored_message_for_resend(&stored_message.bytes[..], now) {
+                Some(bytes) => bytes,
+                None => continue,
+            };
+
+            if next_msg_seq_num < stored_message.msg_seq_num {
+                self.push_resend_gap_fill(next_msg_seq_num..stored_message.msg_seq_num, now);
+            }
+            self.outbound_messages
+                .push(OutboundMessage::from_serialized(bytes));
+            next_msg_seq_num = stored_message.msg_seq_num + 1;
+        }
+        if next_msg_seq_num < range.end {
+            self.push_resend_gap_fill(next_msg_seq_num..range.end, now);
+        }
+    }
+
+    //Take the limits the remote asked for in its Logon into account. Outbound messages are
+    //limited to what the remote receives and inbound messages to what it sends.
+    fn apply_remote_logon_limits(&mut self, logon: &Logon) {
+        self.outbound_max_message_size = logon.max_message_size;
+        restrict_msg_types(
+            &mut self.outbound_msg_types,
+            &logon.no_msg_types,
+            MsgDirection::Receive,
+        );
+        restrict_msg_types(
+            &mut self.inbound_msg_types,
+            &logon.no_msg_types,
+            MsgDirection::Send,
+        );
+    }
+
+    //Logon recovery for FIX 4.4 and later: the remote's Logon says which MsgSeqNum it expects
+    //next so anything it missed before end_msg_seq_num is resent or gap filled right away. A
+    //remote expecting a MsgSeqNum that was never sent can't be recovered.
+    fn on_logon_next_expected_msg_seq_num(
+        &mut self,
+        next_expected_msg_seq_num: MsgSeqNumType,
+        end_msg_seq_num: MsgSeqNumType,
+        tx: &Sender<EngineEvent>,
+        timer: &mut Timer<(TimeoutType, Token)>,
+    ) {
+        if next_expected_msg_seq_num > end_msg_seq_num {
+            let mut text = b"NextExpectedMsgSeqNum too high, expected at most ".to_vec();
+            text.extend_from_slice(end_msg_seq_num.to_string().as_bytes());
+            text.extend_from_slice(b" but received ");
+            text.extend_from_slice(next_expected_msg_seq_num.to_string().as_bytes());
+            self.initiate_logout(
+                timer,
+                LoggingOutType::Error(
+                    ConnectionTerminatedReason::LogonNextExpectedMsgSeqNumTooHighError,
+                ),
+                &text[..],
+            );
+        } else if next_expected_msg_seq_num < end_msg_seq_num {
+            let range = next_expected_msg_seq_num..end_msg_seq_num;
+            if self.is_resending_from_message_store() {
+                self.resend_from_message_store(range, &timer.now_utc_timestamp());
+            } else {
+                tx.send(EngineEvent::ResendRequested(self.as_connection(), range))
+                    .unwrap();
+            }
+        }
+    }
+
+    //Queue a Logon with ResetSeqNumFlag set to start both MsgSeqNums over in the middle of a
+    //session. Used both to request a reset and to acknowledge the remote's request.
+    fn push_reset_seq_num_logon(&mut self) {
+        let mut logon = Logon::new();
+        logon.encrypt_method = EncryptMethod::None;
+        logon.heart_bt_int = self
+            .outbound_heartbeat_timeout_duration
+            .map_or(0, |duration| duration.as_secs() as i64);
+        logon.reset_seq_num_flag = true;
+        logon.default_appl_ver_id = self.default_message_version;
+
+        let mut outbound_message = OutboundMessage::from(logon);
+        outbound_message.message_version = Some(self.fix_version.max_message_version());
+        self.outbound_messages.push(outbound_message);
+    }
+
+    fn as_connection(&self) -> Connection {
+        Connection(self.token.0)
+    }
+
+    fn session_id(&self) -> SessionID {
+        SessionID::new(
+            self.fix_version,
+            &self.sender_comp_id[..],
+            &self.target_comp_id[..],
+        )
+    }
+
+    //Same as session_id() except an acceptor that hasn't received a Logon yet uses the
+    //SenderCompID of message.
+    fn inbound_session_id(&self, message: &dyn FIXTMessage) -> SessionID {
+        if self.target_comp_id.is_empty() {
+            SessionID::new(
+                self.fix_version,
+                &self.sender_comp_id[..],
+                &message.sender_comp_id()[..],
+            )
+        } else {
+            self.session_id()
+        }
+    }
+
+    fn msg_seq_nums(&self) -> MsgSeqNums {
+        MsgSeqNums::new(self.inbound_msg_seq_num, self.outbound_msg_seq_num)
+    }
+
+    fn set_msg_seq_nums(&mut self, msg_seq_nums: MsgSeqNums) {
+        self.inbound_msg_seq_num = msg_seq_nums.inbound;
+        self.outbound_msg_seq_num = msg_seq_nums.outbound;
+        self.publish_msg_seq_nums();
+        self.persisted_msg_seq_nums = Some(msg_seq_nums);
+    }
+
+    fn persist_msg_seq_nums(
+        &mut self,
+        seq_num_stores: &mut SeqNumStores,
+    ) -> Result<(), ConnectionTerminatedReason> {
+        //Connections accepted by a listener don't know which session they belong to until the
+        //Logon has been approved.
+        if self.status.is_receiving_logon() || self.status.is_approving_logon() {
+            return Ok(());
+        }
+
+        let session_id = self.session_id();
+        if let Some(seq_num_store) = seq_num_stores.get_mut(&session_id) {
+            let msg_seq_nums = self.msg_seq_nums();
+            if self.persisted_msg_seq_nums != Some(msg_seq_nums) {
+                if let Err(e) = seq_num_store.save(&session_id, msg_seq_nums) {
+                    self.shutdown();
+                    return Err(ConnectionTerminatedReason::SeqNumStoreError(e));
+                }
+                self.persisted_msg_seq_nums = Some(msg_seq_nums);
+            }
+        }
+
+        Ok(())
+    }
+}
+
+//Narrow the allowed application MsgTypes down to the ones listed in a Logon's NoMsgTypes for
+//the given direction. A Logon that doesn't list any for the direction leaves it unrestricted.
+fn restrict_msg_types(
+    msg_types: &mut Option<HashSet<Vec<u8>>>,
+    no_msg_types: &[Box<MsgTypeGrp>],
+    msg_direction: MsgDirection,
+) {
+    let listed_msg_types: HashSet<Vec<u8>> = no_msg_types
+        .iter()
+        .filter(|msg_type| msg_type.msg_direction == msg_direction)
+        .map(|msg_type| msg_type.ref_msg_type.clone())
+        .collect();
+    if listed_msg_types.is_empty() {
+        return;
+    }
+
+    *msg_types = Some(match msg_types.take() {
+        Some(msg_types) => msg_types.intersection(&listed_msg_types).cloned().collect(),
+        None => listed_msg_types,
+    });
+}
+
+//Size of a parsed message in bytes as it appeared on the wire.
+fn message_size(message: &dyn FIXTMessage) -> u64 {
+    match *message.meta() {
+        Some(ref meta) => {
+            let header_len = b"8=\x019=\x01".len() + meta.begin_string.begin_string().len();
+            let trailer_len = b"10=000\x01".len();
+            (header_len + meta.body_length.to_string().len() + trailer_len) as u64
+                + meta.body_length
+        }
+        None => 0,
+    }
+}
+
+//MsgType of an already serialized message. MsgType is always the third tag so it's found quickly.
+fn serialized_msg_type(bytes: &[u8]) -> &[u8] {
+    let msg_type_tag = b"\x0135=";
+    match bytes
+        .windows(msg_type_tag.len())
+        .position(|window| window == msg_type_tag)
+    {
+        Some(position) => {
+            let msg_type = &bytes[position + msg_type_tag.len()..];
+            let end = msg_type
+                .iter()
+                .position(|b| *b == b'\x01')
+                .unwrap_or(msg_type.len());
+            &msg_type[..end]
+        }
+        None => b"",
+    }
+}
+
+//Administrative messages are always allowed so the session itself keeps working.
+fn is_msg_type_allowed(msg_types: &Option<HashSet<Vec<u8>>>, msg_type: &[u8]) -> bool {
+    match *msg_types {
+        Some(ref msg_types) => {
+            administrative_msg_types().contains(&msg_type) || msg_types.contains(msg_type)
+        }
+        None => true,
+    }
+}
+
+//Writes any pending messages and then saves the MsgSeqNums if they changed. Every path that
+//receives or sends messages ends with this so the SeqNumStore never falls behind.
+macro_rules! try_write_connection_or_terminate {
+    ( $connection_entry:ident, $internal_thread:ident ) => {
+        let result = match $connection_entry.get_mut().write(
+            &mut $internal_thread.timer,
+            &mut $internal_thread.network_read_retry,
+            &$internal_thread.tx,
+            &mut $internal_thread.outbound_message_hook,
+            &mut $internal_thread.message_log,
+            &mut $internal_thread.logon_decorator,
+        ) {
+            Ok(()) => $connection_entry
+                .get_mut()
+                .persist_msg_seq_nums(&mut $internal_thread.seq_num_stores),
+            Err(e) => Err(e),
+        };
+        if let Err(e) = result {
+            return Err(ConnectionEventError::TerminateConnection(
+                Box::new($connection_entry.remove()),
+                e,
+            ));
+        }
+    };
+}
+
+struct ScheduleState {
+    schedule: SessionSchedule,
+    next_reset: Option<DateTime<Utc>>,
+    timeout: Option<Timeout>,
+}
+
+impl ScheduleState {
+    fn new(schedule: SessionSchedule, now: &DateTime<Utc>) -> ScheduleState {
+        let next_reset = schedule.next_reset(now);
+        ScheduleState {
+            schedule,
+            next_reset,
+            timeout: None,
+        }
+    }
+
+    //Returns true once for every reset time that has passed.
+    fn take_due_reset(&mut self, now: &DateTime<Utc>) -> bool {
+        match self.next_reset {
+            Some(next_reset) if next_reset <= *now => {
+                self.next_reset = self.schedule.next_reset(now);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    //Wake up at the next start, end, or reset time. Whichever comes first. A retry wakes up
+    //sooner than that to try connecting again.
+    fn arm(
+        &mut self,
+        timer: &mut Timer<(TimeoutType, Token)>,
+        token: Token,
+        now: &DateTime<Utc>,
+        retry: Option<Duration>,
+    ) {
+        self.cancel(timer);
+
+        let mut wake_time = self.schedule.next_transition(now);
+        if let Some(next_reset) = self.next_reset {
+            wake_time = cmp::min(wake_time, next_reset);
+        }
+        let mut duration = (wake_time - *now)
+            .to_std()
+            .unwrap_or_else(|_| Duration::from_millis(0));
+        if let Some(retry) = retry {
+            duration = cmp::min(duration, retry);
+        }
+
+        self.timeout = Some(timer.set_timeout(duration, (TimeoutType::Schedule, token)));
+    }
+
+    fn cancel(&mut self, timer: &mut Timer<(TimeoutType, Token)>) {
+        if let Some(timeout) = self.timeout.take() {
+            timer.cancel_timeout(&timeout);
+        }
+    }
+}
+
+struct ReconnectState {
+    policy: ReconnectPolicy,
+    attempt: u32,
+    timeout: Option<Timeout>,
+}
+
+impl ReconnectState {
+    fn cancel(&mut self, timer: &mut Timer<(TimeoutType, Token)>) {
+        if let Some(timeout) = self.timeout.take() {
+            timer.cancel_timeout(&timeout);
+        }
+    }
+}
+
+//Everything needed to connect and logon again each time an initiated session starts over. Either
+//because its schedule became active or because it's reconnecting after being disconnected.
+struct InitiatorSession {
+    fix_version: FIXVersion,
+    default_message_version: MessageVersion,
+    sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
+    target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+    addresses: Vec<TransportAddr>, //Primary address followed by any failover addresses.
+    address_index: usize,
+    logon: Box<Logon>,
+    stream_config: StreamConfig,
+    session_options: SessionOptions,
+    schedule: Option<ScheduleState>,
+    reconnect: Option<ReconnectState>,
+    msg_seq_nums: Option<MsgSeqNums>, //Where the last session left off.
+    throttle: Option<Throttle>,
+}
+
+impl InitiatorSession {
+    fn session_id(&self) -> SessionID {
+        SessionID::new(
+            self.fix_version,
+            &self.sender_comp_id[..],
+            &self.target_comp_id[..],
+        )
+    }
+
+    //SessionInfo while there's no connection open for the session.
+    fn disconnected_session_info(&self) -> Arc<Mutex<SessionInfo>> {
+        Arc::new(Mutex::new(SessionInfo {
+            state: SessionState::Disconnected,
+            session_id: self.session_id(),
+            default_appl_ver_id: self.default_message_version,
+            next_msg_seq_nums: self.msg_seq_nums.unwrap_or_default(),
+            heart_bt_int: None,
+            peer_addr: None,
+        }))
+    }
+
+    fn is_active(&self, now: &DateTime<Utc>) -> bool {
+        match self.schedule {
+            Some(ref schedule) => schedule.schedule.is_active(now),
+            None => true,
+        }
+    }
+
+    fn cancel_timeouts(&mut self, timer: &mut Timer<(TimeoutType, Token)>) {
+        if let Some(ref mut schedule) = self.schedule {
+            schedule.cancel(timer);
+        }
+        if let Some(ref mut reconnect) = self.reconnect {
+            reconnect.cancel(timer);
+        }
+    }
+}
+
+//Counterparty a listener expects to logon. Handed to each connection that is waiting on a Logon
+//so it can be matched against the Logon when it arrives.
+#[derive(Clone)]
+struct ExpectedSession {
+    token: Token,
+    fix_version: FIXVersion,
+    target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+    sender_sub_id: Option<Vec<u8>>,
+    target_sub_id: Option<Vec<u8>>,
+    heart_bt_int: Option<i64>,
+    session_options: Option<SessionOptions>,
+    logon_allowed: bool,
+}
+
+impl ExpectedSession {
+    fn matches(&self, fix_version: FIXVersion, message: &Logon) -> bool {
+        self.fix_version == fix_version
+            && self.target_comp_id == message.sender_comp_id
+            && !matches!(self.sender_sub_id, Some(ref sender_sub_id) if *sender_sub_id != message.target_sub_id)
+            && !matches!(self.target_sub_id, Some(ref target_sub_id) if *target_sub_id != message.sender_sub_id)
+    }
+}
+
+struct InternalAcceptorSession {
+    listener: Token,
+    session_id: SessionID,
+    expected: ExpectedSession,
+    schedule: Option<ScheduleState>,
+}
+
+struct InternalListener {
+    socket: TransportListener,
+    stream_config: StreamConfig,
+    token: Token,
+    sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
+    schedule: Option<ScheduleState>,
+    seen_sessions: HashSet<SessionID>,
+    acceptor_sessions: Vec<Token>,
+    session_options: SessionOptions, //Used by accepted connections until they match an acceptor session with its own.
+}
+
+impl InternalListener {
+    fn as_listener(&self) -> Listener {
+        Listener(self.token.0)
+    }
+}
+
+//The Engine's SeqNumStore along with any given to individual acceptor sessions.
+#[derive(Default)]
+struct SeqNumStores {
+    default: Option<Box<dyn SeqNumStore + Send>>,
+    sessions: HashMap<SessionID, Box<dyn SeqNumStore + Send>>,
+}
+
+impl SeqNumStores {
+    fn get_mut(&mut self, session_id: &SessionID) -> Option<&mut Box<dyn SeqNumStore + Send>> {
+        match self.sessions.get_mut(session_id) {
+            Some(seq_num_store) => Some(seq_num_store),
+            None => self.default.as_mut(),
+        }
+    }
+}
+
+struct InternalThread {
+    worker: usize, //Index of this thread amongst the Engine's worker threads.
+    poll: Poll,
+    token_generator: Arc<Mutex<TokenGenerator>>,
+    worker_assignments: WorkerAssignments,
+    tx: Sender<EngineEvent>,
+    rx: Receiver<InternalEngineToThreadEvent>,
+    message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+    max_message_size: u64,
+    connections: HashMap<Token, InternalConnection>,
+    listeners: HashMap<Token, InternalListener>,
+    timer: Timer<(TimeoutType, Token)>,
+    network_read_retry: NetworkReadRetry,
+    message_store_factory: Option<Box<dyn MessageStoreFactory + Send>>,
+    seq_num_stores: SeqNumStores,
+    initiator_sessions: HashMap<Token, InitiatorSession>,
+    acceptor_sessions: HashMap<Token, InternalAcceptorSession>,
+    outbound_message_hook: Option<OutboundMessageHook>,
+    message_log: Option<Box<dyn MessageLog + Send>>,
+    logon_decorator: Option<LogonDecorator>,
+    logon_authenticator: Option<Box<dyn LogonAuthenticator + Send>>,
+    send_receipts: bool,
+    session_state_events: bool,
+    stats: StatsRegistry,
+    session_infos: SessionInfoRegistry,
+}
+
+impl InternalThread {
+    fn on_internal_engine_event(&mut self) -> Result<(), ConnectionEventError> {
+        let engine_event = match self.rx.try_recv() {
+            Ok(e) => e,
+            Err(_) => return Ok(()), //Shouldn't be possible but PROBABLY just means no engine events are available.
+        };
+
+        match engine_event {
+            //Engine wants to setup a new connection.
+            InternalEngineToThreadEvent::NewConnection(
                 token,
                 fix_version,
                 default_message_version,
                 sender_comp_id,
                 target_comp_id,
                 address,
+                msg_seq_nums,
+                stream_config,
+                session_options,
             ) => {
-                let socket = match TcpStream::connect(&address) {
-                    Ok(socket) => socket,
-                    Err(e) => {
-                        self.tx
-                            .send(EngineEvent::ConnectionFailed(Connection(token.0), e))
-                            .unwrap();
-                        return Ok(());
-                    }
+                //Resume the session where it left off when possible.
+                let msg_seq_nums = match msg_seq_nums {
+                    Some(msg_seq_nums) => Some(msg_seq_nums),
+                    None => match self.load_msg_seq_nums(&SessionID::new(
+                        fix_version,
+                        &sender_comp_id[..],
+                        &target_comp_id[..],
+                    )) {
+                        Ok(msg_seq_nums) => msg_seq_nums,
+                        Err(e) => {
+                            self.tx
+                                .send(EngineEvent::ConnectionFailed(Connection(token.0), e))
+                                .unwrap();
+                            return Ok(());
+                        }
+                    },
                 };
 
-                let connection = InternalConnection::new(
-                    self.message_dictionary.clone(),
-                    self.max_message_size,
+                if let Err(e) = self.connect(
+                    token,
                     fix_version,
                     default_message_version,
-                    socket,
-                    token,
                     sender_comp_id,
                     target_comp_id,
-                );
-
-                //Have poll let us know when we can can read or write.
-                if let Err(e) = self.poll.register(
-                    &connection.socket,
-                    connection.token,
-                    Ready::readable() | Ready::writable() | UnixReady::hup() | UnixReady::error(),
-                    PollOpt::edge(),
+                    &address,
+                    msg_seq_nums,
+                    stream_config,
+                    session_options,
                 ) {
                     self.tx
-                        .send(EngineEvent::ConnectionFailed(connection.as_connection(), e))
+                        .send(EngineEvent::ConnectionFailed(Connection(token.0), e))
                         .unwrap();
-                    return Ok(());
                 }
+            }
+            //Engine wants to setup a connection that follows a schedule and/or reconnects
+            //automatically.
+            InternalEngineToThreadEvent::NewInitiatorSession(
+                token,
+                fix_version,
+                default_message_version,
+                sender_comp_id,
+                target_comp_id,
+                address,
+                msg_seq_nums,
+                schedule,
+                reconnect_policy,
+                logon,
+                stream_config,
+                session_options,
+            ) => {
+                let now = self.timer.now_utc();
+                let mut addresses = vec![address];
+                if let Some(ref reconnect_policy) = reconnect_policy {
+                    addresses.extend(
+                        reconnect_policy
+                            .failover_addresses()
+                            .iter()
+                            .cloned()
+                            .map(TransportAddr::Tcp),
+                    );
+                }
+                let mut initiator_session = InitiatorSession {
+                    fix_version,
+                    default_message_version,
+                    sender_comp_id,
+                    target_comp_id,
+                    addresses,
+                    address_index: 0,
+                    logon,
+                    stream_config,
+                    session_options,
+                    schedule: schedule.map(|schedule| ScheduleState::new(schedule, &now)),
+                    reconnect: reconnect_policy.map(|policy| ReconnectState {
+                        policy,
+                        attempt: 0,
+                        timeout: None,
+                    }),
+                    msg_seq_nums,
+                    throttle: None,
+                };
 
-                self.connections.insert(token, connection);
+                //Scheduled sessions leave connecting up to the schedule timeout which fires right
+                //away.
+                self.session_infos.insert(
+                    Connection(token.0),
+                    initiator_session.disconnected_session_info(),
+                );
+                if let Some(ref mut schedule) = initiator_session.schedule {
+                    schedule.arm(&mut self.timer, token, &now, Some(Duration::from_millis(0)));
+                    self.initiator_sessions.insert(token, initiator_session);
+                } else {
+                    self.initiator_sessions.insert(token, initiator_session);
+                    self.start_initiator_session(token);
+                }
             }
             //Engine wants to setup a listener to accept new connections.
-            InternalEngineToThreadEvent::NewListener(token, sender_comp_id, socket) => {
+            InternalEngineToThreadEvent::NewListener(
+                token,
+                sender_comp_id,
+                socket,
+                stream_config,
+                session_options,
+            ) => {
                 let listener = InternalListener {
                     socket,
+                    stream_config,
                     token,
                     sender_comp_id,
+                    schedule: None,
+                    seen_sessions: HashSet::new(),
+                    acceptor_sessions: Vec::new(),
+                    session_options,
                 };
 
                 if let Err(e) = self.poll.register(
@@ -866,16 +2081,97 @@ impl InternalThread {
 
                 self.listeners.insert(token, listener);
             }
+            //Engine wants a listener to only accept logons according to a schedule.
+            InternalEngineToThreadEvent::SetListenerSessionSchedule(token, schedule) => {
+                if let Some(listener) = self.listeners.get_mut(&token) {
+                    //Check the schedule right away in case existing sessions need to logout.
+                    let now = self.timer.now_utc();
+                    if let Some(mut old_schedule) = listener.schedule.take() {
+                        old_schedule.cancel(&mut self.timer);
+                    }
+                    let mut schedule = ScheduleState::new(schedule, &now);
+                    schedule.arm(&mut self.timer, token, &now, Some(Duration::from_millis(0)));
+                    listener.schedule = Some(schedule);
+                } else {
+                    //Silently ignore for an invalid listener.
+                    //TODO: Maybe submit this to a logging system or something?
+                }
+            }
+            //Engine wants a listener to expect a specific counterparty.
+            InternalEngineToThreadEvent::AddAcceptorSession(listener_token, token, session) => {
+                if let Some(listener) = self.listeners.get_mut(&listener_token) {
+                    let session_id = SessionID::new(
+                        session.fix_version,
+                        &listener.sender_comp_id[..],
+                        &session.target_comp_id[..],
+                    );
+                    if let Some(seq_num_store) = session.seq_num_store {
+                        self.seq_num_stores
+                            .sessions
+                            .insert(session_id.clone(), seq_num_store);
+                    }
+
+                    //Check the schedule right away like the listener does.
+                    let now = self.timer.now_utc();
+                    let timer = &mut self.timer;
+                    let schedule = session.schedule.map(|schedule| {
+                        let mut schedule = ScheduleState::new(schedule, &now);
+                        schedule.arm(timer, token, &now, Some(Duration::from_millis(0)));
+                        schedule
+                    });
+
+                    listener.acceptor_sessions.push(token);
+                    self.acceptor_sessions.insert(
+                        token,
+                        InternalAcceptorSession {
+                            listener: listener_token,
+                            session_id,
+                            expected: ExpectedSession {
+                                token,
+                                fix_version: session.fix_version,
+                                target_comp_id: session.target_comp_id,
+                                sender_sub_id: session.sender_sub_id,
+                                target_sub_id: session.target_sub_id,
+                                heart_bt_int: session.heart_bt_int,
+                                session_options: session.session_options,
+                                logon_allowed: true,
+                            },
+                            schedule,
+                        },
+                    );
+                } else {
+                    //Listener failed to start so the session can never be used.
+                    self.token_generator.lock().unwrap().remove(token);
+                }
+            }
             //Engine wants to send a message over a connection.
-            InternalEngineToThreadEvent::SendMessage(token, message_version, message) => {
+            InternalEngineToThreadEvent::SendMessage(
+                token,
+                message_id,
+                message_version,
+                message,
+            ) => {
                 if let Entry::Occupied(mut connection_entry) = self.connections.entry(token) {
                     let mut outbound_message = OutboundMessage::from_box(message);
                     outbound_message.message_version = message_version;
+                    if self.send_receipts {
+                        outbound_message.message_id = Some(message_id);
+                    }
                     connection_entry
                         .get_mut()
                         .outbound_messages
                         .push(outbound_message);
                     try_write_connection_or_terminate!(connection_entry, self);
+                } else if self.send_receipts {
+                    //Hand message back for an invalid connection or an initiated session that's
+                    //between connections.
+                    self.tx
+                        .send(EngineEvent::MessageNotSent(
+                            Connection(token.0),
+                            message_id,
+                            message,
+                        ))
+                        .unwrap();
                 } else {
                     //Silently ignore message for invalid connection.
                     //TODO: Maybe submit this to a logging system or something?
@@ -918,39 +2214,97 @@ impl InternalThread {
                         }
                     }
 
-                    //If we are still waiting on a response to our own RespondRequest, send a new
-                    //RespondRequest. Deferring like this is the correct behavior according to FIXT
-                    //v1.1, page 13.
-                    if connection_entry
-                        .get()
-                        .inbound_resend_request_msg_seq_num
-                        .is_some()
+                    connection_entry.get_mut().push_deferred_resend_request();
+
+                    try_write_connection_or_terminate!(connection_entry, self);
+                } else {
+                    //Silently ignore message for invalid connection.
+                    //TODO: Maybe submit this to a logging system or something?
+                }
+            }
+            //Engine wants every new session to record its outbound messages.
+            InternalEngineToThreadEvent::SetMessageStoreFactory(message_store_factory) => {
+                self.message_store_factory = Some(message_store_factory);
+            }
+            //Engine wants every new session to resume from and keep track of its MsgSeqNums.
+            InternalEngineToThreadEvent::SetSeqNumStore(seq_num_store) => {
+                self.seq_num_stores.default = Some(seq_num_store);
+            }
+            //Engine wants to see every outbound message before it's sent.
+            InternalEngineToThreadEvent::SetOutboundMessageHook(outbound_message_hook) => {
+                self.outbound_message_hook = Some(outbound_message_hook);
+            }
+            //Engine wants every message read or written to be recorded.
+            InternalEngineToThreadEvent::SetMessageLog(message_log) => {
+                self.message_log = Some(message_log);
+            }
+            InternalEngineToThreadEvent::SetSendReceipts(enabled) => {
+                self.send_receipts = enabled;
+            }
+            InternalEngineToThreadEvent::SetSessionStateEvents(enabled) => {
+                self.session_state_events = enabled;
+            }
+            //Engine is blocked until everything before this event has been handled.
+            InternalEngineToThreadEvent::Sync(_, response_tx) => {
+                let _ = response_tx.send(());
+            }
+            InternalEngineToThreadEvent::SetLogonDecorator(logon_decorator) => {
+                self.logon_decorator = Some(logon_decorator);
+            }
+            InternalEngineToThreadEvent::SetLogonAuthenticator(logon_authenticator) => {
+                self.logon_authenticator = Some(logon_authenticator);
+            }
+            InternalEngineToThreadEvent::SetClock(clock) => {
+                self.timer.set_clock(clock);
+            }
+            //Engine wants to change who responds to ResendRequests on a connection.
+            InternalEngineToThreadEvent::SetResendRequestHandling(
+                token,
+                resend_request_handling,
+            ) => {
+                if let Some(connection) = self.connections.get_mut(&token) {
+                    connection.resend_request_handling = resend_request_handling;
+                } else {
+                    //Silently ignore for an invalid connection.
+                    //TODO: Maybe submit this to a logging system or something?
+                }
+            }
+            InternalEngineToThreadEvent::SetThrottle(token, throttle) => {
+                //Initiated sessions keep their throttle across each new connection.
+                if let Some(initiator_session) = self.initiator_sessions.get_mut(&token) {
+                    initiator_session.throttle = throttle.clone();
+                }
+
+                if let Entry::Occupied(mut connection_entry) = self.connections.entry(token) {
                     {
-                        let mut resend_request = ResendRequest::new();
-                        resend_request.begin_seq_no = connection_entry.get().inbound_msg_seq_num;
-                        resend_request.end_seq_no = 0;
-                        connection_entry
-                            .get_mut()
-                            .outbound_messages
-                            .push(OutboundMessage::from(resend_request));
+                        let connection = connection_entry.get_mut();
+                        let now = self.timer.now();
+                        connection.throttle = throttle
+                            .as_ref()
+                            .map(|throttle| ThrottleState::new(throttle, now));
+                        if let Some(timeout) = connection.throttle_timeout.take() {
+                            self.timer.cancel_timeout(&timeout);
+                        }
                     }
 
+                    //Anything held back by the old throttle might be allowed out now.
                     try_write_connection_or_terminate!(connection_entry, self);
                 } else {
-                    //Silently ignore message for invalid connection.
+                    //Silently ignore for an invalid connection.
                     //TODO: Maybe submit this to a logging system or something?
                 }
             }
             //Engine wants to approve logon of a connection that was accepted by a listener.
             InternalEngineToThreadEvent::ApproveNewConnection(
                 connection,
-                message,
+                mut message,
                 inbound_msg_seq_num,
+                msg_seq_nums,
             ) => {
                 if let Entry::Occupied(mut connection_entry) =
                     self.connections.entry(Token(connection.0))
                 {
-                    {
+                    let logon_msg_seq_num = {
                         let connection = connection_entry.get_mut();
                         if !connection.status.is_approving_logon() {
                             //Silently ignore approval of connections that are not awaiting
@@ -959,7 +2313,89 @@ impl InternalThread {
                             return Ok(());
                         }
 
-                        connection.status = ConnectionStatus::Established;
+                        //Now that the remote is known, open the session's message store before the
+                        //Logon response is recorded.
+                        let session_id = connection.session_id();
+                        if let Some(listener) = connection.listener {
+                            if let Some(listener) = self.listeners.get_mut(&Token(listener.0)) {
+                                listener.seen_sessions.insert(session_id.clone());
+                            }
+                        }
+                        if let Some(ref mut message_store_factory) = self.message_store_factory {
+                            match message_store_factory.create(&session_id) {
+                                Ok(message_store) => connection.message_store = Some(message_store),
+                                Err(e) => {
+                                    connection.shutdown();
+                                    return Err(ConnectionEventError::TerminateConnection(
+                                        Box::new(connection_entry.remove()),
+                                        ConnectionTerminatedReason::MessageStoreError(e),
+                                    ));
+                                }
+                            }
+                        }
+
+                        //Resume the session's MsgSeqNums when they weren't explicitly given. Unless
+                        //the remote asked to start over using ResetSeqNumFlag. Then the response
+                        //must echo the flag.
+                        let msg_seq_nums = match (msg_seq_nums, inbound_msg_seq_num) {
+                            _ if connection.received_reset_seq_num_flag => {
+                                message.reset_seq_num_flag = true;
+                                Some(MsgSeqNums::new(connection.inbound_msg_seq_num - 1, 1))
+                            }
+                            (None, None) => {
+                                if let Some(seq_num_store) =
+                                    self.seq_num_stores.get_mut(&session_id)
+                                {
+                                    match seq_num_store.load(&session_id) {
+                                        Ok(msg_seq_nums) => msg_seq_nums,
+                                        Err(e) => {
+                                            connection.shutdown();
+                                            return Err(ConnectionEventError::TerminateConnection(
+                                                Box::new(connection_entry.remove()),
+                                                ConnectionTerminatedReason::SeqNumStoreError(e),
+                                            ));
+                                        }
+                                    }
+                                } else {
+                                    None
+                                }
+                            }
+                            (msg_seq_nums, _) => msg_seq_nums,
+                        };
+                        let inbound_msg_seq_num = if let Some(msg_seq_nums) = msg_seq_nums {
+                            //The received Logon has already been counted so inbound_msg_seq_num
+                            //is one past its MsgSeqNum.
+                            let logon_msg_seq_num = connection.inbound_msg_seq_num - 1;
+                            connection.outbound_msg_seq_num = msg_seq_nums.outbound;
+                            connection.publish_msg_seq_nums();
+
+                            if logon_msg_seq_num < msg_seq_nums.inbound {
+                                let mut text = b"MsgSeqNum too low, expected ".to_vec();
+                                text.extend_from_slice(msg_seq_nums.inbound.to_string().as_bytes());
+                                text.extend_from_slice(b" but received ");
+                                text.extend_from_slice(logon_msg_seq_num.to_string().as_bytes());
+                                connection.inbound_msg_seq_num = msg_seq_nums.inbound;
+                                connection.publish_msg_seq_nums();
+                                connection.initiate_logout(
+                                    &mut self.timer,
+                                    LoggingOutType::Error(
+                                        ConnectionTerminatedReason::InboundMsgSeqNumLowerThanExpectedError,
+                                    ),
+                                    &text[..],
+                                );
+                                connection.persisted_msg_seq_nums = Some(msg_seq_nums);
+                                try_write_connection_or_terminate!(connection_entry, self);
+                                return Ok(());
+                            } else if logon_msg_seq_num == msg_seq_nums.inbound {
+                                connection.inbound_msg_seq_num
+                            } else {
+                                msg_seq_nums.inbound
+                            }
+                        } else {
+                            inbound_msg_seq_num.unwrap_or(2)
+                        };
+
+                        connection.set_status(ConnectionStatus::Established);
 
                         //Setup the version messages should be serialized against by default when
                         //being sent. Only FIXT 1.1 makes this adjustable and it MUST be set by the
@@ -970,11 +2406,13 @@ impl InternalThread {
                             } else {
                                 connection.fix_version.max_message_version()
                             };
+                        connection.publish_session_info();
 
                         //Send the Logon response. It's always sent using the latest message version
                         //for the selected FIX version. This is probably what is always wanted unless a
                         //version is outright not supported. In which case, the connection should have
                         //been rejected, right?
+                        let logon_msg_seq_num = connection.outbound_msg_seq_num;
                         let mut outbound_message = OutboundMessage::from_box(message);
                         outbound_message.message_version =
                             Some(connection.fix_version.max_message_version());
@@ -983,15 +2421,20 @@ impl InternalThread {
 
                         if inbound_msg_seq_num < connection.inbound_msg_seq_num {
                             connection.inbound_msg_seq_num = inbound_msg_seq_num;
+                            connection.publish_msg_seq_nums();
 
                             //Fetch the messages the remote says were sent but we never
-                            //received using a ResendRequest.
-                            let mut resend_request = ResendRequest::new();
-                            resend_request.begin_seq_no = inbound_msg_seq_num;
-                            resend_request.end_seq_no = 0;
-                            connection
-                                .outbound_messages
-                                .push(OutboundMessage::from(resend_request));
+                            //received using a ResendRequest. Unless the remote gave its
+                            //NextExpectedMsgSeqNum. Then it resends them on its own after
+                            //reading ours in the Logon response.
+                            if connection.received_next_expected_msg_seq_num == 0 {
+                                let mut resend_request = ResendRequest::new();
+                                resend_request.begin_seq_no = inbound_msg_seq_num;
+                                resend_request.end_seq_no = 0;
+                                connection
+                                    .outbound_messages
+                                    .push(OutboundMessage::from(resend_request));
+                            }
                         } else if inbound_msg_seq_num > connection.inbound_msg_seq_num {
                             //TODO: Investigate exact handling of this. Maybe SequenceReset?
                         }
@@ -1010,82 +2453,612 @@ impl InternalThread {
                             &connection.inbound_testrequest_timeout_duration,
                             &connection.token,
                         );
+
+                        logon_msg_seq_num
+                    };
+
+                    try_write_connection_or_terminate!(connection_entry, self);
+
+                    //Resend whatever the remote missed only after the Logon response has been
+                    //sent. The remote's NextExpectedMsgSeqNum was picked before it could have seen
+                    //the Logon response so only the messages before it are missing.
+                    let connection = connection_entry.get_mut();
+                    let next_expected_msg_seq_num = connection.received_next_expected_msg_seq_num;
+                    if next_expected_msg_seq_num > 0 && connection.status.is_established() {
+                        connection.on_logon_next_expected_msg_seq_num(
+                            next_expected_msg_seq_num,
+                            logon_msg_seq_num,
+                            &self.tx,
+                            &mut self.timer,
+                        );
+                        try_write_connection_or_terminate!(connection_entry, self);
+                    }
+                } else {
+                    //Silently ignore message for an invalid connection.
+                    //TODO: Maybe submit this to a logging system or something?
+                }
+            }
+            //Engine wants to reject logon of a connection that was accepted by a listener.
+            InternalEngineToThreadEvent::RejectNewConnection(connection, reason) => {
+                //This should only be used for new connections but there is no check for now so
+                //user of the engine has a way to arbitrarily disconnect over an error instead of
+                //logging out cleanly.
+
+                if let Entry::Occupied(mut connection_entry) =
+                    self.connections.entry(Token(connection.0))
+                {
+                    //When a reason is supplied, send a Logout message with the reason as an
+                    //explanation. Otherwise, disconnect immediately.
+                    if let Some(reason) = reason {
+                        connection_entry.get_mut().initiate_logout(
+                            &mut self.timer,
+                            LoggingOutType::Error(ConnectionTerminatedReason::LogonRejectedError),
+                            &reason[..],
+                        );
+                        try_write_connection_or_terminate!(connection_entry, self);
+                    } else {
+                        return Err(ConnectionEventError::TerminateConnection(
+                            Box::new(connection_entry.remove()),
+                            ConnectionTerminatedReason::LogonRejectedError,
+                        ));
+                    }
+                } else {
+                    //Silently ignore for an invalid connection.
+                    //TODO: Maybe submit this to a logging system or something?
+                }
+            }
+            //Engine wants to start both MsgSeqNums over at 1 in the middle of a session.
+            InternalEngineToThreadEvent::ResetSequenceNumbers(token) => {
+                if let Entry::Occupied(mut connection_entry) = self.connections.entry(token) {
+                    if !connection_entry.get().status.is_established()
+                        || connection_entry.get().sent_reset_seq_num_flag
+                    {
+                        //Silently ignore when not logged on or a reset is already in progress.
+                        //TODO: Maybe submit this to a logging system or something?
+                        return Ok(());
+                    }
+
+                    connection_entry.get_mut().push_reset_seq_num_logon();
+                    try_write_connection_or_terminate!(connection_entry, self);
+                } else {
+                    //Silently ignore for an invalid connection.
+                    //TODO: Maybe submit this to a logging system or something?
+                }
+            }
+            //Engine wants to begin the clean logout process on a connection.
+            InternalEngineToThreadEvent::Logout(token) => {
+                //Scheduled and reconnecting connections stop starting new sessions. If not currently
+                //connected, there's nothing left to do except let the user know the connection is
+                //gone.
+                if let Some(mut initiator_session) = self.initiator_sessions.remove(&token) {
+                    initiator_session.cancel_timeouts(&mut self.timer);
+                    if !self.connections.contains_key(&token) {
+                        self.session_infos.remove(Connection(token.0));
+                        let msg_seq_nums = match initiator_session.msg_seq_nums {
+                            Some(msg_seq_nums) => msg_seq_nums,
+                            None => self
+                                .load_msg_seq_nums(&initiator_session.session_id())
+                                .ok()
+                                .and_then(|msg_seq_nums| msg_seq_nums)
+                                .unwrap_or_default(),
+                        };
+                        self.tx
+                            .send(EngineEvent::ConnectionTerminated(
+                                Connection(token.0),
+                                ConnectionTerminatedReason::LocalRequested,
+                                msg_seq_nums,
+                            ))
+                            .unwrap();
+                        return Ok(());
+                    }
+                }
+
+                if let Entry::Occupied(mut connection_entry) = self.connections.entry(token) {
+                    match connection_entry.get_mut().status {
+                        ConnectionStatus::SendingLogon
+                        | ConnectionStatus::ReceivingLogon(_, _)
+                        | ConnectionStatus::ApprovingLogon => {
+                            //Just disconnect since connection hasn't had a chance to logon.
+                            return Err(ConnectionEventError::TerminateConnection(
+                                Box::new(connection_entry.remove()),
+                                ConnectionTerminatedReason::LocalRequested,
+                            ));
+                        }
+                        ConnectionStatus::LoggingOut(_) => {} //Already logging out.
+                        ConnectionStatus::Established => {
+                            //Begin logout.
+                            connection_entry.get_mut().initiate_logout(
+                                &mut self.timer,
+                                LoggingOutType::Ok,
+                                b"",
+                            );
+                            try_write_connection_or_terminate!(connection_entry, self);
+                        }
+                    };
+                } else {
+                    //Silently ignore logout for invalid connection.
+                    //TODO: Maybe submit this to a logging system or something?
+                }
+            }
+            //Engine wants to shutdown all connections immediately. Incoming or outgoing messages
+            //might be lost!
+            InternalEngineToThreadEvent::Shutdown => return Err(ConnectionEventError::Shutdown),
+        };
+
+        Ok(())
+    }
+
+    //Connect a socket for an initiated session and start tracking it. Nothing is sent until a
+    //Logon is queued.
+    fn connect(
+        &mut self,
+        token: Token,
+        fix_version: FIXVersion,
+        default_message_version: MessageVersion,
+        sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
+        target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+        address: &TransportAddr,
+        msg_seq_nums: Option<MsgSeqNums>,
+        stream_config: StreamConfig,
+        session_options: SessionOptions,
+    ) -> Result<(), io::Error> {
+        let socket = TransportStream::connect(address)?;
+
+        let message_store = if let Some(ref mut message_store_factory) = self.message_store_factory
+        {
+            let session_id = SessionID::new(fix_version, &sender_comp_id[..], &target_comp_id[..]);
+            match message_store_factory.create(&session_id) {
+                Ok(message_store) => Some(message_store),
+                Err(e) => {
+                    let _ = socket.shutdown(Shutdown::Both);
+                    return Err(e);
+                }
+            }
+        } else {
+            None
+        };
+
+        let socket = stream_config.wrap(socket)?;
+
+        let mut connection = InternalConnection::new(
+            self.message_dictionary.clone(),
+            self.max_message_size,
+            fix_version,
+            default_message_version,
+            socket,
+            token,
+            sender_comp_id,
+            target_comp_id,
+        );
+        connection.message_store = message_store;
+        connection.options = session_options;
+        if let Some(msg_seq_nums) = msg_seq_nums {
+            connection.set_msg_seq_nums(msg_seq_nums);
+        }
+
+        //Have poll let us know when we can can read or write.
+        self.poll.register(
+            &connection.socket,
+            connection.token,
+            Ready::readable() | Ready::writable() | UnixReady::hup() | UnixReady::error(),
+            PollOpt::edge(),
+        )?;
+
+        connection.publish_session_info();
+        self.stats
+            .insert(connection.as_connection(), connection.stats.clone());
+        self.session_infos.insert(
+            connection.as_connection(),
+            connection.published_session_info.clone(),
+        );
+        self.connections.insert(token, connection);
+        Ok(())
+    }
+
+    fn load_msg_seq_nums(&mut self, session_id: &SessionID) -> io::Result<Option<MsgSeqNums>> {
+        match self.seq_num_stores.get_mut(session_id) {
+            Some(seq_num_store) => seq_num_store.load(session_id),
+            None => Ok(None),
+        }
+    }
+
+    //Make a session start over at MsgSeqNum 1 the next time it logs on. The connection's own
+    //message_store must be used when one is open because a second handle to the same store
+    //wouldn't see the first one's position.
+    fn reset_stored_session(
+        &mut self,
+        session_id: &SessionID,
+        message_store: Option<&mut Box<dyn MessageStore + Send>>,
+    ) {
+        if let Some(seq_num_store) = self.seq_num_stores.get_mut(session_id) {
+            if let Err(e) = seq_num_store.save(session_id, MsgSeqNums::default()) {
+                log::error!(
+                    "could not reset MsgSeqNums of session {}: {}",
+                    session_id,
+                    e
+                );
+            }
+        }
+
+        let result = match message_store {
+            Some(message_store) => message_store.reset(),
+            None => match self.message_store_factory {
+                Some(ref mut message_store_factory) => message_store_factory
+                    .create(session_id)
+                    .and_then(|mut message_store| message_store.reset()),
+                None => Ok(()),
+            },
+        };
+        if let Err(e) = result {
+            log::error!(
+                "could not reset message store of session {}: {}",
+                session_id,
+                e
+            );
+        }
+    }
+
+    //Connect and queue up Logon for an initiated session that isn't currently connected.
+    fn start_initiator_session(&mut self, token: Token) {
+        if self.connections.contains_key(&token) {
+            return;
+        }
+
+        let (
+            fix_version,
+            default_message_version,
+            sender_comp_id,
+            target_comp_id,
+            address,
+            logon,
+            stream_config,
+            session_options,
+        ) = {
+            let initiator_session = self.initiator_sessions.get_mut(&token).unwrap();
+            if let Some(ref mut reconnect) = initiator_session.reconnect {
+                reconnect.cancel(&mut self.timer);
+            }
+            (
+                initiator_session.fix_version,
+                initiator_session.default_message_version,
+                initiator_session.sender_comp_id.clone(),
+                initiator_session.target_comp_id.clone(),
+                initiator_session.addresses[initiator_session.address_index].clone(),
+                initiator_session.logon.clone(),
+                initiator_session.stream_config.clone(),
+                initiator_session.session_options.clone(),
+            )
+        };
+        let session_id = SessionID::new(fix_version, &sender_comp_id[..], &target_comp_id[..]);
+
+        let result = match self.initiator_sessions[&token].msg_seq_nums {
+            Some(msg_seq_nums) => Ok(Some(msg_seq_nums)),
+            None => self
+                .load_msg_seq_nums(&session_id)
+                .map_err(ConnectionTerminatedReason::SeqNumStoreError),
+        }
+        .and_then(|msg_seq_nums| {
+            self.connect(
+                token,
+                fix_version,
+                default_message_version,
+                sender_comp_id,
+                target_comp_id,
+                &address,
+                msg_seq_nums,
+                stream_config,
+                session_options,
+            )
+            .map_err(ConnectionTerminatedReason::SocketConnectError)
+        });
+
+        match result {
+            Ok(()) => {
+                //Logon goes out as soon as the socket finishes connecting.
+                let mut outbound_message = OutboundMessage::from_box(logon);
+                outbound_message.message_version = Some(fix_version.max_message_version());
+                let connection = self.connections.get_mut(&token).unwrap();
+                connection.outbound_messages.push(outbound_message);
+                let now = self.timer.now();
+                connection.throttle = self.initiator_sessions[&token]
+                    .throttle
+                    .as_ref()
+                    .map(|throttle| ThrottleState::new(throttle, now));
+            }
+            Err(e) => {
+                let msg_seq_nums = self.initiator_sessions[&token]
+                    .msg_seq_nums
+                    .unwrap_or_default();
+                self.on_initiator_session_ended(token, e, msg_seq_nums);
+            }
+        }
+    }
+
+    //Decide what happens after an initiated session ends: reconnect according to its
+    //ReconnectPolicy, wait for its schedule to become active again, or give up entirely.
+    fn on_initiator_session_ended(
+        &mut self,
+        token: Token,
+        reason: ConnectionTerminatedReason,
+        msg_seq_nums: MsgSeqNums,
+    ) {
+        let now = self.timer.now_utc();
+        let connection = Connection(token.0);
+        let initiator_session = self.initiator_sessions.get_mut(&token).unwrap();
+        let is_active = initiator_session.is_active(&now);
+
+        //Sessions ended by their schedule wait for the schedule instead of reconnecting.
+        if let (true, Some(reconnect)) = (is_active, initiator_session.reconnect.as_mut()) {
+            if matches!(reconnect.policy.max_attempts(), Some(max_attempts) if reconnect.attempt >= max_attempts)
+            {
+                //Out of attempts so release the connection.
+                self.release_initiator_session(token, reason, msg_seq_nums);
+                return;
+            }
+
+            reconnect.attempt += 1;
+            let delay = reconnect.policy.delay(reconnect.attempt);
+            reconnect.cancel(&mut self.timer);
+            reconnect.timeout = Some(
+                self.timer
+                    .set_timeout(delay, (TimeoutType::Reconnect, token)),
+            );
+            initiator_session.address_index =
+                reconnect.attempt as usize % initiator_session.addresses.len();
+            if let Some(ref mut schedule) = initiator_session.schedule {
+                schedule.arm(&mut self.timer, token, &now, None);
+            }
+
+            self.tx
+                .send(EngineEvent::SessionEnded(connection, reason, msg_seq_nums))
+                .unwrap();
+            self.tx
+                .send(EngineEvent::Reconnecting(
+                    connection,
+                    reconnect.attempt,
+                    delay,
+                ))
+                .unwrap();
+            return;
+        }
+
+        //Sessions that only send Logon automatically are done for good just like any other
+        //connection.
+        if initiator_session.schedule.is_none() && initiator_session.reconnect.is_none() {
+            self.release_initiator_session(token, reason, msg_seq_nums);
+            return;
+        }
+
+        //Scheduled sessions without a ReconnectPolicy try again in a little while if the session
+        //ended early.
+        if let Some(ref mut schedule) = initiator_session.schedule {
+            let retry = if is_active && initiator_session.reconnect.is_none() {
+                Some(Duration::from_secs(AUTO_RECONNECT_SCHEDULED_SESSION_SECS))
+            } else {
+                None
+            };
+            schedule.arm(&mut self.timer, token, &now, retry);
+        }
+        self.tx
+            .send(EngineEvent::SessionEnded(connection, reason, msg_seq_nums))
+            .unwrap();
+    }
+
+    fn release_initiator_session(
+        &mut self,
+        token: Token,
+        reason: ConnectionTerminatedReason,
+        msg_seq_nums: MsgSeqNums,
+    ) {
+        let connection = Connection(token.0);
+        let mut initiator_session = self.initiator_sessions.remove(&token).unwrap();
+        initiator_session.cancel_timeouts(&mut self.timer);
+        self.session_infos.remove(connection);
+        let event = match reason {
+            ConnectionTerminatedReason::SocketConnectError(e) => {
+                EngineEvent::ConnectionFailed(connection, e)
+            }
+            reason => EngineEvent::ConnectionTerminated(connection, reason, msg_seq_nums),
+        };
+        self.tx.send(event).unwrap();
+    }
+
+    //Logout a session because its schedule ended. Sessions that haven't finished logging on are
+    //just disconnected.
+    fn end_session_outside_schedule(&mut self, token: Token) -> Result<(), ConnectionEventError> {
+        if let Entry::Occupied(mut connection_entry) = self.connections.entry(token) {
+            match connection_entry.get().status {
+                ConnectionStatus::SendingLogon
+                | ConnectionStatus::ReceivingLogon(_, _)
+                | ConnectionStatus::ApprovingLogon => {
+                    connection_entry.get_mut().shutdown();
+                    return Err(ConnectionEventError::TerminateConnection(
+                        Box::new(connection_entry.remove()),
+                        ConnectionTerminatedReason::LocalRequested,
+                    ));
+                }
+                ConnectionStatus::LoggingOut(_) => {} //Already logging out.
+                ConnectionStatus::Established => {
+                    connection_entry.get_mut().initiate_logout(
+                        &mut self.timer,
+                        LoggingOutType::Ok,
+                        b"Session is ending",
+                    );
+                    try_write_connection_or_terminate!(connection_entry, self);
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_schedule_timeout(&mut self, token: Token) -> Result<(), ConnectionEventError> {
+        let now = self.timer.now_utc();
+
+        if let Some(initiator_session) = self.initiator_sessions.get_mut(&token) {
+            let (reset_due, is_active) = match initiator_session.schedule {
+                Some(ref mut schedule) => {
+                    let reset_due = schedule.take_due_reset(&now);
+                    schedule.arm(&mut self.timer, token, &now, None);
+                    (reset_due, schedule.schedule.is_active(&now))
+                }
+                None => return Ok(()),
+            };
+
+            if let Some(connection) = self.connections.get_mut(&token) {
+                //Changing MsgSeqNums in the middle of a session isn't possible. So wait until the
+                //session ends.
+                if reset_due {
+                    connection.reset_msg_seq_nums_on_termination = true;
+                }
+                if !is_active {
+                    return self.end_session_outside_schedule(token);
+                }
+            } else {
+                if reset_due {
+                    initiator_session.msg_seq_nums = Some(MsgSeqNums::default());
+                    self.session_infos.insert(
+                        Connection(token.0),
+                        initiator_session.disconnected_session_info(),
+                    );
+                    let session_id = initiator_session.session_id();
+                    self.reset_stored_session(&session_id, None);
+                }
+                if is_active {
+                    self.start_initiator_session(token);
+                }
+            }
+        } else if let Some(listener) = self.listeners.get_mut(&token) {
+            let (reset_due, is_active) = match listener.schedule {
+                Some(ref mut schedule) => {
+                    let reset_due = schedule.take_due_reset(&now);
+                    schedule.arm(&mut self.timer, token, &now, None);
+                    (reset_due, schedule.schedule.is_active(&now))
+                }
+                None => return Ok(()),
+            };
+            let listener = Listener(token.0);
+
+            if reset_due {
+                let session_ids: Vec<SessionID> = self.listeners[&token]
+                    .seen_sessions
+                    .iter()
+                    .cloned()
+                    .collect();
+                for session_id in session_ids {
+                    let connection = self.connections.values_mut().find(|connection| {
+                        connection.listener == Some(listener)
+                            && !connection.status.is_receiving_logon()
+                            && !connection.status.is_approving_logon()
+                            && connection.session_id() == session_id
+                    });
+                    match connection {
+                        Some(connection) => connection.reset_msg_seq_nums_on_termination = true,
+                        None => self.reset_stored_session(&session_id, None),
                     }
-
-                    try_write_connection_or_terminate!(connection_entry, self);
-                } else {
-                    //Silently ignore message for an invalid connection.
-                    //TODO: Maybe submit this to a logging system or something?
                 }
             }
-            //Engine wants to reject logon of a connection that was accepted by a listener.
-            InternalEngineToThreadEvent::RejectNewConnection(connection, reason) => {
-                //This should only be used for new connections but there is no check for now so
-                //user of the engine has a way to arbitrarily disconnect over an error instead of
-                //logging out cleanly.
 
-                if let Entry::Occupied(mut connection_entry) =
-                    self.connections.entry(Token(connection.0))
-                {
-                    //When a reason is supplied, send a Logout message with the reason as an
-                    //explanation. Otherwise, disconnect immediately.
-                    if let Some(reason) = reason {
-                        connection_entry.get_mut().initiate_logout(
-                            &mut self.timer,
-                            LoggingOutType::Error(ConnectionTerminatedReason::LogonRejectedError),
-                            &reason[..],
-                        );
-                        try_write_connection_or_terminate!(connection_entry, self);
-                    } else {
-                        return Err(ConnectionEventError::TerminateConnection(
-                            connection_entry.remove(),
-                            ConnectionTerminatedReason::LogonRejectedError,
-                        ));
+            //Connections still waiting on a Logon are refused when it arrives instead.
+            if !is_active {
+                let connection_tokens: Vec<Token> = self
+                    .connections
+                    .values()
+                    .filter(|connection| {
+                        connection.listener == Some(listener)
+                            && !connection.status.is_receiving_logon()
+                    })
+                    .map(|connection| connection.token)
+                    .collect();
+                for connection_token in connection_tokens {
+                    if let Err(e) = self.end_session_outside_schedule(connection_token) {
+                        //Come back right away to handle the rest of the sessions.
+                        if let Some(ref mut schedule) =
+                            self.listeners.get_mut(&token).unwrap().schedule
+                        {
+                            schedule.arm(
+                                &mut self.timer,
+                                token,
+                                &now,
+                                Some(Duration::from_millis(0)),
+                            );
+                        }
+                        return Err(e);
                     }
-                } else {
-                    //Silently ignore for an invalid connection.
-                    //TODO: Maybe submit this to a logging system or something?
                 }
             }
-            //Engine wants to begin the clean logout process on a connection.
-            InternalEngineToThreadEvent::Logout(token) => {
-                if let Entry::Occupied(mut connection_entry) = self.connections.entry(token) {
-                    match connection_entry.get_mut().status {
-                        ConnectionStatus::SendingLogon
-                        | ConnectionStatus::ReceivingLogon(_, _)
-                        | ConnectionStatus::ApprovingLogon => {
-                            //Just disconnect since connection hasn't had a chance to logon.
-                            return Err(ConnectionEventError::TerminateConnection(
-                                connection_entry.remove(),
-                                ConnectionTerminatedReason::LocalRequested,
-                            ));
-                        }
-                        ConnectionStatus::LoggingOut(_) => {} //Already logging out.
-                        ConnectionStatus::Established => {
-                            //Begin logout.
-                            connection_entry.get_mut().initiate_logout(
+        } else if let Some(acceptor_session) = self.acceptor_sessions.get_mut(&token) {
+            let (reset_due, is_active) = match acceptor_session.schedule {
+                Some(ref mut schedule) => {
+                    let reset_due = schedule.take_due_reset(&now);
+                    schedule.arm(&mut self.timer, token, &now, None);
+                    (reset_due, schedule.schedule.is_active(&now))
+                }
+                None => return Ok(()),
+            };
+            let listener = Listener(acceptor_session.listener.0);
+            let session_id = acceptor_session.session_id.clone();
+
+            if reset_due {
+                let connection = self.connections.values_mut().find(|connection| {
+                    connection.listener == Some(listener)
+                        && !connection.status.is_receiving_logon()
+                        && !connection.status.is_approving_logon()
+                        && connection.acceptor_session == Some(token)
+                });
+                match connection {
+                    Some(connection) => connection.reset_msg_seq_nums_on_termination = true,
+                    None => self.reset_stored_session(&session_id, None),
+                }
+            }
+
+            if !is_active {
+                let connection_tokens: Vec<Token> = self
+                    .connections
+                    .values()
+                    .filter(|connection| connection.acceptor_session == Some(token))
+                    .map(|connection| connection.token)
+                    .collect();
+                for connection_token in connection_tokens {
+                    if let Err(e) = self.end_session_outside_schedule(connection_token) {
+                        //Come back right away to handle the rest of the connections.
+                        if let Some(ref mut schedule) =
+                            self.acceptor_sessions.get_mut(&token).unwrap().schedule
+                        {
+                            schedule.arm(
                                 &mut self.timer,
-                                LoggingOutType::Ok,
-                                b"",
+                                token,
+                                &now,
+                                Some(Duration::from_millis(0)),
                             );
-                            try_write_connection_or_terminate!(connection_entry, self);
                         }
-                    };
-                } else {
-                    //Silently ignore logout for invalid connection.
-                    //TODO: Maybe submit this to a logging system or something?
+                        return Err(e);
+                    }
                 }
             }
-            //Engine wants to shutdown all connections immediately. Incoming or outgoing messages
-            //might be lost!
-            InternalEngineToThreadEvent::Shutdown => return Err(ConnectionEventError::Shutdown),
-        };
+        }
 
         Ok(())
     }
 
     fn on_timeout(&mut self) -> Result<(), ConnectionEventError> {
         if let Some((timeout_type, token)) = self.timer.poll() {
+            match timeout_type {
+                TimeoutType::Schedule => return self.on_schedule_timeout(token),
+                TimeoutType::Reconnect => {
+                    if let Some(initiator_session) = self.initiator_sessions.get_mut(&token) {
+                        if let Some(ref mut reconnect) = initiator_session.reconnect {
+                            reconnect.timeout = None;
+                        }
+                        if initiator_session.is_active(&self.timer.now_utc()) {
+                            self.start_initiator_session(token);
+                        }
+                    }
+                    return Ok(());
+                }
+                _ => {}
+            }
+
             if let Entry::Occupied(mut connection_entry) = self.connections.entry(token) {
                 match timeout_type {
                     TimeoutType::Outbound if connection_entry.get().status.is_established() => {
@@ -1105,7 +3078,7 @@ impl InternalThread {
 
                         //Use current time as TestReqID as recommended. This might not exactly
                         //match the SendingTime field depending on when it gets sent though.
-                        let now_time = UtcTimestampFieldType::new_now();
+                        let now_time = self.timer.now_utc_timestamp();
                         UtcTimestampFieldType::read(
                             &now_time,
                             FIXVersion::FIXT_1_1,
@@ -1117,6 +3090,12 @@ impl InternalThread {
                             .get_mut()
                             .outbound_messages
                             .push(OutboundMessage::from(test_request));
+                        connection_entry
+                            .get()
+                            .stats
+                            .lock()
+                            .unwrap()
+                            .heartbeat_misses += 1;
 
                         //Start a TimeoutType::InboundTestRequest timer to auto-disconnect if we
                         //don't get a response in time. Note that any reploy what-so-ever will stop
@@ -1129,15 +3108,13 @@ impl InternalThread {
                         //hand, if this doesn't go out in a reasonable amount of time, we're
                         //backlogged and might be having negative consequences on the network.
                         connection_entry.get_mut().inbound_testrequest_timeout = Some(
-                            self.timer
-                                .set_timeout(
-                                    connection_entry
-                                        .get_mut()
-                                        .inbound_testrequest_timeout_duration
-                                        .unwrap(),
-                                    (TimeoutType::InboundTestRequest, token),
-                                )
-                                .unwrap(),
+                            self.timer.set_timeout(
+                                connection_entry
+                                    .get_mut()
+                                    .inbound_testrequest_timeout_duration
+                                    .unwrap(),
+                                (TimeoutType::InboundTestRequest, token),
+                            ),
                         );
                     }
                     TimeoutType::InboundTestRequest
@@ -1146,7 +3123,7 @@ impl InternalThread {
                         connection_entry.get_mut().shutdown();
                         println!("Shutting down connection after other side failed to respond to TestRequest before timeout");
                         return Err(ConnectionEventError::TerminateConnection(
-                            connection_entry.remove(),
+                            Box::new(connection_entry.remove()),
                             ConnectionTerminatedReason::TestRequestNotRespondedError,
                         ));
                     }
@@ -1154,7 +3131,7 @@ impl InternalThread {
                         connection_entry.get_mut().shutdown();
                         println!("Shutting down connection after writing to socket resulted in WouldBlock for too long");
                         return Err(ConnectionEventError::TerminateConnection(
-                            connection_entry.remove(),
+                            Box::new(connection_entry.remove()),
                             ConnectionTerminatedReason::SocketNotWritableTimeoutError,
                         ));
                     }
@@ -1171,7 +3148,7 @@ impl InternalThread {
                         connection_entry.get_mut().shutdown();
                         println!("Shutting down connection after no initial Logon received before timeout");
                         return Err(ConnectionEventError::TerminateConnection(
-                            connection_entry.remove(),
+                            Box::new(connection_entry.remove()),
                             ConnectionTerminatedReason::LogonNeverReceivedError,
                         ));
                     }
@@ -1181,7 +3158,7 @@ impl InternalThread {
                             "Shutting down connection after no Logout response before timeout"
                         );
                         return Err(ConnectionEventError::TerminateConnection(
-                            connection_entry.remove(),
+                            Box::new(connection_entry.remove()),
                             ConnectionTerminatedReason::LogoutNoResponseError,
                         ));
                     }
@@ -1189,14 +3166,19 @@ impl InternalThread {
                         connection_entry.get_mut().shutdown();
                         println!("Shutting down connection after other side failed to disconnect before timeout");
                         return Err(ConnectionEventError::TerminateConnection(
-                            connection_entry.remove(),
+                            Box::new(connection_entry.remove()),
                             ConnectionTerminatedReason::LogoutNoHangUpError,
                         ));
                     }
+                    TimeoutType::Throttle => {
+                        //Held messages are sent by the write below.
+                        connection_entry.get_mut().throttle_timeout = None;
+                    }
                     TimeoutType::Outbound
                     | TimeoutType::Inbound
                     | TimeoutType::InboundTestRequest
                     | TimeoutType::ContinueLogout => {} //Special conditions only. Handled above.
+                    TimeoutType::Schedule | TimeoutType::Reconnect => unreachable!(),
                 }
 
                 //Write any new Heartbeat or TestRequest messages.
@@ -1211,15 +3193,50 @@ impl InternalThread {
         //Note: Each event.kind() can indicate more than one state. For example: is_readable() and
         //is_hup() can both return true.
 
+        //Refuse logons while the listener's schedule says sessions are not active. Listeners
+        //with expected sessions also hand them over so the Logon can be matched against them.
+        if let Some(connection) = self.connections.get_mut(&event.token()) {
+            if let ConnectionStatus::ReceivingLogon(listener, _) = connection.status {
+                let now = self.timer.now_utc();
+                let acceptor_sessions = &self.acceptor_sessions;
+                let listener = self.listeners.get(&Token(listener.0));
+                connection.logon_allowed =
+                    match listener.and_then(|listener| listener.schedule.as_ref()) {
+                        Some(schedule) => schedule.schedule.is_active(&now),
+                        None => true,
+                    };
+                connection.expected_sessions = listener
+                    .filter(|listener| !listener.acceptor_sessions.is_empty())
+                    .map(|listener| {
+                        listener
+                            .acceptor_sessions
+                            .iter()
+                            .filter_map(|token| acceptor_sessions.get(token))
+                            .map(|acceptor_session| {
+                                let mut expected = acceptor_session.expected.clone();
+                                expected.logon_allowed = match acceptor_session.schedule {
+                                    Some(ref schedule) => schedule.schedule.is_active(&now),
+                                    None => true,
+                                };
+                                expected
+                            })
+                            .collect()
+                    });
+            }
+        }
+
         if let Entry::Occupied(mut connection_entry) = self.connections.entry(event.token()) {
             //Read all of the bytes available on the socket, parse into messages, perform internal
             //book keeping on the messages, and then pass them off to the application.
             if event.kind().is_readable() {
-                let result = connection_entry.get_mut().read(&mut self.timer);
+                let result = connection_entry
+                    .get_mut()
+                    .read(&mut self.timer, self.message_log.is_some());
                 if let Err(e) = result {
+                    let reason = connection_entry.get().socket.read_error_reason(e);
                     return Err(ConnectionEventError::TerminateConnection(
-                        connection_entry.remove(),
-                        ConnectionTerminatedReason::SocketReadError(e),
+                        Box::new(connection_entry.remove()),
+                        reason,
                     ));
                 }
 
@@ -1235,16 +3252,50 @@ impl InternalThread {
                     }
 
                     for message in messages {
+                        {
+                            let mut stats = connection_entry.get().stats.lock().unwrap();
+                            match message {
+                                ConnectionReadMessage::Message(ref message, _) => stats
+                                    .record_inbound(message.msg_type(), message_size(&**message)),
+                                ConnectionReadMessage::Error(_, _) => stats.garbled_messages += 1,
+                            }
+                        }
+
+                        if let Some(ref mut message_log) = self.message_log {
+                            let connection = connection_entry.get();
+                            let (session_id, raw_message) = match message {
+                                ConnectionReadMessage::Message(ref message, ref raw_message) => {
+                                    (connection.inbound_session_id(&**message), raw_message)
+                                }
+                                ConnectionReadMessage::Error(_, ref raw_message) => {
+                                    (connection.session_id(), raw_message)
+                                }
+                            };
+                            if let Err(e) = message_log.log(
+                                &session_id,
+                                MessageDirection::Inbound,
+                                &self.timer.now_utc(),
+                                &raw_message[..],
+                            ) {
+                                connection_entry.get_mut().shutdown();
+                                return Err(ConnectionEventError::TerminateConnection(
+                                    Box::new(connection_entry.remove()),
+                                    ConnectionTerminatedReason::MessageLogError(e),
+                                ));
+                            }
+                        }
+
                         let result = match message {
-                            ConnectionReadMessage::Message(message) => {
+                            ConnectionReadMessage::Message(message, _) => {
                                 InternalThread::on_network_message(
                                     connection_entry.get_mut(),
                                     message,
                                     &self.tx,
                                     &mut self.timer,
+                                    &mut self.logon_authenticator,
                                 )
                             }
-                            ConnectionReadMessage::Error(parse_error) => {
+                            ConnectionReadMessage::Error(parse_error, _) => {
                                 InternalThread::on_network_parse_error(
                                     connection_entry.get_mut(),
                                     parse_error,
@@ -1255,7 +3306,7 @@ impl InternalThread {
 
                         if let Err(e) = result {
                             return Err(ConnectionEventError::TerminateConnection(
-                                connection_entry.remove(),
+                                Box::new(connection_entry.remove()),
                                 e,
                             ));
                         }
@@ -1267,6 +3318,18 @@ impl InternalThread {
                 //complicated so just blindly try for now. We can optimize this if it's a
                 //performance concern later.
                 try_write_connection_or_terminate!(connection_entry, self);
+
+                //Reconnecting starts over from the first attempt and primary address once a
+                //session is established.
+                if connection_entry.get().status.is_established() {
+                    if let Some(initiator_session) = self.initiator_sessions.get_mut(&event.token())
+                    {
+                        initiator_session.address_index = 0;
+                        if let Some(ref mut reconnect) = initiator_session.reconnect {
+                            reconnect.attempt = 0;
+                        }
+                    }
+                }
             }
 
             //Write all pending messages out to the socket until they are exhausted or the socket
@@ -1277,6 +3340,7 @@ impl InternalThread {
                 if !connection_entry.get().is_connected {
                     //Let user know that the socket's connect() call succeeded.
                     connection_entry.get_mut().is_connected = true;
+                    connection_entry.get_mut().update_session_state();
                     self.tx
                         .send(EngineEvent::ConnectionSucceeded(
                             connection_entry.get().as_connection(),
@@ -1288,7 +3352,10 @@ impl InternalThread {
             //Socket was closed on the other side. If already responded to a Logout initiated by
             //the other side, then this is expected and the logout operation was performed cleanly.
             //Otherwise, the connection dropped for some unknown reason.
-            if event.kind().is_hup() {
+            if event.kind().is_hup()
+                || connection_entry.get().is_peer_closed
+                || connection_entry.get().socket.socket().is_peer_closed()
+            {
                 if connection_entry
                     .get_mut()
                     .status
@@ -1296,16 +3363,17 @@ impl InternalThread {
                 {
                     println!("Shutting down connection after remote logged out cleanly.");
                     return Err(ConnectionEventError::TerminateConnection(
-                        connection_entry.remove(),
+                        Box::new(connection_entry.remove()),
                         ConnectionTerminatedReason::RemoteRequested,
                     ));
                 } else {
                     //Coax a socket write to fail in order to get an error code that we can pass
                     //along.
-                    let result = connection_entry.get_mut().socket.write(b"\x00");
+                    let mut socket = connection_entry.get().socket.socket();
+                    let result = socket.write(b"\x00");
                     if let Err(e) = result {
                         return Err(ConnectionEventError::TerminateConnection(
-                            connection_entry.remove(),
+                            Box::new(connection_entry.remove()),
                             ConnectionTerminatedReason::SocketWriteError(e),
                         ));
                     }
@@ -1331,15 +3399,19 @@ impl InternalThread {
                             }
                         };
 
-                        //Let engine know about the connection and have a chance to reject it
-                        //before remote sends a Logon message.
-                        self.tx
-                            .send(EngineEvent::ConnectionAccepted(
-                                listener_entry.get().as_listener(),
-                                Connection(token.0),
-                                addr,
-                            ))
-                            .unwrap();
+                        let socket = match listener_entry.get().stream_config.wrap(socket) {
+                            Ok(socket) => socket,
+                            Err(_) => {
+                                self.token_generator.lock().unwrap().remove(token);
+                                self.tx
+                                    .send(EngineEvent::ConnectionDropped(
+                                        listener_entry.get().as_listener(),
+                                        addr,
+                                    ))
+                                    .unwrap();
+                                return Ok(());
+                            }
+                        };
 
                         let fix_version = FIXVersion::max_version(); //Accept the latest message version at first. This works out because Logon is forwards version compatible.
                         let mut connection = InternalConnection::new(
@@ -1353,19 +3425,17 @@ impl InternalThread {
                             Vec::new(),
                         );
                         connection.is_connected = true; //Accepted connections don't have to wait for connect().
-                        let timeout = self
-                            .timer
-                            .set_timeout(
-                                Duration::from_secs(
-                                    AUTO_DISCONNECT_AFTER_NO_LOGON_RECEIVED_SECONDS,
-                                ),
-                                (TimeoutType::NoLogon, token),
-                            )
-                            .unwrap();
-                        connection.status = ConnectionStatus::ReceivingLogon(
+                        connection.listener = Some(listener_entry.get().as_listener());
+                        connection.options = listener_entry.get().session_options.clone();
+                        let timeout = self.timer.set_timeout(
+                            connection.options.no_logon_timeout,
+                            (TimeoutType::NoLogon, token),
+                        );
+                        connection.set_status(ConnectionStatus::ReceivingLogon(
                             listener_entry.get().as_listener(),
                             timeout,
-                        );
+                        ));
+                        connection.session_state_changes.clear(); //Accepted connections start out AwaitingLogon.
 
                         //Have poll let us know when we can can read or write.
                         if let Err(_) = self.poll.register(
@@ -1387,6 +3457,28 @@ impl InternalThread {
                             return Ok(());
                         }
 
+                        connection.publish_session_info();
+                        self.stats
+                            .insert(connection.as_connection(), connection.stats.clone());
+                        self.session_infos.insert(
+                            connection.as_connection(),
+                            connection.published_session_info.clone(),
+                        );
+
+                        //Let engine know about the connection and have a chance to reject it
+                        //before remote sends a Logon message. Anything the engine does with it
+                        //from then on has to come to this worker thread.
+                        self.worker_assignments
+                            .lock()
+                            .unwrap()
+                            .insert(token, self.worker);
+                        self.tx
+                            .send(EngineEvent::ConnectionAccepted(
+                                listener_entry.get().as_listener(),
+                                Connection(token.0),
+                                addr,
+                            ))
+                            .unwrap();
                         self.connections.insert(token, connection);
                     }
                     Err(err) => {
@@ -1409,6 +3501,7 @@ impl InternalThread {
         mut message: Box<dyn FIXTMessage + Send>,
         tx: &Sender<EngineEvent>,
         timer: &mut Timer<(TimeoutType, Token)>,
+        logon_authenticator: &mut Option<Box<dyn LogonAuthenticator + Send>>,
     ) -> Result<(), ConnectionTerminatedReason> {
         //Perform book keeping needed to maintain the FIX connection and then pass off the message
         //to the engine.
@@ -1462,7 +3555,7 @@ impl InternalThread {
                         connection.inbound_last_seen_resend_request.count += 1;
 
                         if connection.inbound_last_seen_resend_request.count
-                            > AUTO_DISCONNECT_AFTER_INBOUND_RESEND_REQUEST_LOOP_COUNT
+                            > connection.options.inbound_resend_request_loop_count
                         {
                             let mut text = b"Detected ResendRequest loop for BeginSeqNo ".to_vec();
                             text.extend_from_slice(
@@ -1483,18 +3576,27 @@ impl InternalThread {
                         connection.inbound_last_seen_resend_request.count = 1;
                     }
 
-                    //Notify the engine of which messages are requested. Then it's up to the engine
-                    //to give said messages to us so we can send them.
+                    //Resend the requested messages straight from the message store when possible.
+                    //Otherwise, notify the engine of which messages are requested. Then it's up to
+                    //the engine to give said messages to us so we can send them.
                     let end_seq_no = if resend_request.end_seq_no == 0 {
                         connection.outbound_msg_seq_num
                     } else {
                         resend_request.end_seq_no + 1
                     }; //TODO: Handle potential overflow.
-                    tx.send(EngineEvent::ResendRequested(
-                        connection.as_connection(),
-                        resend_request.begin_seq_no..end_seq_no,
-                    ))
-                    .unwrap();
+                    if connection.is_resending_from_message_store() {
+                        connection.resend_from_message_store(
+                            resend_request.begin_seq_no..end_seq_no,
+                            &timer.now_utc_timestamp(),
+                        );
+                        connection.push_deferred_resend_request();
+                    } else {
+                        tx.send(EngineEvent::ResendRequested(
+                            connection.as_connection(),
+                            resend_request.begin_seq_no..end_seq_no,
+                        ))
+                        .unwrap();
+                    }
                 }
 
                 //If:
@@ -1505,7 +3607,7 @@ impl InternalThread {
                 //Then we need to assume the logout was cancelled.
                 //See FIXT v1.1, page 42.
                 if connection.status.is_logging_out_with_responded() {
-                    connection.status = ConnectionStatus::Established;
+                    connection.set_status(ConnectionStatus::Established);
 
                     //Stop timeout so we don't auto-disconnect.
                     if let Some(ref timeout) = connection.logout_timeout {
@@ -1557,6 +3659,7 @@ impl InternalThread {
         ) -> Option<Box<dyn FIXTMessage + Send>> {
             //FIXT v1.1, page 13: We should reply to ResendRequest first when MsgSeqNum is higher
             //than expected. Afterwards, we should send our own ResendRequest.
+            let was_resend_requesting = connection.inbound_resend_request_msg_seq_num.is_some();
             message = match if_on_resend_request(connection, message, msg_seq_num, tx, timer) {
                 Some(message) => message,
                 None => return None,
@@ -1564,14 +3667,27 @@ impl InternalThread {
 
             //Fetch the messages the remote says were sent but we never received using
             //ResendRequest. The one exception is if we are _receiving_ a ResendRequest message
-            //because then we're suppose to defer until after we respond.
-            if message.as_any().downcast_ref::<ResendRequest>().is_none() {
-                let mut resend_request = ResendRequest::new();
-                resend_request.begin_seq_no = connection.inbound_msg_seq_num;
-                resend_request.end_seq_no = 0;
-                connection
-                    .outbound_messages
-                    .push(OutboundMessage::from(resend_request));
+            //because then we're suppose to defer until after we respond. A Logon with
+            //NextExpectedMsgSeqNum is also an exception because the remote resends the messages
+            //on its own using the NextExpectedMsgSeqNum from our Logon.
+            let is_recovering_logon = matches!(
+                message.as_any().downcast_ref::<Logon>(),
+                Some(logon) if logon.next_expected_msg_seq_num > 0
+            );
+            if message.as_any().downcast_ref::<ResendRequest>().is_none() && !is_recovering_logon {
+                let newest_msg_seq_num = cmp::max(
+                    connection
+                        .inbound_resend_request_msg_seq_num
+                        .unwrap_or(msg_seq_num),
+                    msg_seq_num,
+                );
+                if !connection.push_resend_request(
+                    connection.inbound_msg_seq_num..newest_msg_seq_num + 1,
+                    tx,
+                    timer,
+                ) {
+                    return None;
+                }
             }
 
             //Keep track of the newest msg_seq_num that's been seen so we know when the message gap has
@@ -1582,6 +3698,16 @@ impl InternalThread {
                     .unwrap_or(msg_seq_num),
                 msg_seq_num,
             ));
+            connection.update_session_state();
+
+            //A ResendRequest that was just answered from the message store won't be followed up by
+            //the engine so the deferred ResendRequest must be sent now instead.
+            if !was_resend_requesting
+                && connection.is_resending_from_message_store()
+                && message.as_any().is::<ResendRequest>()
+            {
+                connection.push_deferred_resend_request();
+            }
 
             //Handle Logout messages as a special case where we need to delicately retrieve the
             //missing messages while still going through with the logout process. See FIXT v1.1,
@@ -1611,9 +3737,9 @@ impl InternalThread {
 
                 //Begin watching for missing messages so we can finish logging out.
                 if let Some(logging_out_initiator) = logging_out_initiator {
-                    connection.status = ConnectionStatus::LoggingOut(
+                    connection.set_status(ConnectionStatus::LoggingOut(
                         LoggingOutType::ResendRequesting(logging_out_initiator),
-                    );
+                    ));
 
                     //Start a timer to acknowledge Logout if messages are not fulfilled in a reasonable
                     //amount of time. If they are fulfilled sooner, we'll just acknowledge sooner.
@@ -1706,6 +3832,18 @@ impl InternalThread {
                 reject_for_sending_time_accuracy(connection, message, msg_seq_num, tx);
                 return Ok(None);
             }
+            if let Some(sending_time_skew) = connection.options.sending_time_skew {
+                let skew = timer
+                    .now_utc()
+                    .signed_duration_since(message.sending_time())
+                    .abs()
+                    .to_std()
+                    .unwrap_or_default();
+                if skew > sending_time_skew {
+                    reject_for_sending_time_accuracy(connection, message, msg_seq_num, tx);
+                    return Ok(None);
+                }
+            }
 
             //Handle SequenceReset-GapFill messages.
             if let Some(sequence_reset) = message.as_any_mut().downcast_mut::<SequenceReset>() {
@@ -1713,6 +3851,7 @@ impl InternalThread {
                     if sequence_reset.new_seq_no > connection.inbound_msg_seq_num {
                         //Fast forward to the new expected inbound MsgSeqNum.
                         connection.inbound_msg_seq_num = sequence_reset.new_seq_no;
+                        connection.publish_msg_seq_nums();
                     } else {
                         //Attempting to rewind MsgSeqNum is not allowed according to FIXT v1.1,
                         //page 29.
@@ -1861,20 +4000,28 @@ impl InternalThread {
         //established and other messages can now be sent or received.
         let just_logged_on = if connection.status.is_sending_logon() {
             if let Some(message) = message.as_any().downcast_ref::<Logon>() {
-                connection.status = ConnectionStatus::Established;
+                connection.set_status(ConnectionStatus::Established);
+
+                if connection
+                    .initiate_logout_if_heart_bt_int_out_of_range(timer, message.heart_bt_int)
+                {
+                    return Ok(());
+                }
 
                 if message.heart_bt_int > 0 {
                     connection.outbound_heartbeat_timeout_duration =
                         Some(Duration::from_secs(message.heart_bt_int as u64));
+                    connection.publish_session_info();
                     reset_outbound_timeout(
                         timer,
                         &mut connection.outbound_heartbeat_timeout,
                         &connection.outbound_heartbeat_timeout_duration,
                         &connection.token,
                     );
-                    connection.inbound_testrequest_timeout_duration = Some(Duration::from_millis(
-                        message.heart_bt_int as u64 * 1000 + NO_INBOUND_TIMEOUT_PADDING_MS,
-                    ));
+                    connection.inbound_testrequest_timeout_duration = Some(
+                        Duration::from_secs(message.heart_bt_int as u64)
+                            + connection.options.inbound_timeout_padding,
+                    );
                     reset_inbound_timeout(
                         timer,
                         &mut connection.inbound_testrequest_timeout,
@@ -1901,19 +4048,21 @@ impl InternalThread {
 
                 //Make parser use the Message Type Default Application Version if specified.
                 for msg_type in &message.no_msg_types {
-                    if msg_type.default_ver_indicator
-                        && msg_type.msg_direction == MsgDirection::Send
-                        && msg_type.ref_appl_ver_id.is_some()
+                    if !msg_type.default_ver_indicator
+                        || msg_type.msg_direction != MsgDirection::Send
                     {
+                        continue;
+                    }
+                    if let Some(ref_appl_ver_id) = msg_type.ref_appl_ver_id {
                         connection.parser.set_default_message_type_version(
                             &msg_type.ref_msg_type[..],
-                            msg_type.ref_appl_ver_id.unwrap(),
+                            ref_appl_ver_id,
                         );
                     }
                 }
 
-                //TODO: Need to take MaxMessageSize into account.
-                //TODO: Optionally support filtering message types (NoMsgTypes).
+                connection.apply_remote_logon_limits(message);
+
                 tx.send(EngineEvent::SessionEstablished(connection.as_connection()))
                     .unwrap();
             } else {
@@ -1930,6 +4079,7 @@ impl InternalThread {
             //Switch from ReceivingLogon to ApprovingLogon. Have to be careful to cancel the
             //timeout.
             let old_status = mem::replace(&mut connection.status, ConnectionStatus::ApprovingLogon);
+            connection.update_session_state();
             let (listener, no_logon_timeout) =
                 if let ConnectionStatus::ReceivingLogon(listener, timeout) = old_status {
                     (listener, timeout)
@@ -1952,13 +4102,99 @@ impl InternalThread {
                     .set_default_message_version(message.default_appl_ver_id);
                 connection.inbound_msg_seq_num = message.msg_seq_num + 1;
                 connection.target_comp_id = message.sender_comp_id.clone();
+                connection.stats.lock().unwrap().session_id = connection.session_id();
+                connection.publish_session_info();
+                connection.received_reset_seq_num_flag = message.reset_seq_num_flag;
+                if !message.reset_seq_num_flag {
+                    connection.received_next_expected_msg_seq_num =
+                        message.next_expected_msg_seq_num;
+                }
+                connection.apply_remote_logon_limits(message);
+
+                //Only let in counterparties the listener expects. Each one can further restrict
+                //when it may logon and which HeartBtInt it must use.
+                if let Some(expected_sessions) = connection.expected_sessions.take() {
+                    let fix_version = connection.fix_version;
+                    match expected_sessions
+                        .into_iter()
+                        .find(|expected| expected.matches(fix_version, message))
+                    {
+                        Some(expected) => {
+                            connection.acceptor_session = Some(expected.token);
+                            connection.logon_allowed =
+                                connection.logon_allowed && expected.logon_allowed;
+                            if let Some(session_options) = expected.session_options {
+                                connection.options = session_options;
+                            }
+
+                            if let Some(heart_bt_int) = expected.heart_bt_int {
+                                if message.heart_bt_int != heart_bt_int {
+                                    let mut text = b"HeartBtInt must be ".to_vec();
+                                    text.extend_from_slice(heart_bt_int.to_string().as_bytes());
+                                    connection.initiate_logout(
+                                        timer,
+                                        LoggingOutType::Error(
+                                            ConnectionTerminatedReason::LogonHeartBtIntMismatchError,
+                                        ),
+                                        &text[..],
+                                    );
+                                    return Ok(());
+                                }
+                            }
+                        }
+                        None => {
+                            connection.initiate_logout(
+                                timer,
+                                LoggingOutType::Error(
+                                    ConnectionTerminatedReason::LogonUnknownSessionError,
+                                ),
+                                b"Unknown session",
+                            );
+                            return Ok(());
+                        }
+                    }
+                }
+
+                if connection
+                    .initiate_logout_if_heart_bt_int_out_of_range(timer, message.heart_bt_int)
+                {
+                    return Ok(());
+                }
+
+                if !connection.logon_allowed {
+                    connection.initiate_logout(
+                        timer,
+                        LoggingOutType::Error(
+                            ConnectionTerminatedReason::LogonOutsideScheduleError,
+                        ),
+                        b"Session is not active",
+                    );
+                    return Ok(());
+                }
+
+                if let Some(ref mut logon_authenticator) = *logon_authenticator {
+                    if let Err(text) =
+                        logon_authenticator.authenticate(&connection.session_id(), message)
+                    {
+                        connection.initiate_logout(
+                            timer,
+                            LoggingOutType::Error(
+                                ConnectionTerminatedReason::LogonAuthenticationError,
+                            ),
+                            &text[..],
+                        );
+                        return Ok(());
+                    }
+                }
 
                 if message.heart_bt_int > 0 {
                     connection.outbound_heartbeat_timeout_duration =
                         Some(Duration::from_secs(message.heart_bt_int as u64));
-                    connection.inbound_testrequest_timeout_duration = Some(Duration::from_millis(
-                        message.heart_bt_int as u64 * 1000 + NO_INBOUND_TIMEOUT_PADDING_MS,
-                    ));
+                    connection.publish_session_info();
+                    connection.inbound_testrequest_timeout_duration = Some(
+                        Duration::from_secs(message.heart_bt_int as u64)
+                            + connection.options.inbound_timeout_padding,
+                    );
                 } else if message.heart_bt_int < 0 {
                     connection.initiate_logout(
                         timer,
@@ -1983,13 +4219,15 @@ impl InternalThread {
 
                 //Make parser use the Message Type Default Application Version if specified.
                 for msg_type in &message.no_msg_types {
-                    if msg_type.default_ver_indicator
-                        && msg_type.msg_direction == MsgDirection::Send
-                        && msg_type.ref_appl_ver_id.is_some()
+                    if !msg_type.default_ver_indicator
+                        || msg_type.msg_direction != MsgDirection::Send
                     {
+                        continue;
+                    }
+                    if let Some(ref_appl_ver_id) = msg_type.ref_appl_ver_id {
                         connection.parser.set_default_message_type_version(
                             &msg_type.ref_msg_type[..],
-                            msg_type.ref_appl_ver_id.unwrap(),
+                            ref_appl_ver_id,
                         );
                     }
                 }
@@ -2018,16 +4256,34 @@ impl InternalThread {
             false
         };
 
+        //Logon with ResetSeqNumFlag starts the inbound MsgSeqNum over. This is either the response
+        //to our own reset or the remote asking for a reset which must be acknowledged with the
+        //same. See FIXT v1.1, page 36.
+        let msg_seq_num = message.msg_seq_num();
+        if matches!(
+            message.as_any().downcast_ref::<Logon>(),
+            Some(logon) if logon.reset_seq_num_flag
+        ) {
+            connection.inbound_msg_seq_num = msg_seq_num;
+            connection.publish_msg_seq_nums();
+            connection.clear_inbound_resend_request_msg_seq_num(timer);
+            if !just_logged_on && !connection.sent_reset_seq_num_flag {
+                connection.received_reset_seq_num_flag = true;
+                connection.push_reset_seq_num_logon();
+            }
+            connection.sent_reset_seq_num_flag = false;
+        }
+
         //Perform MsgSeqNum error handling if MsgSeqNum > or < expected. Otherwise, perform
         //administrative message handling and related book keeping.
-        let msg_seq_num = message.msg_seq_num();
         if message
             .as_any_mut()
             .downcast_mut::<SequenceReset>()
-            .map_or(false, |sequence_reset| {
+            .map(|sequence_reset| {
                 if !sequence_reset.gap_fill_flag {
                     if sequence_reset.new_seq_no > connection.inbound_msg_seq_num {
                         connection.inbound_msg_seq_num = sequence_reset.new_seq_no;
+                        connection.publish_msg_seq_nums();
                         connection.clear_inbound_resend_request_msg_seq_num(timer);
                     } else if sequence_reset.new_seq_no == connection.inbound_msg_seq_num {
                         tx.send(EngineEvent::SequenceResetResetHasNoEffect(
@@ -2060,6 +4316,7 @@ impl InternalThread {
                     false
                 }
             })
+            == Some(true)
         {
             //Special case where MsgSeqNum does not matter. Handled above.
         } else if msg_seq_num > connection.inbound_msg_seq_num {
@@ -2099,6 +4356,47 @@ impl InternalThread {
             }
         }
 
+        //Application messages the remote said it wouldn't send are refused at the application
+        //level. The MsgSeqNum has already been consumed so no session level Reject is needed.
+        if !is_msg_type_allowed(&connection.inbound_msg_types, message.msg_type()) {
+            let mut business_message_reject = BusinessMessageReject::new();
+            business_message_reject.ref_seq_num = msg_seq_num;
+            business_message_reject.ref_msg_type = message.msg_type().to_vec();
+            business_message_reject.business_reject_reason =
+                BusinessRejectReason::UnsupportedMessageType;
+            business_message_reject.business_reject_ref_id =
+                business_message_reject.ref_msg_type.clone();
+            business_message_reject.text = b"Unsupported Message Type".to_vec();
+            connection
+                .outbound_messages
+                .push(OutboundMessage::from(business_message_reject));
+
+            tx.send(EngineEvent::MessageRejected(
+                connection.as_connection(),
+                message,
+            ))
+            .unwrap();
+            return Ok(());
+        }
+
+        //Resend whatever the remote says it missed while we were disconnected.
+        if just_logged_on {
+            let next_expected_msg_seq_num = message
+                .as_any()
+                .downcast_ref::<Logon>()
+                .filter(|logon| !logon.reset_seq_num_flag)
+                .map_or(0, |logon| logon.next_expected_msg_seq_num);
+            if next_expected_msg_seq_num > 0 {
+                let end_msg_seq_num = connection.outbound_msg_seq_num;
+                connection.on_logon_next_expected_msg_seq_num(
+                    next_expected_msg_seq_num,
+                    end_msg_seq_num,
+                    tx,
+                    timer,
+                );
+            }
+        }
+
         //Reply to TestRequest automatically with a Heartbeat. Typical keep alive stuff.
         if let Some(test_request) = message.as_any().downcast_ref::<TestRequest>() {
             let mut heartbeat = Heartbeat::new();
@@ -2337,6 +4635,17 @@ impl InternalThread {
                     _ => {} //TODO: Support other errors as appropriate.
                 };
 
+                //Remember when messages we asked to be resent come back garbled so asking for
+                //them yet again can be detected as a loop.
+                if let Some(ref mut outbound_resend_request) = connection.outbound_resend_request {
+                    if outbound_resend_request
+                        .range
+                        .contains(&connection.inbound_msg_seq_num)
+                    {
+                        outbound_resend_request.received_garbled = true;
+                    }
+                }
+
                 //Always increment expected inbound MsgSeqNum after encountering a message that is
                 //garbled, cannot be parsed, or is otherwise invalid. See FIXT 1.1, page 26.
                 connection.increment_inbound_msg_seq_num()?;
@@ -2355,32 +4664,42 @@ impl InternalThread {
 }
 
 pub fn internal_engine_thread(
+    worker: usize,
     poll: Poll,
     token_generator: Arc<Mutex<TokenGenerator>>,
+    worker_assignments: WorkerAssignments,
+    stats: StatsRegistry,
+    session_infos: SessionInfoRegistry,
     tx: Sender<EngineEvent>,
     rx: Receiver<InternalEngineToThreadEvent>,
     message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
     max_message_size: u64,
 ) {
-    //TODO: There should probably be a mechanism to log every possible message, even those we
-    //handle automatically. One method might be to have a layer above this that handles the
-    //automatic stuff and allows for logging...this is probably just too low level.
-
     let mut internal_thread = InternalThread {
+        worker,
         poll,
         token_generator,
+        worker_assignments,
         tx,
         rx,
         message_dictionary,
         max_message_size,
         connections: HashMap::new(),
         listeners: HashMap::new(),
-        timer: TimerBuilder::default()
-            .tick_duration(Duration::from_millis(TIMER_TICK_MS))
-            .num_slots(TIMER_TIMEOUTS_PER_TICK_MAX)
-            .capacity(CONNECTION_COUNT_MAX * TIMEOUTS_PER_CONNECTION_MAX)
-            .build(),
+        timer: Timer::new(Arc::new(SystemClock)),
         network_read_retry: NetworkReadRetry::new(),
+        message_store_factory: None,
+        seq_num_stores: SeqNumStores::default(),
+        initiator_sessions: HashMap::new(),
+        acceptor_sessions: HashMap::new(),
+        outbound_message_hook: None,
+        message_log: None,
+        logon_decorator: None,
+        logon_authenticator: None,
+        send_receipts: true,
+        session_state_events: false,
+        stats,
+        session_infos,
     };
     let mut terminated_connections: Vec<(InternalConnection, ConnectionTerminatedReason)> =
         Vec::new();
@@ -2426,7 +4745,8 @@ pub fn internal_engine_thread(
     //on a per-connection basis.
     let mut events = Events::with_capacity(EVENT_POLL_CAPACITY);
     loop {
-        if let Err(e) = internal_thread.poll.poll(&mut events, None) {
+        let poll_duration = internal_thread.timer.poll_duration();
+        if let Err(e) = internal_thread.poll.poll(&mut events, poll_duration) {
             internal_thread
                 .tx
                 .send(EngineEvent::FatalError("Cannot poll events", e))
@@ -2455,13 +4775,21 @@ pub fn internal_engine_thread(
             if let Err(e) = result {
                 match e {
                     ConnectionEventError::TerminateConnection(connection, e) => {
-                        terminated_connections.push((connection, e));
+                        terminated_connections.push((*connection, e));
                     }
                     ConnectionEventError::Shutdown => return,
                 };
             }
         }
 
+        //Let the application know about any session state transitions caused by the events above.
+        for connection in internal_thread.connections.values_mut() {
+            connection.report_session_state_changes(
+                &internal_thread.tx,
+                internal_thread.session_state_events,
+            );
+        }
+
         //Clean-up connections that have been shutdown (cleanly or on error).
         terminated_connections
             .drain(..)
@@ -2481,6 +4809,9 @@ pub fn internal_engine_thread(
                 if let Some(ref timeout) = connection.logout_timeout {
                     internal_thread.timer.cancel_timeout(timeout);
                 }
+                if let Some(ref timeout) = connection.throttle_timeout {
+                    internal_thread.timer.cancel_timeout(timeout);
+                }
                 if let ConnectionStatus::ReceivingLogon(_, ref timeout) = connection.status {
                     internal_thread.timer.cancel_timeout(timeout);
                 }
@@ -2488,11 +4819,32 @@ pub fn internal_engine_thread(
                 internal_thread
                     .network_read_retry
                     .remove_all(connection.token);
+                internal_thread.stats.remove(connection.as_connection());
+                internal_thread
+                    .session_infos
+                    .remove(connection.as_connection());
+
+                //Hand back anything the application queued that never made it out.
+                let mut connection = connection;
+                connection.shutdown();
+                connection.report_unsent_messages(&internal_thread.tx);
+                connection.set_session_state(SessionState::Disconnected);
+                connection.report_session_state_changes(
+                    &internal_thread.tx,
+                    internal_thread.session_state_events,
+                );
 
                 //Notify user in the special case where connection was never even established. This
                 //block is incredibly ugly but required to appease the borrow checker.
+                let is_initiator_session = internal_thread
+                    .initiator_sessions
+                    .contains_key(&connection.token);
                 let e = if let ConnectionTerminatedReason::SocketReadError(err) = e {
-                    if !connection.is_connected {
+                    if connection.is_connected {
+                        ConnectionTerminatedReason::SocketReadError(err)
+                    } else if is_initiator_session {
+                        ConnectionTerminatedReason::SocketConnectError(err)
+                    } else {
                         internal_thread
                             .tx
                             .send(EngineEvent::ConnectionFailed(
@@ -2502,20 +4854,50 @@ pub fn internal_engine_thread(
                             .unwrap();
                         return true;
                     }
-
-                    ConnectionTerminatedReason::SocketReadError(err)
                 } else {
                     e
                 };
 
-                //Notify user that connection was terminated.
-                internal_thread
-                    .tx
-                    .send(EngineEvent::ConnectionTerminated(
+                //Save the final MsgSeqNums one last time. There's nothing left to terminate if this
+                //fails but the user still finds out the MsgSeqNums through the event below.
+                let _ = connection.persist_msg_seq_nums(&mut internal_thread.seq_num_stores);
+                if connection.reset_msg_seq_nums_on_termination {
+                    let session_id = connection.session_id();
+                    internal_thread
+                        .reset_stored_session(&session_id, connection.message_store.as_mut());
+                }
+
+                //Notify user that connection was terminated. Scheduled and reconnecting
+                //connections live on until their next session.
+                if let Some(initiator_session) = internal_thread
+                    .initiator_sessions
+                    .get_mut(&connection.token)
+                {
+                    initiator_session.msg_seq_nums =
+                        Some(if connection.reset_msg_seq_nums_on_termination {
+                            MsgSeqNums::default()
+                        } else {
+                            connection.msg_seq_nums()
+                        });
+                    internal_thread.session_infos.insert(
                         connection.as_connection(),
+                        initiator_session.disconnected_session_info(),
+                    );
+                    internal_thread.on_initiator_session_ended(
+                        connection.token,
                         e,
-                    ))
-                    .unwrap();
+                        connection.msg_seq_nums(),
+                    );
+                } else {
+                    internal_thread
+                        .tx
+                        .send(EngineEvent::ConnectionTerminated(
+                            connection.as_connection(),
+                            e,
+                            connection.msg_seq_nums(),
+                        ))
+                        .unwrap();
+                }
 
                 true
             });
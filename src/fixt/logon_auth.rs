@@ -0,0 +1,159 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashMap;
+
+use crate::dictionary::field_types::generic::UtcTimestampFieldType;
+use crate::dictionary::messages::Logon;
+use crate::field_type::FieldType;
+use crate::fix_version::FIXVersion;
+use crate::fixt::engine::{Connection, SessionID};
+use crate::fixt::message::FIXTMessage;
+use crate::message_version::MessageVersion;
+
+//Called by the engine's thread for every outbound Logon after its MsgSeqNum, CompIDs, and
+//SendingTime are final but before it's serialized. Use it to fill in authentication fields like
+//Username, Password, or a signature in RawData.
+pub type LogonDecorator = Box<dyn FnMut(Connection, &mut Logon) + Send>;
+
+//Decides whether a remote connecting to a listener is allowed to logon. Called before
+//EngineEvent::ConnectionLoggingOn so the application only ever sees authenticated Logons.
+pub trait LogonAuthenticator {
+    //Returns Err with the text to include in the Logout when the Logon should be refused.
+    fn authenticate(&mut self, session_id: &SessionID, logon: &Logon) -> Result<(), Vec<u8>>;
+}
+
+impl<F> LogonAuthenticator for F
+where
+    F: FnMut(&SessionID, &Logon) -> Result<(), Vec<u8>>,
+{
+    fn authenticate(&mut self, session_id: &SessionID, logon: &Logon) -> Result<(), Vec<u8>> {
+        self(session_id, logon)
+    }
+}
+
+//Accepts Logons with a known Username (553) and matching Password (554).
+#[derive(Clone, Debug, Default)]
+pub struct PasswordAuthenticator {
+    passwords: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+impl PasswordAuthenticator {
+    pub fn new() -> PasswordAuthenticator {
+        PasswordAuthenticator::default()
+    }
+
+    pub fn with_user(mut self, username: &[u8], password: &[u8]) -> PasswordAuthenticator {
+        self.passwords.insert(username.to_vec(), password.to_vec());
+        self
+    }
+}
+
+impl LogonAuthenticator for PasswordAuthenticator {
+    fn authenticate(&mut self, _session_id: &SessionID, logon: &Logon) -> Result<(), Vec<u8>> {
+        match self.passwords.get(&logon.username) {
+            Some(password) if *password == logon.password => Ok(()),
+            _ => Err(b"Invalid username or password".to_vec()),
+        }
+    }
+}
+
+//Bytes that are signed to authenticate a Logon: SendingTime, MsgType, MsgSeqNum, SenderCompID,
+//and TargetCompID joined by SOH. This matches what crypto venues like FTX expect in RawData.
+pub fn logon_signature_payload(logon: &Logon) -> Vec<u8> {
+    let mut payload = Vec::new();
+    UtcTimestampFieldType::read(
+        &logon.sending_time,
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        &mut payload,
+    );
+    for value in [
+        logon.msg_type(),
+        logon.msg_seq_num.to_string().as_bytes(),
+        &logon.sender_comp_id[..],
+        &logon.target_comp_id[..],
+    ] {
+        payload.push(b'\x01');
+        payload.extend_from_slice(value);
+    }
+
+    payload
+}
+
+//Put the lowercase hex HMAC-SHA256 of logon_signature_payload() into RawData (96). Call from a
+//LogonDecorator so the signature covers the final session header.
+#[cfg(feature = "hmac")]
+pub fn sign_logon(secret: &[u8], logon: &mut Logon) {
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret);
+    let tag = ring::hmac::sign(&key, &logon_signature_payload(logon));
+    logon.raw_data = tag
+        .as_ref()
+        .iter()
+        .flat_map(|byte| format!("{:02x}", byte).into_bytes())
+        .collect();
+}
+
+//Check that RawData holds the signature sign_logon() would have produced with secret.
+#[cfg(feature = "hmac")]
+pub fn verify_logon_signature(secret: &[u8], logon: &Logon) -> bool {
+    fn decode_hex(bytes: &[u8]) -> Option<Vec<u8>> {
+        let pairs = bytes.chunks_exact(2);
+        if !pairs.remainder().is_empty() {
+            return None;
+        }
+
+        pairs
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+            })
+            .collect()
+    }
+
+    let signature = match decode_hex(&logon.raw_data) {
+        Some(signature) => signature,
+        None => return false,
+    };
+    let key = ring::hmac::Key::new(ring::hmac::HMAC_SHA256, secret);
+    ring::hmac::verify(&key, &logon_signature_payload(logon), &signature).is_ok()
+}
+
+//Accepts Logons signed by sign_logon() using the secret belonging to the remote's SenderCompID.
+#[cfg(feature = "hmac")]
+#[derive(Clone, Debug, Default)]
+pub struct HmacAuthenticator {
+    secrets: HashMap<Vec<u8>, Vec<u8>>,
+}
+
+#[cfg(feature = "hmac")]
+impl HmacAuthenticator {
+    pub fn new() -> HmacAuthenticator {
+        HmacAuthenticator::default()
+    }
+
+    pub fn with_secret(mut self, sender_comp_id: &[u8], secret: &[u8]) -> HmacAuthenticator {
+        self.secrets
+            .insert(sender_comp_id.to_vec(), secret.to_vec());
+        self
+    }
+}
+
+#[cfg(feature = "hmac")]
+impl LogonAuthenticator for HmacAuthenticator {
+    fn authenticate(&mut self, _session_id: &SessionID, logon: &Logon) -> Result<(), Vec<u8>> {
+        match self.secrets.get(&logon.sender_comp_id) {
+            Some(secret) if verify_logon_signature(secret, logon) => Ok(()),
+            _ => Err(b"Invalid signature".to_vec()),
+        }
+    }
+}
@@ -48,6 +48,7 @@ pub trait FIXTMessage: Message {
         msg_seq_num: Option<<<MsgSeqNum as Field>::Type as FieldType>::Type>,
         sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
         target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+        sending_time: <<SendingTime as Field>::Type as FieldType>::Type,
     );
 }
 
@@ -182,13 +183,14 @@ macro_rules! define_fixt_message {
             fn setup_fixt_session_header(&mut self,
                                          msg_seq_num: Option<<<$crate::dictionary::fields::MsgSeqNum as $crate::field::Field>::Type as $crate::field_type::FieldType>::Type>,
                                          sender_comp_id: <<$crate::dictionary::fields::SenderCompID as $crate::field::Field>::Type as $crate::field_type::FieldType>::Type,
-                                         target_comp_id: <<$crate::dictionary::fields::TargetCompID as $crate::field::Field>::Type as $crate::field_type::FieldType>::Type) {
+                                         target_comp_id: <<$crate::dictionary::fields::TargetCompID as $crate::field::Field>::Type as $crate::field_type::FieldType>::Type,
+                                         sending_time: <<$crate::dictionary::fields::SendingTime as $crate::field::Field>::Type as $crate::field_type::FieldType>::Type) {
                 if let Some(msg_seq_num) = msg_seq_num {
                     self.msg_seq_num = msg_seq_num;
                 }
                 self.sender_comp_id = sender_comp_id;
                 self.target_comp_id = target_comp_id;
-                self.sending_time = <$crate::dictionary::fields::SendingTime as $crate::field::Field>::Type::new_now();
+                self.sending_time = sending_time;
             }
         }
     };
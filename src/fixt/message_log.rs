@@ -0,0 +1,155 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Write};
+use std::path::PathBuf;
+
+use crate::fixt::engine::SessionID;
+
+const DEFAULT_MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
+const DEFAULT_MAX_BACKUP_FILES: usize = 10;
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum MessageDirection {
+    Inbound,
+    Outbound,
+}
+
+pub trait MessageLog {
+    //Record the exact bytes of a message that was just read from or is about to be written to a
+    //session's socket. Called for every message, including those handled automatically by the
+    //Engine and garbled ones that could not be parsed. Inbound messages received before a
+    //Logon identifies the remote use a SessionID with an empty TargetCompID.
+    fn log(
+        &mut self,
+        session_id: &SessionID,
+        direction: MessageDirection,
+        timestamp: &DateTime<Utc>,
+        bytes: &[u8],
+    ) -> io::Result<()>;
+}
+
+struct OpenLogFile {
+    file: File,
+    size: u64,
+}
+
+//Writes each session's messages to "<session>.messages.log" in directory using the same
+//"YYYYMMDD-HH:MM:SS.sss : <message>" line format as QuickFIX so existing tools can read them.
+//Once a file grows past max_file_size it's renamed to "<session>.messages.log.1" (bumping any
+//older backups up by one) and a new file is started. Only max_backup_files backups are kept.
+pub struct FileMessageLog {
+    directory: PathBuf,
+    max_file_size: u64,
+    max_backup_files: usize,
+    files: HashMap<SessionID, OpenLogFile>,
+}
+
+impl FileMessageLog {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> io::Result<FileMessageLog> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+
+        Ok(FileMessageLog {
+            directory,
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_backup_files: DEFAULT_MAX_BACKUP_FILES,
+            files: HashMap::new(),
+        })
+    }
+
+    pub fn with_max_file_size(mut self, max_file_size: u64) -> FileMessageLog {
+        self.max_file_size = max_file_size;
+        self
+    }
+
+    pub fn with_max_backup_files(mut self, max_backup_files: usize) -> FileMessageLog {
+        self.max_backup_files = max_backup_files;
+        self
+    }
+
+    fn path(&self, session_id: &SessionID, backup: usize) -> PathBuf {
+        let mut path = self.directory.clone();
+        if backup == 0 {
+            path.push(format!("{}.messages.log", session_id.file_stem()));
+        } else {
+            path.push(format!(
+                "{}.messages.log.{}",
+                session_id.file_stem(),
+                backup
+            ));
+        }
+        path
+    }
+
+    fn open(&self, session_id: &SessionID) -> io::Result<OpenLogFile> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(session_id, 0))?;
+        let size = file.metadata()?.len();
+
+        Ok(OpenLogFile { file, size })
+    }
+
+    fn rotate(&mut self, session_id: &SessionID) -> io::Result<()> {
+        self.files.remove(session_id);
+
+        if self.max_backup_files == 0 {
+            return fs::remove_file(self.path(session_id, 0));
+        }
+
+        for backup in (1..self.max_backup_files).rev() {
+            let path = self.path(session_id, backup);
+            if path.exists() {
+                fs::rename(path, self.path(session_id, backup + 1))?;
+            }
+        }
+        fs::rename(self.path(session_id, 0), self.path(session_id, 1))
+    }
+}
+
+impl MessageLog for FileMessageLog {
+    fn log(
+        &mut self,
+        session_id: &SessionID,
+        _direction: MessageDirection,
+        timestamp: &DateTime<Utc>,
+        bytes: &[u8],
+    ) -> io::Result<()> {
+        let mut line = timestamp
+            .format("%Y%m%d-%H:%M:%S%.3f : ")
+            .to_string()
+            .into_bytes();
+        line.extend_from_slice(bytes);
+        line.push(b'\n');
+
+        if !self.files.contains_key(session_id) {
+            let open_log_file = self.open(session_id)?;
+            self.files.insert(session_id.clone(), open_log_file);
+        }
+        let size = self.files[session_id].size;
+        if size > 0 && size + line.len() as u64 > self.max_file_size {
+            self.rotate(session_id)?;
+            let open_log_file = self.open(session_id)?;
+            self.files.insert(session_id.clone(), open_log_file);
+        }
+
+        let open_log_file = self.files.get_mut(session_id).unwrap();
+        open_log_file.file.write_all(&line[..])?;
+        open_log_file.size += line.len() as u64;
+
+        Ok(())
+    }
+}
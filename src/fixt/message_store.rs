@@ -0,0 +1,376 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::ops::Range;
+use std::path::PathBuf;
+
+use crate::dictionary::field_types::generic::UtcTimestampFieldType;
+use crate::field_type::FieldType;
+use crate::fix_version::FIXVersion;
+use crate::fixt::engine::SessionID;
+use crate::message_version::MessageVersion;
+
+//Every outbound message that was assigned a MsgSeqNum, serialized exactly as it was written to
+//the socket.
+#[derive(Clone, Debug, PartialEq)]
+pub struct StoredMessage {
+    pub msg_seq_num: u64,
+    pub msg_type: Vec<u8>,
+    pub bytes: Vec<u8>,
+}
+
+pub trait MessageStore {
+    //Record a message that was just assigned msg_seq_num and is about to be sent.
+    fn store(&mut self, msg_seq_num: u64, msg_type: &[u8], bytes: &[u8]) -> io::Result<()>;
+
+    //Retrieve all recorded messages with a MsgSeqNum in [range.start,range.end) in increasing
+    //MsgSeqNum order. MsgSeqNums that were never recorded are simply skipped.
+    fn fetch(&mut self, range: Range<u64>) -> io::Result<Vec<StoredMessage>>;
+
+    //Forget every recorded message. Used when a session's MsgSeqNums start over.
+    fn reset(&mut self) -> io::Result<()>;
+}
+
+pub trait MessageStoreFactory {
+    fn create(&mut self, session_id: &SessionID) -> io::Result<Box<dyn MessageStore + Send>>;
+}
+
+#[derive(Default)]
+pub struct MemoryMessageStore {
+    messages: BTreeMap<u64, StoredMessage>,
+}
+
+impl MemoryMessageStore {
+    pub fn new() -> MemoryMessageStore {
+        Default::default()
+    }
+}
+
+impl MessageStore for MemoryMessageStore {
+    fn store(&mut self, msg_seq_num: u64, msg_type: &[u8], bytes: &[u8]) -> io::Result<()> {
+        self.messages.insert(
+            msg_seq_num,
+            StoredMessage {
+                msg_seq_num,
+                msg_type: msg_type.to_vec(),
+                bytes: bytes.to_vec(),
+            },
+        );
+
+        Ok(())
+    }
+
+    fn fetch(&mut self, range: Range<u64>) -> io::Result<Vec<StoredMessage>> {
+        if range.start >= range.end {
+            return Ok(Vec::new());
+        }
+
+        Ok(self
+            .messages
+            .range(range)
+            .map(|(_, message)| message.clone())
+            .collect())
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.messages.clear();
+        Ok(())
+    }
+}
+
+#[derive(Default)]
+pub struct MemoryMessageStoreFactory;
+
+impl MemoryMessageStoreFactory {
+    pub fn new() -> MemoryMessageStoreFactory {
+        MemoryMessageStoreFactory
+    }
+}
+
+impl MessageStoreFactory for MemoryMessageStoreFactory {
+    fn create(&mut self, _session_id: &SessionID) -> io::Result<Box<dyn MessageStore + Send>> {
+        Ok(Box::new(MemoryMessageStore::new()))
+    }
+}
+
+struct JournalEntry {
+    offset: u64,
+    msg_type: Vec<u8>,
+    bytes_len: u32,
+}
+
+//Append-only journal of outbound messages. Each record is laid out as:
+//  MsgSeqNum (u64, little endian)
+//  MsgType length (u32, little endian)
+//  Message length (u32, little endian)
+//  MsgType bytes
+//  Message bytes
+//Only an index of where each message lives is kept in memory. A record that was only partially
+//written (ie. the process died mid-write) is discarded when the journal is reopened.
+pub struct FileMessageStore {
+    file: File,
+    index: BTreeMap<u64, JournalEntry>,
+    end_offset: u64,
+}
+
+const JOURNAL_RECORD_HEADER_LEN: u64 = 8 + 4 + 4;
+
+impl FileMessageStore {
+    pub fn open<P: Into<PathBuf>>(path: P) -> io::Result<FileMessageStore> {
+        let path = path.into();
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)?;
+
+        //Rebuild the index by walking every complete record.
+        let mut contents = Vec::new();
+        file.read_to_end(&mut contents)?;
+
+        let mut index = BTreeMap::new();
+        let mut offset = 0u64;
+        while offset + JOURNAL_RECORD_HEADER_LEN <= contents.len() as u64 {
+            let header = &contents[offset as usize..(offset + JOURNAL_RECORD_HEADER_LEN) as usize];
+            let msg_seq_num = read_u64(&header[0..8]);
+            let msg_type_len = read_u32(&header[8..12]) as u64;
+            let bytes_len = read_u32(&header[12..16]);
+
+            let record_end = offset + JOURNAL_RECORD_HEADER_LEN + msg_type_len + bytes_len as u64;
+            if record_end > contents.len() as u64 {
+                break;
+            }
+
+            let msg_type_begin = (offset + JOURNAL_RECORD_HEADER_LEN) as usize;
+            let msg_type_end = msg_type_begin + msg_type_len as usize;
+            index.insert(
+                msg_seq_num,
+                JournalEntry {
+                    offset: msg_type_end as u64,
+                    msg_type: contents[msg_type_begin..msg_type_end].to_vec(),
+                    bytes_len,
+                },
+            );
+
+            offset = record_end;
+        }
+
+        //Drop any trailing partial record so new records are appended to a consistent journal.
+        if offset != contents.len() as u64 {
+            file.set_len(offset)?;
+        }
+
+        Ok(FileMessageStore {
+            file,
+            index,
+            end_offset: offset,
+        })
+    }
+}
+
+fn read_u64(bytes: &[u8]) -> u64 {
+    let mut array = [0u8; 8];
+    array.copy_from_slice(bytes);
+    u64::from_le_bytes(array)
+}
+
+fn read_u32(bytes: &[u8]) -> u32 {
+    let mut array = [0u8; 4];
+    array.copy_from_slice(bytes);
+    u32::from_le_bytes(array)
+}
+
+impl MessageStore for FileMessageStore {
+    fn store(&mut self, msg_seq_num: u64, msg_type: &[u8], bytes: &[u8]) -> io::Result<()> {
+        let mut record =
+            Vec::with_capacity(JOURNAL_RECORD_HEADER_LEN as usize + msg_type.len() + bytes.len());
+        record.extend_from_slice(&msg_seq_num.to_le_bytes());
+        record.extend_from_slice(&(msg_type.len() as u32).to_le_bytes());
+        record.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+        record.extend_from_slice(msg_type);
+        record.extend_from_slice(bytes);
+
+        self.file.seek(SeekFrom::Start(self.end_offset))?;
+        self.file.write_all(&record[..])?;
+        self.file.flush()?;
+
+        self.index.insert(
+            msg_seq_num,
+            JournalEntry {
+                offset: self.end_offset + JOURNAL_RECORD_HEADER_LEN + msg_type.len() as u64,
+                msg_type: msg_type.to_vec(),
+                bytes_len: bytes.len() as u32,
+            },
+        );
+        self.end_offset += record.len() as u64;
+
+        Ok(())
+    }
+
+    fn fetch(&mut self, range: Range<u64>) -> io::Result<Vec<StoredMessage>> {
+        let mut result = Vec::new();
+        if range.start >= range.end {
+            return Ok(result);
+        }
+
+        for (msg_seq_num, entry) in self.index.range(range) {
+            let mut bytes = vec![0u8; entry.bytes_len as usize];
+            self.file.seek(SeekFrom::Start(entry.offset))?;
+            self.file.read_exact(&mut bytes[..])?;
+
+            result.push(StoredMessage {
+                msg_seq_num: *msg_seq_num,
+                msg_type: entry.msg_type.clone(),
+                bytes,
+            });
+        }
+
+        Ok(result)
+    }
+
+    fn reset(&mut self) -> io::Result<()> {
+        self.file.set_len(0)?;
+        self.index.clear();
+        self.end_offset = 0;
+
+        Ok(())
+    }
+}
+
+pub struct FileMessageStoreFactory {
+    directory: PathBuf,
+}
+
+impl FileMessageStoreFactory {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> FileMessageStoreFactory {
+        FileMessageStoreFactory {
+            directory: directory.into(),
+        }
+    }
+}
+
+impl MessageStoreFactory for FileMessageStoreFactory {
+    fn create(&mut self, session_id: &SessionID) -> io::Result<Box<dyn MessageStore + Send>> {
+        fs::create_dir_all(&self.directory)?;
+
+        let mut path = self.directory.clone();
+        path.push(format!("{}.journal", session_id.file_stem()));
+        Ok(Box::new(FileMessageStore::open(path)?))
+    }
+}
+
+//Prepare a previously sent message to be sent again in response to a ResendRequest. The message
+//keeps its MsgSeqNum but is marked with PossDupFlag=Y, its original SendingTime is moved to
+//OrigSendingTime, and SendingTime is set to sending_time. BodyLength and CheckSum are
+//recalculated. Returns None if the stored bytes are not a recognizable FIX message.
+pub fn prepare_stored_message_for_resend(
+    bytes: &[u8],
+    sending_time: &DateTime<Utc>,
+) -> Option<Vec<u8>> {
+    const POSS_DUP_FLAG_TAG: &[u8] = b"43";
+    const SENDING_TIME_TAG: &[u8] = b"52";
+    const ORIG_SENDING_TIME_TAG: &[u8] = b"122";
+    const POSS_RESEND_TAG: &[u8] = b"97";
+
+    //Split message into (tag,value) pairs. Data fields are the only ones that may contain a SOH
+    //byte so their preceding length fields must be respected.
+    let mut fields: Vec<(&[u8], &[u8])> = Vec::new();
+    let mut offset = 0;
+    let mut next_value_len: Option<usize> = None;
+    while offset < bytes.len() {
+        let equal_offset = offset + bytes[offset..].iter().position(|b| *b == b'=')?;
+        let tag = &bytes[offset..equal_offset];
+        let value_begin = equal_offset + 1;
+        let value_end = if let Some(value_len) = next_value_len.take() {
+            value_begin + value_len
+        } else {
+            value_begin + bytes[value_begin..].iter().position(|b| *b == b'\x01')?
+        };
+        if value_end >= bytes.len() || bytes[value_end] != b'\x01' {
+            return None;
+        }
+        let value = &bytes[value_begin..value_end];
+
+        //Length fields for data fields that can appear in the standard header.
+        if tag == b"90" || tag == b"95" || tag == b"212" {
+            next_value_len = Some(::std::str::from_utf8(value).ok()?.parse::<usize>().ok()?);
+        }
+
+        fields.push((tag, value));
+        offset = value_end + 1;
+    }
+
+    if fields.len() < 4 || fields[0].0 != b"8" || fields[1].0 != b"9" || fields[2].0 != b"35" {
+        return None;
+    }
+    if fields[fields.len() - 1].0 != b"10" {
+        return None;
+    }
+
+    let mut new_sending_time = Vec::new();
+    UtcTimestampFieldType::read(
+        sending_time,
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        &mut new_sending_time,
+    );
+
+    //Rebuild the body with the adjusted header fields.
+    let mut body = Vec::with_capacity(bytes.len() + 64);
+    let mut found_sending_time = false;
+    for &(tag, value) in &fields[2..fields.len() - 1] {
+        if tag == POSS_DUP_FLAG_TAG || tag == ORIG_SENDING_TIME_TAG || tag == POSS_RESEND_TAG {
+            continue;
+        }
+
+        if tag == SENDING_TIME_TAG {
+            found_sending_time = true;
+
+            body.extend_from_slice(POSS_DUP_FLAG_TAG);
+            body.extend_from_slice(b"=Y\x01");
+            body.extend_from_slice(SENDING_TIME_TAG);
+            body.push(b'=');
+            body.extend_from_slice(&new_sending_time[..]);
+            body.push(b'\x01');
+            body.extend_from_slice(ORIG_SENDING_TIME_TAG);
+            body.push(b'=');
+            body.extend_from_slice(value);
+            body.push(b'\x01');
+            continue;
+        }
+
+        body.extend_from_slice(tag);
+        body.push(b'=');
+        body.extend_from_slice(value);
+        body.push(b'\x01');
+    }
+    if !found_sending_time {
+        return None;
+    }
+
+    let mut result = Vec::with_capacity(body.len() + 32);
+    result.extend_from_slice(b"8=");
+    result.extend_from_slice(fields[0].1);
+    result.extend_from_slice(b"\x019=");
+    result.extend_from_slice(body.len().to_string().as_bytes());
+    result.push(b'\x01');
+    result.extend_from_slice(&body[..]);
+
+    let checksum = result.iter().fold(0u8, |sum, b| sum.wrapping_add(*b));
+    result.extend_from_slice(format!("10={:03}\x01", checksum).as_bytes());
+
+    Some(result)
+}
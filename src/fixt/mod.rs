@@ -9,13 +9,37 @@
 // at your option. This file may not be copied, modified, or distributed
 // except according to those terms.
 
+pub mod acceptor_session;
+pub mod application;
+#[cfg(feature = "async")]
+pub mod async_engine;
+pub mod clock;
+#[cfg(feature = "config")]
+pub mod config;
 pub mod engine;
 mod engine_thread;
+pub mod logon_auth;
 #[macro_use]
 pub mod message;
+pub mod message_log;
+pub mod message_store;
+pub mod reconnect_policy;
+pub mod seq_num_store;
+pub mod session_config;
+pub mod session_info;
+pub mod session_options;
+pub mod session_schedule;
+pub mod stats;
+mod stream;
+pub mod throttle;
+mod timer;
+#[cfg(feature = "tls")]
+pub mod tls;
+pub mod transport;
 
 pub mod tests {
     pub use super::engine_thread::{
+        AUTO_DISCONNECT_AFTER_GARBLED_RESEND_REQUEST_RETRY_COUNT,
         AUTO_DISCONNECT_AFTER_INBOUND_RESEND_REQUEST_LOOP_COUNT,
         AUTO_DISCONNECT_AFTER_NO_LOGON_RECEIVED_SECONDS, INBOUND_BYTES_BUFFER_CAPACITY,
         INBOUND_MESSAGES_BUFFER_LEN_MAX,
@@ -0,0 +1,83 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use rand::Rng;
+use std::cmp;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+//How an initiator connection goes about reconnecting after it's disconnected or fails to connect.
+//The delay before each attempt starts at initial_delay and doubles with every consecutive attempt
+//up to max_delay. Attempts cycle through the connection's address followed by each failover
+//address in the order they were added. The attempt count starts over once a session is
+//established again.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ReconnectPolicy {
+    initial_delay: Duration,
+    max_delay: Duration,
+    jitter: f64,
+    max_attempts: Option<u32>,
+    failover_addresses: Vec<SocketAddr>,
+}
+
+impl ReconnectPolicy {
+    pub fn new(initial_delay: Duration, max_delay: Duration) -> ReconnectPolicy {
+        ReconnectPolicy {
+            initial_delay,
+            max_delay: cmp::max(initial_delay, max_delay),
+            jitter: 0.0,
+            max_attempts: None,
+            failover_addresses: Vec::new(),
+        }
+    }
+
+    //Randomly spread each delay by up to +/- jitter (0.0 to 1.0) of itself so many connections
+    //dropped at the same time don't all reconnect at the same time.
+    pub fn with_jitter(mut self, jitter: f64) -> ReconnectPolicy {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    //Give up after this many consecutive attempts. Unlimited by default.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> ReconnectPolicy {
+        self.max_attempts = Some(max_attempts);
+        self
+    }
+
+    pub fn with_failover_address(mut self, address: SocketAddr) -> ReconnectPolicy {
+        self.failover_addresses.push(address);
+        self
+    }
+
+    pub fn max_attempts(&self) -> Option<u32> {
+        self.max_attempts
+    }
+
+    pub fn failover_addresses(&self) -> &[SocketAddr] {
+        &self.failover_addresses[..]
+    }
+
+    //Delay before the given attempt, starting at 1, including jitter.
+    pub fn delay(&self, attempt: u32) -> Duration {
+        let exponent = cmp::min(attempt.saturating_sub(1), 31);
+        let delay = self
+            .initial_delay
+            .checked_mul(1 << exponent)
+            .map_or(self.max_delay, |delay| cmp::min(delay, self.max_delay));
+
+        if self.jitter > 0.0 {
+            let scale = rand::thread_rng().gen_range(1.0 - self.jitter..=1.0 + self.jitter);
+            delay.mul_f64(scale)
+        } else {
+            delay
+        }
+    }
+}
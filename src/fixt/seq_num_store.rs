@@ -0,0 +1,107 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, Read, Write};
+use std::path::PathBuf;
+
+use crate::fixt::engine::{MsgSeqNums, SessionID};
+
+pub trait SeqNumStore {
+    //Retrieve the last saved MsgSeqNums for a session or None if the session has never been seen.
+    fn load(&mut self, session_id: &SessionID) -> io::Result<Option<MsgSeqNums>>;
+
+    //Remember the MsgSeqNums for a session. Called every time they change.
+    fn save(&mut self, session_id: &SessionID, msg_seq_nums: MsgSeqNums) -> io::Result<()>;
+}
+
+//Keeps MsgSeqNums across reconnects but not across process restarts.
+#[derive(Default)]
+pub struct MemorySeqNumStore {
+    msg_seq_nums: HashMap<SessionID, MsgSeqNums>,
+}
+
+impl MemorySeqNumStore {
+    pub fn new() -> MemorySeqNumStore {
+        Default::default()
+    }
+}
+
+impl SeqNumStore for MemorySeqNumStore {
+    fn load(&mut self, session_id: &SessionID) -> io::Result<Option<MsgSeqNums>> {
+        Ok(self.msg_seq_nums.get(session_id).cloned())
+    }
+
+    fn save(&mut self, session_id: &SessionID, msg_seq_nums: MsgSeqNums) -> io::Result<()> {
+        self.msg_seq_nums.insert(session_id.clone(), msg_seq_nums);
+        Ok(())
+    }
+}
+
+//Keeps each session's MsgSeqNums in its own small text file in directory formatted as
+//"<inbound> <outbound>\n". Files are replaced atomically by writing a temporary file and renaming
+//it over the old one so a crash never leaves a half written file behind.
+pub struct FileSeqNumStore {
+    directory: PathBuf,
+}
+
+impl FileSeqNumStore {
+    pub fn new<P: Into<PathBuf>>(directory: P) -> io::Result<FileSeqNumStore> {
+        let directory = directory.into();
+        fs::create_dir_all(&directory)?;
+
+        Ok(FileSeqNumStore { directory })
+    }
+
+    fn path(&self, session_id: &SessionID, extension: &str) -> PathBuf {
+        let mut path = self.directory.clone();
+        path.push(format!("{}.{}", session_id.file_stem(), extension));
+        path
+    }
+}
+
+impl SeqNumStore for FileSeqNumStore {
+    fn load(&mut self, session_id: &SessionID) -> io::Result<Option<MsgSeqNums>> {
+        let mut file = match File::open(self.path(session_id, "seqnums")) {
+            Ok(file) => file,
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e),
+        };
+
+        let mut contents = String::new();
+        file.read_to_string(&mut contents)?;
+
+        let mut values = contents
+            .split_whitespace()
+            .map(|value| value.parse::<u64>());
+        match (values.next(), values.next(), values.next()) {
+            (Some(Ok(inbound)), Some(Ok(outbound)), None) if inbound > 0 && outbound > 0 => {
+                Ok(Some(MsgSeqNums::new(inbound, outbound)))
+            }
+            _ => Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("Malformed sequence number file for session {}", session_id),
+            )),
+        }
+    }
+
+    fn save(&mut self, session_id: &SessionID, msg_seq_nums: MsgSeqNums) -> io::Result<()> {
+        let temp_path = self.path(session_id, "seqnums.tmp");
+        {
+            let mut file = File::create(&temp_path)?;
+            writeln!(file, "{} {}", msg_seq_nums.inbound, msg_seq_nums.outbound)?;
+            file.sync_all()?;
+        }
+
+        fs::rename(temp_path, self.path(session_id, "seqnums"))
+    }
+}
@@ -0,0 +1,161 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use crate::dictionary::messages::Logon;
+use crate::fix_version::FIXVersion;
+use crate::fixt::engine::MsgSeqNums;
+use crate::fixt::reconnect_policy::ReconnectPolicy;
+use crate::fixt::session_options::SessionOptions;
+use crate::fixt::session_schedule::SessionSchedule;
+use crate::fixt::stream::StreamConfig;
+#[cfg(feature = "tls")]
+use crate::fixt::tls::{TlsAcceptor, TlsConnector};
+use crate::fixt::transport::TransportAddr;
+use crate::message_version::MessageVersion;
+
+//Everything about a connection started using Engine::add_connection_with_config(). Only the
+//CompIDs and address are required. Everything else can be combined however is needed.
+pub struct ConnectionConfig {
+    pub(crate) fix_version: FIXVersion,
+    pub(crate) default_message_version: MessageVersion,
+    pub(crate) sender_comp_id: Vec<u8>,
+    pub(crate) target_comp_id: Vec<u8>,
+    pub(crate) address: TransportAddr,
+    pub(crate) msg_seq_nums: Option<MsgSeqNums>,
+    pub(crate) session_options: SessionOptions,
+    pub(crate) stream_config: StreamConfig,
+    pub(crate) schedule: Option<SessionSchedule>,
+    pub(crate) reconnect_policy: Option<ReconnectPolicy>,
+    pub(crate) logon: Option<Box<Logon>>,
+}
+
+impl ConnectionConfig {
+    pub fn new<A: Into<TransportAddr>>(
+        fix_version: FIXVersion,
+        default_message_version: MessageVersion,
+        sender_comp_id: &[u8],
+        target_comp_id: &[u8],
+        address: A,
+    ) -> ConnectionConfig {
+        ConnectionConfig {
+            fix_version,
+            default_message_version,
+            sender_comp_id: sender_comp_id.to_vec(),
+            target_comp_id: target_comp_id.to_vec(),
+            address: address.into(),
+            msg_seq_nums: None,
+            session_options: SessionOptions::default(),
+            stream_config: StreamConfig::Plain,
+            schedule: None,
+            reconnect_policy: None,
+            logon: None,
+        }
+    }
+
+    //Resume the session at msg_seq_nums instead of the values in the SeqNumStore (or starting
+    //over at 1 when there is no SeqNumStore).
+    pub fn with_msg_seq_nums(mut self, msg_seq_nums: MsgSeqNums) -> ConnectionConfig {
+        self.msg_seq_nums = Some(msg_seq_nums);
+        self
+    }
+
+    //Replace the engine's default timeouts and limits for this connection.
+    pub fn with_session_options(mut self, session_options: SessionOptions) -> ConnectionConfig {
+        self.session_options = session_options;
+        self
+    }
+
+    //Secure the connection using TLS. The TLS handshake is performed before Logon is sent.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls_connector: TlsConnector) -> ConnectionConfig {
+        self.stream_config = StreamConfig::TlsClient(tls_connector);
+        self
+    }
+
+    #[cfg(feature = "config")]
+    pub(crate) fn with_stream_config(mut self, stream_config: StreamConfig) -> ConnectionConfig {
+        self.stream_config = stream_config;
+        self
+    }
+
+    //Send logon automatically as soon as the connection connects instead of waiting on the
+    //application to send one after EngineEvent::ConnectionSucceeded. Required by with_schedule()
+    //and with_reconnect_policy() because they connect again on their own.
+    pub fn with_logon(mut self, logon: Box<Logon>) -> ConnectionConfig {
+        self.logon = Some(logon);
+        self
+    }
+
+    //Only connect while schedule says the session is active and logout when it's not. The
+    //Connection remains valid between sessions (see EngineEvent::SessionEnded) until logout() is
+    //called.
+    pub fn with_schedule(mut self, schedule: SessionSchedule) -> ConnectionConfig {
+        self.schedule = Some(schedule);
+        self
+    }
+
+    //Reconnect according to reconnect_policy whenever the connection fails or is disconnected for
+    //any reason other than logout() being called. The Connection remains valid between sessions
+    //(see EngineEvent::SessionEnded and EngineEvent::Reconnecting) until logout() is called or
+    //reconnect_policy gives up.
+    pub fn with_reconnect_policy(mut self, reconnect_policy: ReconnectPolicy) -> ConnectionConfig {
+        self.reconnect_policy = Some(reconnect_policy);
+        self
+    }
+}
+
+//Everything about a listener started using Engine::add_listener_with_config().
+pub struct ListenerConfig {
+    pub(crate) sender_comp_id: Vec<u8>,
+    pub(crate) address: TransportAddr,
+    pub(crate) session_options: SessionOptions,
+    pub(crate) stream_config: StreamConfig,
+    pub(crate) schedule: Option<SessionSchedule>,
+}
+
+impl ListenerConfig {
+    pub fn new<A: Into<TransportAddr>>(sender_comp_id: &[u8], address: A) -> ListenerConfig {
+        ListenerConfig {
+            sender_comp_id: sender_comp_id.to_vec(),
+            address: address.into(),
+            session_options: SessionOptions::default(),
+            stream_config: StreamConfig::Plain,
+            schedule: None,
+        }
+    }
+
+    //Accepted connections use session_options instead of the engine's default timeouts and
+    //limits. Acceptor sessions can replace these once their Logon is received using
+    //AcceptorSession::with_session_options().
+    pub fn with_session_options(mut self, session_options: SessionOptions) -> ListenerConfig {
+        self.session_options = session_options;
+        self
+    }
+
+    //Every accepted connection must complete a TLS handshake before its Logon is read.
+    #[cfg(feature = "tls")]
+    pub fn with_tls(mut self, tls_acceptor: TlsAcceptor) -> ListenerConfig {
+        self.stream_config = StreamConfig::TlsServer(tls_acceptor);
+        self
+    }
+
+    #[cfg(feature = "config")]
+    pub(crate) fn with_stream_config(mut self, stream_config: StreamConfig) -> ListenerConfig {
+        self.stream_config = stream_config;
+        self
+    }
+
+    //Same as calling Engine::set_listener_session_schedule() once the listener is added.
+    pub fn with_schedule(mut self, schedule: SessionSchedule) -> ListenerConfig {
+        self.schedule = Some(schedule);
+        self
+    }
+}
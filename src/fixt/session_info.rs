@@ -0,0 +1,78 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::fixt::engine::{Connection, MsgSeqNums, SessionID};
+use crate::message_version::MessageVersion;
+
+//Where a connection is in the lifetime of a FIX session.
+#[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
+pub enum SessionState {
+    Connecting,     //Initiated connection's socket is still connecting.
+    AwaitingLogon,  //Accepted connection is waiting on the remote to send a Logon.
+    ApprovingLogon, //Accepted connection is waiting on Engine::approve_new_connection() or Engine::reject_new_connection().
+    LoggingOn,      //Logon was sent and the remote's response hasn't been received yet.
+    Established,    //Logon completed successfully and messages can flow.
+    Resending,      //Established but waiting on messages requested using a ResendRequest.
+    LoggingOut,     //Logout was sent or received and the connection is about to close.
+    Disconnected,   //No socket. Initiated sessions wait here to reconnect or for their schedule.
+}
+
+impl fmt::Display for SessionState {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Debug::fmt(self, f)
+    }
+}
+
+//Snapshot of a connection returned by Engine::session_info().
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionInfo {
+    pub state: SessionState,
+    pub session_id: SessionID, //FIX version and CompIDs.
+    pub default_appl_ver_id: MessageVersion,
+    pub next_msg_seq_nums: MsgSeqNums, //Next MsgSeqNum expected to be received and to be sent.
+    pub heart_bt_int: Option<Duration>, //None until negotiated by Logon.
+    pub peer_addr: Option<SocketAddr>, //None while Disconnected or when not connected over TCP.
+}
+
+//SessionInfo of every Connection. Shared between the Engine and its internal threads. Each
+//Connection's SessionInfo is updated in place by its internal thread as soon as it changes so the
+//Engine can read it without waiting on the internal thread.
+#[derive(Clone, Default)]
+pub(crate) struct SessionInfoRegistry {
+    sessions: Arc<Mutex<HashMap<Connection, Arc<Mutex<SessionInfo>>>>>,
+}
+
+impl SessionInfoRegistry {
+    pub fn insert(&self, connection: Connection, session_info: Arc<Mutex<SessionInfo>>) {
+        self.sessions
+            .lock()
+            .unwrap()
+            .insert(connection, session_info);
+    }
+
+    pub fn remove(&self, connection: Connection) {
+        self.sessions.lock().unwrap().remove(&connection);
+    }
+
+    pub fn get(&self, connection: Connection) -> Option<SessionInfo> {
+        self.sessions
+            .lock()
+            .unwrap()
+            .get(&connection)
+            .map(|session_info| session_info.lock().unwrap().clone())
+    }
+}
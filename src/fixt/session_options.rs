@@ -0,0 +1,113 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::cmp;
+use std::ops::RangeInclusive;
+use std::time::Duration;
+
+use crate::fixt::engine_thread::{
+    AUTO_DISCONNECT_AFTER_GARBLED_RESEND_REQUEST_RETRY_COUNT,
+    AUTO_DISCONNECT_AFTER_INBOUND_RESEND_REQUEST_LOOP_COUNT,
+    AUTO_DISCONNECT_AFTER_LOGOUT_RESPONSE_SECS, AUTO_DISCONNECT_AFTER_NO_LOGON_RECEIVED_SECONDS,
+    INBOUND_MESSAGES_BUFFER_LEN_MAX, NO_INBOUND_TIMEOUT_PADDING_MS,
+};
+
+//Session level timeouts and limits that otherwise use the engine's defaults. Useful when a
+//counterparty (ie. an exchange's certification environment) expects different values than
+//production.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionOptions {
+    pub(crate) no_logon_timeout: Duration,
+    pub(crate) logout_response_timeout: Duration,
+    pub(crate) inbound_resend_request_loop_count: u64,
+    pub(crate) garbled_resend_request_retry_count: u64,
+    pub(crate) inbound_timeout_padding: Duration,
+    pub(crate) inbound_messages_buffer_len_max: usize,
+    pub(crate) heart_bt_int_range: Option<RangeInclusive<i64>>,
+    pub(crate) sending_time_skew: Option<Duration>,
+}
+
+impl Default for SessionOptions {
+    fn default() -> Self {
+        SessionOptions {
+            no_logon_timeout: Duration::from_secs(AUTO_DISCONNECT_AFTER_NO_LOGON_RECEIVED_SECONDS),
+            logout_response_timeout: Duration::from_secs(
+                AUTO_DISCONNECT_AFTER_LOGOUT_RESPONSE_SECS,
+            ),
+            inbound_resend_request_loop_count:
+                AUTO_DISCONNECT_AFTER_INBOUND_RESEND_REQUEST_LOOP_COUNT,
+            garbled_resend_request_retry_count:
+                AUTO_DISCONNECT_AFTER_GARBLED_RESEND_REQUEST_RETRY_COUNT,
+            inbound_timeout_padding: Duration::from_millis(NO_INBOUND_TIMEOUT_PADDING_MS),
+            inbound_messages_buffer_len_max: INBOUND_MESSAGES_BUFFER_LEN_MAX,
+            heart_bt_int_range: None,
+            sending_time_skew: None,
+        }
+    }
+}
+
+impl SessionOptions {
+    pub fn new() -> SessionOptions {
+        SessionOptions::default()
+    }
+
+    //How long an accepted connection has to send a Logon before it's disconnected. Only applies
+    //to listeners because the remote's session isn't known until its Logon arrives.
+    pub fn with_no_logon_timeout(mut self, timeout: Duration) -> SessionOptions {
+        self.no_logon_timeout = timeout;
+        self
+    }
+
+    //How long to wait for the remote to disconnect after responding to its Logout.
+    pub fn with_logout_response_timeout(mut self, timeout: Duration) -> SessionOptions {
+        self.logout_response_timeout = timeout;
+        self
+    }
+
+    //Logout after receiving more than this many ResendRequests in a row with the same BeginSeqNo.
+    pub fn with_inbound_resend_request_loop_count(mut self, count: u64) -> SessionOptions {
+        self.inbound_resend_request_loop_count = count;
+        self
+    }
+
+    //How many times to ask for the same missing messages again after the remote answers a
+    //ResendRequest with garbled messages. Once these run out, logout instead.
+    pub fn with_garbled_resend_request_retry_count(mut self, count: u64) -> SessionOptions {
+        self.garbled_resend_request_retry_count = count;
+        self
+    }
+
+    //Extra time on top of HeartBtInt to wait for any message before sending a TestRequest.
+    pub fn with_inbound_timeout_padding(mut self, padding: Duration) -> SessionOptions {
+        self.inbound_timeout_padding = padding;
+        self
+    }
+
+    //Most messages parsed from a single read before they're handed off and reading is resumed
+    //later. At least 1.
+    pub fn with_inbound_messages_buffer_len_max(mut self, len: usize) -> SessionOptions {
+        self.inbound_messages_buffer_len_max = cmp::max(len, 1);
+        self
+    }
+
+    //Logout when the remote's Logon asks for a HeartBtInt outside of [min,max].
+    pub fn with_heart_bt_int_range(mut self, min: i64, max: i64) -> SessionOptions {
+        self.heart_bt_int_range = Some(min..=max);
+        self
+    }
+
+    //Reject messages with a SendingTime more than skew away from the local clock. SendingTime
+    //isn't checked by default.
+    pub fn with_sending_time_skew(mut self, skew: Duration) -> SessionOptions {
+        self.sending_time_skew = Some(skew);
+        self
+    }
+}
@@ -0,0 +1,132 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::Tz;
+use std::cmp;
+
+//A point in time that repeats every day or every week in a SessionSchedule's time zone.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ScheduleTime {
+    Daily(NaiveTime),
+    Weekly(Weekday, NaiveTime),
+}
+
+impl ScheduleTime {
+    fn occurrence_on(&self, time_zone: &Tz, date: NaiveDate) -> Option<DateTime<Utc>> {
+        let time = match *self {
+            ScheduleTime::Daily(time) => time,
+            ScheduleTime::Weekly(weekday, time) => {
+                if date.weekday() != weekday {
+                    return None;
+                }
+                time
+            }
+        };
+
+        //Times that are skipped by a daylight saving time change happen as soon as the clock
+        //jumps forward. Times that happen twice use the first one.
+        let local = date.and_time(time);
+        time_zone
+            .from_local_datetime(&local)
+            .earliest()
+            .or_else(|| {
+                time_zone
+                    .from_local_datetime(&(local + Duration::hours(1)))
+                    .earliest()
+            })
+            .map(|date_time| date_time.with_timezone(&Utc))
+    }
+
+    //Most recent occurrence at or before now.
+    fn previous(&self, time_zone: &Tz, now: &DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.with_timezone(time_zone).date_naive();
+        (-1..9)
+            .filter_map(|days| self.occurrence_on(time_zone, today - Duration::days(days)))
+            .find(|occurrence| occurrence <= now)
+            .expect("ScheduleTime occurs at least once a week")
+    }
+
+    //Next occurrence strictly after now.
+    fn next(&self, time_zone: &Tz, now: &DateTime<Utc>) -> DateTime<Utc> {
+        let today = now.with_timezone(time_zone).date_naive();
+        (-1..9)
+            .filter_map(|days| self.occurrence_on(time_zone, today + Duration::days(days)))
+            .find(|occurrence| occurrence > now)
+            .expect("ScheduleTime occurs at least once a week")
+    }
+}
+
+//When a session is allowed to be logged on and when its MsgSeqNums start over. A session is
+//active from start until end. When start and end are the same, the session never closes but it
+//can still be reset.
+#[derive(Clone, Debug, PartialEq)]
+pub struct SessionSchedule {
+    start: ScheduleTime,
+    end: ScheduleTime,
+    reset: Option<ScheduleTime>,
+    time_zone: Tz,
+}
+
+impl SessionSchedule {
+    pub fn daily(start_time: NaiveTime, end_time: NaiveTime, time_zone: Tz) -> SessionSchedule {
+        SessionSchedule {
+            start: ScheduleTime::Daily(start_time),
+            end: ScheduleTime::Daily(end_time),
+            reset: None,
+            time_zone,
+        }
+    }
+
+    pub fn weekly(
+        start_day: Weekday,
+        start_time: NaiveTime,
+        end_day: Weekday,
+        end_time: NaiveTime,
+        time_zone: Tz,
+    ) -> SessionSchedule {
+        SessionSchedule {
+            start: ScheduleTime::Weekly(start_day, start_time),
+            end: ScheduleTime::Weekly(end_day, end_time),
+            reset: None,
+            time_zone,
+        }
+    }
+
+    //Reset the session's stored MsgSeqNums back to 1 every time reset occurs. A session that is
+    //logged on at that moment is reset as soon as it ends.
+    pub fn with_reset_time(mut self, reset: ScheduleTime) -> SessionSchedule {
+        self.reset = Some(reset);
+        self
+    }
+
+    pub fn time_zone(&self) -> Tz {
+        self.time_zone
+    }
+
+    pub fn is_active(&self, now: &DateTime<Utc>) -> bool {
+        self.start.previous(&self.time_zone, now) >= self.end.previous(&self.time_zone, now)
+    }
+
+    //Next time the session either becomes active or inactive.
+    pub fn next_transition(&self, now: &DateTime<Utc>) -> DateTime<Utc> {
+        cmp::min(
+            self.start.next(&self.time_zone, now),
+            self.end.next(&self.time_zone, now),
+        )
+    }
+
+    pub fn next_reset(&self, now: &DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.reset
+            .as_ref()
+            .map(|reset| reset.next(&self.time_zone, now))
+    }
+}
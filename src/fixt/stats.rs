@@ -0,0 +1,412 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use crate::dictionary::messages::{Reject, ResendRequest};
+use crate::fixt::engine::{Connection, Engine, SessionID};
+
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct MsgTypeStats {
+    pub messages: u64,
+    pub bytes: u64,
+}
+
+//Counters and gauges for a single Connection. Counters only ever go up over the life of the
+//Connection.
+#[derive(Clone, Debug)]
+pub struct SessionStats {
+    pub session_id: SessionID, //TargetCompID is empty until an acceptor receives a Logon.
+    pub inbound: HashMap<Vec<u8>, MsgTypeStats>, //Keyed by MsgType.
+    pub outbound: HashMap<Vec<u8>, MsgTypeStats>, //Keyed by MsgType.
+    pub rejects_received: u64,
+    pub rejects_sent: u64,
+    pub resend_requests_received: u64,
+    pub resend_requests_sent: u64,
+    pub garbled_messages: u64,
+    pub heartbeat_misses: u64, //Times the remote went quiet long enough to be sent a TestRequest.
+    pub outbound_queue_depth: u64, //Messages waiting to be serialized and sent.
+    pub parse_time: Duration,  //Total time spent parsing inbound bytes.
+    pub parse_time_max: Duration, //Longest time spent parsing a single read.
+}
+
+impl SessionStats {
+    pub(crate) fn new(session_id: SessionID) -> SessionStats {
+        SessionStats {
+            session_id,
+            inbound: HashMap::new(),
+            outbound: HashMap::new(),
+            rejects_received: 0,
+            rejects_sent: 0,
+            resend_requests_received: 0,
+            resend_requests_sent: 0,
+            garbled_messages: 0,
+            heartbeat_misses: 0,
+            outbound_queue_depth: 0,
+            parse_time: Duration::from_secs(0),
+            parse_time_max: Duration::from_secs(0),
+        }
+    }
+
+    pub fn messages_received(&self) -> u64 {
+        self.inbound.values().map(|stats| stats.messages).sum()
+    }
+
+    pub fn messages_sent(&self) -> u64 {
+        self.outbound.values().map(|stats| stats.messages).sum()
+    }
+
+    pub fn bytes_received(&self) -> u64 {
+        self.inbound.values().map(|stats| stats.bytes).sum()
+    }
+
+    pub fn bytes_sent(&self) -> u64 {
+        self.outbound.values().map(|stats| stats.bytes).sum()
+    }
+
+    pub(crate) fn record_inbound(&mut self, msg_type: &[u8], bytes: u64) {
+        if msg_type == Reject::msg_type() {
+            self.rejects_received += 1;
+        } else if msg_type == ResendRequest::msg_type() {
+            self.resend_requests_received += 1;
+        }
+        record_msg_type(&mut self.inbound, msg_type, bytes);
+    }
+
+    pub(crate) fn record_outbound(&mut self, msg_type: &[u8], bytes: u64) {
+        if msg_type == Reject::msg_type() {
+            self.rejects_sent += 1;
+        } else if msg_type == ResendRequest::msg_type() {
+            self.resend_requests_sent += 1;
+        }
+        record_msg_type(&mut self.outbound, msg_type, bytes);
+    }
+
+    pub(crate) fn record_parse_time(&mut self, parse_time: Duration) {
+        self.parse_time += parse_time;
+        if parse_time > self.parse_time_max {
+            self.parse_time_max = parse_time;
+        }
+    }
+}
+
+fn record_msg_type(msg_types: &mut HashMap<Vec<u8>, MsgTypeStats>, msg_type: &[u8], bytes: u64) {
+    if !msg_types.contains_key(msg_type) {
+        msg_types.insert(msg_type.to_vec(), MsgTypeStats::default());
+    }
+    let stats = msg_types.get_mut(msg_type).unwrap();
+    stats.messages += 1;
+    stats.bytes += bytes;
+}
+
+//Snapshot of every open Connection's stats. Connections are dropped from the snapshot once
+//they are terminated.
+#[derive(Clone, Debug, Default)]
+pub struct EngineStats {
+    pub sessions: HashMap<Connection, SessionStats>,
+}
+
+impl EngineStats {
+    //Write all stats using the Prometheus text exposition format.
+    pub fn write_prometheus<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        //Sort so the output is stable between snapshots.
+        let mut sessions = self.sessions.iter().collect::<Vec<_>>();
+        sessions.sort_by_key(|&(connection, _)| connection.0);
+
+        fn header<W: Write>(
+            writer: &mut W,
+            name: &str,
+            metric_type: &str,
+            help: &str,
+        ) -> io::Result<()> {
+            writeln!(writer, "# HELP {} {}", name, help)?;
+            writeln!(writer, "# TYPE {} {}", name, metric_type)
+        }
+
+        fn labels(connection: &Connection, stats: &SessionStats) -> String {
+            format!(
+                "connection=\"{}\",session=\"{}\"",
+                connection,
+                escape_label_value(&stats.session_id.to_string())
+            )
+        }
+
+        fn write_msg_types<W: Write, F: Fn(&MsgTypeStats) -> u64>(
+            writer: &mut W,
+            name: &str,
+            sessions: &[(&Connection, &SessionStats)],
+            value: F,
+        ) -> io::Result<()> {
+            for &(connection, stats) in sessions {
+                for (direction, msg_types) in &[("in", &stats.inbound), ("out", &stats.outbound)] {
+                    let mut msg_types = msg_types.iter().collect::<Vec<_>>();
+                    msg_types.sort_by(|a, b| a.0.cmp(b.0));
+                    for (msg_type, msg_type_stats) in msg_types {
+                        writeln!(
+                            writer,
+                            "{}{{{},direction=\"{}\",msg_type=\"{}\"}} {}",
+                            name,
+                            labels(connection, stats),
+                            direction,
+                            escape_label_value(&String::from_utf8_lossy(msg_type)),
+                            value(msg_type_stats)
+                        )?;
+                    }
+                }
+            }
+
+            Ok(())
+        }
+
+        fn write_directions<W: Write, F: Fn(&SessionStats) -> (u64, u64)>(
+            writer: &mut W,
+            name: &str,
+            sessions: &[(&Connection, &SessionStats)],
+            value: F,
+        ) -> io::Result<()> {
+            for &(connection, stats) in sessions {
+                let (inbound, outbound) = value(stats);
+                writeln!(
+                    writer,
+                    "{}{{{},direction=\"in\"}} {}",
+                    name,
+                    labels(connection, stats),
+                    inbound
+                )?;
+                writeln!(
+                    writer,
+                    "{}{{{},direction=\"out\"}} {}",
+                    name,
+                    labels(connection, stats),
+                    outbound
+                )?;
+            }
+
+            Ok(())
+        }
+
+        fn write_values<W: Write, F: Fn(&SessionStats) -> String>(
+            writer: &mut W,
+            name: &str,
+            sessions: &[(&Connection, &SessionStats)],
+            value: F,
+        ) -> io::Result<()> {
+            for &(connection, stats) in sessions {
+                writeln!(
+                    writer,
+                    "{}{{{}}} {}",
+                    name,
+                    labels(connection, stats),
+                    value(stats)
+                )?;
+            }
+
+            Ok(())
+        }
+
+        header(
+            writer,
+            "fix_messages_total",
+            "counter",
+            "Messages received (in) and sent (out) by MsgType.",
+        )?;
+        write_msg_types(writer, "fix_messages_total", &sessions, |stats| {
+            stats.messages
+        })?;
+        header(
+            writer,
+            "fix_message_bytes_total",
+            "counter",
+            "Bytes received (in) and sent (out) by MsgType.",
+        )?;
+        write_msg_types(writer, "fix_message_bytes_total", &sessions, |stats| {
+            stats.bytes
+        })?;
+        header(
+            writer,
+            "fix_rejects_total",
+            "counter",
+            "Reject messages received (in) and sent (out).",
+        )?;
+        write_directions(writer, "fix_rejects_total", &sessions, |stats| {
+            (stats.rejects_received, stats.rejects_sent)
+        })?;
+        header(
+            writer,
+            "fix_resend_requests_total",
+            "counter",
+            "ResendRequest messages received (in) and sent (out).",
+        )?;
+        write_directions(writer, "fix_resend_requests_total", &sessions, |stats| {
+            (stats.resend_requests_received, stats.resend_requests_sent)
+        })?;
+        header(
+            writer,
+            "fix_garbled_messages_total",
+            "counter",
+            "Inbound messages that could not be parsed.",
+        )?;
+        write_values(writer, "fix_garbled_messages_total", &sessions, |stats| {
+            stats.garbled_messages.to_string()
+        })?;
+        header(
+            writer,
+            "fix_heartbeat_misses_total",
+            "counter",
+            "Times the remote went quiet long enough to be sent a TestRequest.",
+        )?;
+        write_values(writer, "fix_heartbeat_misses_total", &sessions, |stats| {
+            stats.heartbeat_misses.to_string()
+        })?;
+        header(
+            writer,
+            "fix_outbound_queue_depth",
+            "gauge",
+            "Messages waiting to be serialized and sent.",
+        )?;
+        write_values(writer, "fix_outbound_queue_depth", &sessions, |stats| {
+            stats.outbound_queue_depth.to_string()
+        })?;
+        header(
+            writer,
+            "fix_parse_seconds_total",
+            "counter",
+            "Total time spent parsing inbound bytes.",
+        )?;
+        write_values(writer, "fix_parse_seconds_total", &sessions, |stats| {
+            stats.parse_time.as_secs_f64().to_string()
+        })?;
+        header(
+            writer,
+            "fix_parse_seconds_max",
+            "gauge",
+            "Longest time spent parsing a single read.",
+        )?;
+        write_values(writer, "fix_parse_seconds_max", &sessions, |stats| {
+            stats.parse_time_max.as_secs_f64().to_string()
+        })?;
+
+        Ok(())
+    }
+}
+
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+//Stats of every open Connection. Shared between the Engine and its internal thread. Each
+//Connection's stats are updated in place by the internal thread and only copied when a snapshot
+//is taken.
+#[derive(Clone, Default)]
+pub(crate) struct StatsRegistry {
+    sessions: Arc<Mutex<HashMap<Connection, Arc<Mutex<SessionStats>>>>>,
+}
+
+impl StatsRegistry {
+    pub fn insert(&self, connection: Connection, stats: Arc<Mutex<SessionStats>>) {
+        self.sessions.lock().unwrap().insert(connection, stats);
+    }
+
+    pub fn remove(&self, connection: Connection) {
+        self.sessions.lock().unwrap().remove(&connection);
+    }
+
+    pub fn snapshot(&self) -> EngineStats {
+        EngineStats {
+            sessions: self
+                .sessions
+                .lock()
+                .unwrap()
+                .iter()
+                .map(|(connection, stats)| (*connection, stats.lock().unwrap().clone()))
+                .collect(),
+        }
+    }
+}
+
+//Exports an Engine's stats in the Prometheus text exposition format, either by writing them to a
+//file (ie. for node_exporter's textfile collector) or by serving them over HTTP.
+pub struct PrometheusExporter {
+    registry: StatsRegistry,
+}
+
+impl PrometheusExporter {
+    pub fn new(engine: &Engine) -> PrometheusExporter {
+        PrometheusExporter {
+            registry: engine.stats_registry(),
+        }
+    }
+
+    //Replace the file at path with a fresh snapshot. The snapshot is written to a temporary file
+    //first and then renamed so readers never see a partially written file.
+    pub fn write_file<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let mut temp_path = path.as_os_str().to_owned();
+        temp_path.push(".tmp");
+
+        let mut file = File::create(&temp_path)?;
+        self.registry.snapshot().write_prometheus(&mut file)?;
+        file.sync_all()?;
+        fs::rename(&temp_path, path)
+    }
+
+    //Serve a fresh snapshot to every HTTP request received on address. Serving happens on a
+    //background thread that runs for the rest of the process. Returns the address actually bound
+    //so port 0 can be used to pick any available port.
+    pub fn serve<A: ToSocketAddrs>(self, address: A) -> io::Result<SocketAddr> {
+        let listener = TcpListener::bind(address)?;
+        let local_addr = listener.local_addr()?;
+
+        thread::spawn(move || {
+            //A misbehaving client shouldn't stop everyone else from being served.
+            for stream in listener.incoming().flatten() {
+                let _ = self.respond(stream);
+            }
+        });
+
+        Ok(local_addr)
+    }
+
+    fn respond(&self, mut stream: TcpStream) -> io::Result<()> {
+        stream.set_read_timeout(Some(Duration::from_secs(5)))?;
+        stream.set_write_timeout(Some(Duration::from_secs(5)))?;
+
+        //Every path gets the same response so only the end of the request headers matters.
+        let mut reader = BufReader::new(stream.try_clone()?);
+        let mut line = String::new();
+        loop {
+            line.clear();
+            if reader.read_line(&mut line)? == 0 || line == "\r\n" || line == "\n" {
+                break;
+            }
+        }
+
+        let mut body = Vec::new();
+        self.registry.snapshot().write_prometheus(&mut body)?;
+        write!(
+            stream,
+            "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+            body.len()
+        )?;
+        stream.write_all(&body[..])?;
+        stream.flush()
+    }
+}
@@ -0,0 +1,141 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use mio::tcp::Shutdown;
+use mio::{Evented, Poll, PollOpt, Ready, Token};
+use std::io::{self, Read, Write};
+
+use crate::fixt::engine::ConnectionTerminatedReason;
+#[cfg(feature = "tls")]
+use crate::fixt::tls::{self, TlsAcceptor, TlsConnector, TlsStream};
+use crate::fixt::transport::TransportStream;
+
+//How a connection's socket should be wrapped once it's connected or accepted.
+#[derive(Clone)]
+pub enum StreamConfig {
+    Plain,
+    #[cfg(feature = "tls")]
+    TlsClient(TlsConnector),
+    #[cfg(feature = "tls")]
+    TlsServer(TlsAcceptor),
+}
+
+impl StreamConfig {
+    //Socket is shut down if it can't be wrapped.
+    pub fn wrap(&self, socket: TransportStream) -> io::Result<Stream> {
+        match *self {
+            StreamConfig::Plain => Ok(Stream::Plain(socket)),
+            #[cfg(feature = "tls")]
+            StreamConfig::TlsClient(ref connector) => {
+                Ok(Stream::Tls(Box::new(connector.connect(socket)?)))
+            }
+            #[cfg(feature = "tls")]
+            StreamConfig::TlsServer(ref acceptor) => {
+                Ok(Stream::Tls(Box::new(acceptor.accept(socket)?)))
+            }
+        }
+    }
+}
+
+pub enum Stream {
+    Plain(TransportStream),
+    #[cfg(feature = "tls")]
+    Tls(Box<TlsStream>),
+}
+
+impl Stream {
+    pub fn socket(&self) -> &TransportStream {
+        match *self {
+            Stream::Plain(ref socket) => socket,
+            #[cfg(feature = "tls")]
+            Stream::Tls(ref stream) => stream.socket(),
+        }
+    }
+
+    pub fn shutdown(&mut self, how: Shutdown) -> io::Result<()> {
+        match *self {
+            Stream::Plain(ref socket) => socket.shutdown(how),
+            #[cfg(feature = "tls")]
+            Stream::Tls(ref mut stream) => stream.shutdown(how),
+        }
+    }
+
+    //Pick the reason a connection is terminated after read() returns an error.
+    pub fn read_error_reason(&self, e: io::Error) -> ConnectionTerminatedReason {
+        match *self {
+            Stream::Plain(_) => ConnectionTerminatedReason::SocketReadError(e),
+            #[cfg(feature = "tls")]
+            Stream::Tls(ref stream) => {
+                if tls::is_certificate_error(&e) {
+                    ConnectionTerminatedReason::TlsCertificateError(e)
+                } else if stream.is_handshaking() && tls::is_tls_error(&e) {
+                    ConnectionTerminatedReason::TlsHandshakeError(e)
+                } else {
+                    ConnectionTerminatedReason::SocketReadError(e)
+                }
+            }
+        }
+    }
+}
+
+impl Read for Stream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match *self {
+            Stream::Plain(ref mut socket) => socket.read(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(ref mut stream) => stream.read(buf),
+        }
+    }
+}
+
+impl Write for Stream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match *self {
+            Stream::Plain(ref mut socket) => socket.write(buf),
+            #[cfg(feature = "tls")]
+            Stream::Tls(ref mut stream) => stream.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match *self {
+            Stream::Plain(ref mut socket) => socket.flush(),
+            #[cfg(feature = "tls")]
+            Stream::Tls(ref mut stream) => stream.flush(),
+        }
+    }
+}
+
+impl Evented for Stream {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.socket().register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.socket().reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        self.socket().deregister(poll)
+    }
+}
@@ -0,0 +1,163 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use std::collections::HashMap;
+use std::time::{Duration, Instant};
+
+//What happens to an application message that would go over a Throttle's limit.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ThrottleAction {
+    Delay,  //Hold message, and every application message queued after it, until it's allowed out.
+    Reject, //Return message using EngineEvent::MessageRefused with MessageRefusedReason::Throttled.
+}
+
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+struct RateLimit {
+    max_messages: u32,
+    per: Duration,
+}
+
+//Token bucket limits on how fast application messages are sent. Up to max_messages can be sent
+//at once and then more are allowed as the bucket refills over per. Administrative messages are
+//never throttled and don't count against any limit.
+#[derive(Clone, Debug)]
+pub struct Throttle {
+    limit: Option<RateLimit>,
+    msg_type_limits: HashMap<Vec<u8>, RateLimit>,
+    action: ThrottleAction,
+}
+
+impl Throttle {
+    //Limit all application messages to max_messages every per.
+    pub fn new(max_messages: u32, per: Duration) -> Throttle {
+        Throttle {
+            limit: Some(RateLimit { max_messages, per }),
+            msg_type_limits: HashMap::new(),
+            action: ThrottleAction::Delay,
+        }
+    }
+
+    //No limit on application messages as a whole. Useful when only specific MsgTypes should be
+    //limited using with_msg_type_limit().
+    pub fn unlimited() -> Throttle {
+        Throttle {
+            limit: None,
+            msg_type_limits: HashMap::new(),
+            action: ThrottleAction::Delay,
+        }
+    }
+
+    //Additionally limit messages with msg_type to max_messages every per. Messages must be within
+    //both this and the overall limit to be sent.
+    pub fn with_msg_type_limit(
+        mut self,
+        msg_type: &[u8],
+        max_messages: u32,
+        per: Duration,
+    ) -> Throttle {
+        self.msg_type_limits
+            .insert(msg_type.to_vec(), RateLimit { max_messages, per });
+        self
+    }
+
+    pub fn with_action(mut self, action: ThrottleAction) -> Throttle {
+        self.action = action;
+        self
+    }
+}
+
+struct TokenBucket {
+    capacity: f64,
+    tokens: f64,
+    tokens_per_sec: f64,
+    last_refill: Instant,
+}
+
+impl TokenBucket {
+    fn new(limit: &RateLimit, now: Instant) -> TokenBucket {
+        let capacity = f64::from(limit.max_messages);
+        TokenBucket {
+            capacity,
+            tokens: capacity,
+            tokens_per_sec: capacity / limit.per.as_secs_f64(),
+            last_refill: now,
+        }
+    }
+
+    //How long until a token is available. Zero when one is available right now.
+    fn wait(&mut self, now: Instant) -> Duration {
+        let elapsed = now.saturating_duration_since(self.last_refill);
+        self.tokens =
+            (self.tokens + elapsed.as_secs_f64() * self.tokens_per_sec).min(self.capacity);
+        self.last_refill = now;
+
+        if self.tokens >= 1.0 {
+            Duration::from_secs(0)
+        } else if self.tokens_per_sec > 0.0 {
+            Duration::from_secs_f64((1.0 - self.tokens) / self.tokens_per_sec)
+        } else {
+            Duration::from_secs(u64::from(u32::MAX))
+        }
+    }
+}
+
+pub(crate) struct ThrottleState {
+    action: ThrottleAction,
+    bucket: Option<TokenBucket>,
+    msg_type_buckets: HashMap<Vec<u8>, TokenBucket>,
+    pub is_holding: bool, //Application messages are currently being held back.
+}
+
+impl ThrottleState {
+    pub fn new(throttle: &Throttle, now: Instant) -> ThrottleState {
+        ThrottleState {
+            action: throttle.action,
+            bucket: throttle
+                .limit
+                .as_ref()
+                .map(|limit| TokenBucket::new(limit, now)),
+            msg_type_buckets: throttle
+                .msg_type_limits
+                .iter()
+                .map(|(msg_type, limit)| (msg_type.clone(), TokenBucket::new(limit, now)))
+                .collect(),
+            is_holding: false,
+        }
+    }
+
+    pub fn action(&self) -> ThrottleAction {
+        self.action
+    }
+
+    //Use up a token for a message with msg_type if it can be sent right now. Otherwise, returns
+    //how long until it can be sent without using up anything.
+    pub fn acquire(&mut self, msg_type: &[u8], now: Instant) -> Result<(), Duration> {
+        let mut wait = Duration::from_secs(0);
+        if let Some(ref mut bucket) = self.bucket {
+            wait = wait.max(bucket.wait(now));
+        }
+        if let Some(bucket) = self.msg_type_buckets.get_mut(msg_type) {
+            wait = wait.max(bucket.wait(now));
+        }
+        if wait > Duration::from_secs(0) {
+            return Err(wait);
+        }
+
+        if let Some(ref mut bucket) = self.bucket {
+            bucket.tokens -= 1.0;
+        }
+        if let Some(bucket) = self.msg_type_buckets.get_mut(msg_type) {
+            bucket.tokens -= 1.0;
+        }
+
+        Ok(())
+    }
+}
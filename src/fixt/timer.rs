@@ -0,0 +1,160 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use chrono::{DateTime, Utc};
+use mio::{Evented, Poll, PollOpt, Ready, Registration, SetReadiness, Token};
+use std::collections::BTreeMap;
+use std::io;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use crate::fixt::clock::{self, Clock, ClockWaker};
+
+//Handle used to cancel a timeout.
+#[derive(Debug)]
+pub struct Timeout {
+    deadline: Instant,
+    id: u64,
+}
+
+//Timeouts measured using a Clock. Readable whenever at least one timeout has expired. Poll should
+//wait no longer than poll_duration() so timeouts are noticed when the clock follows real time.
+pub struct Timer<T> {
+    clock: Arc<dyn Clock>,
+    timeouts: BTreeMap<(Instant, u64), T>,
+    next_id: u64,
+    registration: Registration,
+    set_readiness: Arc<SetReadiness>, //Clocks only hold weak references to this.
+}
+
+impl<T> Timer<T> {
+    pub fn new(clock: Arc<dyn Clock>) -> Timer<T> {
+        let (registration, set_readiness) = Registration::new2();
+        let set_readiness = Arc::new(set_readiness);
+        clock.add_waker(ClockWaker::new(&set_readiness));
+
+        Timer {
+            clock,
+            timeouts: BTreeMap::new(),
+            next_id: 0,
+            registration,
+            set_readiness,
+        }
+    }
+
+    pub fn now(&self) -> Instant {
+        self.clock.now()
+    }
+
+    pub fn now_utc(&self) -> DateTime<Utc> {
+        self.clock.now_utc()
+    }
+
+    pub fn now_utc_timestamp(&self) -> DateTime<Utc> {
+        clock::now_utc_timestamp(&*self.clock)
+    }
+
+    //Switch to measuring time using clock. Timeouts that haven't expired yet keep however much
+    //time they had left.
+    pub fn set_clock(&mut self, clock: Arc<dyn Clock>) {
+        let old_now = self.clock.now();
+        let new_now = clock.now();
+        clock.add_waker(ClockWaker::new(&self.set_readiness));
+        self.clock = clock;
+
+        let timeouts = std::mem::take(&mut self.timeouts);
+        for ((deadline, id), state) in timeouts {
+            let remaining = deadline.saturating_duration_since(old_now);
+            self.timeouts.insert((new_now + remaining, id), state);
+        }
+        self.update_readiness();
+    }
+
+    pub fn set_timeout(&mut self, delay: Duration, state: T) -> Timeout {
+        let timeout = Timeout {
+            deadline: self.clock.now() + delay,
+            id: self.next_id,
+        };
+        self.next_id += 1;
+        self.timeouts.insert((timeout.deadline, timeout.id), state);
+        self.update_readiness();
+
+        timeout
+    }
+
+    pub fn cancel_timeout(&mut self, timeout: &Timeout) -> Option<T> {
+        self.timeouts.remove(&(timeout.deadline, timeout.id))
+    }
+
+    //Remove the next expired timeout.
+    pub fn poll(&mut self) -> Option<T> {
+        let now = self.clock.now();
+        let key = match self.timeouts.keys().next() {
+            Some(&key) if key.0 <= now => key,
+            _ => {
+                let _ = self.set_readiness.set_readiness(Ready::empty());
+                return None;
+            }
+        };
+        let state = self.timeouts.remove(&key);
+        self.update_readiness();
+
+        state
+    }
+
+    //Longest poll can wait before the next timeout expires. None means wait until woken up.
+    pub fn poll_duration(&mut self) -> Option<Duration> {
+        self.update_readiness();
+        if !self.clock.is_real_time() {
+            return None;
+        }
+
+        self.timeouts
+            .keys()
+            .next()
+            .map(|&(deadline, _)| deadline.saturating_duration_since(self.clock.now()))
+    }
+
+    fn update_readiness(&self) {
+        let now = self.clock.now();
+        let ready = match self.timeouts.keys().next() {
+            Some(&(deadline, _)) if deadline <= now => Ready::readable(),
+            _ => Ready::empty(),
+        };
+        let _ = self.set_readiness.set_readiness(ready);
+    }
+}
+
+impl<T> Evented for Timer<T> {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.registration.register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.registration.reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        poll.deregister(&self.registration)
+    }
+}
@@ -0,0 +1,310 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use mio::tcp::Shutdown;
+use rustls::server::AllowAnyAuthenticatedClient;
+use rustls::{
+    AlertDescription, Certificate, ClientConfig, ClientConnection, Connection, PrivateKey,
+    RootCertStore, ServerConfig, ServerConnection, ServerName,
+};
+use std::convert::TryFrom;
+use std::fs::File;
+use std::io::{self, BufReader, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::fixt::transport::TransportStream;
+
+//Settings used to secure connections created with ConnectionConfig::with_tls(). The
+//server's certificate must be valid for server_name, which is also sent as SNI.
+#[derive(Clone)]
+pub struct TlsConnector {
+    config: Arc<ClientConfig>,
+    server_name: ServerName,
+}
+
+impl TlsConnector {
+    pub fn new(config: Arc<ClientConfig>, server_name: &str) -> io::Result<TlsConnector> {
+        let server_name = ServerName::try_from(server_name)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        Ok(TlsConnector {
+            config,
+            server_name,
+        })
+    }
+
+    //Trust servers with certificates signed by any certificate in the PEM encoded ca_certs file.
+    pub fn from_pem_files<P: AsRef<Path>>(
+        ca_certs: P,
+        server_name: &str,
+    ) -> io::Result<TlsConnector> {
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(load_root_cert_store(ca_certs)?)
+            .with_no_client_auth();
+
+        TlsConnector::new(Arc::new(config), server_name)
+    }
+
+    //Same as from_pem_files() but also presents cert_chain to servers that require client
+    //authentication.
+    pub fn from_pem_files_with_client_auth<P: AsRef<Path>>(
+        ca_certs: P,
+        cert_chain: P,
+        private_key: P,
+        server_name: &str,
+    ) -> io::Result<TlsConnector> {
+        let config = ClientConfig::builder()
+            .with_safe_defaults()
+            .with_root_certificates(load_root_cert_store(ca_certs)?)
+            .with_client_auth_cert(load_certs(cert_chain)?, load_private_key(private_key)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        TlsConnector::new(Arc::new(config), server_name)
+    }
+
+    pub(crate) fn connect(&self, socket: TransportStream) -> io::Result<TlsStream> {
+        match ClientConnection::new(self.config.clone(), self.server_name.clone()) {
+            Ok(connection) => Ok(TlsStream::new(socket, Connection::Client(connection))),
+            Err(e) => {
+                let _ = socket.shutdown(Shutdown::Both);
+                Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+            }
+        }
+    }
+}
+
+//Settings used to secure connections accepted by a listener created with
+//Engine::add_listener_with_tls().
+#[derive(Clone)]
+pub struct TlsAcceptor {
+    config: Arc<ServerConfig>,
+}
+
+impl TlsAcceptor {
+    pub fn new(config: Arc<ServerConfig>) -> TlsAcceptor {
+        TlsAcceptor { config }
+    }
+
+    pub fn from_pem_files<P: AsRef<Path>>(
+        cert_chain: P,
+        private_key: P,
+    ) -> io::Result<TlsAcceptor> {
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(load_certs(cert_chain)?, load_private_key(private_key)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        Ok(TlsAcceptor::new(Arc::new(config)))
+    }
+
+    //Same as from_pem_files() but clients must present a certificate signed by any certificate
+    //in the PEM encoded client_ca_certs file.
+    pub fn from_pem_files_with_client_auth<P: AsRef<Path>>(
+        cert_chain: P,
+        private_key: P,
+        client_ca_certs: P,
+    ) -> io::Result<TlsAcceptor> {
+        let client_verifier =
+            AllowAnyAuthenticatedClient::new(load_root_cert_store(client_ca_certs)?).boxed();
+        let config = ServerConfig::builder()
+            .with_safe_defaults()
+            .with_client_cert_verifier(client_verifier)
+            .with_single_cert(load_certs(cert_chain)?, load_private_key(private_key)?)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e))?;
+
+        Ok(TlsAcceptor::new(Arc::new(config)))
+    }
+
+    pub(crate) fn accept(&self, socket: TransportStream) -> io::Result<TlsStream> {
+        match ServerConnection::new(self.config.clone()) {
+            Ok(connection) => Ok(TlsStream::new(socket, Connection::Server(connection))),
+            Err(e) => {
+                let _ = socket.shutdown(Shutdown::Both);
+                Err(io::Error::new(io::ErrorKind::InvalidInput, e))
+            }
+        }
+    }
+}
+
+fn load_certs<P: AsRef<Path>>(path: P) -> io::Result<Vec<Certificate>> {
+    let mut reader = BufReader::new(File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader)?;
+    if certs.is_empty() {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "No certificates found in PEM file",
+        ));
+    }
+
+    Ok(certs.into_iter().map(Certificate).collect())
+}
+
+fn load_private_key<P: AsRef<Path>>(path: P) -> io::Result<PrivateKey> {
+    let mut reader = BufReader::new(File::open(path)?);
+    while let Some(item) = rustls_pemfile::read_one(&mut reader)? {
+        match item {
+            rustls_pemfile::Item::RSAKey(key)
+            | rustls_pemfile::Item::PKCS8Key(key)
+            | rustls_pemfile::Item::ECKey(key) => return Ok(PrivateKey(key)),
+            _ => {}
+        }
+    }
+
+    Err(io::Error::new(
+        io::ErrorKind::InvalidData,
+        "No private key found in PEM file",
+    ))
+}
+
+fn load_root_cert_store<P: AsRef<Path>>(path: P) -> io::Result<RootCertStore> {
+    let mut root_cert_store = RootCertStore::empty();
+    for cert in load_certs(path)? {
+        root_cert_store
+            .add(&cert)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))?;
+    }
+
+    Ok(root_cert_store)
+}
+
+//Non-blocking TLS session on top of a socket. Reads and writes only ever deal with
+//plaintext. Encrypted records that could not be written because the socket would block are
+//kept until flush() is called.
+pub(crate) struct TlsStream {
+    socket: TransportStream,
+    connection: Connection,
+}
+
+impl TlsStream {
+    fn new(socket: TransportStream, connection: Connection) -> TlsStream {
+        TlsStream { socket, connection }
+    }
+
+    pub(crate) fn socket(&self) -> &TransportStream {
+        &self.socket
+    }
+
+    pub fn is_handshaking(&self) -> bool {
+        self.connection.is_handshaking()
+    }
+
+    pub fn shutdown(&mut self, how: Shutdown) -> io::Result<()> {
+        self.connection.send_close_notify();
+        let _ = self.write_tls();
+        self.socket.shutdown(how)
+    }
+
+    //Write as many pending records as possible. Returns whether all of them were written.
+    fn write_tls(&mut self) -> io::Result<bool> {
+        while self.connection.wants_write() {
+            match self.connection.write_tls(&mut self.socket) {
+                Ok(_) => {}
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => return Ok(false),
+                Err(e) => return Err(e),
+            }
+        }
+
+        Ok(true)
+    }
+}
+
+impl Read for TlsStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            //Hand over any plaintext that has already been decrypted.
+            match self.connection.reader().read(buf) {
+                Ok(bytes_read) => return Ok(bytes_read),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                //Remote closed the socket without sending close_notify. Treat it like any other
+                //closed socket so hang ups are handled the same as plain TCP.
+                Err(ref e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(0),
+                Err(e) => return Err(e),
+            }
+
+            //Otherwise, pull more records off of the socket.
+            if self.connection.read_tls(&mut self.socket)? == 0 {
+                return Ok(0);
+            }
+
+            if let Err(e) = self.connection.process_new_packets() {
+                //Make a best effort to let remote know why the connection is being dropped.
+                let _ = self.write_tls();
+                return Err(io::Error::new(io::ErrorKind::InvalidData, e));
+            }
+
+            //Handshake and key update responses need to go out right away.
+            self.write_tls()?;
+        }
+    }
+}
+
+impl Write for TlsStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        //Plaintext written before the handshake finishes is buffered by rustls and sent once
+        //the handshake completes.
+        let bytes_written = self.connection.writer().write(buf)?;
+        self.write_tls()?;
+
+        if bytes_written == 0 && !buf.is_empty() {
+            return Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "TLS buffer is full",
+            ));
+        }
+
+        Ok(bytes_written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        if self.write_tls()? {
+            Ok(())
+        } else {
+            Err(io::Error::new(
+                io::ErrorKind::WouldBlock,
+                "TLS records still pending",
+            ))
+        }
+    }
+}
+
+//Whether a TLS read error was caused by a certificate being rejected, either locally or by
+//remote.
+pub(crate) fn is_certificate_error(e: &io::Error) -> bool {
+    let e = match e.get_ref().and_then(|e| e.downcast_ref::<rustls::Error>()) {
+        Some(e) => e,
+        None => return false,
+    };
+
+    match *e {
+        rustls::Error::InvalidCertificate(_) | rustls::Error::NoCertificatesPresented => true,
+        rustls::Error::AlertReceived(alert) => matches!(
+            alert,
+            AlertDescription::BadCertificate
+                | AlertDescription::UnsupportedCertificate
+                | AlertDescription::CertificateRevoked
+                | AlertDescription::CertificateExpired
+                | AlertDescription::CertificateUnknown
+                | AlertDescription::UnknownCA
+                | AlertDescription::CertificateRequired
+        ),
+        _ => false,
+    }
+}
+
+//Whether a read error came from TLS itself instead of the underlying socket.
+pub(crate) fn is_tls_error(e: &io::Error) -> bool {
+    e.get_ref()
+        .and_then(|e| e.downcast_ref::<rustls::Error>())
+        .is_some()
+}
@@ -0,0 +1,613 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+use mio::tcp::{Shutdown, TcpListener, TcpStream};
+use mio::unix::{EventedFd, UnixReady};
+use mio::{Evented, Poll, PollOpt, Ready, Registration, SetReadiness, Token};
+use std::cmp;
+use std::collections::VecDeque;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::os::unix::io::AsRawFd;
+use std::os::unix::net;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+
+//Where a connection connects to or a listener accepts connections from.
+#[derive(Clone, Debug)]
+pub enum TransportAddr {
+    Tcp(SocketAddr),
+    Unix(PathBuf), //Unix domain socket. Useful for gateways running on the same machine.
+    Loopback(LoopbackAddr), //In-process connection that doesn't touch the network at all.
+}
+
+impl From<SocketAddr> for TransportAddr {
+    fn from(address: SocketAddr) -> Self {
+        TransportAddr::Tcp(address)
+    }
+}
+
+impl From<LoopbackAddr> for TransportAddr {
+    fn from(address: LoopbackAddr) -> Self {
+        TransportAddr::Loopback(address)
+    }
+}
+
+//Address reported for accepted connections that don't have an IP address of their own.
+fn unspecified_addr() -> SocketAddr {
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0))
+}
+
+//Connected socket of any transport. Everything above this, including TLS, doesn't care which
+//transport is in use.
+pub(crate) enum TransportStream {
+    Tcp(TcpStream),
+    Unix(UnixStream),
+    Loopback(LoopbackStream),
+}
+
+impl TransportStream {
+    pub fn connect(address: &TransportAddr) -> io::Result<TransportStream> {
+        match *address {
+            TransportAddr::Tcp(ref address) => {
+                Ok(TransportStream::Tcp(TcpStream::connect(address)?))
+            }
+            TransportAddr::Unix(ref path) => Ok(TransportStream::Unix(UnixStream::new(
+                net::UnixStream::connect(path)?,
+            )?)),
+            TransportAddr::Loopback(ref address) => {
+                Ok(TransportStream::Loopback(address.connect()?))
+            }
+        }
+    }
+
+    //Only TCP connections have a peer address.
+    pub fn peer_addr(&self) -> Option<SocketAddr> {
+        match *self {
+            TransportStream::Tcp(ref socket) => socket.peer_addr().ok(),
+            TransportStream::Unix(_) | TransportStream::Loopback(_) => None,
+        }
+    }
+
+    //Poll doesn't pass hup along for loopback streams so they have to be asked directly. Other
+    //transports report it through Poll instead.
+    pub fn is_peer_closed(&self) -> bool {
+        match *self {
+            TransportStream::Loopback(ref socket) => socket.is_peer_closed(),
+            TransportStream::Tcp(_) | TransportStream::Unix(_) => false,
+        }
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        match *self {
+            TransportStream::Tcp(ref socket) => socket.shutdown(how),
+            TransportStream::Unix(ref socket) => socket.0.shutdown(how),
+            TransportStream::Loopback(ref socket) => socket.shutdown(how),
+        }
+    }
+}
+
+impl Read for &TransportStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        match **self {
+            TransportStream::Tcp(ref socket) => (&*socket).read(buf),
+            TransportStream::Unix(ref socket) => (&socket.0).read(buf),
+            TransportStream::Loopback(ref socket) => (&*socket).read(buf),
+        }
+    }
+}
+
+impl Write for &TransportStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match **self {
+            TransportStream::Tcp(ref socket) => (&*socket).write(buf),
+            TransportStream::Unix(ref socket) => (&socket.0).write(buf),
+            TransportStream::Loopback(ref socket) => (&*socket).write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match **self {
+            TransportStream::Tcp(ref socket) => (&*socket).flush(),
+            TransportStream::Unix(ref socket) => (&socket.0).flush(),
+            TransportStream::Loopback(ref socket) => (&*socket).flush(),
+        }
+    }
+}
+
+impl Read for TransportStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self).read(buf)
+    }
+}
+
+impl Write for TransportStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self).flush()
+    }
+}
+
+impl Evented for TransportStream {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        match *self {
+            TransportStream::Tcp(ref socket) => socket.register(poll, token, interest, opts),
+            TransportStream::Unix(ref socket) => socket.register(poll, token, interest, opts),
+            TransportStream::Loopback(ref socket) => socket.register(poll, token, interest, opts),
+        }
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        match *self {
+            TransportStream::Tcp(ref socket) => socket.reregister(poll, token, interest, opts),
+            TransportStream::Unix(ref socket) => socket.reregister(poll, token, interest, opts),
+            TransportStream::Loopback(ref socket) => socket.reregister(poll, token, interest, opts),
+        }
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        match *self {
+            TransportStream::Tcp(ref socket) => socket.deregister(poll),
+            TransportStream::Unix(ref socket) => socket.deregister(poll),
+            TransportStream::Loopback(ref socket) => socket.deregister(poll),
+        }
+    }
+}
+
+//Listening socket of any transport.
+pub(crate) enum TransportListener {
+    Tcp(TcpListener),
+    Unix(UnixListener),
+    Loopback(LoopbackListener),
+}
+
+impl TransportListener {
+    pub fn bind(address: &TransportAddr) -> io::Result<TransportListener> {
+        match *address {
+            TransportAddr::Tcp(ref address) => {
+                Ok(TransportListener::Tcp(TcpListener::bind(address)?))
+            }
+            TransportAddr::Unix(ref path) => Ok(TransportListener::Unix(UnixListener::bind(path)?)),
+            TransportAddr::Loopback(ref address) => {
+                Ok(TransportListener::Loopback(address.bind()?))
+            }
+        }
+    }
+
+    //Connections without an IP address of their own are reported as coming from 0.0.0.0:0.
+    pub fn accept(&self) -> io::Result<(TransportStream, SocketAddr)> {
+        match *self {
+            TransportListener::Tcp(ref listener) => {
+                let (socket, addr) = listener.accept()?;
+                Ok((TransportStream::Tcp(socket), addr))
+            }
+            TransportListener::Unix(ref listener) => {
+                let (socket, _) = listener.socket.accept()?;
+                Ok((
+                    TransportStream::Unix(UnixStream::new(socket)?),
+                    unspecified_addr(),
+                ))
+            }
+            TransportListener::Loopback(ref listener) => Ok((
+                TransportStream::Loopback(listener.accept()?),
+                unspecified_addr(),
+            )),
+        }
+    }
+}
+
+impl Evented for TransportListener {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        match *self {
+            TransportListener::Tcp(ref listener) => listener.register(poll, token, interest, opts),
+            TransportListener::Unix(ref listener) => {
+                EventedFd(&listener.socket.as_raw_fd()).register(poll, token, interest, opts)
+            }
+            TransportListener::Loopback(ref listener) => {
+                listener.registration.register(poll, token, interest, opts)
+            }
+        }
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        match *self {
+            TransportListener::Tcp(ref listener) => {
+                listener.reregister(poll, token, interest, opts)
+            }
+            TransportListener::Unix(ref listener) => {
+                EventedFd(&listener.socket.as_raw_fd()).reregister(poll, token, interest, opts)
+            }
+            TransportListener::Loopback(ref listener) => listener
+                .registration
+                .reregister(poll, token, interest, opts),
+        }
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        match *self {
+            TransportListener::Tcp(ref listener) => listener.deregister(poll),
+            TransportListener::Unix(ref listener) => {
+                EventedFd(&listener.socket.as_raw_fd()).deregister(poll)
+            }
+            TransportListener::Loopback(ref listener) => poll.deregister(&listener.registration),
+        }
+    }
+}
+
+//Non-blocking Unix domain socket that can be registered with Poll.
+pub(crate) struct UnixStream(net::UnixStream);
+
+impl UnixStream {
+    fn new(socket: net::UnixStream) -> io::Result<UnixStream> {
+        socket.set_nonblocking(true)?;
+        Ok(UnixStream(socket))
+    }
+}
+
+impl Evented for UnixStream {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        EventedFd(&self.0.as_raw_fd()).deregister(poll)
+    }
+}
+
+//Non-blocking Unix domain socket listener. The socket's file is removed when the listener is
+//dropped so the same path can be bound again later.
+pub(crate) struct UnixListener {
+    socket: net::UnixListener,
+    path: PathBuf,
+}
+
+impl UnixListener {
+    fn bind(path: &PathBuf) -> io::Result<UnixListener> {
+        let socket = net::UnixListener::bind(path)?;
+        socket.set_nonblocking(true)?;
+        Ok(UnixListener {
+            socket,
+            path: path.clone(),
+        })
+    }
+}
+
+impl Drop for UnixListener {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+//One direction of a loopback connection.
+#[derive(Default)]
+struct LoopbackPipe {
+    buffer: VecDeque<u8>,
+    writer_closed: bool,
+    reader_closed: bool,
+}
+
+struct LoopbackPair {
+    pipes: [LoopbackPipe; 2], //Indexed by the side that reads from the pipe.
+    set_readiness: [SetReadiness; 2],
+}
+
+impl LoopbackPair {
+    //Let side's Poll know what it can do now. Mirrors how a TCP socket behaves: readable when
+    //bytes are waiting or the other side closed, hup once the other side closed, and always
+    //writable so write errors get noticed.
+    fn update_readiness(&self, side: usize) {
+        let inbound = &self.pipes[side];
+        let mut ready = Ready::writable();
+        if !inbound.buffer.is_empty() || inbound.writer_closed {
+            ready |= Ready::readable();
+        }
+        if inbound.writer_closed {
+            ready |= UnixReady::hup();
+        }
+        let _ = self.set_readiness[side].set_readiness(ready);
+    }
+}
+
+//One end of an in-process connection. Works like a non-blocking TCP socket without needing to
+//bind any ports. Two Engines, or an Engine and a test driver, can talk using a LoopbackAddr or a
+//pair of these from LoopbackStream::pair().
+pub struct LoopbackStream {
+    pair: Arc<Mutex<LoopbackPair>>,
+    side: usize,
+    registration: Registration,
+}
+
+impl LoopbackStream {
+    pub fn pair() -> (LoopbackStream, LoopbackStream) {
+        let (registration0, set_readiness0) = Registration::new2();
+        let (registration1, set_readiness1) = Registration::new2();
+        let pair = Arc::new(Mutex::new(LoopbackPair {
+            pipes: [LoopbackPipe::default(), LoopbackPipe::default()],
+            set_readiness: [set_readiness0, set_readiness1],
+        }));
+        {
+            let pair = pair.lock().unwrap();
+            pair.update_readiness(0);
+            pair.update_readiness(1);
+        }
+
+        (
+            LoopbackStream {
+                pair: pair.clone(),
+                side: 0,
+                registration: registration0,
+            },
+            LoopbackStream {
+                pair,
+                side: 1,
+                registration: registration1,
+            },
+        )
+    }
+
+    fn peer_side(&self) -> usize {
+        1 - self.side
+    }
+
+    fn is_peer_closed(&self) -> bool {
+        self.pair.lock().unwrap().pipes[self.side].writer_closed
+    }
+
+    pub fn shutdown(&self, how: Shutdown) -> io::Result<()> {
+        let mut pair = self.pair.lock().unwrap();
+        let (side, peer_side) = (self.side, self.peer_side());
+        if let Shutdown::Read | Shutdown::Both = how {
+            pair.pipes[side].reader_closed = true;
+            pair.pipes[side].buffer.clear();
+        }
+        if let Shutdown::Write | Shutdown::Both = how {
+            pair.pipes[peer_side].writer_closed = true;
+        }
+        pair.update_readiness(side);
+        pair.update_readiness(peer_side);
+        Ok(())
+    }
+}
+
+impl Read for &LoopbackStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let mut pair = self.pair.lock().unwrap();
+        let bytes_read = {
+            let inbound = &mut pair.pipes[self.side];
+            if inbound.buffer.is_empty() {
+                if inbound.writer_closed || inbound.reader_closed {
+                    return Ok(0);
+                }
+
+                pair.update_readiness(self.side);
+                return Err(io::Error::from(io::ErrorKind::WouldBlock));
+            }
+
+            let (front, back) = inbound.buffer.as_slices();
+            let bytes_read = if front.len() >= buf.len() {
+                buf.copy_from_slice(&front[..buf.len()]);
+                buf.len()
+            } else {
+                let back_len = cmp::min(back.len(), buf.len() - front.len());
+                buf[..front.len()].copy_from_slice(front);
+                buf[front.len()..front.len() + back_len].copy_from_slice(&back[..back_len]);
+                front.len() + back_len
+            };
+            inbound.buffer.drain(..bytes_read);
+            bytes_read
+        };
+        pair.update_readiness(self.side);
+
+        Ok(bytes_read)
+    }
+}
+
+impl Write for &LoopbackStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let mut pair = self.pair.lock().unwrap();
+        let peer_side = self.peer_side();
+        {
+            let outbound = &mut pair.pipes[peer_side];
+            if outbound.writer_closed || outbound.reader_closed {
+                return Err(io::Error::from(io::ErrorKind::BrokenPipe));
+            }
+            outbound.buffer.extend(buf);
+        }
+        pair.update_readiness(peer_side);
+
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Read for LoopbackStream {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        (&*self).read(buf)
+    }
+}
+
+impl Write for LoopbackStream {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        (&*self).write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        (&*self).flush()
+    }
+}
+
+impl Evented for LoopbackStream {
+    fn register(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.registration.register(poll, token, interest, opts)
+    }
+
+    fn reregister(
+        &self,
+        poll: &Poll,
+        token: Token,
+        interest: Ready,
+        opts: PollOpt,
+    ) -> io::Result<()> {
+        self.registration.reregister(poll, token, interest, opts)
+    }
+
+    fn deregister(&self, poll: &Poll) -> io::Result<()> {
+        poll.deregister(&self.registration)
+    }
+}
+
+impl Drop for LoopbackStream {
+    fn drop(&mut self) {
+        let _ = self.shutdown(Shutdown::Both);
+    }
+}
+
+impl fmt::Debug for LoopbackStream {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LoopbackStream({})", self.side)
+    }
+}
+
+struct LoopbackBacklog {
+    pending: VecDeque<LoopbackStream>,
+    set_readiness: SetReadiness,
+}
+
+//In-process address that a single listener can be bound to at a time. Clones refer to the same
+//address.
+#[derive(Clone, Default)]
+pub struct LoopbackAddr(Arc<Mutex<Option<LoopbackBacklog>>>);
+
+impl LoopbackAddr {
+    pub fn new() -> LoopbackAddr {
+        LoopbackAddr::default()
+    }
+
+    //Connect to the listener bound to this address. Fails with ConnectionRefused when nothing is
+    //listening.
+    pub fn connect(&self) -> io::Result<LoopbackStream> {
+        let mut backlog = self.0.lock().unwrap();
+        match *backlog {
+            Some(ref mut backlog) => {
+                let (local, remote) = LoopbackStream::pair();
+                backlog.pending.push_back(remote);
+                let _ = backlog.set_readiness.set_readiness(Ready::readable());
+                Ok(local)
+            }
+            None => Err(io::Error::from(io::ErrorKind::ConnectionRefused)),
+        }
+    }
+
+    fn bind(&self) -> io::Result<LoopbackListener> {
+        let mut backlog = self.0.lock().unwrap();
+        if backlog.is_some() {
+            return Err(io::Error::from(io::ErrorKind::AddrInUse));
+        }
+
+        let (registration, set_readiness) = Registration::new2();
+        *backlog = Some(LoopbackBacklog {
+            pending: VecDeque::new(),
+            set_readiness,
+        });
+        Ok(LoopbackListener {
+            address: self.clone(),
+            registration,
+        })
+    }
+}
+
+impl fmt::Debug for LoopbackAddr {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LoopbackAddr({:p})", Arc::as_ptr(&self.0))
+    }
+}
+
+pub(crate) struct LoopbackListener {
+    address: LoopbackAddr,
+    registration: Registration,
+}
+
+impl LoopbackListener {
+    fn accept(&self) -> io::Result<LoopbackStream> {
+        let mut backlog = self.address.0.lock().unwrap();
+        let backlog = backlog.as_mut().unwrap();
+        let stream = backlog.pending.pop_front();
+        let ready = if backlog.pending.is_empty() {
+            Ready::empty()
+        } else {
+            Ready::readable()
+        };
+        let _ = backlog.set_readiness.set_readiness(ready);
+
+        stream.ok_or_else(|| io::Error::from(io::ErrorKind::WouldBlock))
+    }
+}
+
+impl Drop for LoopbackListener {
+    fn drop(&mut self) {
+        //Anyone still waiting to be accepted sees the connection close.
+        *self.address.0.lock().unwrap() = None;
+    }
+}
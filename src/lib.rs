@@ -54,6 +54,8 @@ pub mod message;
 pub mod message_version;
 mod network_read_retry;
 pub mod rule;
+#[cfg(feature = "testing")]
+pub mod testing;
 mod token_generator;
 
 //Dictionary is put last because it needs the above macros.
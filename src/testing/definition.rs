@@ -0,0 +1,692 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Runner for acceptance test definitions written in the QuickFIX `.def` format.
+
+use chrono::Duration as ChronoDuration;
+use std::collections::{HashMap, HashSet};
+use std::fmt;
+use std::fs;
+use std::io::{self, Read, Write};
+use std::path::Path;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::constant::{TAG_END, VALUE_END};
+use crate::fixt::clock::{Clock, SystemClock};
+use crate::fixt::transport::{TransportAddr, TransportListener, TransportStream};
+
+const DEFAULT_TIMEOUT_SECS: u64 = 10;
+const DEFAULT_IGNORED_TAGS: &[u32] = &[
+    9,   //BodyLength
+    10,  //CheckSum
+    52,  //SendingTime
+    122, //OrigSendingTime
+];
+const DEFAULT_CONNECTION: usize = 1;
+const READ_BUFFER_LEN: usize = 1024;
+const TIME_PLACEHOLDER: &str = "<TIME";
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Action {
+    Connect,          //iCONNECT: Connect to the acceptor being tested.
+    Disconnect,       //iDISCONNECT: Close the connection.
+    Send(Vec<u8>),    //I: Send a message. BodyLength and CheckSum are filled in when missing.
+    ExpectConnect,    //eCONNECT: Wait for the initiator being tested to connect.
+    ExpectDisconnect, //eDISCONNECT: Wait for the other side to close the connection.
+    Expect(Vec<u8>),  //E: Next message received must match this one.
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct Step {
+    pub line: usize,
+    pub connection: usize, //Scripts with more than one connection prefix each line with "N,".
+    pub action: Action,
+}
+
+//Parsed `.def` file. Ie.
+//
+//    iCONNECT
+//    I8=FIX.4.2|35=A|34=1|49=TW|52=<TIME>|56=ISLD|98=0|108=30|
+//    E8=FIX.4.2|9=57|35=A|34=1|49=ISLD|52=00000000-00:00:00|56=TW|98=0|108=30|10=0|
+//    eDISCONNECT
+//
+//Fields can be delimited with SOH or '|'. `<TIME>`, `<TIME+N>` and `<TIME-N>` are replaced with
+//the current time, offset by N seconds, when the step runs.
+#[derive(Clone, Debug)]
+pub struct Definition {
+    pub name: String,
+    pub steps: Vec<Step>,
+}
+
+#[derive(Error, Debug)]
+pub enum DefinitionError {
+    #[error("line {line}: {reason}")]
+    Parse { line: usize, reason: String },
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+impl Definition {
+    pub fn parse<N: Into<String>>(name: N, text: &str) -> Result<Definition, DefinitionError> {
+        let mut steps = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let parse_error = |reason: &str| DefinitionError::Parse {
+                line: line_number,
+                reason: reason.to_string(),
+            };
+
+            let (prefix, rest) = line.split_at(1);
+            let (connection, rest) = match rest.find(',') {
+                Some(comma_index)
+                    if comma_index > 0
+                        && rest[..comma_index].bytes().all(|c| c.is_ascii_digit()) =>
+                {
+                    let connection = rest[..comma_index]
+                        .parse::<usize>()
+                        .map_err(|_| parse_error("connection number is too large"))?;
+                    (connection, &rest[comma_index + 1..])
+                }
+                _ => (DEFAULT_CONNECTION, rest),
+            };
+
+            let action = match (prefix, rest) {
+                ("i", "CONNECT") => Action::Connect,
+                ("i", "DISCONNECT") => Action::Disconnect,
+                ("e", "CONNECT") => Action::ExpectConnect,
+                ("e", "DISCONNECT") => Action::ExpectDisconnect,
+                ("I", message) if !message.is_empty() => Action::Send(normalize_message(message)),
+                ("E", message) if !message.is_empty() => Action::Expect(normalize_message(message)),
+                ("i", _) | ("e", _) => return Err(parse_error("unsupported command")),
+                ("I", _) | ("E", _) => return Err(parse_error("missing message")),
+                _ => return Err(parse_error("line must start with I, E, i or e")),
+            };
+
+            steps.push(Step {
+                line: line_number,
+                connection,
+                action,
+            });
+        }
+
+        Ok(Definition {
+            name: name.into(),
+            steps,
+        })
+    }
+
+    //Load a `.def` file. The definition is named after the file without its extension.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Definition, DefinitionError> {
+        let path = path.as_ref();
+        let text = fs::read_to_string(path)?;
+        let name = path
+            .file_stem()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or_default();
+        Definition::parse(name, &text)
+    }
+}
+
+//Use SOH as the field delimiter and make sure the message ends with one.
+fn normalize_message(message: &str) -> Vec<u8> {
+    let mut bytes = message.as_bytes().to_vec();
+    if !bytes.contains(&VALUE_END) {
+        for c in &mut bytes {
+            if *c == b'|' {
+                *c = VALUE_END;
+            }
+        }
+    }
+    if bytes.last() != Some(&VALUE_END) {
+        bytes.push(VALUE_END);
+    }
+
+    bytes
+}
+
+//Field that didn't match. Either side is None when the field is missing.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FieldMismatch {
+    pub tag: String,
+    pub expected: Option<String>,
+    pub received: Option<String>,
+}
+
+impl fmt::Display for FieldMismatch {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let missing = "<missing>".to_string();
+        write!(
+            f,
+            "{} expected {} received {}",
+            self.tag,
+            self.expected.as_ref().unwrap_or(&missing),
+            self.received.as_ref().unwrap_or(&missing)
+        )
+    }
+}
+
+struct FieldMismatches<'a>(&'a [FieldMismatch]);
+
+impl<'a> fmt::Display for FieldMismatches<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for (index, mismatch) in self.0.iter().enumerate() {
+            if index > 0 {
+                write!(f, ", ")?;
+            }
+            write!(f, "{}", mismatch)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum StepError {
+    #[error("timed out waiting for {0}")]
+    Timeout(&'static str),
+    #[error("{0}")]
+    Unsupported(&'static str),
+    #[error("connection {0} is not open")]
+    NotConnected(usize),
+    #[error("connection {0} is already open")]
+    AlreadyConnected(usize),
+    #[error("received {received} which does not match {expected} ({})", FieldMismatches(.fields))]
+    Mismatch {
+        expected: String,
+        received: String,
+        fields: Vec<FieldMismatch>,
+    },
+    #[error("expected disconnect but received {0}")]
+    UnexpectedMessage(String),
+    #[error("received garbled message {0}")]
+    Garbled(String),
+    #[error("connection closed")]
+    Closed,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+//Step of a Definition that failed.
+#[derive(Error, Debug)]
+#[error("line {line} failed: {error}")]
+pub struct RunError {
+    pub line: usize,
+    pub error: StepError,
+}
+
+struct RunnerConnection {
+    stream: TransportStream,
+    buffer: Vec<u8>,
+}
+
+//Runs Definitions against an Engine. The Engine should be set up with SenderCompID and
+//TargetCompID to match the definitions, ie. ISLD and TW respectively for the QuickFIX server
+//definitions, and approve every Logon it receives.
+pub struct DefinitionRunner {
+    acceptor_address: Option<TransportAddr>,
+    listener: Option<TransportListener>,
+    clock: Arc<dyn Clock>,
+    timeout: Duration,
+    ignored_tags: HashSet<Vec<u8>>,
+    connections: HashMap<usize, RunnerConnection>,
+}
+
+impl DefinitionRunner {
+    fn new(
+        acceptor_address: Option<TransportAddr>,
+        listener: Option<TransportListener>,
+    ) -> DefinitionRunner {
+        DefinitionRunner {
+            acceptor_address,
+            listener,
+            clock: Arc::new(SystemClock),
+            timeout: Duration::from_secs(DEFAULT_TIMEOUT_SECS),
+            ignored_tags: DEFAULT_IGNORED_TAGS
+                .iter()
+                .map(|tag| tag.to_string().into_bytes())
+                .collect(),
+            connections: HashMap::new(),
+        }
+    }
+
+    //Test an acceptor listening on address. Definitions connect to it using iCONNECT.
+    pub fn acceptor<A: Into<TransportAddr>>(address: A) -> DefinitionRunner {
+        DefinitionRunner::new(Some(address.into()), None)
+    }
+
+    //Test an initiator that connects to address. Definitions wait for it using eCONNECT.
+    pub fn initiator<A: Into<TransportAddr>>(address: A) -> io::Result<DefinitionRunner> {
+        let listener = TransportListener::bind(&address.into())?;
+        Ok(DefinitionRunner::new(None, Some(listener)))
+    }
+
+    //Clock used to fill in `<TIME>`. Usually the same one given to Engine::set_clock().
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> DefinitionRunner {
+        self.clock = clock;
+        self
+    }
+
+    //How long to wait for each expected message, connect or disconnect. Defaults to 10 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> DefinitionRunner {
+        self.timeout = timeout;
+        self
+    }
+
+    //Skip tag when comparing expected messages. BodyLength, CheckSum, SendingTime and
+    //OrigSendingTime are always skipped because they change every run.
+    pub fn with_ignored_tag(mut self, tag: u32) -> DefinitionRunner {
+        self.ignored_tags.insert(tag.to_string().into_bytes());
+        self
+    }
+
+    //Run every step of definition in order, stopping at the first one that fails. Connections
+    //left open are closed afterwards.
+    pub fn run(&mut self, definition: &Definition) -> Result<(), RunError> {
+        let result = definition.steps.iter().try_for_each(|step| {
+            self.run_step(step).map_err(|error| RunError {
+                line: step.line,
+                error,
+            })
+        });
+        self.connections.clear();
+
+        result
+    }
+
+    fn run_step(&mut self, step: &Step) -> Result<(), StepError> {
+        match step.action {
+            Action::Connect => {
+                let address = self
+                    .acceptor_address
+                    .as_ref()
+                    .ok_or(StepError::Unsupported("iCONNECT needs an acceptor to test"))?;
+                let stream = TransportStream::connect(address)?;
+                self.add_connection(step.connection, stream)
+            }
+            Action::Disconnect => match self.connections.remove(&step.connection) {
+                Some(_) => Ok(()),
+                None => Err(StepError::NotConnected(step.connection)),
+            },
+            Action::Send(ref message) => {
+                let message = complete_message(&self.replace_time(message));
+                self.send(step.connection, &message)
+            }
+            Action::ExpectConnect => {
+                let stream = self.accept()?;
+                self.add_connection(step.connection, stream)
+            }
+            Action::ExpectDisconnect => self.expect_disconnect(step.connection),
+            Action::Expect(ref message) => {
+                let expected = self.replace_time(message);
+                let received = self.recv(step.connection)?;
+                let fields = compare_messages(&expected, &received, &self.ignored_tags);
+                if !fields.is_empty() {
+                    return Err(StepError::Mismatch {
+                        expected: printable(&expected),
+                        received: printable(&received),
+                        fields,
+                    });
+                }
+
+                Ok(())
+            }
+        }
+    }
+
+    fn add_connection(
+        &mut self,
+        connection: usize,
+        stream: TransportStream,
+    ) -> Result<(), StepError> {
+        if self.connections.contains_key(&connection) {
+            return Err(StepError::AlreadyConnected(connection));
+        }
+
+        self.connections.insert(
+            connection,
+            RunnerConnection {
+                stream,
+                buffer: Vec::new(),
+            },
+        );
+        Ok(())
+    }
+
+    fn accept(&self) -> Result<TransportStream, StepError> {
+        let listener = self.listener.as_ref().ok_or(StepError::Unsupported(
+            "eCONNECT needs an initiator to test",
+        ))?;
+        let now = Instant::now();
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => return Ok(stream),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(StepError::Io(e)),
+            }
+
+            if now.elapsed() > self.timeout {
+                return Err(StepError::Timeout("connect"));
+            }
+            thread::yield_now();
+        }
+    }
+
+    fn send(&mut self, connection: usize, bytes: &[u8]) -> Result<(), StepError> {
+        let timeout = self.timeout;
+        let stream = &mut self
+            .connections
+            .get_mut(&connection)
+            .ok_or(StepError::NotConnected(connection))?
+            .stream;
+        let now = Instant::now();
+        let mut bytes_written = 0;
+        while bytes_written < bytes.len() {
+            match stream.write(&bytes[bytes_written..]) {
+                Ok(0) => return Err(StepError::Closed),
+                Ok(len) => bytes_written += len,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    if now.elapsed() > timeout {
+                        return Err(StepError::Timeout("message to be sent"));
+                    }
+                    thread::yield_now();
+                }
+                Err(e) => return Err(StepError::Io(e)),
+            }
+        }
+
+        Ok(())
+    }
+
+    //Read until a complete message is buffered or the connection closes. None means the
+    //connection closed cleanly without any partial message left over.
+    fn try_recv(&mut self, connection: usize) -> Result<Option<Vec<u8>>, StepError> {
+        let timeout = self.timeout;
+        let runner_connection = self
+            .connections
+            .get_mut(&connection)
+            .ok_or(StepError::NotConnected(connection))?;
+        let now = Instant::now();
+        let mut buffer = vec![0; READ_BUFFER_LEN];
+        loop {
+            if let Some(message) = split_message(&mut runner_connection.buffer)? {
+                return Ok(Some(message));
+            }
+
+            match runner_connection.stream.read(&mut buffer[..]) {
+                Ok(0) => {
+                    self.connections.remove(&connection);
+                    return Ok(None);
+                }
+                Ok(bytes_read) => {
+                    runner_connection
+                        .buffer
+                        .extend_from_slice(&buffer[..bytes_read]);
+                    continue;
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(ref e) if e.kind() == io::ErrorKind::ConnectionReset => {
+                    self.connections.remove(&connection);
+                    return Ok(None);
+                }
+                Err(e) => return Err(StepError::Io(e)),
+            }
+
+            if now.elapsed() > timeout {
+                return Err(StepError::Timeout("message"));
+            }
+            thread::yield_now();
+        }
+    }
+
+    fn recv(&mut self, connection: usize) -> Result<Vec<u8>, StepError> {
+        self.try_recv(connection)?.ok_or(StepError::Closed)
+    }
+
+    fn expect_disconnect(&mut self, connection: usize) -> Result<(), StepError> {
+        match self.try_recv(connection) {
+            Ok(None) => Ok(()),
+            Ok(Some(message)) => Err(StepError::UnexpectedMessage(printable(&message))),
+            Err(StepError::Timeout(_)) => Err(StepError::Timeout("disconnect")),
+            Err(e) => Err(e),
+        }
+    }
+
+    //Replace `<TIME>`, `<TIME+N>` and `<TIME-N>` with the current time offset by N seconds.
+    fn replace_time(&self, message: &[u8]) -> Vec<u8> {
+        let message = String::from_utf8_lossy(message);
+        let mut result = String::with_capacity(message.len());
+        let mut rest = &message[..];
+        while let Some(start) = rest.find(TIME_PLACEHOLDER) {
+            let end = match rest[start..].find('>') {
+                Some(end) => start + end,
+                None => break,
+            };
+            let offset = &rest[start + TIME_PLACEHOLDER.len()..end];
+            let offset = match offset.strip_prefix('+') {
+                Some(offset) => offset.parse::<i64>().ok(),
+                None if offset.starts_with('-') => offset.parse::<i64>().ok(),
+                None if offset.is_empty() => Some(0),
+                None => None,
+            };
+
+            result.push_str(&rest[..start]);
+            match offset {
+                Some(offset) => {
+                    let time = self.clock.now_utc() + ChronoDuration::seconds(offset);
+                    result.push_str(&time.format("%Y%m%d-%H:%M:%S").to_string());
+                }
+                None => result.push_str(&rest[start..=end]),
+            }
+            rest = &rest[end + 1..];
+        }
+        result.push_str(rest);
+
+        result.into_bytes()
+    }
+}
+
+impl fmt::Debug for DefinitionRunner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "DefinitionRunner {{ acceptor_address: {:?}, connections: {} }}",
+            self.acceptor_address,
+            self.connections.len()
+        )
+    }
+}
+
+fn fields(message: &[u8]) -> impl Iterator<Item = (&[u8], &[u8])> {
+    message
+        .split(|c| *c == VALUE_END)
+        .filter(|field| !field.is_empty())
+        .map(|field| match field.iter().position(|c| *c == TAG_END) {
+            Some(index) => (&field[..index], &field[index + 1..]),
+            None => (field, &field[field.len()..]),
+        })
+}
+
+//Fill in BodyLength and CheckSum when they're missing so definitions don't have to calculate
+//them by hand. Fields that are present are left alone so garbled messages can still be sent.
+fn complete_message(message: &[u8]) -> Vec<u8> {
+    let mut fields: Vec<Vec<u8>> = message
+        .split(|c| *c == VALUE_END)
+        .filter(|field| !field.is_empty())
+        .map(|field| field.to_vec())
+        .collect();
+    let tag_of = |field: &[u8]| {
+        field
+            .iter()
+            .position(|c| *c == TAG_END)
+            .map(|index| field[..index].to_vec())
+            .unwrap_or_default()
+    };
+
+    if !fields.iter().any(|field| tag_of(field) == b"9") {
+        let index = fields
+            .iter()
+            .position(|field| tag_of(field) == b"8")
+            .map(|index| index + 1)
+            .unwrap_or(0);
+        let body_length: usize = fields[index..]
+            .iter()
+            .filter(|field| tag_of(field) != b"10")
+            .map(|field| field.len() + 1)
+            .sum();
+        fields.insert(index, format!("9={}", body_length).into_bytes());
+    }
+
+    let has_checksum = fields.iter().any(|field| tag_of(field) == b"10");
+    let mut bytes = Vec::with_capacity(message.len() + 16);
+    for field in fields {
+        bytes.extend_from_slice(&field);
+        bytes.push(VALUE_END);
+    }
+    if !has_checksum {
+        let checksum = bytes.iter().fold(0u8, |sum, c| sum.wrapping_add(*c));
+        bytes.extend_from_slice(format!("10={:03}", checksum).as_bytes());
+        bytes.push(VALUE_END);
+    }
+
+    bytes
+}
+
+//Remove the first complete message from the front of buffer.
+fn split_message(buffer: &mut Vec<u8>) -> Result<Option<Vec<u8>>, StepError> {
+    const BEGIN_STRING_PREFIX: &[u8] = b"8=";
+    const BODY_LENGTH_PREFIX: &[u8] = b"\x019=";
+    const CHECKSUM_PREFIX: &[u8] = b"10=";
+
+    if buffer.len() < BEGIN_STRING_PREFIX.len() {
+        return Ok(None);
+    }
+    let garbled = |buffer: &[u8]| StepError::Garbled(printable(buffer));
+    if !buffer.starts_with(BEGIN_STRING_PREFIX) {
+        return Err(garbled(buffer));
+    }
+
+    //Find where the body starts and how long it is.
+    let body_length_index = match buffer
+        .windows(BODY_LENGTH_PREFIX.len())
+        .position(|window| window == BODY_LENGTH_PREFIX)
+    {
+        Some(index) => index + BODY_LENGTH_PREFIX.len(),
+        None => return Ok(None),
+    };
+    let body_index = match buffer[body_length_index..]
+        .iter()
+        .position(|c| *c == VALUE_END)
+    {
+        Some(index) => body_length_index + index + 1,
+        None => return Ok(None),
+    };
+    let body_length = String::from_utf8_lossy(&buffer[body_length_index..body_index - 1])
+        .parse::<usize>()
+        .map_err(|_| garbled(buffer))?;
+
+    //Message ends after the CheckSum field following the body.
+    let checksum_index = body_index + body_length;
+    if buffer.len() < checksum_index + CHECKSUM_PREFIX.len() {
+        return Ok(None);
+    }
+    if !buffer[checksum_index..].starts_with(CHECKSUM_PREFIX) {
+        return Err(garbled(buffer));
+    }
+    let end_index = match buffer[checksum_index..]
+        .iter()
+        .position(|c| *c == VALUE_END)
+    {
+        Some(index) => checksum_index + index + 1,
+        None => return Ok(None),
+    };
+
+    Ok(Some(buffer.drain(..end_index).collect()))
+}
+
+//Compare every field that isn't ignored. Repeated tags, like those in repeating groups, are
+//compared in the order they appear. Otherwise, field order doesn't matter because it differs
+//between engines.
+fn compare_messages(
+    expected: &[u8],
+    received: &[u8],
+    ignored_tags: &HashSet<Vec<u8>>,
+) -> Vec<FieldMismatch> {
+    fn group_values<'a>(
+        message: &'a [u8],
+        ignored_tags: &HashSet<Vec<u8>>,
+    ) -> Vec<(&'a [u8], Vec<&'a [u8]>)> {
+        let mut groups: Vec<(&[u8], Vec<&[u8]>)> = Vec::new();
+        for (tag, value) in fields(message) {
+            if ignored_tags.contains(tag) {
+                continue;
+            }
+
+            match groups.iter_mut().find(|group| group.0 == tag) {
+                Some(group) => group.1.push(value),
+                None => groups.push((tag, vec![value])),
+            }
+        }
+
+        groups
+    }
+
+    let expected_groups = group_values(expected, ignored_tags);
+    let received_groups = group_values(received, ignored_tags);
+    let mut tags: Vec<&[u8]> = expected_groups.iter().map(|group| group.0).collect();
+    for group in &received_groups {
+        if !tags.contains(&group.0) {
+            tags.push(group.0);
+        }
+    }
+
+    fn values_of<'a, 'b>(groups: &'b [(&'a [u8], Vec<&'a [u8]>)], tag: &[u8]) -> &'b [&'a [u8]] {
+        groups
+            .iter()
+            .find(|group| group.0 == tag)
+            .map(|group| &group.1[..])
+            .unwrap_or(&[])
+    }
+
+    let mut mismatches = Vec::new();
+    for tag in tags {
+        let expected_values = values_of(&expected_groups, tag);
+        let received_values = values_of(&received_groups, tag);
+        for index in 0..expected_values.len().max(received_values.len()) {
+            let expected_value = expected_values.get(index);
+            let received_value = received_values.get(index);
+            if expected_value != received_value {
+                let to_string = |value: Option<&&[u8]>| {
+                    value.map(|value| String::from_utf8_lossy(value).into_owned())
+                };
+                mismatches.push(FieldMismatch {
+                    tag: String::from_utf8_lossy(tag).into_owned(),
+                    expected: to_string(expected_value),
+                    received: to_string(received_value),
+                });
+            }
+        }
+    }
+
+    mismatches
+}
+
+//Message with SOH replaced by '|' so it can be read.
+fn printable(message: &[u8]) -> String {
+    String::from_utf8_lossy(message).replace(VALUE_END as char, "|")
+}
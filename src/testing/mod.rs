@@ -0,0 +1,595 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+//! Scripted FIX counterparty for testing applications built on an Engine against the FIXT session
+//! rules. Acceptance test definitions written for QuickFIX can be run using the definition module.
+
+pub mod definition;
+
+use mio::tcp::{TcpListener, TcpStream};
+use std::any::{self, Any};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Read, Write};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::thread;
+use std::time::{Duration, Instant};
+use thiserror::Error;
+
+use crate::byte_buffer::ByteBuffer;
+use crate::dictionary::field_types::other::EncryptMethod;
+use crate::dictionary::fields::MsgSeqNum;
+use crate::dictionary::messages::Logon;
+use crate::field::Field;
+use crate::field_type::FieldType;
+use crate::fix::{ParseError, Parser};
+use crate::fix_version::FIXVersion;
+use crate::fixt::clock::{self, Clock, SystemClock};
+use crate::fixt::message::{BuildFIXTMessage, FIXTMessage};
+use crate::fixt::transport::{LoopbackAddr, LoopbackStream};
+use crate::message_version::MessageVersion;
+
+type MsgSeqNumType = <<MsgSeqNum as Field>::Type as FieldType>::Type;
+
+const DEFAULT_MAX_MESSAGE_SIZE: u64 = 4096;
+const DEFAULT_EXPECT_TIMEOUT_SECS: u64 = 5;
+const READ_BUFFER_LEN: usize = 1024;
+
+#[derive(Error, Debug)]
+pub enum CounterpartyError {
+    #[error("timed out waiting for {0}")]
+    Timeout(&'static str),
+    #[error("expected {expected} but received {received:?}")]
+    UnexpectedMessage {
+        expected: &'static str,
+        received: Box<dyn FIXTMessage + Send>,
+    },
+    #[error("{0} did not match")]
+    Mismatch(&'static str),
+    #[error("could not parse message: {0}")]
+    Parse(ParseError),
+    #[error("connection closed")]
+    Closed,
+    #[error("connection still open")]
+    NotClosed,
+    #[error(transparent)]
+    Io(#[from] io::Error),
+}
+
+trait CounterpartyStream: Read + Write + Send {}
+impl<S: Read + Write + Send> CounterpartyStream for S {}
+
+//Remote side of a session that's driven directly by a test instead of an Engine. Messages sent
+//using send() have their session header filled in automatically, including the next MsgSeqNum.
+//Use send_as_is() to send a message exactly as it's given.
+pub struct Counterparty {
+    fix_version: FIXVersion,
+    message_version: MessageVersion,
+    sender_comp_id: Vec<u8>,
+    target_comp_id: Vec<u8>,
+    stream: Option<Box<dyn CounterpartyStream>>,
+    parser: Parser,
+    clock: Arc<dyn Clock>,
+    timeout: Duration,
+    next_msg_seq_num: MsgSeqNumType,
+}
+
+impl Counterparty {
+    pub fn new<S: Read + Write + Send + 'static>(
+        stream: S,
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+    ) -> Counterparty {
+        let message_version = MessageVersion::FIX50SP2;
+        let mut parser = Parser::new(message_dictionary, DEFAULT_MAX_MESSAGE_SIZE);
+        parser.set_default_message_version(message_version);
+
+        Counterparty {
+            fix_version: FIXVersion::FIXT_1_1,
+            message_version,
+            sender_comp_id: Vec::new(),
+            target_comp_id: Vec::new(),
+            stream: Some(Box::new(stream)),
+            parser,
+            clock: Arc::new(SystemClock),
+            timeout: Duration::from_secs(DEFAULT_EXPECT_TIMEOUT_SECS),
+            next_msg_seq_num: 1,
+        }
+    }
+
+    //Connect to an Engine's listener.
+    pub fn connect(
+        address: &SocketAddr,
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+    ) -> io::Result<Counterparty> {
+        let stream = TcpStream::connect(address)?;
+        Ok(Counterparty::new(stream, message_dictionary))
+    }
+
+    //Connect to an Engine's loopback listener.
+    pub fn connect_loopback(
+        address: &LoopbackAddr,
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+    ) -> io::Result<Counterparty> {
+        let stream = address.connect()?;
+        Ok(Counterparty::new(stream, message_dictionary))
+    }
+
+    //Wait for an Engine to connect to listener.
+    pub fn accept(
+        listener: &TcpListener,
+        timeout: Duration,
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+    ) -> io::Result<Counterparty> {
+        let now = Instant::now();
+        loop {
+            match listener.accept() {
+                Ok((stream, _)) => return Ok(Counterparty::new(stream, message_dictionary)),
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(e) => return Err(e),
+            }
+
+            if now.elapsed() > timeout {
+                return Err(io::Error::from(io::ErrorKind::TimedOut));
+            }
+            thread::yield_now();
+        }
+    }
+
+    //Use one end of a LoopbackStream::pair() and hand the other end to the code being tested.
+    pub fn from_loopback_stream(
+        stream: LoopbackStream,
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+    ) -> Counterparty {
+        Counterparty::new(stream, message_dictionary)
+    }
+
+    pub fn with_version(
+        mut self,
+        fix_version: FIXVersion,
+        message_version: MessageVersion,
+    ) -> Counterparty {
+        self.fix_version = fix_version;
+        self.message_version = message_version;
+        self.parser.set_default_message_version(message_version);
+        self
+    }
+
+    pub fn with_comp_ids(mut self, sender_comp_id: &[u8], target_comp_id: &[u8]) -> Counterparty {
+        self.sender_comp_id = sender_comp_id.to_vec();
+        self.target_comp_id = target_comp_id.to_vec();
+        self
+    }
+
+    //Clock used for SendingTime. Usually the same one given to Engine::set_clock().
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Counterparty {
+        self.clock = clock;
+        self
+    }
+
+    //How long expect() waits for a message. Defaults to 5 seconds.
+    pub fn with_timeout(mut self, timeout: Duration) -> Counterparty {
+        self.timeout = timeout;
+        self
+    }
+
+    //Message version assumed for inbound messages that don't specify one. Like an Engine, this
+    //should be changed to the DefaultApplVerID once logged on.
+    pub fn set_default_message_version(&mut self, message_version: MessageVersion) {
+        self.message_version = message_version;
+        self.parser.set_default_message_version(message_version);
+    }
+
+    pub fn next_msg_seq_num(&self) -> MsgSeqNumType {
+        self.next_msg_seq_num
+    }
+
+    //Change the MsgSeqNum of the next message sent. Skipping ahead creates a gap the other side
+    //should ask to be resent.
+    pub fn set_next_msg_seq_num(&mut self, msg_seq_num: MsgSeqNumType) {
+        self.next_msg_seq_num = msg_seq_num;
+    }
+
+    pub fn send<T: FIXTMessage + Send + 'static>(
+        &mut self,
+        message: T,
+    ) -> Result<(), CounterpartyError> {
+        self.send_box(Box::new(message))
+    }
+
+    pub fn send_box(
+        &mut self,
+        mut message: Box<dyn FIXTMessage + Send>,
+    ) -> Result<(), CounterpartyError> {
+        message.setup_fixt_session_header(
+            Some(self.next_msg_seq_num),
+            self.sender_comp_id.clone(),
+            self.target_comp_id.clone(),
+            clock::now_utc_timestamp(&*self.clock),
+        );
+        self.next_msg_seq_num += 1;
+        self.send_box_as_is(message)
+    }
+
+    pub fn send_as_is<T: FIXTMessage + Send + 'static>(
+        &mut self,
+        message: T,
+    ) -> Result<(), CounterpartyError> {
+        self.send_box_as_is(Box::new(message))
+    }
+
+    pub fn send_box_as_is(
+        &mut self,
+        message: Box<dyn FIXTMessage + Send>,
+    ) -> Result<(), CounterpartyError> {
+        let mut bytes = ByteBuffer::with_capacity(512);
+        message.read(self.fix_version, self.message_version, &mut bytes);
+        self.send_bytes(bytes.bytes())
+    }
+
+    //Send raw bytes. Useful for garbled messages.
+    pub fn send_bytes(&mut self, bytes: &[u8]) -> Result<(), CounterpartyError> {
+        let stream = self.stream.as_mut().ok_or(CounterpartyError::Closed)?;
+        let now = Instant::now();
+        let mut bytes_written = 0;
+        while bytes_written < bytes.len() {
+            match stream.write(&bytes[bytes_written..]) {
+                Ok(0) => return Err(CounterpartyError::Closed),
+                Ok(len) => bytes_written += len,
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {
+                    if now.elapsed() > self.timeout {
+                        return Err(CounterpartyError::Timeout("message to be sent"));
+                    }
+                    thread::yield_now();
+                }
+                Err(e) => return Err(CounterpartyError::Io(e)),
+            }
+        }
+
+        Ok(())
+    }
+
+    //Next message received within timeout.
+    pub fn recv(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Box<dyn FIXTMessage + Send>, CounterpartyError> {
+        match self.try_recv(timeout)? {
+            Some(message) => Ok(message),
+            None => Err(CounterpartyError::Timeout("message")),
+        }
+    }
+
+    fn try_recv(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<Option<Box<dyn FIXTMessage + Send>>, CounterpartyError> {
+        if !self.parser.messages.is_empty() {
+            return Ok(Some(self.parser.messages.remove(0)));
+        }
+
+        let now = Instant::now();
+        let mut buffer = vec![0; READ_BUFFER_LEN];
+        loop {
+            let stream = self.stream.as_mut().ok_or(CounterpartyError::Closed)?;
+            match stream.read(&mut buffer[..]) {
+                Ok(0) => {
+                    self.stream = None;
+                    return Err(CounterpartyError::Closed);
+                }
+                Ok(bytes_read) => {
+                    let mut total_bytes_parsed = 0;
+                    while total_bytes_parsed < bytes_read {
+                        let (bytes_parsed, result) =
+                            self.parser.parse(&buffer[total_bytes_parsed..bytes_read]);
+                        if let Err(e) = result {
+                            return Err(CounterpartyError::Parse(e));
+                        }
+                        total_bytes_parsed += bytes_parsed;
+                    }
+
+                    if !self.parser.messages.is_empty() {
+                        return Ok(Some(self.parser.messages.remove(0)));
+                    }
+                }
+                Err(ref e) if e.kind() == io::ErrorKind::WouldBlock => {}
+                Err(ref e) if e.kind() == io::ErrorKind::ConnectionReset => {
+                    self.stream = None;
+                    return Err(CounterpartyError::Closed);
+                }
+                Err(e) => return Err(CounterpartyError::Io(e)),
+            }
+
+            if now.elapsed() > timeout {
+                return Ok(None);
+            }
+            thread::yield_now();
+        }
+    }
+
+    //Next message must be a T.
+    pub fn expect<T: FIXTMessage + Any + Clone>(&mut self) -> Result<T, CounterpartyError> {
+        let timeout = self.timeout;
+        self.expect_within(timeout)
+    }
+
+    pub fn expect_within<T: FIXTMessage + Any + Clone>(
+        &mut self,
+        timeout: Duration,
+    ) -> Result<T, CounterpartyError> {
+        let expected = message_name::<T>();
+        let message = match self.try_recv(timeout)? {
+            Some(message) => message,
+            None => return Err(CounterpartyError::Timeout(expected)),
+        };
+        match message.as_any().downcast_ref::<T>() {
+            Some(message) => Ok(message.clone()),
+            None => Err(CounterpartyError::UnexpectedMessage {
+                expected,
+                received: message,
+            }),
+        }
+    }
+
+    //Next message must be a T that check() returns true for.
+    pub fn expect_matching<T: FIXTMessage + Any + Clone, F: FnOnce(&T) -> bool>(
+        &mut self,
+        check: F,
+    ) -> Result<T, CounterpartyError> {
+        let message = self.expect::<T>()?;
+        if !check(&message) {
+            return Err(CounterpartyError::Mismatch(message_name::<T>()));
+        }
+
+        Ok(message)
+    }
+
+    //Nothing should be received for duration.
+    pub fn expect_nothing(&mut self, duration: Duration) -> Result<(), CounterpartyError> {
+        match self.try_recv(duration)? {
+            Some(message) => Err(CounterpartyError::UnexpectedMessage {
+                expected: "nothing",
+                received: message,
+            }),
+            None => Ok(()),
+        }
+    }
+
+    //Other side should close the connection within timeout without sending anything else first.
+    pub fn expect_closed(&mut self, timeout: Duration) -> Result<(), CounterpartyError> {
+        match self.try_recv(timeout) {
+            Err(CounterpartyError::Closed) => Ok(()),
+            Err(e) => Err(e),
+            Ok(Some(message)) => Err(CounterpartyError::UnexpectedMessage {
+                expected: "connection to close",
+                received: message,
+            }),
+            Ok(None) => Err(CounterpartyError::NotClosed),
+        }
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.stream.is_none()
+    }
+
+    //Close the connection without logging out.
+    pub fn disconnect(&mut self) {
+        self.stream = None;
+    }
+
+    //Act as the acceptor for a session an Engine is initiating: expect its Logon and respond with
+    //a matching one. Inbound messages are assumed to use the Logon's DefaultApplVerID afterwards.
+    pub fn accept_logon(&mut self) -> Result<Logon, CounterpartyError> {
+        let message = self.expect::<Logon>()?;
+
+        let mut response_message = Logon::new();
+        response_message.encrypt_method = message.encrypt_method.clone();
+        response_message.heart_bt_int = message.heart_bt_int;
+        response_message.default_appl_ver_id = message.default_appl_ver_id;
+        self.send(response_message)?;
+        self.set_default_message_version(message.default_appl_ver_id);
+
+        Ok(message)
+    }
+
+    //Act as the initiator of a session with an Engine's listener: send a Logon with heart_bt_int.
+    //The Engine's response still needs to be expected because the application decides whether to
+    //approve it.
+    pub fn send_logon(&mut self, heart_bt_int: i64) -> Result<(), CounterpartyError> {
+        let mut message = Logon::new();
+        message.encrypt_method = EncryptMethod::None;
+        message.heart_bt_int = heart_bt_int;
+        message.default_appl_ver_id = self.message_version;
+        self.send(message)
+    }
+
+    //Run every step of script in order, stopping at the first one that fails.
+    pub fn run(&mut self, script: Script) -> Result<(), ScriptError> {
+        for (index, (description, step)) in script.steps.into_iter().enumerate() {
+            if let Err(error) = step(self) {
+                return Err(ScriptError {
+                    step: index,
+                    description,
+                    error,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl fmt::Debug for Counterparty {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "Counterparty {{ sender_comp_id: {}, target_comp_id: {}, next_msg_seq_num: {} }}",
+            String::from_utf8_lossy(&self.sender_comp_id),
+            String::from_utf8_lossy(&self.target_comp_id),
+            self.next_msg_seq_num
+        )
+    }
+}
+
+//Name of a message type without its module path. Ie. "Logon".
+fn message_name<T>() -> &'static str {
+    let name = any::type_name::<T>();
+    name.rsplit("::").next().unwrap_or(name)
+}
+
+type Step<'a> = Box<dyn FnOnce(&mut Counterparty) -> Result<(), CounterpartyError> + 'a>;
+
+//Steps a Counterparty runs through in order. Ie.
+//
+//    Script::new()
+//        .expect::<Logon>()
+//        .send(logon_response)
+//        .expect_within::<Heartbeat>(Duration::from_secs(6))
+//        .send_with_msg_seq_num(10, test_request)
+//        .expect_matching(|message: &ResendRequest| {
+//            message.begin_seq_no == 2 && message.end_seq_no == 0
+//        })
+#[derive(Default)]
+pub struct Script<'a> {
+    steps: Vec<(String, Step<'a>)>,
+}
+
+impl<'a> Script<'a> {
+    pub fn new() -> Script<'a> {
+        Script { steps: Vec::new() }
+    }
+
+    //Run step with a description that's reported if it fails. Useful for steps that need to poke
+    //at the code being tested, like advancing its clock.
+    pub fn then<
+        D: Into<String>,
+        F: FnOnce(&mut Counterparty) -> Result<(), CounterpartyError> + 'a,
+    >(
+        mut self,
+        description: D,
+        step: F,
+    ) -> Script<'a> {
+        self.steps.push((description.into(), Box::new(step)));
+        self
+    }
+
+    pub fn expect<T: FIXTMessage + Any + Clone>(self) -> Script<'a> {
+        self.then(format!("expect {}", message_name::<T>()), |counterparty| {
+            counterparty.expect::<T>().map(|_| ())
+        })
+    }
+
+    pub fn expect_within<T: FIXTMessage + Any + Clone>(self, timeout: Duration) -> Script<'a> {
+        self.then(
+            format!("expect {} within {:?}", message_name::<T>(), timeout),
+            move |counterparty| counterparty.expect_within::<T>(timeout).map(|_| ()),
+        )
+    }
+
+    pub fn expect_matching<T: FIXTMessage + Any + Clone, F: FnOnce(&T) -> bool + 'a>(
+        self,
+        check: F,
+    ) -> Script<'a> {
+        self.then(
+            format!("expect matching {}", message_name::<T>()),
+            |counterparty| counterparty.expect_matching(check).map(|_| ()),
+        )
+    }
+
+    pub fn expect_nothing(self, duration: Duration) -> Script<'a> {
+        self.then(
+            format!("expect nothing for {:?}", duration),
+            move |counterparty| counterparty.expect_nothing(duration),
+        )
+    }
+
+    pub fn expect_closed(self, timeout: Duration) -> Script<'a> {
+        self.then(
+            format!("expect connection closed within {:?}", timeout),
+            move |counterparty| counterparty.expect_closed(timeout),
+        )
+    }
+
+    pub fn accept_logon(self) -> Script<'a> {
+        self.then("accept Logon", |counterparty| {
+            counterparty.accept_logon().map(|_| ())
+        })
+    }
+
+    pub fn send_logon(self, heart_bt_int: i64) -> Script<'a> {
+        self.then(
+            format!("send Logon with HeartBtInt={}", heart_bt_int),
+            move |counterparty| counterparty.send_logon(heart_bt_int),
+        )
+    }
+
+    pub fn send<T: FIXTMessage + Send + 'static>(self, message: T) -> Script<'a> {
+        self.then(format!("send {}", message_name::<T>()), |counterparty| {
+            counterparty.send(message)
+        })
+    }
+
+    //Send message with msg_seq_num, continuing on from there afterwards.
+    pub fn send_with_msg_seq_num<T: FIXTMessage + Send + 'static>(
+        self,
+        msg_seq_num: MsgSeqNumType,
+        message: T,
+    ) -> Script<'a> {
+        self.then(
+            format!(
+                "send {} with MsgSeqNum={}",
+                message_name::<T>(),
+                msg_seq_num
+            ),
+            move |counterparty| {
+                counterparty.set_next_msg_seq_num(msg_seq_num);
+                counterparty.send(message)
+            },
+        )
+    }
+
+    pub fn send_as_is<T: FIXTMessage + Send + 'static>(self, message: T) -> Script<'a> {
+        self.then(
+            format!("send {} as is", message_name::<T>()),
+            |counterparty| counterparty.send_as_is(message),
+        )
+    }
+
+    pub fn send_bytes<B: Into<Vec<u8>>>(self, bytes: B) -> Script<'a> {
+        let bytes = bytes.into();
+        self.then("send bytes", move |counterparty| {
+            counterparty.send_bytes(&bytes[..])
+        })
+    }
+
+    pub fn wait(self, duration: Duration) -> Script<'a> {
+        self.then(format!("wait {:?}", duration), move |_| {
+            thread::sleep(duration);
+            Ok(())
+        })
+    }
+
+    pub fn disconnect(self) -> Script<'a> {
+        self.then("disconnect", |counterparty| {
+            counterparty.disconnect();
+            Ok(())
+        })
+    }
+}
+
+//Step of a Script that failed.
+#[derive(Error, Debug)]
+#[error("step {step} ({description}) failed: {error}")]
+pub struct ScriptError {
+    pub step: usize,
+    pub description: String,
+    pub error: CounterpartyError,
+}
@@ -52,4 +52,8 @@ impl TokenGenerator {
     pub fn remove(&mut self, token: Token) {
         self.active_tokens.remove(&token);
     }
+
+    pub fn len(&self) -> usize {
+        self.active_tokens.len()
+    }
 }
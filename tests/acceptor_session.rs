@@ -0,0 +1,213 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate chrono;
+extern crate chrono_tz;
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use chrono::{Duration as ChronoDuration, Utc};
+use chrono_tz::UTC;
+use mio::tcp::{TcpListener, TcpStream};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{
+    new_logon_message, TestStream, CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID,
+    SERVER_SENDER_COMP_ID,
+};
+use fix_rs::dictionary::messages::{Logon, Logout};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::acceptor_session::AcceptorSession;
+use fix_rs::fixt::engine::{
+    Connection, ConnectionTerminatedReason, Engine, EngineEvent, Listener, MsgSeqNums, SessionID,
+};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::seq_num_store::{MemorySeqNumStore, SeqNumStore};
+use fix_rs::fixt::session_schedule::SessionSchedule;
+use fix_rs::message_version::MessageVersion;
+
+//Setup a listener that only expects session and connect to it.
+fn setup_listener_with_session(session: AcceptorSession) -> (TestStream, Engine, Connection) {
+    define_dictionary!(Logon, Logout,);
+
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let listener_socket = TcpListener::bind(&addr).unwrap();
+    let addr = listener_socket.local_addr().unwrap();
+    drop(listener_socket);
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    let listener = engine
+        .add_listener(SERVER_SENDER_COMP_ID, addr)
+        .unwrap()
+        .unwrap();
+    assert!(engine.add_acceptor_session(listener, session));
+
+    let stream = TcpStream::connect(&addr).unwrap();
+    let connection = engine_poll_event!(engine,EngineEvent::ConnectionAccepted(_,connection,_) => {
+        connection
+    });
+    let test_client = TestStream::new(
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        stream,
+        build_dictionary(),
+    );
+
+    (test_client, engine, connection)
+}
+
+fn new_client_logon_message() -> Logon {
+    let mut logon_message = new_logon_message();
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    logon_message
+}
+
+//Logon is answered with a Logout and the connection is dropped without asking the application.
+fn assert_logon_refused(
+    mut test_client: TestStream,
+    mut engine: Engine,
+    connection: Connection,
+    logon_message: Logon,
+    text: &[u8],
+    expected_reason: ConnectionTerminatedReason,
+) {
+    test_client.send_message(logon_message);
+    let message = test_client.recv_message::<Logout>();
+    assert_eq!(message.text, text.to_vec());
+    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
+        assert_eq!(terminated_connection,connection);
+        assert_eq!(format!("{:?}",reason),format!("{:?}",expected_reason));
+    });
+}
+
+#[test]
+fn test_unknown_session_is_rejected() {
+    let (test_client, engine, connection) =
+        setup_listener_with_session(AcceptorSession::new(FIXVersion::FIXT_1_1, b"SOMEONE_ELSE"));
+
+    assert_logon_refused(
+        test_client,
+        engine,
+        connection,
+        new_client_logon_message(),
+        b"Unknown session",
+        ConnectionTerminatedReason::LogonUnknownSessionError,
+    );
+}
+
+#[test]
+fn test_unknown_sub_id_is_rejected() {
+    let (test_client, engine, connection) = setup_listener_with_session(
+        AcceptorSession::new(FIXVersion::FIXT_1_1, CLIENT_SENDER_COMP_ID)
+            .with_target_sub_id(b"DESK"),
+    );
+
+    let mut logon_message = new_client_logon_message();
+    logon_message.sender_sub_id = b"OTHER_DESK".to_vec();
+    assert_logon_refused(
+        test_client,
+        engine,
+        connection,
+        logon_message,
+        b"Unknown session",
+        ConnectionTerminatedReason::LogonUnknownSessionError,
+    );
+}
+
+#[test]
+fn test_known_session_uses_its_seq_num_store() {
+    //The session's own store says where it left off even though the Engine has no store.
+    let mut seq_num_store = MemorySeqNumStore::new();
+    seq_num_store
+        .save(
+            &SessionID::new(
+                FIXVersion::FIXT_1_1,
+                SERVER_SENDER_COMP_ID,
+                CLIENT_SENDER_COMP_ID,
+            ),
+            MsgSeqNums::new(1, 7),
+        )
+        .unwrap();
+    let (mut test_client, mut engine, connection) = setup_listener_with_session(
+        AcceptorSession::new(FIXVersion::FIXT_1_1, CLIENT_SENDER_COMP_ID)
+            .with_target_sub_id(b"DESK")
+            .with_seq_num_store(Box::new(seq_num_store)),
+    );
+
+    let mut logon_message = new_client_logon_message();
+    logon_message.sender_sub_id = b"DESK".to_vec();
+    test_client.send_message(logon_message);
+    engine_poll_event!(engine,EngineEvent::ConnectionLoggingOn(_,logging_on_connection,logon_message) => {
+        assert_eq!(logging_on_connection,connection);
+
+        let mut response_message = new_fixt_message!(Logon);
+        response_message.encrypt_method = logon_message.encrypt_method.clone();
+        response_message.heart_bt_int = logon_message.heart_bt_int;
+        response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+        engine.approve_new_connection(connection,Box::new(response_message),None);
+    });
+
+    let message = test_client.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 7);
+}
+
+#[test]
+fn test_session_heart_bt_int_mismatch_is_rejected() {
+    let (test_client, engine, connection) = setup_listener_with_session(
+        AcceptorSession::new(FIXVersion::FIXT_1_1, CLIENT_SENDER_COMP_ID).with_heart_bt_int(30),
+    );
+
+    assert_logon_refused(
+        test_client,
+        engine,
+        connection,
+        new_client_logon_message(),
+        b"HeartBtInt must be 30",
+        ConnectionTerminatedReason::LogonHeartBtIntMismatchError,
+    );
+}
+
+#[test]
+fn test_session_rejects_logon_outside_its_schedule() {
+    //Session doesn't start for another hour even though the listener has no schedule.
+    let (test_client, engine, connection) = setup_listener_with_session(
+        AcceptorSession::new(FIXVersion::FIXT_1_1, CLIENT_SENDER_COMP_ID).with_schedule(
+            SessionSchedule::daily(
+                (Utc::now() + ChronoDuration::hours(1)).time(),
+                (Utc::now() + ChronoDuration::hours(2)).time(),
+                UTC,
+            ),
+        ),
+    );
+
+    assert_logon_refused(
+        test_client,
+        engine,
+        connection,
+        new_client_logon_message(),
+        b"Session is not active",
+        ConnectionTerminatedReason::LogonOutsideScheduleError,
+    );
+}
+
+#[test]
+fn test_acceptor_session_for_unknown_listener() {
+    define_dictionary!(Logon, Logout,);
+
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    let session = AcceptorSession::new(FIXVersion::FIXT_1_1, CLIENT_SENDER_COMP_ID);
+    assert!(!engine.add_acceptor_session(Listener(100), session));
+    assert_eq!(engine.connection_count(), 0);
+}
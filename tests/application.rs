@@ -0,0 +1,307 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{new_logon_message, TestStream, CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID};
+use fix_rs::dictionary::field_types::other::{
+    BusinessRejectReason, OrdType, SecurityIDSource, Side,
+};
+use fix_rs::dictionary::messages::{
+    BusinessMessageReject, Heartbeat, Logon, Logout, NewOrderSingle, TestRequest,
+};
+use fix_rs::fixt::application::{
+    Application, ApplicationRunner, BusinessReject, DoNotSend, RejectLogon,
+};
+use fix_rs::fixt::engine::{
+    Connection, ConnectionTerminatedReason, Engine, EngineEvent, Listener, MessageRefusedReason,
+};
+use fix_rs::fixt::message::FIXTMessage;
+
+define_dictionary!(
+    BusinessMessageReject,
+    Heartbeat,
+    Logon,
+    Logout,
+    NewOrderSingle,
+    TestRequest,
+);
+
+#[derive(Default)]
+struct TestApplication {
+    username: Vec<u8>,
+    reject_logon: bool,
+    veto_test_requests: bool,
+    reject_orders: bool,
+    calls: Vec<String>,
+}
+
+impl Application for TestApplication {
+    type Message = MessageEnum;
+
+    fn on_logon_request(
+        &mut self,
+        _engine: &mut Engine,
+        _listener: Listener,
+        _connection: Connection,
+        logon: &Logon,
+        response: &mut Logon,
+    ) -> Result<(), RejectLogon> {
+        self.calls.push(String::from("on_logon_request"));
+        if self.reject_logon {
+            return Err(RejectLogon {
+                text: Some(b"Unknown user".to_vec()),
+            });
+        }
+
+        response.heart_bt_int = logon.heart_bt_int * 2;
+        Ok(())
+    }
+
+    fn on_logon(&mut self, _engine: &mut Engine, _connection: Connection) {
+        self.calls.push(String::from("on_logon"));
+    }
+
+    fn on_logout(
+        &mut self,
+        _engine: &mut Engine,
+        _connection: Connection,
+        _reason: &ConnectionTerminatedReason,
+    ) {
+        self.calls.push(String::from("on_logout"));
+    }
+
+    fn to_admin(
+        &mut self,
+        _connection: Connection,
+        message: &mut dyn FIXTMessage,
+    ) -> Result<(), DoNotSend> {
+        if let Some(logon) = message.as_any_mut().downcast_mut::<Logon>() {
+            logon.username = self.username.clone();
+        } else if self.veto_test_requests && message.as_any().is::<TestRequest>() {
+            return Err(DoNotSend);
+        }
+
+        Ok(())
+    }
+
+    fn from_admin(&mut self, _engine: &mut Engine, _connection: Connection, message: MessageEnum) {
+        if let MessageEnum::Logon(_) = message {
+            self.calls.push(String::from("from_admin(Logon)"));
+        }
+    }
+
+    fn from_app(
+        &mut self,
+        _engine: &mut Engine,
+        _connection: Connection,
+        message: MessageEnum,
+    ) -> Result<(), BusinessReject> {
+        if let MessageEnum::NewOrderSingle(_) = message {
+            self.calls.push(String::from("from_app(NewOrderSingle)"));
+            if self.reject_orders {
+                return Err(BusinessReject::new(
+                    BusinessRejectReason::NotAuthorized,
+                    b"Trading halted".to_vec(),
+                ));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn on_event(&mut self, _engine: &mut Engine, event: EngineEvent) {
+        if let EngineEvent::MessageRefused(_, _, _, MessageRefusedReason::Vetoed) = event {
+            self.calls.push(String::from("on_event(MessageRefused)"));
+        }
+    }
+}
+
+fn new_order_single() -> NewOrderSingle {
+    let mut new_order_single = new_fixt_message!(NewOrderSingle);
+    new_order_single.cl_ord_id = b"0".to_vec();
+    new_order_single.symbol = b"TEST".to_vec();
+    new_order_single.security_id = b"0".to_vec();
+    new_order_single.security_id_source = Some(SecurityIDSource::CUSIP);
+    new_order_single.side = Side::Buy;
+    new_order_single.transact_time = new_order_single.sending_time;
+    new_order_single.order_qty = b"1".to_vec();
+    new_order_single.ord_type = OrdType::Market;
+
+    new_order_single
+}
+
+fn poll_runner(runner: &mut ApplicationRunner<TestApplication>) {
+    assert!(runner.poll(Duration::from_secs(5)));
+}
+
+fn setup_runner_and_logon(
+    application: TestApplication,
+) -> (
+    TestStream,
+    ApplicationRunner<TestApplication>,
+    Connection,
+    Logon,
+) {
+    let (mut test_server, client, connection) = TestStream::setup_test_server(build_dictionary());
+    let mut runner = ApplicationRunner::new(client, application);
+
+    runner
+        .engine_mut()
+        .send_message(connection, new_logon_message());
+    let logon_message = test_server.recv_message::<Logon>();
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.encrypt_method = logon_message.encrypt_method.clone();
+    response_message.heart_bt_int = logon_message.heart_bt_int;
+    response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+    test_server.send_message(response_message);
+
+    //SessionEstablished followed by the Logon response itself.
+    poll_runner(&mut runner);
+    poll_runner(&mut runner);
+
+    (test_server, runner, connection, logon_message)
+}
+
+#[test]
+fn test_initiator_logon_and_logout() {
+    let application = TestApplication {
+        username: b"some_user".to_vec(),
+        ..Default::default()
+    };
+    let (mut test_server, mut runner, connection, logon_message) =
+        setup_runner_and_logon(application);
+
+    //to_admin() was able to fill in the Logon before it was sent.
+    assert_eq!(logon_message.username, b"some_user".to_vec());
+    assert_eq!(
+        runner.application().calls,
+        vec![String::from("on_logon"), String::from("from_admin(Logon)")]
+    );
+
+    //on_logout() is called once the established session is closed.
+    runner.engine_mut().logout(connection);
+    let _ = test_server.recv_message::<Logout>();
+    let mut logout_message = new_fixt_message!(Logout);
+    logout_message.msg_seq_num = 2;
+    test_server.send_message(logout_message);
+    poll_runner(&mut runner);
+    assert_eq!(runner.application().calls[2], String::from("on_logout"));
+}
+
+#[test]
+fn test_to_admin_vetoes_message() {
+    let application = TestApplication {
+        veto_test_requests: true,
+        ..Default::default()
+    };
+    let (mut test_server, mut runner, connection, _) = setup_runner_and_logon(application);
+
+    //Vetoed message is returned and doesn't use up a MsgSeqNum.
+    let mut test_request_message = new_fixt_message!(TestRequest);
+    test_request_message.test_req_id = b"1".to_vec();
+    runner
+        .engine_mut()
+        .send_message(connection, test_request_message);
+    runner
+        .engine_mut()
+        .send_message(connection, new_fixt_message!(Heartbeat));
+
+    let message = test_server.recv_message::<Heartbeat>();
+    assert_eq!(message.msg_seq_num, 2);
+
+    poll_runner(&mut runner);
+    assert_eq!(
+        runner.application().calls.last(),
+        Some(&String::from("on_event(MessageRefused)"))
+    );
+}
+
+#[test]
+fn test_from_app_rejects_message() {
+    let application = TestApplication {
+        reject_orders: true,
+        ..Default::default()
+    };
+    let (mut test_server, mut runner, _, _) = setup_runner_and_logon(application);
+
+    let mut message = new_order_single();
+    message.msg_seq_num = 2;
+    test_server.send_message(message);
+    poll_runner(&mut runner);
+    assert_eq!(
+        runner.application().calls.last(),
+        Some(&String::from("from_app(NewOrderSingle)"))
+    );
+
+    let message = test_server.recv_message::<BusinessMessageReject>();
+    assert_eq!(message.ref_seq_num, 2);
+    assert_eq!(message.ref_msg_type, b"D".to_vec());
+    assert_eq!(
+        message.business_reject_reason,
+        BusinessRejectReason::NotAuthorized
+    );
+    assert_eq!(message.text, b"Trading halted".to_vec());
+}
+
+fn send_logon_to_runner(test_client: &mut TestStream) {
+    let mut logon_message = new_logon_message();
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    test_client.send_message(logon_message);
+}
+
+#[test]
+fn test_acceptor_approves_logon() {
+    let (mut test_client, engine, _, _) = TestStream::setup_test_client(build_dictionary());
+    let mut runner = ApplicationRunner::new(engine, TestApplication::default());
+
+    send_logon_to_runner(&mut test_client);
+    poll_runner(&mut runner);
+    assert_eq!(
+        runner.application().calls,
+        vec![String::from("on_logon_request"), String::from("on_logon")]
+    );
+
+    //Response was adjusted by on_logon_request().
+    let message = test_client.recv_message::<Logon>();
+    assert_eq!(message.heart_bt_int, 10);
+}
+
+#[test]
+fn test_acceptor_rejects_logon() {
+    let application = TestApplication {
+        reject_logon: true,
+        ..Default::default()
+    };
+    let (mut test_client, engine, _, _) = TestStream::setup_test_client(build_dictionary());
+    let mut runner = ApplicationRunner::new(engine, application);
+
+    send_logon_to_runner(&mut test_client);
+    poll_runner(&mut runner);
+
+    let message = test_client.recv_message::<Logout>();
+    assert_eq!(message.text, b"Unknown user".to_vec());
+
+    //Session was never established so on_logout() is never called.
+    poll_runner(&mut runner);
+    assert_eq!(
+        runner.application().calls,
+        vec![String::from("on_logon_request")]
+    );
+}
@@ -0,0 +1,238 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use futures::executor::block_on;
+use futures::task::{self, ArcWake};
+use futures::{SinkExt, StreamExt};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::Context;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{new_logon_message, TestStream};
+use fix_rs::dictionary::messages::{Heartbeat, Logon, Logout, TestRequest};
+use fix_rs::fixt::async_engine::{AsyncConnection, AsyncEngine, ConnectionClosed, DeliveryError};
+use fix_rs::fixt::engine::{Connection, ConnectionTerminatedReason, EngineEvent};
+use fix_rs::fixt::message::FIXTMessage;
+
+fn setup_async_test_server() -> (TestStream, AsyncEngine, AsyncConnection) {
+    define_dictionary!(Heartbeat, Logon, Logout, TestRequest,);
+
+    let (test_server, client, connection) = TestStream::setup_test_server(build_dictionary());
+    let mut client = AsyncEngine::from_engine(client);
+    client.set_send_receipts(false);
+    let connection = client.connection(connection);
+
+    (test_server, client, connection)
+}
+
+fn respond_to_logon(test_server: &mut TestStream) {
+    let message = test_server.recv_message::<Logon>();
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.encrypt_method = message.encrypt_method;
+    response_message.heart_bt_int = message.heart_bt_int;
+    response_message.default_appl_ver_id = message.default_appl_ver_id;
+    test_server.send_message(response_message);
+}
+
+#[test]
+fn test_events_stream_and_established() {
+    let (mut test_server, mut client, connection) = setup_async_test_server();
+
+    //Logon is sent right away even though the session isn't established yet.
+    connection.send_message(new_logon_message()).unwrap();
+    respond_to_logon(&mut test_server);
+    block_on(connection.established()).unwrap();
+
+    //Events show up on the stream in the same order Engine::poll() would return them.
+    let event = block_on(client.next()).unwrap();
+    assert!(
+        if let EngineEvent::SessionEstablished(established_connection) = event {
+            established_connection == connection.connection()
+        } else {
+            false
+        }
+    );
+    let event = block_on(client.next()).unwrap();
+    assert!(if let EngineEvent::MessageReceived(_, message) = event {
+        message.as_any().is::<Logon>()
+    } else {
+        false
+    });
+
+    //Engine::poll() no longer returns anything because events go to the stream instead.
+    assert!(client
+        .engine_mut()
+        .poll(Duration::from_millis(100))
+        .is_none());
+}
+
+#[test]
+fn test_deliver_message_waits_for_established() {
+    let (mut test_server, _client, connection) = setup_async_test_server();
+
+    //Message is only queued once the session is established from another task.
+    let other_connection = connection.clone();
+    let handle = std::thread::spawn(move || {
+        let mut message = new_fixt_message!(TestRequest);
+        message.test_req_id = b"1".to_vec();
+        block_on(other_connection.deliver_message(message))
+    });
+
+    connection.send_message(new_logon_message()).unwrap();
+    respond_to_logon(&mut test_server);
+    handle.join().unwrap().unwrap();
+
+    let message = test_server.recv_message::<TestRequest>();
+    assert_eq!(message.msg_seq_num, 2);
+    assert_eq!(message.test_req_id, b"1".to_vec());
+}
+
+#[test]
+fn test_connection_as_sink() {
+    let (mut test_server, _client, mut connection) = setup_async_test_server();
+
+    let logon_message: Box<dyn FIXTMessage + Send> = Box::new(new_logon_message());
+    block_on(connection.send(logon_message)).unwrap();
+    respond_to_logon(&mut test_server);
+    block_on(connection.established()).unwrap();
+
+    let mut message = new_fixt_message!(TestRequest);
+    message.test_req_id = b"2".to_vec();
+    let message: Box<dyn FIXTMessage + Send> = Box::new(message);
+    block_on(connection.send(message)).unwrap();
+    let message = test_server.recv_message::<TestRequest>();
+    assert_eq!(message.test_req_id, b"2".to_vec());
+}
+
+#[test]
+fn test_established_fails_when_connection_closes() {
+    let (test_server, mut client, connection) = setup_async_test_server();
+
+    connection.send_message(new_logon_message()).unwrap();
+    drop(test_server);
+
+    assert_eq!(block_on(connection.established()), Err(ConnectionClosed));
+    assert_eq!(
+        connection.send_message(new_logon_message()),
+        Err(ConnectionClosed)
+    );
+
+    let event = block_on(client.next()).unwrap();
+    assert!(
+        if let EngineEvent::ConnectionTerminated(terminated_connection, reason, _) = event {
+            terminated_connection == connection.connection()
+                && !matches!(reason, ConnectionTerminatedReason::LocalRequested)
+        } else {
+            false
+        }
+    );
+
+    //New handles to the released connection are closed instead of waiting forever.
+    let connection = client.connection(connection.connection());
+    assert_eq!(block_on(connection.established()), Err(ConnectionClosed));
+}
+
+#[test]
+fn test_unknown_connection_is_closed() {
+    let (_test_server, client, _connection) = setup_async_test_server();
+
+    let connection = client.connection(Connection(100));
+    assert_eq!(block_on(connection.established()), Err(ConnectionClosed));
+    assert_eq!(
+        connection.send_message(new_logon_message()),
+        Err(ConnectionClosed)
+    );
+}
+
+#[test]
+fn test_poll_established_keeps_one_waker() {
+    struct CountingWaker(AtomicUsize);
+
+    impl ArcWake for CountingWaker {
+        fn wake_by_ref(arc_self: &Arc<Self>) {
+            arc_self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    let (mut test_server, _client, connection) = setup_async_test_server();
+
+    //Polling over and over with the same waker only wakes it once.
+    let counting_waker = Arc::new(CountingWaker(AtomicUsize::new(0)));
+    let waker = task::waker(counting_waker.clone());
+    let mut cx = Context::from_waker(&waker);
+    for _ in 0..3 {
+        assert!(connection.poll_established(&mut cx).is_pending());
+    }
+
+    connection.send_message(new_logon_message()).unwrap();
+    respond_to_logon(&mut test_server);
+    block_on(connection.established()).unwrap();
+    assert_eq!(counting_waker.0.load(Ordering::SeqCst), 1);
+}
+
+#[test]
+fn test_deliver_message_waits_until_sent() {
+    let (mut test_server, mut client, connection) = setup_async_test_server();
+    client.set_send_receipts(true);
+    client
+        .engine_mut()
+        .set_outbound_message_hook(Box::new(|_, message| !message.as_any().is::<Heartbeat>()));
+
+    connection.send_message(new_logon_message()).unwrap();
+    respond_to_logon(&mut test_server);
+
+    //Resolves only after the message is written to the socket.
+    let mut message = new_fixt_message!(TestRequest);
+    message.test_req_id = b"1".to_vec();
+    let message_id = block_on(connection.deliver_message(message)).unwrap();
+    let sent_message_id = loop {
+        if let EngineEvent::MessageSent(_, message_id, 2, _) = block_on(client.next()).unwrap() {
+            break message_id;
+        }
+    };
+    assert_eq!(sent_message_id, message_id);
+
+    //Fails when the message is refused instead.
+    assert_eq!(
+        block_on(connection.deliver_message(new_fixt_message!(Heartbeat))),
+        Err(DeliveryError::Refused)
+    );
+}
+
+#[test]
+fn test_sink_flush_fails_when_refused() {
+    let (mut test_server, mut client, mut connection) = setup_async_test_server();
+    client
+        .engine_mut()
+        .set_outbound_message_hook(Box::new(|_, message| !message.as_any().is::<Heartbeat>()));
+
+    let logon_message: Box<dyn FIXTMessage + Send> = Box::new(new_logon_message());
+    block_on(connection.send(logon_message)).unwrap();
+    respond_to_logon(&mut test_server);
+    block_on(connection.established()).unwrap();
+
+    let message: Box<dyn FIXTMessage + Send> = Box::new(new_fixt_message!(Heartbeat));
+    assert_eq!(
+        block_on(connection.send(message)),
+        Err(DeliveryError::Refused)
+    );
+
+    //Nothing is left to flush afterwards.
+    block_on(connection.flush()).unwrap();
+}
@@ -101,7 +101,7 @@ fn test_send_logout_before_logon() {
     assert!(test_server.is_stream_closed(Duration::from_secs(5)));
 
     //Confirm client notified that it disconnected.
-    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::LogonNotFirstMessageError = reason { true } else { false });
     });
@@ -140,7 +140,7 @@ fn test_recv_logout_with_high_msg_seq_num() {
 
     //Close connection and make sure client notifies that connection closed cleanly.
     let _ = test_server.stream.shutdown(Shutdown::Both);
-    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::RemoteRequested = reason { true } else { false });
     });
@@ -174,7 +174,7 @@ fn test_recv_logout_with_high_msg_seq_num_and_no_reply() {
 
     //Close connection and make sure client notifies that connection closed cleanly.
     let _ = test_server.stream.shutdown(Shutdown::Both);
-    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::RemoteRequested = reason { true } else { false });
     });
@@ -236,7 +236,7 @@ fn test_recv_logout_send_logout_recv_resend_request() {
 
     //Close connection and make sure client notifies that connection closed cleanly.
     let _ = test_server.stream.shutdown(Shutdown::Both);
-    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::RemoteRequested = reason { true } else { false });
     });
@@ -287,7 +287,7 @@ fn test_send_logout_and_recv_resend_request() {
     message.msg_seq_num = 3;
     test_server.send_message(message);
 
-    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::LocalRequested = reason { true } else { false });
     });
@@ -339,7 +339,7 @@ fn test_send_logout_and_recv_logout_with_high_msg_seq_num() {
     message.msg_seq_num = 16;
     test_server.send_message(message);
 
-    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::LocalRequested = reason { true } else { false });
     });
@@ -382,7 +382,7 @@ fn test_send_logout_and_recv_logout_with_high_msg_seq_num_and_no_reply() {
     //logout with an expected MsgSeqNum, then we saw a later MsgSeqNum once already and something
     //has gone terribly wrong. If the other sends a further out MsgSeqNum but won't reply to
     //ResendRequest then we're just going to keep looping.
-    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::LogoutNoResponseError = reason { true } else { false });
     });
@@ -426,7 +426,7 @@ fn test_wrong_sender_comp_id_in_logon_response() {
         assert_eq!(message.sender_comp_id,b"unknown".to_vec());
     });
 
-    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::SenderCompIDWrongError = reason { true } else { false });
     });
@@ -470,7 +470,7 @@ fn test_wrong_target_comp_id_in_logon_response() {
         assert_eq!(message.target_comp_id,b"unknown".to_vec());
     });
 
-    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::TargetCompIDWrongError = reason { true } else { false });
     });
@@ -1183,7 +1183,7 @@ fn test_logout_and_terminate_wrong_versioned_test_request_immediately_after_logo
         b"BeginStr is wrong, expected 'FIXT.1.1' but received 'FIX.4.2'".to_vec()
     );
 
-    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(
             if let ConnectionTerminatedReason::BeginStrWrongError{received,expected} = reason {
@@ -1289,7 +1289,7 @@ fn test_max_message_size() {
         test_server.send_message(response_message);
 
         //Make sure Engine just disconnects.
-        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::LogonParseError(parse_error) = reason {
                 if let ParseError::MessageSizeTooBig = parse_error { true } else { false }
@@ -1323,7 +1323,7 @@ fn test_block_read_when_write_blocks() {
             let mut client = client_clone.lock().unwrap();
             while let Some(event) = client.poll(Duration::from_secs(2)) {
                 match event {
-                    EngineEvent::ConnectionTerminated(_, _) => {
+                    EngineEvent::ConnectionTerminated(_, _, _) => {
                         panic!("Engine should not have terminated connection yet.")
                     }
                     _ => {}
@@ -1407,7 +1407,7 @@ fn test_block_read_when_write_blocks() {
                 panic!("Engine never disconnected.");
             }
 
-            if let Some(EngineEvent::ConnectionTerminated(_, reason)) =
+            if let Some(EngineEvent::ConnectionTerminated(_, reason, _)) =
                 client.poll(Duration::from_millis(0))
             {
                 assert!(
@@ -1498,7 +1498,7 @@ fn test_inbound_resend_loop_detection() {
         b"Detected ResendRequest loop for BeginSeqNo 2".to_vec()
     );
 
-    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::InboundResendRequestLoopError = reason { true } else { false });
     });
@@ -0,0 +1,188 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate chrono;
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use chrono::offset::Utc;
+use chrono::{DateTime, Duration as ChronoDuration, TimeZone};
+use mio::tcp::{TcpListener, TcpStream};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[macro_use]
+mod common;
+use crate::common::{advance_clock, new_logon_message, TestStream, SERVER_SENDER_COMP_ID};
+use fix_rs::dictionary::messages::{Heartbeat, Logon, Logout, TestRequest};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::clock::{Clock, ClockWaker, ManualClock};
+use fix_rs::fixt::engine::{ConnectionTerminatedReason, Engine, EngineEvent};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::message_version::MessageVersion;
+
+define_dictionary!(Heartbeat, Logon, Logout, TestRequest,);
+
+fn unused_addr() -> SocketAddr {
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let listener_socket = TcpListener::bind(&addr).unwrap();
+    listener_socket.local_addr().unwrap()
+}
+
+#[test]
+fn test_manual_clock() {
+    let start = Utc.with_ymd_and_hms(2017, 3, 10, 21, 59, 59).unwrap();
+    let clock = ManualClock::new_at(start);
+    let shared_clock = clock.clone();
+    let instant = clock.now();
+
+    clock.advance(Duration::from_millis(1500));
+    assert_eq!(shared_clock.elapsed(), Duration::from_millis(1500));
+    assert_eq!(shared_clock.now() - instant, Duration::from_millis(1500));
+    assert_eq!(
+        shared_clock.now_utc(),
+        start + ChronoDuration::milliseconds(1500)
+    );
+}
+
+#[test]
+fn test_sending_time_uses_clock() {
+    let start =
+        Utc.with_ymd_and_hms(2017, 3, 10, 21, 59, 59).unwrap() + ChronoDuration::milliseconds(123);
+    let clock = ManualClock::new_at(start);
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_with_ver_and_engine_setup(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            build_dictionary(),
+            |engine| engine.set_clock(Arc::new(clock.clone())),
+        );
+
+    client.send_message(connection, new_logon_message());
+    let message = test_server.recv_message::<Logon>();
+    assert_eq!(message.sending_time, start);
+
+    //Sub-millisecond precision is truncated because UtcTimestamp can't represent it.
+    advance_clock(
+        &client,
+        connection,
+        &clock,
+        Duration::from_secs(1) + Duration::from_micros(999),
+    );
+    let mut message = new_fixt_message!(TestRequest);
+    message.test_req_id = b"1".to_vec();
+    client.send_message(connection, message);
+    let message = test_server.recv_message::<TestRequest>();
+    assert_eq!(message.sending_time, start + ChronoDuration::seconds(1));
+}
+
+#[test]
+fn test_no_logon_timeout_uses_clock() {
+    let clock = ManualClock::new();
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    engine.set_clock(Arc::new(clock.clone()));
+    let addr = unused_addr();
+    engine
+        .add_listener(SERVER_SENDER_COMP_ID, addr)
+        .unwrap()
+        .unwrap();
+
+    let _stream = TcpStream::connect(&addr).unwrap();
+    let connection = engine_poll_event!(engine,EngineEvent::ConnectionAccepted(_,connection,_) => {
+        connection
+    });
+
+    //Nothing happens until the clock moves far enough.
+    advance_clock(&engine, connection, &clock, Duration::from_secs(9));
+    assert!(engine.poll(Duration::from_millis(250)).is_none());
+
+    advance_clock(&engine, connection, &clock, Duration::from_secs(1));
+    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
+        assert_eq!(terminated_connection,connection);
+        assert!(matches!(reason, ConnectionTerminatedReason::LogonNeverReceivedError));
+    });
+}
+
+#[test]
+fn test_logout_timeout_uses_clock() {
+    let clock = ManualClock::new();
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon_with_clock(build_dictionary(), &clock);
+
+    //Logout is never answered so the connection is dropped once the clock moves far enough.
+    client.logout(connection);
+    let _ = test_server.recv_message::<Logout>();
+    advance_clock(&client, connection, &clock, Duration::from_secs(10));
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
+        assert_eq!(terminated_connection,connection);
+        assert!(matches!(reason, ConnectionTerminatedReason::LogoutNoResponseError));
+    });
+}
+
+#[test]
+fn test_set_clock_keeps_remaining_time() {
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+
+    //Heartbeat timeout started using the system's clock now waits for the manual clock instead.
+    let clock = ManualClock::new();
+    client.set_clock(Arc::new(clock.clone()));
+    advance_clock(&client, connection, &clock, Duration::from_secs(1));
+    assert!(test_server
+        .try_recv_fixt_message(Duration::from_millis(250))
+        .is_none());
+
+    advance_clock(&client, connection, &clock, Duration::from_secs(4));
+    let message = test_server.recv_message::<Heartbeat>();
+    assert_eq!(message.msg_seq_num, 2);
+}
+
+#[test]
+fn test_clock_wakers_stop_once_engine_is_dropped() {
+    //Keeps every ClockWaker it's given so they can be checked later.
+    struct RecordingClock {
+        clock: ManualClock,
+        wakers: Mutex<Vec<ClockWaker>>,
+    }
+
+    impl Clock for RecordingClock {
+        fn now(&self) -> Instant {
+            self.clock.now()
+        }
+
+        fn now_utc(&self) -> DateTime<Utc> {
+            self.clock.now_utc()
+        }
+
+        fn is_real_time(&self) -> bool {
+            false
+        }
+
+        fn add_waker(&self, waker: ClockWaker) {
+            self.wakers.lock().unwrap().push(waker.clone());
+            self.clock.add_waker(waker);
+        }
+    }
+
+    let clock = Arc::new(RecordingClock {
+        clock: ManualClock::new(),
+        wakers: Mutex::new(Vec::new()),
+    });
+    let mut engine = Engine::new_with_worker_threads(build_dictionary(), 4096, 2).unwrap();
+    engine.set_clock(clock.clone());
+    drop(engine);
+
+    let wakers = clock.wakers.lock().unwrap();
+    assert_eq!(wakers.len(), 2);
+    assert!(wakers.iter().all(ClockWaker::is_dropped));
+}
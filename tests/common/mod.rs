@@ -19,9 +19,10 @@ use mio::unix::UnixReady;
 use mio::{Events, Poll, PollOpt, Ready, Token};
 use std::any::Any;
 use std::collections::HashMap;
-use std::io::Read;
+use std::io::{Read, Write};
 use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
 use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
 
@@ -31,6 +32,7 @@ use fix_rs::dictionary::messages::Logon;
 use fix_rs::dictionary::CloneDictionary;
 use fix_rs::fix::Parser;
 use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::clock::ManualClock;
 use fix_rs::fixt::engine::{Connection, Engine, EngineEvent, Listener};
 use fix_rs::fixt::message::{BuildFIXTMessage, FIXTMessage};
 use fix_rs::message_version::MessageVersion;
@@ -131,7 +133,8 @@ macro_rules! new_fixt_message {
         message.setup_fixt_session_header(
             Some(1),
             $crate::common::SERVER_SENDER_COMP_ID.to_vec(),
-            $crate::common::SERVER_TARGET_COMP_ID.to_vec()
+            $crate::common::SERVER_TARGET_COMP_ID.to_vec(),
+            fix_rs::dictionary::field_types::generic::UtcTimestampFieldType::new_now()
         );
 
         message
@@ -142,7 +145,8 @@ macro_rules! new_fixt_message {
         message.setup_fixt_session_header(
             Some(1),
             $crate::common::CLIENT_SENDER_COMP_ID.to_vec(),
-            $crate::common::CLIENT_TARGET_COMP_ID.to_vec()
+            $crate::common::CLIENT_TARGET_COMP_ID.to_vec(),
+            fix_rs::dictionary::field_types::generic::UtcTimestampFieldType::new_now()
         );
 
         message
@@ -162,6 +166,19 @@ pub fn new_logon_message() -> Logon {
     message
 }
 
+//Move clock forward once engine's worker thread is done with everything it was already doing for
+//connection. Otherwise, a timeout being started at the same time could be measured from after the
+//clock moved.
+pub fn advance_clock(
+    engine: &Engine,
+    connection: Connection,
+    clock: &ManualClock,
+    duration: Duration,
+) {
+    engine.sync(connection);
+    clock.advance(duration);
+}
+
 pub fn accept_with_timeout(listener: &TcpListener, timeout: Duration) -> Option<TcpStream> {
     let now = Instant::now();
 
@@ -176,7 +193,7 @@ pub fn accept_with_timeout(listener: &TcpListener, timeout: Duration) -> Option<
     None
 }
 
-pub fn recv_bytes_with_timeout(stream: &mut TcpStream, timeout: Duration) -> Option<Vec<u8>> {
+pub fn recv_bytes_with_timeout<S: Read>(stream: &mut S, timeout: Duration) -> Option<Vec<u8>> {
     let now = Instant::now();
 
     let mut buffer = Vec::new();
@@ -196,8 +213,8 @@ pub fn recv_bytes_with_timeout(stream: &mut TcpStream, timeout: Duration) -> Opt
     None
 }
 
-pub fn send_message_with_timeout(
-    stream: &mut TcpStream,
+pub fn send_message_with_timeout<S: Write>(
+    stream: &mut S,
     fix_version: FIXVersion,
     message_version: MessageVersion,
     message: Box<dyn FIXTMessage + Send>,
@@ -225,8 +242,8 @@ pub fn send_message_with_timeout(
     Ok(())
 }
 
-pub fn send_message(
-    stream: &mut TcpStream,
+pub fn send_message<S: Write>(
+    stream: &mut S,
     fix_version: FIXVersion,
     message_version: MessageVersion,
     message: Box<dyn FIXTMessage + Send>,
@@ -243,7 +260,7 @@ pub struct TestStream {
 }
 
 impl TestStream {
-    fn new(
+    pub fn new(
         fix_version: FIXVersion,
         message_version: MessageVersion,
         stream: TcpStream,
@@ -278,6 +295,20 @@ impl TestStream {
         fix_version: FIXVersion,
         message_version: MessageVersion,
         message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+    ) -> (TestStream, Engine, Connection) {
+        Self::setup_test_server_with_ver_and_engine_setup(
+            fix_version,
+            message_version,
+            message_dictionary,
+            |_| {},
+        )
+    }
+
+    pub fn setup_test_server_with_ver_and_engine_setup<F: FnOnce(&mut Engine)>(
+        fix_version: FIXVersion,
+        message_version: MessageVersion,
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+        engine_setup: F,
     ) -> (TestStream, Engine, Connection) {
         //Setup server listener socket.
         let addr = SocketAddr::V4(SocketAddrV4::new(
@@ -288,6 +319,9 @@ impl TestStream {
 
         //Setup client and connect to socket.
         let mut client = Engine::new(message_dictionary.clone(), MAX_MESSAGE_SIZE).unwrap();
+        //Most tests don't care about send receipts. Those that do turn them back on.
+        client.set_send_receipts(false);
+        engine_setup(&mut client);
         let connection = client
             .add_connection(
                 fix_version,
@@ -335,10 +369,41 @@ impl TestStream {
         fix_version: FIXVersion,
         message_version: MessageVersion,
         message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+    ) -> (TestStream, Engine, Connection) {
+        Self::setup_test_server_and_logon_with_ver_and_engine_setup(
+            fix_version,
+            message_version,
+            message_dictionary,
+            |_| {},
+        )
+    }
+
+    pub fn setup_test_server_and_logon_with_engine_setup<F: FnOnce(&mut Engine)>(
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+        engine_setup: F,
+    ) -> (TestStream, Engine, Connection) {
+        Self::setup_test_server_and_logon_with_ver_and_engine_setup(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            message_dictionary,
+            engine_setup,
+        )
+    }
+
+    pub fn setup_test_server_and_logon_with_ver_and_engine_setup<F: FnOnce(&mut Engine)>(
+        fix_version: FIXVersion,
+        message_version: MessageVersion,
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+        engine_setup: F,
     ) -> (TestStream, Engine, Connection) {
         //Connect.
         let (mut test_server, mut client, connection) =
-            Self::setup_test_server_with_ver(fix_version, message_version, message_dictionary);
+            Self::setup_test_server_with_ver_and_engine_setup(
+                fix_version,
+                message_version,
+                message_dictionary,
+                engine_setup,
+            );
         test_server
             .parser
             .set_default_message_version(MessageVersion::FIX50);
@@ -376,6 +441,17 @@ impl TestStream {
         (test_server, client, connection)
     }
 
+    //Same as setup_test_server_and_logon() except the engine uses clock.
+    pub fn setup_test_server_and_logon_with_clock(
+        message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
+        clock: &ManualClock,
+    ) -> (TestStream, Engine, Connection) {
+        let clock = clock.clone();
+        Self::setup_test_server_and_logon_with_engine_setup(message_dictionary, move |engine| {
+            engine.set_clock(Arc::new(clock))
+        })
+    }
+
     pub fn setup_test_server_and_logon(
         message_dictionary: HashMap<&'static [u8], Box<dyn BuildFIXTMessage + Send>>,
     ) -> (TestStream, Engine, Connection) {
@@ -397,6 +473,8 @@ impl TestStream {
             SOCKET_PORT.fetch_add(1, Ordering::SeqCst) as u16,
         ));
         let mut client = Engine::new(message_dictionary.clone(), MAX_MESSAGE_SIZE).unwrap();
+        //Most tests don't care about send receipts. Those that do turn them back on.
+        client.set_send_receipts(false);
         let listener = client
             .add_listener(SERVER_SENDER_COMP_ID, &addr)
             .unwrap()
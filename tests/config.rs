@@ -0,0 +1,395 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate chrono;
+extern crate chrono_tz;
+#[macro_use]
+extern crate fix_rs;
+
+use chrono::{NaiveTime, Weekday};
+use chrono_tz::America::New_York;
+use chrono_tz::UTC;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpListener};
+use std::path::PathBuf;
+use std::time::Duration;
+
+use fix_rs::dictionary::messages::{Heartbeat, Logon, Logout};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::config::{ConfigError, EngineConfig};
+use fix_rs::fixt::engine::EngineEvent;
+use fix_rs::fixt::reconnect_policy::ReconnectPolicy;
+use fix_rs::fixt::session_schedule::{ScheduleTime, SessionSchedule};
+use fix_rs::message_version::MessageVersion;
+
+fn assert_invalid(result: Result<EngineConfig, ConfigError>, expected: &str) {
+    match result {
+        Err(error @ ConfigError::Invalid { .. }) => assert_eq!(error.to_string(), expected),
+        Err(error) => panic!("Expected ConfigError::Invalid but got {:?}", error),
+        Ok(_) => panic!("Expected ConfigError::Invalid but config was accepted"),
+    }
+}
+
+fn unused_addr() -> SocketAddr {
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    TcpListener::bind(addr).unwrap().local_addr().unwrap()
+}
+
+#[test]
+fn test_toml_config() {
+    let config = EngineConfig::from_toml_str(
+        r#"
+        [engine]
+        max_message_size = 8192
+        seq_num_store = "store"
+
+        [[initiator]]
+        fix_version = "FIX.4.2"
+        sender_comp_id = "CLIENT"
+        target_comp_id = "SERVER"
+        address = "127.0.0.1:7001"
+        reset_on_logon = true
+        schedule = { start_time = "08:00", end_time = "17:00:00", start_day = "Monday", end_day = "Fri", time_zone = "America/New_York", reset_time = "07:00:00", reset_day = "Mon" }
+        reconnect = { initial_delay_ms = 100, max_delay_ms = 1000, failover_addresses = ["127.0.0.1:7002"] }
+
+        [[acceptor]]
+        sender_comp_id = "SERVER"
+        address = "127.0.0.1:7003"
+
+        [[acceptor.session]]
+        fix_version = "FIXT.1.1"
+        target_comp_id = "CLIENT"
+        target_sub_id = "DESK"
+        heart_bt_int = 10
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.max_message_size, 8192);
+    assert_eq!(config.seq_num_store, Some(PathBuf::from("store")));
+    assert_eq!(config.message_store, None);
+
+    assert_eq!(config.initiators.len(), 1);
+    let initiator = &config.initiators[0];
+    assert_eq!(initiator.fix_version, FIXVersion::FIX_4_2);
+    assert_eq!(initiator.default_message_version, MessageVersion::FIX42);
+    assert_eq!(initiator.sender_comp_id, b"CLIENT".to_vec());
+    assert_eq!(initiator.target_comp_id, b"SERVER".to_vec());
+    assert_eq!(initiator.address, "127.0.0.1:7001".parse().unwrap());
+    assert_eq!(initiator.heart_bt_int, 30);
+    assert!(initiator.reset_on_logon);
+    assert_eq!(
+        initiator.schedule,
+        Some(
+            SessionSchedule::weekly(
+                Weekday::Mon,
+                NaiveTime::from_hms_opt(8, 0, 0).unwrap(),
+                Weekday::Fri,
+                NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+                New_York
+            )
+            .with_reset_time(ScheduleTime::Weekly(
+                Weekday::Mon,
+                NaiveTime::from_hms_opt(7, 0, 0).unwrap()
+            ))
+        )
+    );
+    assert_eq!(
+        initiator.reconnect_policy,
+        Some(
+            ReconnectPolicy::new(Duration::from_millis(100), Duration::from_millis(1000))
+                .with_failover_address("127.0.0.1:7002".parse().unwrap())
+        )
+    );
+    assert!(initiator.tls.is_none());
+
+    assert_eq!(config.acceptors.len(), 1);
+    let acceptor = &config.acceptors[0];
+    assert_eq!(acceptor.sender_comp_id, b"SERVER".to_vec());
+    assert_eq!(acceptor.address, "127.0.0.1:7003".parse().unwrap());
+    assert_eq!(acceptor.sessions.len(), 1);
+    let session = &acceptor.sessions[0];
+    assert_eq!(session.fix_version, FIXVersion::FIXT_1_1);
+    assert_eq!(session.target_comp_id, b"CLIENT".to_vec());
+    assert_eq!(session.sender_sub_id, None);
+    assert_eq!(session.target_sub_id, Some(b"DESK".to_vec()));
+    assert_eq!(session.heart_bt_int, Some(10));
+}
+
+#[test]
+fn test_toml_config_errors() {
+    let initiator = |extra: &str| {
+        format!(
+            "[[initiator]]\nfix_version = \"FIXT.1.1\"\nsender_comp_id = \"CLIENT\"\ntarget_comp_id = \"SERVER\"\naddress = \"127.0.0.1:7001\"\n{}",
+            extra
+        )
+    };
+
+    //Misspelled keys are caught by the parser and point at the line they're on.
+    match EngineConfig::from_toml_str(&initiator("heartbeat = 30")) {
+        Err(ConfigError::Toml(error)) => {
+            let error = error.to_string();
+            assert!(error.contains("line 6"), "{}", error);
+            assert!(error.contains("unknown field `heartbeat`"), "{}", error);
+        }
+        result => panic!("Expected ConfigError::Toml but got {:?}", result.err()),
+    }
+
+    assert_invalid(
+        EngineConfig::from_toml_str(
+            &initiator("").replace("\"FIXT.1.1\"", "\"FIX.5.0\""),
+        ),
+        "initiator[0].fix_version: unknown FIX version \"FIX.5.0\", expected one of FIX.4.0, FIX.4.1, FIX.4.2, FIX.4.3, FIX.4.4, FIXT.1.1",
+    );
+    assert_invalid(
+        EngineConfig::from_toml_str(&initiator("default_appl_ver_id = \"FIX.6.0\"")),
+        "initiator[0].default_appl_ver_id: unknown ApplVerID \"FIX.6.0\", expected one of FIX.4.0, FIX.4.1, FIX.4.2, FIX.4.3, FIX.4.4, FIX.5.0, FIX.5.0SP1, FIX.5.0SP2",
+    );
+    assert_invalid(
+        EngineConfig::from_toml_str(&initiator("heart_bt_int = 0")),
+        "initiator[0].heart_bt_int: HeartBtInt must be greater than 0 but is 0",
+    );
+    assert_invalid(
+        EngineConfig::from_toml_str(&initiator(
+            "schedule = { start_time = \"8am\", end_time = \"17:00:00\" }",
+        )),
+        "initiator[0].schedule.start_time: invalid time \"8am\", expected HH:MM:SS",
+    );
+    assert_invalid(
+        EngineConfig::from_toml_str(&initiator(
+            "schedule = { start_time = \"08:00:00\", end_time = \"17:00:00\", time_zone = \"Mars/Olympus_Mons\" }",
+        )),
+        "initiator[0].schedule.time_zone: unknown time zone \"Mars/Olympus_Mons\", expected a name like \"America/New_York\"",
+    );
+    assert_invalid(
+        EngineConfig::from_toml_str(&initiator(
+            "schedule = { start_time = \"08:00:00\", end_time = \"17:00:00\", start_day = \"Monday\" }",
+        )),
+        "initiator[0].schedule: weekly schedule requires both a start day and an end day",
+    );
+    assert_invalid(
+        EngineConfig::from_toml_str(&initiator(
+            "reconnect = { initial_delay_ms = 1000, max_delay_ms = 100 }",
+        )),
+        "initiator[0].reconnect.max_delay_ms: must be greater than or equal to initial_delay_ms",
+    );
+    assert_invalid(
+        EngineConfig::from_toml_str(
+            "[[acceptor]]\nsender_comp_id = \"SERVER\"\naddress = \"127.0.0.1:7001\"\n[[acceptor.session]]\nfix_version = \"FIXT.1.1\"\ntarget_comp_id = \"\"\n",
+        ),
+        "acceptor[0].session[0].target_comp_id: must not be empty",
+    );
+}
+
+#[test]
+fn test_quickfix_import() {
+    let config = EngineConfig::from_quickfix_str(
+        r#"
+        # Shared by every session.
+        [DEFAULT]
+        BeginString=FIXT.1.1
+        DefaultApplVerID=FIX.5.0SP1
+        FileStorePath=store
+        StartTime=00:00:00
+        EndTime=00:00:00
+        HeartBtInt=20
+        SenderCompID=ME
+
+        [SESSION]
+        ConnectionType=initiator
+        TargetCompID=BROKER
+        SocketConnectHost=127.0.0.1
+        SocketConnectPort=7001
+        SocketConnectHost1=127.0.0.1
+        SocketConnectPort1=7002
+        ReconnectInterval=5
+        ResetOnLogon=Y
+        TimeZone=America/New_York
+
+        [SESSION]
+        ConnectionType=acceptor
+        TargetCompID=CLIENT1
+        SocketAcceptPort=7003
+
+        [SESSION]
+        ConnectionType=acceptor
+        BeginString=FIX.4.4
+        TargetCompID=CLIENT2
+        TargetSubID=DESK
+        SocketAcceptPort=7003
+        FileStorePath=client2_store
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(config.seq_num_store, Some(PathBuf::from("store")));
+    assert_eq!(config.message_store, Some(PathBuf::from("store")));
+
+    assert_eq!(config.initiators.len(), 1);
+    let initiator = &config.initiators[0];
+    assert_eq!(initiator.fix_version, FIXVersion::FIXT_1_1);
+    assert_eq!(initiator.default_message_version, MessageVersion::FIX50SP1);
+    assert_eq!(initiator.sender_comp_id, b"ME".to_vec());
+    assert_eq!(initiator.target_comp_id, b"BROKER".to_vec());
+    assert_eq!(initiator.address, "127.0.0.1:7001".parse().unwrap());
+    assert_eq!(initiator.heart_bt_int, 20);
+    assert!(initiator.reset_on_logon);
+    assert_eq!(
+        initiator.schedule,
+        Some(SessionSchedule::daily(
+            NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+            New_York
+        ))
+    );
+    assert_eq!(
+        initiator.reconnect_policy,
+        Some(
+            ReconnectPolicy::new(Duration::from_secs(5), Duration::from_secs(5))
+                .with_failover_address("127.0.0.1:7002".parse().unwrap())
+        )
+    );
+
+    //Both acceptor sessions share a listener.
+    assert_eq!(config.acceptors.len(), 1);
+    let acceptor = &config.acceptors[0];
+    assert_eq!(acceptor.sender_comp_id, b"ME".to_vec());
+    assert_eq!(acceptor.address, "0.0.0.0:7003".parse().unwrap());
+    assert_eq!(acceptor.sessions.len(), 2);
+    assert_eq!(acceptor.sessions[0].fix_version, FIXVersion::FIXT_1_1);
+    assert_eq!(acceptor.sessions[0].target_comp_id, b"CLIENT1".to_vec());
+    assert_eq!(acceptor.sessions[0].heart_bt_int, Some(20));
+    assert_eq!(
+        acceptor.sessions[0].schedule,
+        Some(SessionSchedule::daily(
+            NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+            NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+            UTC
+        ))
+    );
+    assert_eq!(acceptor.sessions[0].seq_num_store, None);
+    assert_eq!(acceptor.sessions[1].fix_version, FIXVersion::FIX_4_4);
+    assert_eq!(acceptor.sessions[1].target_sub_id, Some(b"DESK".to_vec()));
+    assert_eq!(
+        acceptor.sessions[1].seq_num_store,
+        Some(PathBuf::from("client2_store"))
+    );
+}
+
+#[test]
+fn test_quickfix_import_errors() {
+    assert_invalid(
+        EngineConfig::from_quickfix_str("[SESSION]\nConnectionType=initiator\n"),
+        "line 1: [SESSION]: BeginString is required",
+    );
+    assert_invalid(
+        EngineConfig::from_quickfix_str("BeginString=FIX.4.2\n"),
+        "line 1: BeginString appears before [DEFAULT] or [SESSION]",
+    );
+    assert_invalid(
+        EngineConfig::from_quickfix_str("[SESSIONS]\n"),
+        "line 1: unknown section [SESSIONS], expected [DEFAULT] or [SESSION]",
+    );
+    assert_invalid(
+        EngineConfig::from_quickfix_str(
+            "[DEFAULT]\nBeginString=FIX.4.2\nSenderCompID=ME\nTargetCompID=YOU\n\n[SESSION]\nConnectionType=server\n",
+        ),
+        "line 7: ConnectionType: expected initiator or acceptor but found \"server\"",
+    );
+    assert_invalid(
+        EngineConfig::from_quickfix_str(
+            "[SESSION]\nConnectionType=acceptor\nBeginString=FIX.4.2\nSenderCompID=ME\nTargetCompID=YOU\nSocketAcceptPort=7001\nHeartBtInt=thirty\n",
+        ),
+        "line 7: HeartBtInt: expected a number but found \"thirty\"",
+    );
+    assert_invalid(
+        EngineConfig::from_quickfix_str(
+            "[SESSION]\nConnectionType=acceptor\nBeginString=FIX.4.2\nSenderCompID=ME\nTargetCompID=YOU\nSocketAcceptPort=7001\nStartTime=08:00:00\nEndTime=25:00:00\n",
+        ),
+        "line 8: EndTime: invalid time \"25:00:00\", expected HH:MM:SS",
+    );
+}
+
+#[test]
+fn test_build_engine() {
+    define_dictionary!(Heartbeat, Logon, Logout,);
+
+    //Initiator logs on to an acceptor in the same Engine.
+    let addr = unused_addr();
+    let config = EngineConfig::from_toml_str(&format!(
+        r#"
+        [[initiator]]
+        fix_version = "FIXT.1.1"
+        sender_comp_id = "CLIENT"
+        target_comp_id = "SERVER"
+        address = "{addr}"
+        heart_bt_int = 5
+
+        [[acceptor]]
+        sender_comp_id = "SERVER"
+        address = "{addr}"
+
+        [[acceptor.session]]
+        fix_version = "FIXT.1.1"
+        target_comp_id = "CLIENT"
+        heart_bt_int = 5
+        "#,
+        addr = addr
+    ))
+    .unwrap();
+    let mut configured_engine = config.build(build_dictionary()).unwrap();
+    assert_eq!(configured_engine.connections.len(), 1);
+    assert_eq!(configured_engine.listeners.len(), 1);
+    let initiator_connection = configured_engine.connections[0];
+    let listener = configured_engine.listeners[0];
+
+    let engine = &mut configured_engine.engine;
+    loop {
+        match engine.poll(Duration::from_secs(5)) {
+            Some(EngineEvent::ConnectionLoggingOn(logon_listener, connection, logon)) => {
+                assert_eq!(logon_listener, listener);
+                assert_eq!(logon.heart_bt_int, 5);
+
+                let mut response = Logon::new();
+                response.encrypt_method = logon.encrypt_method.clone();
+                response.heart_bt_int = logon.heart_bt_int;
+                response.default_appl_ver_id = logon.default_appl_ver_id;
+                engine.approve_new_connection(connection, Box::new(response), None);
+            }
+            Some(EngineEvent::SessionEstablished(connection)) => {
+                assert_eq!(connection, initiator_connection);
+                break;
+            }
+            Some(EngineEvent::ConnectionSucceeded(_))
+            | Some(EngineEvent::ConnectionAccepted(_, _, _)) => {}
+            event => panic!("Unexpected event: {:?}", event),
+        }
+    }
+}
+
+#[test]
+fn test_build_engine_errors() {
+    define_dictionary!(Heartbeat, Logon, Logout,);
+
+    //Listener can't be created on an address that's already in use.
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let config = EngineConfig::from_toml_str(&format!(
+        "[[acceptor]]\nsender_comp_id = \"SERVER\"\naddress = \"{}\"\n",
+        listener.local_addr().unwrap()
+    ))
+    .unwrap();
+    match config.build(build_dictionary()) {
+        Err(error @ ConfigError::Build { .. }) => {
+            assert!(error.to_string().starts_with("acceptor[0].address: "))
+        }
+        Err(error) => panic!("Expected ConfigError::Build but got {:?}", error),
+        Ok(_) => panic!("Expected ConfigError::Build but engine was built"),
+    }
+}
@@ -0,0 +1,207 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+
+use std::sync::mpsc;
+use std::thread;
+use std::time::Duration;
+
+use fix_rs::dictionary::field_types::other::EncryptMethod;
+use fix_rs::dictionary::messages::{Heartbeat, Logon, Logout, ResendRequest, TestRequest};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{Engine, EngineEvent};
+use fix_rs::fixt::session_config::ConnectionConfig;
+use fix_rs::fixt::transport::LoopbackAddr;
+use fix_rs::message_version::MessageVersion;
+use fix_rs::testing::definition::{
+    Action, Definition, DefinitionError, DefinitionRunner, FieldMismatch, RunError, StepError,
+};
+
+define_dictionary!(Heartbeat, Logon, Logout, ResendRequest, TestRequest,);
+
+const LOGON_DEFINITION: &str = "
+# Logon and response
+iCONNECT
+I8=FIX.4.2|35=A|34=1|49=TW|52=<TIME>|56=ISLD|98=0|108=30|
+E8=FIX.4.2|9=57|35=A|34=1|49=ISLD|52=00000000-00:00:00|56=TW|98=0|108=30|383=4096|10=0|
+";
+
+//Run definition with runner on another thread while engine approves every Logon and logs on as
+//soon as it connects.
+fn run_with_engine(
+    mut engine: Engine,
+    mut runner: DefinitionRunner,
+    definition: Definition,
+) -> Result<(), RunError> {
+    let (result_sender, result_receiver) = mpsc::channel();
+    let runner_thread = thread::spawn(move || {
+        let _ = result_sender.send(runner.run(&definition));
+    });
+
+    let result = loop {
+        if let Ok(result) = result_receiver.try_recv() {
+            break result;
+        }
+
+        match engine.poll(Duration::from_millis(10)) {
+            Some(EngineEvent::ConnectionSucceeded(connection)) => {
+                let mut message = Logon::new();
+                message.encrypt_method = EncryptMethod::None;
+                message.heart_bt_int = 30;
+                message.default_appl_ver_id = MessageVersion::FIX42;
+                engine.send_message(connection, message);
+            }
+            Some(EngineEvent::ConnectionLoggingOn(_, connection, logon_message)) => {
+                let mut response_message = Logon::new();
+                response_message.encrypt_method = logon_message.encrypt_method.clone();
+                response_message.heart_bt_int = logon_message.heart_bt_int;
+                response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+                engine.approve_new_connection(connection, Box::new(response_message), None);
+            }
+            _ => {}
+        }
+    };
+    runner_thread.join().unwrap();
+
+    result
+}
+
+fn new_acceptor() -> (Engine, DefinitionRunner) {
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    let address = LoopbackAddr::new();
+    engine
+        .add_listener_with_transport(b"ISLD", address.clone())
+        .unwrap()
+        .unwrap();
+    let runner = DefinitionRunner::acceptor(address).with_timeout(Duration::from_secs(5));
+
+    (engine, runner)
+}
+
+#[test]
+fn test_parse_definition() {
+    let definition = Definition::parse(
+        "parse",
+        "# comment\n\
+         i1,CONNECT\n\
+         \n\
+         I1,8=FIX.4.2|35=0|\n\
+         E8=FIX.4.2\x0135=0\x01\n\
+         i2,CONNECT\n\
+         e2,DISCONNECT\n",
+    )
+    .unwrap();
+    assert_eq!(definition.name, "parse");
+    assert_eq!(definition.steps.len(), 5);
+    assert_eq!(definition.steps[0].line, 2);
+    assert_eq!(definition.steps[0].connection, 1);
+    assert_eq!(definition.steps[0].action, Action::Connect);
+    assert_eq!(
+        definition.steps[1].action,
+        Action::Send(b"8=FIX.4.2\x0135=0\x01".to_vec())
+    );
+    assert_eq!(
+        definition.steps[2].action,
+        Action::Expect(b"8=FIX.4.2\x0135=0\x01".to_vec())
+    );
+    assert_eq!(definition.steps[3].connection, 2);
+    assert_eq!(definition.steps[4].connection, 2);
+    assert_eq!(definition.steps[4].action, Action::ExpectDisconnect);
+
+    //Unknown commands are refused instead of silently skipped.
+    match Definition::parse("parse", "iCONNECT\niSLEEP\n") {
+        Err(DefinitionError::Parse { line, .. }) => assert_eq!(line, 2),
+        _ => panic!("unknown command was not refused"),
+    }
+}
+
+#[test]
+fn test_definition_against_acceptor() {
+    let (engine, runner) = new_acceptor();
+    let definition = Definition::parse(
+        "acceptor",
+        &format!(
+            "{}\
+             # Sequence number too high\n\
+             I8=FIX.4.2|35=1|34=5|49=TW|52=<TIME-1>|56=ISLD|112=HELLO|\n\
+             E8=FIX.4.2|9=61|35=2|34=2|49=ISLD|52=00000000-00:00:00|56=TW|7=2|16=0|10=0|\n",
+            LOGON_DEFINITION
+        ),
+    )
+    .unwrap();
+
+    run_with_engine(engine, runner, definition).unwrap();
+}
+
+#[test]
+fn test_definition_reports_mismatch() {
+    let (engine, runner) = new_acceptor();
+    let definition = Definition::parse(
+        "mismatch",
+        &LOGON_DEFINITION.replace("108=30|383", "108=60|383"),
+    )
+    .unwrap();
+
+    let error = run_with_engine(engine, runner, definition).unwrap_err();
+    assert_eq!(error.line, 5);
+    match error.error {
+        StepError::Mismatch { fields, .. } => assert_eq!(
+            fields,
+            vec![FieldMismatch {
+                tag: String::from("108"),
+                expected: Some(String::from("60")),
+                received: Some(String::from("30")),
+            }]
+        ),
+        e => panic!("unexpected error: {}", e),
+    }
+
+    //Ignored tags aren't compared at all.
+    let (engine, runner) = new_acceptor();
+    let definition = Definition::parse(
+        "ignored",
+        &LOGON_DEFINITION.replace("108=30|383", "108=60|383"),
+    )
+    .unwrap();
+    run_with_engine(engine, runner.with_ignored_tag(108), definition).unwrap();
+}
+
+#[test]
+fn test_definition_against_initiator() {
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    let address = LoopbackAddr::new();
+    let runner = DefinitionRunner::initiator(address.clone())
+        .unwrap()
+        .with_timeout(Duration::from_secs(5));
+    engine
+        .add_connection_with_config(ConnectionConfig::new(
+            FIXVersion::FIX_4_2,
+            MessageVersion::FIX42,
+            b"TW",
+            b"ISLD",
+            address,
+        ))
+        .unwrap();
+    let definition = Definition::parse(
+        "initiator",
+        "eCONNECT\n\
+         E8=FIX.4.2|35=A|34=1|49=TW|52=<TIME>|56=ISLD|98=0|108=30|383=4096|\n\
+         I8=FIX.4.2|35=A|34=1|49=ISLD|52=<TIME>|56=TW|98=0|108=30|\n\
+         I8=FIX.4.2|35=1|34=2|49=ISLD|52=<TIME>|56=TW|112=HELLO|\n\
+         E8=FIX.4.2|35=0|34=2|49=TW|52=<TIME>|56=ISLD|112=HELLO|\n\
+         iDISCONNECT\n",
+    )
+    .unwrap();
+
+    run_with_engine(engine, runner, definition).unwrap();
+}
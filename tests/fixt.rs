@@ -29,8 +29,8 @@ use std::time::Duration;
 #[macro_use]
 mod common;
 use crate::common::{
-    new_logon_message, recv_bytes_with_timeout, send_message, TestStream, CLIENT_SENDER_COMP_ID,
-    CLIENT_TARGET_COMP_ID, SERVER_SENDER_COMP_ID, SERVER_TARGET_COMP_ID,
+    advance_clock, new_logon_message, recv_bytes_with_timeout, send_message, TestStream,
+    CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID, SERVER_SENDER_COMP_ID, SERVER_TARGET_COMP_ID,
 };
 use fix_rs::dictionary::field_types::generic::{CharFieldType, NoneFieldType, StringFieldType};
 use fix_rs::dictionary::field_types::other::{
@@ -49,6 +49,7 @@ use fix_rs::field_tag::{self, FieldTag};
 use fix_rs::fix::ParseError;
 use fix_rs::fix_version::FIXVersion;
 use fix_rs::fixt;
+use fix_rs::fixt::clock::ManualClock;
 use fix_rs::fixt::engine::{
     Connection, ConnectionTerminatedReason, Engine, EngineEvent, Listener, ResendResponse,
 };
@@ -174,7 +175,7 @@ fn test_1B() {
         assert!(test_server.is_stream_closed(Duration::from_secs(5)));
 
         //Confirm client notified that it disconnected.
-        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::LogonHeartBtIntNegativeError = reason { true } else { false });
         });
@@ -206,7 +207,7 @@ fn test_1B() {
         assert!(test_server.is_stream_closed(Duration::from_secs(5)));
 
         //Confirm client notified that it disconnected.
-        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::LogonNotFirstMessageError = reason { true } else { false });
         });
@@ -340,7 +341,7 @@ fn test_1S() {
         assert!(test_client.is_stream_closed(Duration::from_secs(5)));
 
         //Confirm listener notified that it disconnected..
-        engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::LogonRejectedError = reason { true } else { false });
         });
@@ -365,7 +366,7 @@ fn test_1S() {
         assert!(test_client.is_stream_closed(Duration::from_secs(5)));
 
         //Confirm listener notified that it disconnected.
-        engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::LogonNotFirstMessageError = reason { true } else { false });
         });
@@ -503,7 +504,7 @@ fn test_2B() {
         assert!(test_server.is_stream_closed(Duration::from_secs(5)));
 
         //Confirm client notified that it disconnected.
-        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::InboundMsgSeqNumLowerThanExpectedError = reason { true } else { false });
         });
@@ -715,7 +716,7 @@ fn test_2B() {
             b"BeginStr is wrong, expected 'FIXT.1.1' but received 'FIX.4.2'".to_vec()
         );
 
-        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(
                 if let ConnectionTerminatedReason::BeginStrWrongError{received,expected} = reason {
@@ -779,7 +780,7 @@ fn test_2B() {
                 let _ = rejected_message.as_any().downcast_ref::<TestRequest>().expect("Not expected message type").clone();
             });
 
-            engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+            engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
                 assert_eq!(terminated_connection,connection);
                 assert!(if let ConnectionTerminatedReason::SenderCompIDWrongError = reason { true } else { false });
             });
@@ -818,7 +819,7 @@ fn test_2B() {
                 let _ = rejected_message.as_any().downcast_ref::<TestRequest>().expect("Not expected message type").clone();
             });
 
-            engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+            engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
                 assert_eq!(terminated_connection,connection);
                 assert!(if let ConnectionTerminatedReason::TargetCompIDWrongError = reason { true } else { false });
             });
@@ -948,11 +949,12 @@ fn test_4B() {
     //HeartBeatInt seconds.
     {
         //Connect and logon.
-        let (mut test_server, _client, _) =
-            TestStream::setup_test_server_and_logon(build_dictionary());
+        let clock = ManualClock::new();
+        let (mut test_server, client, connection) =
+            TestStream::setup_test_server_and_logon_with_clock(build_dictionary(), &clock);
 
-        //Sleep until Heartbeat is triggered.
-        thread::sleep(Duration::from_millis(5500));
+        //Move time forward until Heartbeat is triggered.
+        advance_clock(&client, connection, &clock, Duration::from_secs(5));
 
         //Make sure Heartbeat was sent by client.
         let _ = test_server.recv_message::<Heartbeat>();
@@ -962,11 +964,12 @@ fn test_4B() {
     //data is sent before HeartBeatInt seconds.
     {
         //Connect and logon.
+        let clock = ManualClock::new();
         let (mut test_server, mut client, connection) =
-            TestStream::setup_test_server_and_logon(build_dictionary());
+            TestStream::setup_test_server_and_logon_with_clock(build_dictionary(), &clock);
 
-        //Sleep for half the Heartbeat session.
-        thread::sleep(Duration::from_millis(2500));
+        //Move time forward for half the Heartbeat session.
+        advance_clock(&client, connection, &clock, Duration::from_millis(2500));
 
         //Send message to reset Engine's output heartbeat.
         let mut message = new_fixt_message!(TestRequest);
@@ -974,17 +977,16 @@ fn test_4B() {
         client.send_message(connection, message);
         let _ = test_server.recv_message::<TestRequest>();
 
-        //Sleep a little bit and make sure clienent sends a TestRequest because it didn't receive
-        //anything.
-        thread::sleep(Duration::from_millis(1000));
+        //Move time forward until client sends a TestRequest because it didn't receive anything.
+        advance_clock(&client, connection, &clock, Duration::from_millis(3500));
         let _ = test_server.recv_message::<TestRequest>();
 
-        //Sleep a little longer than the original heartbeat session.
-        thread::sleep(Duration::from_millis(2000));
+        //Move time forward a little longer than the original heartbeat session.
+        advance_clock(&client, connection, &clock, Duration::from_millis(2000));
 
         //Make sure Heartbeat was NOT sent by client.
         assert!(test_server
-            .try_recv_fixt_message(Duration::from_secs(1))
+            .try_recv_fixt_message(Duration::from_millis(250))
             .is_none());
     }
 
@@ -1036,11 +1038,12 @@ fn test_6B() {
     //and the client should make this confirmation.
     {
         //Connect and logon.
+        let clock = ManualClock::new();
         let (mut test_server, mut client, connection) =
-            TestStream::setup_test_server_and_logon(build_dictionary());
+            TestStream::setup_test_server_and_logon_with_clock(build_dictionary(), &clock);
 
-        //Sleep until TestRequest is triggered.
-        thread::sleep(Duration::from_millis(6000)); //1.2 * HeartBeatInt as stated.
+        //Move time forward until TestRequest is triggered.
+        advance_clock(&client, connection, &clock, Duration::from_secs(6)); //1.2 * HeartBeatInt as stated.
 
         //Ignore HeartBeat because Engine didn't send anything for HeartBeatInt seconds.
         let message = test_server.recv_message::<Heartbeat>();
@@ -1066,11 +1069,12 @@ fn test_6B() {
     //lost.
     {
         //Connect and logon.
+        let clock = ManualClock::new();
         let (mut test_server, mut client, connection) =
-            TestStream::setup_test_server_and_logon(build_dictionary());
+            TestStream::setup_test_server_and_logon_with_clock(build_dictionary(), &clock);
 
-        //Sleep until TestRequest is triggered.
-        thread::sleep(Duration::from_millis(6000)); //1.2 * HeartBeatInt as stated.
+        //Move time forward until TestRequest is triggered.
+        advance_clock(&client, connection, &clock, Duration::from_secs(6)); //1.2 * HeartBeatInt as stated.
 
         //Ignore HeartBeat because Engine didn't send anything for HeartBeatInt seconds.
         let _ = test_server.recv_message::<Heartbeat>();
@@ -1079,11 +1083,11 @@ fn test_6B() {
         let message = test_server.recv_message::<TestRequest>();
         assert_eq!(message.msg_seq_num, 3);
 
-        //Sleep until disconnect.
-        thread::sleep(Duration::from_millis(6000)); //1.2 * HeartBeatInt as stated.
+        //Move time forward until disconnect.
+        advance_clock(&client, connection, &clock, Duration::from_secs(6)); //1.2 * HeartBeatInt as stated.
 
         //Confirm client notified that it disconnected.
-        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::TestRequestNotRespondedError = reason { true } else { false });
         });
@@ -1131,11 +1135,12 @@ fn test_8B() {
     define_dictionary!(Logon, TestRequest, Heartbeat, ResendRequest, SequenceReset,);
 
     //Connect and logon.
+    let clock = ManualClock::new();
     let (mut test_server, mut client, connection) =
-        TestStream::setup_test_server_and_logon(build_dictionary());
+        TestStream::setup_test_server_and_logon_with_clock(build_dictionary(), &clock);
 
-    //Sleep until TestRequest and Heartbeat are triggered.
-    thread::sleep(Duration::from_millis(6000)); //1.2 * HeartBeatInt as stated.
+    //Move time forward until TestRequest and Heartbeat are triggered.
+    advance_clock(&client, connection, &clock, Duration::from_secs(6)); //1.2 * HeartBeatInt as stated.
 
     let _ = test_server.recv_message::<Heartbeat>();
     let _ = test_server.recv_message::<TestRequest>();
@@ -1293,7 +1298,7 @@ fn test_10B() {
         assert!(test_server.is_stream_closed(Duration::from_secs(5)));
 
         //Confirm client notified that it disconnected.
-        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::InboundMsgSeqNumLowerThanExpectedError = reason { true } else { false });
         });
@@ -1534,7 +1539,7 @@ fn test_12B() {
         assert!(test_server.is_stream_closed(Duration::from_secs(5)));
 
         //Confirm client notified that it disconnected.
-        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::LocalRequested = reason { true } else { false });
         });
@@ -1544,23 +1549,25 @@ fn test_12B() {
     //disconnect automatically after 10 seconds and issue a warning.
     {
         //Connect and Logon.
+        let clock = ManualClock::new();
         let (test_server, mut client, connection) =
-            TestStream::setup_test_server_and_logon(build_dictionary());
+            TestStream::setup_test_server_and_logon_with_clock(build_dictionary(), &clock);
 
         //Begin Logout.
         client.logout(connection);
 
         //Make sure socket isn't closed immediatelly.
-        assert!(!test_server.is_stream_closed(Duration::from_secs(1)));
+        advance_clock(&client, connection, &clock, Duration::from_secs(1));
+        assert!(!test_server.is_stream_closed(Duration::from_millis(100)));
 
-        //Wait for Logout to timeout.
-        thread::sleep(Duration::from_millis(9500));
+        //Move time forward until Logout times out.
+        advance_clock(&client, connection, &clock, Duration::from_millis(9500));
 
         //Confirm the client socket disconnected.
         assert!(test_server.is_stream_closed(Duration::from_secs(5)));
 
         //Confirm client notified that it disconnected.
-        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::LogoutNoResponseError = reason { true } else { false });
         });
@@ -1579,8 +1586,9 @@ fn test_13B() {
     //respond with a Logout message and wait for server to disconnect.
     {
         //Connect and Logon.
+        let clock = ManualClock::new();
         let (mut test_server, mut client, connection) =
-            TestStream::setup_test_server_and_logon(build_dictionary());
+            TestStream::setup_test_server_and_logon_with_clock(build_dictionary(), &clock);
 
         //Send Logout to client.
         let mut message = new_fixt_message!(Logout);
@@ -1591,12 +1599,11 @@ fn test_13B() {
         let _ = engine_poll_message!(client, connection, Logout);
         let _ = test_server.recv_message::<Logout>();
 
-        //Server disconnects and client should acknowledge that the connection has been closed.
-        //Since client isn't sending anymore data in this state, it can take up to 10 seconds to
-        //notice the shutdown.
+        //Server disconnects and client should acknowledge that the connection has been closed
+        //before it gives up waiting 10 seconds for the server to do so.
         let _ = test_server.stream.shutdown(Shutdown::Both);
-        thread::sleep(Duration::from_secs(6)); //6 seconds + the duration in engine_poll_event!() >= 10 seconds
-        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        advance_clock(&client, connection, &clock, Duration::from_secs(6));
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::RemoteRequested = reason { true } else { false });
         });
@@ -1606,8 +1613,9 @@ fn test_13B() {
     //10 seconds and issue an error.
     {
         //Connect and Logon.
+        let clock = ManualClock::new();
         let (mut test_server, mut client, connection) =
-            TestStream::setup_test_server_and_logon(build_dictionary());
+            TestStream::setup_test_server_and_logon_with_clock(build_dictionary(), &clock);
 
         //Send Logout to client.
         let mut message = new_fixt_message!(Logout);
@@ -1618,16 +1626,16 @@ fn test_13B() {
         //Engine should respond with a response Logout message.
         let _ = test_server.recv_message::<Logout>();
 
-        //Wait around a little bit and make sure client doesn't disconnect instantly.
-        thread::sleep(Duration::from_secs(5));
+        //Move time forward a little bit and make sure client doesn't disconnect instantly.
+        advance_clock(&client, connection, &clock, Duration::from_secs(5));
         assert!(!test_server.is_stream_closed(Duration::from_millis(100)));
 
-        //Wait around a little over the full 10 seconds and make sure client does force a
+        //Move time forward a little over the full 10 seconds and make sure client does force a
         //disconnect.
-        thread::sleep(Duration::from_millis(5500));
+        advance_clock(&client, connection, &clock, Duration::from_millis(5500));
         assert!(recv_bytes_with_timeout(&mut test_server.stream, Duration::from_secs(1)).is_none()); //Engine should have stopped sending TestRequests and Heartbeats!
         assert!(test_server.is_stream_closed(Duration::from_secs(1)));
-        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::LogoutNoHangUpError = reason { true } else { false });
         });
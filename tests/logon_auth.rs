@@ -0,0 +1,168 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use mio::tcp::{TcpListener, TcpStream};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{
+    new_logon_message, TestStream, CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID,
+    SERVER_SENDER_COMP_ID,
+};
+use fix_rs::dictionary::messages::{
+    Heartbeat, Logon, Logout, Reject, ResendRequest, SequenceReset, TestRequest,
+};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{
+    Connection, ConnectionTerminatedReason, Engine, EngineEvent, SessionID,
+};
+use fix_rs::fixt::logon_auth::{
+    logon_signature_payload, LogonAuthenticator, PasswordAuthenticator,
+};
+use fix_rs::message_version::MessageVersion;
+
+//Setup a listener using logon_authenticator and connect to it.
+fn setup_listener_with_authenticator(
+    logon_authenticator: Box<dyn LogonAuthenticator + Send>,
+) -> (TestStream, Engine, Connection) {
+    define_dictionary!(Logon, Logout,);
+
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let listener_socket = TcpListener::bind(&addr).unwrap();
+    let addr = listener_socket.local_addr().unwrap();
+    drop(listener_socket);
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    engine.set_logon_authenticator(logon_authenticator);
+    engine
+        .add_listener(SERVER_SENDER_COMP_ID, addr)
+        .unwrap()
+        .unwrap();
+
+    let stream = TcpStream::connect(&addr).unwrap();
+    let connection = engine_poll_event!(engine,EngineEvent::ConnectionAccepted(_,connection,_) => {
+        connection
+    });
+    let test_client = TestStream::new(
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        stream,
+        build_dictionary(),
+    );
+
+    (test_client, engine, connection)
+}
+
+fn new_client_logon_message(username: &[u8], password: &[u8]) -> Logon {
+    let mut logon_message = new_logon_message();
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    logon_message.username = username.to_vec();
+    logon_message.password = password.to_vec();
+    logon_message
+}
+
+#[test]
+fn test_password_authenticator_accepts_valid_credentials() {
+    let (mut test_client, mut engine, connection) = setup_listener_with_authenticator(Box::new(
+        PasswordAuthenticator::new().with_user(b"trader", b"secret"),
+    ));
+
+    test_client.send_message(new_client_logon_message(b"trader", b"secret"));
+    engine_poll_event!(engine,EngineEvent::ConnectionLoggingOn(_,logging_on_connection,logon) => {
+        assert_eq!(logging_on_connection,connection);
+        assert_eq!(logon.username,b"trader");
+    });
+}
+
+#[test]
+fn test_password_authenticator_rejects_invalid_credentials() {
+    for (username, password) in [(&b"trader"[..], &b"wrong"[..]), (b"nobody", b"secret")] {
+        let (mut test_client, mut engine, connection) = setup_listener_with_authenticator(
+            Box::new(PasswordAuthenticator::new().with_user(b"trader", b"secret")),
+        );
+
+        //Application never sees the Logon.
+        test_client.send_message(new_client_logon_message(username, password));
+        let message = test_client.recv_message::<Logout>();
+        assert_eq!(message.text, b"Invalid username or password");
+        engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
+            assert_eq!(terminated_connection,connection);
+            assert!(matches!(reason,ConnectionTerminatedReason::LogonAuthenticationError));
+        });
+    }
+}
+
+#[test]
+fn test_closure_authenticator_sees_session_id() {
+    let (mut test_client, _engine, _) = setup_listener_with_authenticator(Box::new(
+        |session_id: &SessionID, _: &Logon| -> Result<(), Vec<u8>> {
+            if session_id.target_comp_id == CLIENT_SENDER_COMP_ID {
+                Err(b"Go away".to_vec())
+            } else {
+                Ok(())
+            }
+        },
+    ));
+
+    test_client.send_message(new_client_logon_message(b"", b""));
+    let message = test_client.recv_message::<Logout>();
+    assert_eq!(message.text, b"Go away");
+}
+
+#[test]
+fn test_logon_decorator_fills_authentication_fields() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_with_ver_and_engine_setup(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            build_dictionary(),
+            |engine| {
+                engine.set_logon_decorator(Box::new(|_, logon: &mut Logon| {
+                    logon.username = b"trader".to_vec();
+                    logon.password = b"secret".to_vec();
+                    logon.raw_data = logon_signature_payload(logon);
+                }));
+            },
+        );
+    client.send_message(connection, new_logon_message());
+
+    //RawData was filled in using the final session header.
+    let message = test_server.recv_message::<Logon>();
+    assert_eq!(message.username, b"trader");
+    assert_eq!(message.password, b"secret");
+    assert_eq!(message.raw_data, logon_signature_payload(&message));
+    let fields: Vec<&[u8]> = message.raw_data.split(|byte| *byte == b'\x01').collect();
+    assert_eq!(
+        &fields[1..],
+        &[
+            &b"A"[..],
+            b"1",
+            CLIENT_SENDER_COMP_ID,
+            CLIENT_TARGET_COMP_ID
+        ]
+    );
+}
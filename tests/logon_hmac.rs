@@ -0,0 +1,114 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use mio::tcp::{TcpListener, TcpStream};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{
+    new_logon_message, TestStream, CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID,
+    SERVER_SENDER_COMP_ID,
+};
+use fix_rs::dictionary::messages::{
+    Heartbeat, Logon, Logout, Reject, ResendRequest, SequenceReset, TestRequest,
+};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{ConnectionTerminatedReason, Engine, EngineEvent};
+use fix_rs::fixt::logon_auth::{sign_logon, verify_logon_signature, HmacAuthenticator};
+use fix_rs::message_version::MessageVersion;
+
+const SECRET: &[u8] = b"api-secret";
+
+#[test]
+fn test_initiator_signs_logon() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_with_ver_and_engine_setup(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            build_dictionary(),
+            |engine| {
+                engine.set_logon_decorator(Box::new(|_, logon: &mut Logon| {
+                    sign_logon(SECRET, logon);
+                }));
+            },
+        );
+    client.send_message(connection, new_logon_message());
+
+    let mut message = test_server.recv_message::<Logon>();
+    assert_eq!(message.raw_data.len(), 64);
+    assert!(verify_logon_signature(SECRET, &message));
+    assert!(!verify_logon_signature(b"wrong-secret", &message));
+
+    //Signature covers the session header.
+    message.msg_seq_num += 1;
+    assert!(!verify_logon_signature(SECRET, &message));
+}
+
+#[test]
+fn test_acceptor_verifies_signed_logon() {
+    define_dictionary!(Logon, Logout,);
+
+    for (secret, accepted) in [(SECRET, true), (&b"wrong-secret"[..], false)] {
+        let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+        let listener_socket = TcpListener::bind(&addr).unwrap();
+        let addr = listener_socket.local_addr().unwrap();
+        drop(listener_socket);
+        let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+        engine.set_logon_authenticator(Box::new(
+            HmacAuthenticator::new().with_secret(CLIENT_SENDER_COMP_ID, SECRET),
+        ));
+        engine
+            .add_listener(SERVER_SENDER_COMP_ID, addr)
+            .unwrap()
+            .unwrap();
+
+        let stream = TcpStream::connect(&addr).unwrap();
+        engine_poll_event!(engine, EngineEvent::ConnectionAccepted(_, _, _) => {});
+        let mut test_client = TestStream::new(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            stream,
+            build_dictionary(),
+        );
+
+        let mut logon_message = new_logon_message();
+        logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+        logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+        sign_logon(secret, &mut logon_message);
+        test_client.send_message(logon_message);
+
+        if accepted {
+            engine_poll_event!(engine, EngineEvent::ConnectionLoggingOn(_, _, _) => {});
+        } else {
+            let message = test_client.recv_message::<Logout>();
+            assert_eq!(message.text, b"Invalid signature");
+            engine_poll_event!(engine,EngineEvent::ConnectionTerminated(_,reason,_) => {
+                assert!(matches!(reason,ConnectionTerminatedReason::LogonAuthenticationError));
+            });
+        }
+    }
+}
@@ -0,0 +1,181 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{new_logon_message, TestStream};
+use fix_rs::dictionary::field_types::other::{
+    BusinessRejectReason, MsgDirection, OrdType, SecurityIDSource, Side,
+};
+use fix_rs::dictionary::fields::MsgTypeGrp;
+use fix_rs::dictionary::messages::{
+    BusinessMessageReject, Heartbeat, Logon, NewOrderSingle, TestRequest,
+};
+use fix_rs::fixt::engine::{Connection, Engine, EngineEvent, MessageRefusedReason};
+use fix_rs::fixt::message::FIXTMessage;
+
+fn new_order_single() -> NewOrderSingle {
+    let mut new_order_single = new_fixt_message!(NewOrderSingle);
+    new_order_single.cl_ord_id = b"0".to_vec();
+    new_order_single.symbol = b"TEST".to_vec();
+    new_order_single.security_id = b"0".to_vec();
+    new_order_single.security_id_source = Some(SecurityIDSource::CUSIP);
+    new_order_single.side = Side::Buy;
+    new_order_single.transact_time = new_order_single.sending_time;
+    new_order_single.order_qty = b"1".to_vec();
+    new_order_single.ord_type = OrdType::Market;
+
+    new_order_single
+}
+
+fn new_msg_type_grp(msg_type: &[u8], msg_direction: MsgDirection) -> Box<MsgTypeGrp> {
+    let mut msg_type_grp = MsgTypeGrp::new();
+    msg_type_grp.ref_msg_type = msg_type.to_vec();
+    msg_type_grp.msg_direction = msg_direction;
+    Box::new(msg_type_grp)
+}
+
+//Connect and Logon where the remote's Logon response is adjusted first.
+fn setup_test_server_and_logon_with_response<F: FnOnce(&mut Logon)>(
+    response_setup: F,
+) -> (TestStream, Engine, Connection, Logon) {
+    define_dictionary!(
+        BusinessMessageReject,
+        Heartbeat,
+        Logon,
+        NewOrderSingle,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server(build_dictionary());
+    client.send_message(connection, new_logon_message());
+    let logon_message = test_server.recv_message::<Logon>();
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.encrypt_method = logon_message.encrypt_method.clone();
+    response_message.heart_bt_int = logon_message.heart_bt_int;
+    response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+    response_setup(&mut response_message);
+    test_server.send_message(response_message);
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    (test_server, client, connection, logon_message)
+}
+
+fn send_test_request_and_recv_heartbeat(test_server: &mut TestStream, msg_seq_num: u64) -> u64 {
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = msg_seq_num;
+    message.test_req_id = b"1".to_vec();
+    test_server.send_message(message);
+    test_server.recv_message::<Heartbeat>().msg_seq_num
+}
+
+#[test]
+fn test_logon_advertises_max_message_size() {
+    let (_, _, _, logon_message) = setup_test_server_and_logon_with_response(|_| {});
+    assert_eq!(logon_message.max_message_size, 4096);
+}
+
+#[test]
+fn test_refuse_message_larger_than_max_message_size() {
+    let (mut test_server, mut client, connection, _) =
+        setup_test_server_and_logon_with_response(|response_message| {
+            response_message.max_message_size = 256;
+        });
+
+    //Message is refused instead of being sent.
+    let mut message = new_order_single();
+    message.symbol = vec![b'A'; 256];
+    client.send_message(connection, message);
+    engine_poll_event!(client,EngineEvent::MessageRefused(refused_connection,_,message,reason) => {
+        assert_eq!(refused_connection,connection);
+        assert!(message.as_any().is::<NewOrderSingle>());
+        assert!(if let MessageRefusedReason::MaxMessageSizeExceeded { message_size, max_message_size } = reason {
+            message_size > 256 && max_message_size == 256
+        } else {
+            false
+        });
+    });
+
+    //Refused message didn't use up a MsgSeqNum.
+    assert_eq!(send_test_request_and_recv_heartbeat(&mut test_server, 2), 2);
+    let _ = engine_poll_message!(client, connection, TestRequest);
+
+    //Smaller messages are still sent.
+    client.send_message(connection, new_order_single());
+    let message = test_server.recv_message::<NewOrderSingle>();
+    assert_eq!(message.msg_seq_num, 3);
+
+    //Administrative messages are sent no matter their size.
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 3;
+    message.test_req_id = vec![b'1'; 256];
+    test_server.send_message(message);
+    let message = test_server.recv_message::<Heartbeat>();
+    assert_eq!(message.msg_seq_num, 4);
+    assert_eq!(message.test_req_id, vec![b'1'; 256]);
+}
+
+#[test]
+fn test_refuse_msg_type_remote_does_not_receive() {
+    let (mut test_server, mut client, connection, _) =
+        setup_test_server_and_logon_with_response(|response_message| {
+            response_message
+                .no_msg_types
+                .push(new_msg_type_grp(b"j", MsgDirection::Receive));
+        });
+
+    client.send_message(connection, new_order_single());
+    engine_poll_event!(client,EngineEvent::MessageRefused(refused_connection,_,message,MessageRefusedReason::MsgTypeNotSupported) => {
+        assert_eq!(refused_connection,connection);
+        assert!(message.as_any().is::<NewOrderSingle>());
+    });
+
+    //Administrative messages are never restricted.
+    assert_eq!(send_test_request_and_recv_heartbeat(&mut test_server, 2), 2);
+    let _ = engine_poll_message!(client, connection, TestRequest);
+}
+
+#[test]
+fn test_reject_msg_type_remote_does_not_send() {
+    let (mut test_server, mut client, connection, _) =
+        setup_test_server_and_logon_with_response(|response_message| {
+            response_message
+                .no_msg_types
+                .push(new_msg_type_grp(b"j", MsgDirection::Send));
+        });
+
+    let mut message = new_order_single();
+    message.msg_seq_num = 2;
+    test_server.send_message(message);
+    let message = test_server.recv_message::<BusinessMessageReject>();
+    assert_eq!(message.ref_seq_num, 2);
+    assert_eq!(message.ref_msg_type, b"D".to_vec());
+    assert_eq!(
+        message.business_reject_reason,
+        BusinessRejectReason::UnsupportedMessageType
+    );
+    engine_poll_event!(client,EngineEvent::MessageRejected(rejected_connection,message) => {
+        assert_eq!(rejected_connection,connection);
+        assert!(message.as_any().is::<NewOrderSingle>());
+    });
+
+    //Rejected message still counts towards the expected MsgSeqNum.
+    assert_eq!(send_test_request_and_recv_heartbeat(&mut test_server, 3), 3);
+}
@@ -0,0 +1,267 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate chrono;
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use chrono::{DateTime, Duration as ChronoDuration, TimeZone, Utc};
+use mio::tcp::{TcpListener, TcpStream};
+use std::env;
+use std::fs;
+use std::io::{self, Write};
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{
+    TestStream, CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID, SERVER_SENDER_COMP_ID,
+};
+use fix_rs::dictionary::messages::{Heartbeat, Logon, Logout, Reject, ResendRequest, TestRequest};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{Engine, EngineEvent, SessionID};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::message_log::{FileMessageLog, MessageDirection, MessageLog};
+use fix_rs::message_version::MessageVersion;
+
+type LogEntries = Arc<Mutex<Vec<(SessionID, MessageDirection, Vec<u8>)>>>;
+
+struct TestMessageLog {
+    entries: LogEntries,
+}
+
+impl MessageLog for TestMessageLog {
+    fn log(
+        &mut self,
+        session_id: &SessionID,
+        direction: MessageDirection,
+        _timestamp: &DateTime<Utc>,
+        bytes: &[u8],
+    ) -> io::Result<()> {
+        self.entries
+            .lock()
+            .unwrap()
+            .push((session_id.clone(), direction, bytes.to_vec()));
+        Ok(())
+    }
+}
+
+fn contains(bytes: &[u8], needle: &[u8]) -> bool {
+    bytes.windows(needle.len()).any(|window| window == needle)
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    let mut path = env::temp_dir();
+    path.push(format!(
+        "fix-rs-message-log-{}-{}",
+        name,
+        std::process::id()
+    ));
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+#[test]
+fn test_every_message_is_logged() {
+    define_dictionary!(Heartbeat, Logon, Logout, Reject, ResendRequest, TestRequest,);
+
+    let entries = LogEntries::default();
+    let log_entries = entries.clone();
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon_with_engine_setup(build_dictionary(), |engine| {
+            engine.set_message_log(Box::new(TestMessageLog {
+                entries: log_entries,
+            }));
+        });
+
+    //Heartbeat answering a TestRequest is sent automatically but still logged.
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 2;
+    message.test_req_id = b"1".to_vec();
+    test_server.send_message(message);
+    engine_poll_message!(client, connection, TestRequest);
+    let _ = test_server.recv_message::<Heartbeat>();
+
+    //So are garbled messages and the Reject sent in response.
+    let garbled_message = b"8=FIXT.1.1\x019=5\x0135=0\x0110=000\x01";
+    test_server.stream.write_all(garbled_message).unwrap();
+    engine_poll_event!(client,EngineEvent::MessageReceivedGarbled(garbled_connection,_) => {
+        assert_eq!(garbled_connection,connection);
+    });
+    let _ = test_server.recv_message::<Reject>();
+
+    let entries = entries.lock().unwrap();
+    let session_id = SessionID::new(
+        FIXVersion::FIXT_1_1,
+        CLIENT_SENDER_COMP_ID,
+        CLIENT_TARGET_COMP_ID,
+    );
+    assert_eq!(entries.len(), 6);
+    for (entry_session_id, _, bytes) in entries.iter() {
+        assert_eq!(*entry_session_id, session_id);
+        assert!(bytes.starts_with(b"8=FIXT.1.1\x01"));
+        assert!(bytes.ends_with(b"\x01"));
+    }
+    assert_eq!(entries[0].1, MessageDirection::Outbound);
+    assert!(contains(&entries[0].2, b"\x0135=A\x01"));
+    assert_eq!(entries[1].1, MessageDirection::Inbound);
+    assert!(contains(&entries[1].2, b"\x0135=A\x01"));
+    assert_eq!(entries[2].1, MessageDirection::Inbound);
+    assert!(contains(&entries[2].2, b"\x0135=1\x01"));
+    assert_eq!(entries[3].1, MessageDirection::Outbound);
+    assert!(contains(&entries[3].2, b"\x0135=0\x01"));
+    assert!(contains(&entries[3].2, b"\x01112=1\x01"));
+    assert_eq!(entries[4].1, MessageDirection::Inbound);
+    assert_eq!(entries[4].2, garbled_message.to_vec());
+    assert_eq!(entries[5].1, MessageDirection::Outbound);
+    assert!(contains(&entries[5].2, b"\x0135=3\x01"));
+}
+
+#[test]
+fn test_acceptor_logs_logon_under_remote_session() {
+    define_dictionary!(Heartbeat, Logon, Logout, Reject, ResendRequest, TestRequest,);
+
+    //Log must be in place before the Logon can arrive.
+    let entries = LogEntries::default();
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let listener_socket = TcpListener::bind(&addr).unwrap();
+    let addr = listener_socket.local_addr().unwrap();
+    drop(listener_socket);
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    engine.set_message_log(Box::new(TestMessageLog {
+        entries: entries.clone(),
+    }));
+    engine
+        .add_listener(SERVER_SENDER_COMP_ID, addr)
+        .unwrap()
+        .unwrap();
+
+    let stream = TcpStream::connect(&addr).unwrap();
+    let connection = engine_poll_event!(engine,EngineEvent::ConnectionAccepted(_,connection,_) => {
+        connection
+    });
+    let mut test_client = TestStream::new(
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        stream,
+        build_dictionary(),
+    );
+
+    let mut logon_message = common::new_logon_message();
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    test_client.send_message(logon_message);
+    engine_poll_event!(engine,EngineEvent::ConnectionLoggingOn(_,logging_on_connection,_) => {
+        assert_eq!(logging_on_connection,connection);
+    });
+
+    //Logon arrived before the remote was known but is still logged under its session.
+    let entries = entries.lock().unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(
+        entries[0].0,
+        SessionID::new(
+            FIXVersion::FIXT_1_1,
+            CLIENT_TARGET_COMP_ID,
+            CLIENT_SENDER_COMP_ID
+        )
+    );
+    assert_eq!(entries[0].1, MessageDirection::Inbound);
+}
+
+#[test]
+fn test_file_message_log_rotates() {
+    let directory = temp_dir("rotates");
+    let session_id = SessionID::new(
+        FIXVersion::FIXT_1_1,
+        CLIENT_SENDER_COMP_ID,
+        CLIENT_TARGET_COMP_ID,
+    );
+    let timestamp =
+        Utc.with_ymd_and_hms(2017, 1, 2, 3, 4, 5).unwrap() + ChronoDuration::milliseconds(6);
+    let message = b"8=FIXT.1.1\x019=5\x0135=0\x0110=161\x01";
+    let line_len = "20170102-03:04:05.006 : ".len() + message.len() + 1;
+
+    //Room for two lines per file and two backups.
+    let mut message_log = FileMessageLog::new(&directory)
+        .unwrap()
+        .with_max_file_size((line_len * 2) as u64)
+        .with_max_backup_files(2);
+    for _ in 0..7 {
+        message_log
+            .log(&session_id, MessageDirection::Outbound, &timestamp, message)
+            .unwrap();
+    }
+
+    let path = |suffix: &str| {
+        let mut path = directory.clone();
+        path.push(format!("{}.messages.log{}", session_id.file_stem(), suffix));
+        path
+    };
+    let mut expected_line = b"20170102-03:04:05.006 : ".to_vec();
+    expected_line.extend_from_slice(message);
+    expected_line.push(b'\n');
+    assert_eq!(fs::read(path("")).unwrap(), expected_line);
+    assert_eq!(fs::read(path(".1")).unwrap(), expected_line.repeat(2));
+    assert_eq!(fs::read(path(".2")).unwrap(), expected_line.repeat(2));
+    assert!(!path(".3").exists());
+
+    //Reopening continues where the current file left off.
+    let mut message_log = FileMessageLog::new(&directory)
+        .unwrap()
+        .with_max_file_size((line_len * 2) as u64)
+        .with_max_backup_files(2);
+    message_log
+        .log(&session_id, MessageDirection::Inbound, &timestamp, message)
+        .unwrap();
+    assert_eq!(fs::read(path("")).unwrap(), expected_line.repeat(2));
+
+    let _ = fs::remove_dir_all(&directory);
+}
+
+#[test]
+fn test_message_log_error_terminates_connection() {
+    define_dictionary!(Heartbeat, Logon, Logout, Reject, ResendRequest, TestRequest,);
+
+    struct FailingMessageLog;
+    impl MessageLog for FailingMessageLog {
+        fn log(
+            &mut self,
+            _session_id: &SessionID,
+            direction: MessageDirection,
+            _timestamp: &DateTime<Utc>,
+            bytes: &[u8],
+        ) -> io::Result<()> {
+            //Only fail once logged on so the failure is triggered by the Heartbeat below.
+            if direction == MessageDirection::Inbound && contains(bytes, b"\x0135=0\x01") {
+                return Err(io::Error::other("disk full"));
+            }
+            Ok(())
+        }
+    }
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon_with_engine_setup(build_dictionary(), |engine| {
+            engine.set_message_log(Box::new(FailingMessageLog));
+        });
+
+    let mut message = new_fixt_message!(Heartbeat);
+    message.msg_seq_num = 2;
+    test_server.send_message(message);
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
+        assert_eq!(terminated_connection,connection);
+        assert_eq!(format!("{:?}",reason),"Message log could not be written to: disk full");
+    });
+}
@@ -0,0 +1,285 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use std::env;
+use std::fs::{self, OpenOptions};
+use std::io::Write;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::TestStream;
+use fix_rs::byte_buffer::ByteBuffer;
+use fix_rs::dictionary::field_types::generic::UtcTimestampFieldType;
+use fix_rs::dictionary::field_types::other::{OrdType, SecurityIDSource, Side};
+use fix_rs::dictionary::messages::{
+    Heartbeat, Logon, NewOrderSingle, ResendRequest, SequenceReset, TestRequest,
+};
+use fix_rs::fix::Parser;
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{
+    EngineEvent, MessageRefusedReason, ResendRequestHandling, ResendResponse,
+};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::message_store::{
+    prepare_stored_message_for_resend, FileMessageStore, MemoryMessageStoreFactory, MessageStore,
+};
+use fix_rs::message::Message;
+use fix_rs::message_version::MessageVersion;
+
+fn new_order_single() -> NewOrderSingle {
+    let mut new_order_single = new_fixt_message!(FROM_CLIENT NewOrderSingle);
+    new_order_single.cl_ord_id = b"0".to_vec();
+    new_order_single.symbol = b"TEST".to_vec();
+    new_order_single.security_id = b"0".to_vec();
+    new_order_single.security_id_source = Some(SecurityIDSource::CUSIP);
+    new_order_single.side = Side::Buy;
+    new_order_single.transact_time = new_order_single.sending_time;
+    new_order_single.order_qty = b"1".to_vec();
+    new_order_single.ord_type = OrdType::Market;
+
+    new_order_single
+}
+
+#[test]
+fn test_resend_from_message_store() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        NewOrderSingle,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    //Connect and Logon with every outbound message being recorded.
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon_with_engine_setup(build_dictionary(), |engine| {
+            engine.set_message_store_factory(Box::new(MemoryMessageStoreFactory::new()));
+        });
+
+    //Send a business message and an administrative message.
+    client.send_message(connection, new_order_single());
+    let message = test_server.recv_message::<NewOrderSingle>();
+    assert_eq!(message.msg_seq_num, 2);
+    let original_sending_time = message.sending_time;
+
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 2;
+    message.test_req_id = b"1".to_vec();
+    test_server.send_message(message);
+    let _ = engine_poll_message!(client, connection, TestRequest);
+    let message = test_server.recv_message::<Heartbeat>();
+    assert_eq!(message.msg_seq_num, 3);
+
+    //Ask for everything to be resent. The Engine should handle it without involving the
+    //application.
+    let mut message = new_fixt_message!(ResendRequest);
+    message.msg_seq_num = 3;
+    message.begin_seq_no = 1;
+    message.end_seq_no = 0;
+    test_server.send_message(message);
+
+    //Logon is gap filled.
+    let message = test_server.recv_message::<SequenceReset>();
+    assert_eq!(message.msg_seq_num, 1);
+    assert!(message.gap_fill_flag);
+    assert!(message.poss_dup_flag);
+    assert_eq!(message.new_seq_no, 2);
+
+    //NewOrderSingle is resent as a possible duplicate.
+    let message = test_server.recv_message::<NewOrderSingle>();
+    assert_eq!(message.msg_seq_num, 2);
+    assert!(message.poss_dup_flag);
+    assert_eq!(message.orig_sending_time, original_sending_time);
+    assert!(message.sending_time >= original_sending_time);
+    assert_eq!(message.cl_ord_id, b"0".to_vec());
+
+    //Heartbeat is gap filled.
+    let message = test_server.recv_message::<SequenceReset>();
+    assert_eq!(message.msg_seq_num, 3);
+    assert!(message.gap_fill_flag);
+    assert_eq!(message.new_seq_no, 4);
+
+    let _ = engine_poll_message!(client, connection, ResendRequest);
+    engine_poll_no_event!(client);
+}
+
+#[test]
+fn test_vetoed_reset_seq_num_logon_keeps_message_store() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        NewOrderSingle,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon_with_engine_setup(build_dictionary(), |engine| {
+            engine.set_message_store_factory(Box::new(MemoryMessageStoreFactory::new()));
+        });
+    let veto_logon = Arc::new(AtomicBool::new(true));
+    let hook_veto_logon = veto_logon.clone();
+    client.set_outbound_message_hook(Box::new(move |_, message| {
+        !(hook_veto_logon.load(Ordering::SeqCst) && message.as_any().is::<Logon>())
+    }));
+
+    client.send_message(connection, new_order_single());
+    let message = test_server.recv_message::<NewOrderSingle>();
+    assert_eq!(message.msg_seq_num, 2);
+
+    //Refused Logon doesn't reset anything.
+    client.reset_sequence_numbers(connection);
+    engine_poll_event!(client, EngineEvent::MessageRefused(_, _, _, MessageRefusedReason::Vetoed) => {});
+    assert_eq!(
+        client
+            .session_info(connection)
+            .unwrap()
+            .next_msg_seq_nums
+            .outbound,
+        3
+    );
+
+    //Everything sent before the refused Logon can still be resent.
+    let mut message = new_fixt_message!(ResendRequest);
+    message.msg_seq_num = 2;
+    message.begin_seq_no = 1;
+    message.end_seq_no = 0;
+    test_server.send_message(message);
+    let message = test_server.recv_message::<SequenceReset>();
+    assert_eq!(message.msg_seq_num, 1);
+    assert_eq!(message.new_seq_no, 2);
+    let message = test_server.recv_message::<NewOrderSingle>();
+    assert_eq!(message.msg_seq_num, 2);
+    assert!(message.poss_dup_flag);
+    let _ = engine_poll_message!(client, connection, ResendRequest);
+
+    //Reset can still be tried again later.
+    veto_logon.store(false, Ordering::SeqCst);
+    client.reset_sequence_numbers(connection);
+    let message = test_server.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 1);
+    assert!(message.reset_seq_num_flag);
+}
+
+#[test]
+fn test_resend_request_handling_by_application() {
+    define_dictionary!(Logon, NewOrderSingle, ResendRequest, SequenceReset,);
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon_with_engine_setup(build_dictionary(), |engine| {
+            engine.set_message_store_factory(Box::new(MemoryMessageStoreFactory::new()));
+        });
+    client.set_resend_request_handling(connection, ResendRequestHandling::Application);
+
+    client.send_message(connection, new_order_single());
+    let _ = test_server.recv_message::<NewOrderSingle>();
+
+    //Application is asked to respond even though a message store exists.
+    let mut message = new_fixt_message!(ResendRequest);
+    message.msg_seq_num = 2;
+    message.begin_seq_no = 1;
+    message.end_seq_no = 0;
+    test_server.send_message(message);
+    engine_gap_fill_resend_request!(client, connection, 1..3);
+    let _ = engine_poll_message!(client, connection, ResendRequest);
+
+    let message = test_server.recv_message::<SequenceReset>();
+    assert_eq!(message.msg_seq_num, 1);
+    assert_eq!(message.new_seq_no, 3);
+}
+
+#[test]
+fn test_file_message_store_reopen() {
+    let mut path = env::temp_dir();
+    path.push(format!(
+        "fix-rs-message-store-{}.journal",
+        std::process::id()
+    ));
+    let _ = fs::remove_file(&path);
+
+    {
+        let mut message_store = FileMessageStore::open(path.clone()).unwrap();
+        message_store.store(1, b"A", b"first").unwrap();
+        message_store.store(2, b"D", b"second").unwrap();
+        message_store.store(4, b"D", b"fourth").unwrap();
+    }
+
+    //Simulate a crash in the middle of writing a record.
+    {
+        let mut file = OpenOptions::new().append(true).open(&path).unwrap();
+        file.write_all(&5u64.to_le_bytes()).unwrap();
+    }
+
+    {
+        let mut message_store = FileMessageStore::open(path.clone()).unwrap();
+        let messages = message_store.fetch(2..10).unwrap();
+        assert_eq!(messages.len(), 2);
+        assert_eq!(messages[0].msg_seq_num, 2);
+        assert_eq!(messages[0].msg_type, b"D".to_vec());
+        assert_eq!(messages[0].bytes, b"second".to_vec());
+        assert_eq!(messages[1].msg_seq_num, 4);
+        assert_eq!(messages[1].bytes, b"fourth".to_vec());
+
+        //Partial record was dropped so appending continues cleanly.
+        message_store.store(5, b"D", b"fifth").unwrap();
+        assert_eq!(
+            message_store.fetch(5..6).unwrap()[0].bytes,
+            b"fifth".to_vec()
+        );
+
+        message_store.reset().unwrap();
+        assert!(message_store.fetch(1..10).unwrap().is_empty());
+    }
+
+    let _ = fs::remove_file(&path);
+}
+
+#[test]
+fn test_prepare_stored_message_for_resend() {
+    define_dictionary!(NewOrderSingle,);
+
+    let mut message = new_order_single();
+    message.msg_seq_num = 7;
+    let mut bytes = ByteBuffer::new();
+    message.read(FIXVersion::FIXT_1_1, MessageVersion::FIX50SP2, &mut bytes);
+
+    let sending_time = UtcTimestampFieldType::new_now();
+    let resend_bytes = prepare_stored_message_for_resend(bytes.bytes(), &sending_time).unwrap();
+
+    //BodyLength and CheckSum must still be valid for the message to parse.
+    let mut parser = Parser::new(build_dictionary(), 4096);
+    parser.set_default_message_version(MessageVersion::FIX50SP2);
+    let (bytes_parsed, result) = parser.parse(&resend_bytes[..]);
+    assert!(result.is_ok());
+    assert_eq!(bytes_parsed, resend_bytes.len());
+
+    let resent_message = parser.messages.remove(0);
+    let resent_message = resent_message
+        .as_any()
+        .downcast_ref::<NewOrderSingle>()
+        .unwrap();
+    assert_eq!(resent_message.msg_seq_num, 7);
+    assert!(resent_message.poss_dup_flag);
+    assert_eq!(resent_message.orig_sending_time, message.sending_time);
+    assert_eq!(resent_message.sending_time, sending_time);
+
+    //Garbage can't be prepared.
+    assert!(prepare_stored_message_for_resend(b"not a message", &sending_time).is_none());
+}
@@ -0,0 +1,227 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{new_logon_message, TestStream, CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID};
+use fix_rs::dictionary::messages::{
+    Heartbeat, Logon, Logout, ResendRequest, SequenceReset, TestRequest,
+};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{
+    Connection, ConnectionTerminatedReason, Engine, EngineEvent, MsgSeqNums, SessionID,
+};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::message_store::MemoryMessageStoreFactory;
+use fix_rs::fixt::seq_num_store::{MemorySeqNumStore, SeqNumStore};
+use fix_rs::message_version::MessageVersion;
+
+fn setup_test_server_with_msg_seq_nums<F: FnOnce(&mut Engine)>(
+    msg_seq_nums: MsgSeqNums,
+    engine_setup: F,
+) -> (TestStream, Engine, Connection) {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let mut seq_num_store = MemorySeqNumStore::new();
+    seq_num_store
+        .save(
+            &SessionID::new(
+                FIXVersion::FIXT_1_1,
+                CLIENT_SENDER_COMP_ID,
+                CLIENT_TARGET_COMP_ID,
+            ),
+            msg_seq_nums,
+        )
+        .unwrap();
+    TestStream::setup_test_server_with_ver_and_engine_setup(
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        build_dictionary(),
+        |engine| {
+            engine.set_seq_num_store(Box::new(seq_num_store));
+            engine_setup(engine);
+        },
+    )
+}
+
+fn new_logon_response(logon_message: &Logon, msg_seq_num: u64, next_expected: u64) -> Logon {
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.msg_seq_num = msg_seq_num;
+    response_message.encrypt_method = logon_message.encrypt_method.clone();
+    response_message.heart_bt_int = logon_message.heart_bt_int;
+    response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+    response_message.next_expected_msg_seq_num = next_expected;
+    response_message
+}
+
+#[test]
+fn test_logon_includes_next_expected_msg_seq_num() {
+    let (mut test_server, mut client, connection) =
+        setup_test_server_with_msg_seq_nums(MsgSeqNums::new(7, 9), |_| {});
+
+    client.send_message(connection, new_logon_message());
+    let message = test_server.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 9);
+    assert_eq!(message.next_expected_msg_seq_num, 7);
+}
+
+#[test]
+fn test_gap_fill_from_next_expected_msg_seq_num() {
+    let (mut test_server, mut client, connection) =
+        setup_test_server_with_msg_seq_nums(MsgSeqNums::new(1, 4), |engine| {
+            engine.set_message_store_factory(Box::new(MemoryMessageStoreFactory::new()));
+        });
+
+    client.send_message(connection, new_logon_message());
+    let message = test_server.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 4);
+
+    //Remote never received 2 and 3. Nothing was recorded for them and the Logon is
+    //administrative so everything, including the Logon, is gap filled without a ResendRequest.
+    test_server.send_message(new_logon_response(&message, 1, 2));
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    let message = test_server.recv_message::<SequenceReset>();
+    assert_eq!(message.msg_seq_num, 2);
+    assert!(message.gap_fill_flag);
+    assert!(message.poss_dup_flag);
+    assert_eq!(message.new_seq_no, 5);
+    engine_poll_no_event!(client);
+}
+
+#[test]
+fn test_remote_recovers_after_logon_with_high_msg_seq_num() {
+    let (mut test_server, mut client, connection) =
+        setup_test_server_with_msg_seq_nums(MsgSeqNums::new(1, 1), |_| {});
+
+    client.send_message(connection, new_logon_message());
+    let message = test_server.recv_message::<Logon>();
+    assert_eq!(message.next_expected_msg_seq_num, 1);
+
+    //Remote knows what we're missing from our Logon so no ResendRequest is sent.
+    test_server.send_message(new_logon_response(&message, 10, 2));
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    let mut message = new_fixt_message!(SequenceReset);
+    message.msg_seq_num = 1;
+    message.gap_fill_flag = true;
+    message.new_seq_no = 11;
+    test_server.send_message(message);
+    let _ = engine_poll_message!(client, connection, SequenceReset);
+
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 11;
+    message.test_req_id = b"1".to_vec();
+    test_server.send_message(message);
+    let _ = engine_poll_message!(client, connection, TestRequest);
+    let message = test_server.recv_message::<Heartbeat>();
+    assert_eq!(message.msg_seq_num, 2);
+}
+
+#[test]
+fn test_next_expected_msg_seq_num_too_high() {
+    let (mut test_server, mut client, connection) =
+        setup_test_server_with_msg_seq_nums(MsgSeqNums::new(1, 1), |_| {});
+
+    client.send_message(connection, new_logon_message());
+    let message = test_server.recv_message::<Logon>();
+
+    //Remote expects messages that were never sent.
+    test_server.send_message(new_logon_response(&message, 1, 5));
+    let message = test_server.recv_message::<Logout>();
+    assert_eq!(
+        message.text,
+        b"NextExpectedMsgSeqNum too high, expected at most 2 but received 5".to_vec()
+    );
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,ConnectionTerminatedReason::LogonNextExpectedMsgSeqNumTooHighError,_) => {
+        assert_eq!(terminated_connection,connection);
+    });
+}
+
+#[test]
+fn test_acceptor_resends_from_next_expected_msg_seq_num() {
+    define_dictionary!(Logon, ResendRequest, SequenceReset,);
+
+    let (mut test_client, mut engine, _listener, connection) =
+        TestStream::setup_test_client(build_dictionary());
+
+    let mut logon_message = new_logon_message();
+    logon_message.msg_seq_num = 1;
+    logon_message.next_expected_msg_seq_num = 2;
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    test_client.send_message(logon_message);
+
+    engine_poll_event!(engine,EngineEvent::ConnectionLoggingOn(_,_,logon_message) => {
+        assert_eq!(logon_message.next_expected_msg_seq_num,2);
+
+        let mut response_message = new_fixt_message!(Logon);
+        response_message.encrypt_method = logon_message.encrypt_method.clone();
+        response_message.heart_bt_int = logon_message.heart_bt_int;
+        response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+        engine.approve_new_connection_with_msg_seq_nums(connection,Box::new(response_message),MsgSeqNums::new(1,4));
+    });
+    let message = test_client.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 4);
+    assert_eq!(message.next_expected_msg_seq_num, 2);
+
+    //Without a message store, the application is asked to fill in everything the remote missed
+    //before the Logon response.
+    engine_poll_event!(engine,EngineEvent::ResendRequested(resend_connection,range) => {
+        assert_eq!(resend_connection,connection);
+        assert_eq!(range,2..4);
+    });
+}
+
+#[test]
+fn test_acceptor_does_not_resend_when_remote_is_caught_up() {
+    define_dictionary!(Logon, ResendRequest, SequenceReset,);
+
+    let (mut test_client, mut engine, _listener, connection) =
+        TestStream::setup_test_client(build_dictionary());
+
+    let mut logon_message = new_logon_message();
+    logon_message.msg_seq_num = 1;
+    logon_message.next_expected_msg_seq_num = 1;
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    test_client.send_message(logon_message);
+
+    engine_poll_event!(engine,EngineEvent::ConnectionLoggingOn(_,_,logon_message) => {
+        let mut response_message = new_fixt_message!(Logon);
+        response_message.encrypt_method = logon_message.encrypt_method.clone();
+        response_message.heart_bt_int = logon_message.heart_bt_int;
+        response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+        engine.approve_new_connection(connection,Box::new(response_message),None);
+    });
+    let message = test_client.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 1);
+
+    //The Logon response is exactly what the remote expects next so nothing was missed.
+    engine_poll_no_event!(engine);
+}
@@ -105,6 +105,7 @@ impl FIXTMessage for LogonTest {
         _msg_seq_num: Option<<<MsgSeqNum as Field>::Type as FieldType>::Type>,
         _sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
         _target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+        _sending_time: <<SendingTime as Field>::Type as FieldType>::Type,
     ) {
         unimplemented!();
     }
@@ -539,6 +540,7 @@ fn length_tag_test() {
             _msg_seq_num: Option<<<MsgSeqNum as Field>::Type as FieldType>::Type>,
             _sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
             _target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+            _sending_time: <<SendingTime as Field>::Type as FieldType>::Type,
         ) {
             unimplemented!();
         }
@@ -672,6 +674,7 @@ fn repeating_groups_test() {
             _msg_seq_num: Option<<<MsgSeqNum as Field>::Type as FieldType>::Type>,
             _sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
             _target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+            _sending_time: <<SendingTime as Field>::Type as FieldType>::Type,
         ) {
             unimplemented!();
         }
@@ -985,6 +988,7 @@ fn nested_repeating_groups_test() {
             _msg_seq_num: Option<<<MsgSeqNum as Field>::Type as FieldType>::Type>,
             _sender_comp_id: <<SenderCompID as Field>::Type as FieldType>::Type,
             _target_comp_id: <<TargetCompID as Field>::Type as FieldType>::Type,
+            _sending_time: <<SendingTime as Field>::Type as FieldType>::Type,
         ) {
             unimplemented!();
         }
@@ -1181,3 +1185,46 @@ fn no_value_after_tag_test() {
         _ => assert!(false),
     }
 }
+
+#[test]
+fn raw_messages_test() {
+    define_dictionary!(LogonTest,);
+
+    let message = b"8=FIX.4.2\x019=65\x0135=L\x0149=SERVER\x0156=CLIENT\x0134=177\x0152=20090107-18:15:16\x0198=0\x01108=30\x0110=073\x01";
+    let garbled_message = b"8=FIX.4.2\x019=65\x0135=L\x0149=SERVER\x0156=CLIENT\x0134=177\x0152=20090107-18:15:16\x0198=0\x01108=30\x0110=000\x01";
+    let mut bytes = b"garbage".to_vec();
+    bytes.extend_from_slice(message);
+    bytes.extend_from_slice(garbled_message);
+    bytes.extend_from_slice(message);
+
+    //Raw bytes aren't kept unless asked for.
+    let mut parser = Parser::new(build_dictionary(), MAX_MESSAGE_SIZE);
+    let _ = parser.parse(message);
+    assert_eq!(parser.messages.len(), 1);
+    assert!(parser.raw_messages.is_empty());
+
+    //Exact bytes of each message are kept even when they're split across calls to parse().
+    for chunk_size in [1, 7, bytes.len()] {
+        let mut parser = Parser::new(build_dictionary(), MAX_MESSAGE_SIZE);
+        parser.set_capture_raw_messages(true);
+
+        let mut raw_garbled_messages = Vec::new();
+        for chunk in bytes.chunks(chunk_size) {
+            let mut chunk = chunk;
+            while !chunk.is_empty() {
+                let (bytes_read, result) = parser.parse(chunk);
+                if result.is_err() {
+                    raw_garbled_messages.push(parser.raw_garbled_message.clone());
+                }
+                chunk = &chunk[bytes_read..];
+            }
+        }
+
+        assert_eq!(parser.messages.len(), 2);
+        assert_eq!(
+            parser.raw_messages,
+            vec![message.to_vec(), message.to_vec()]
+        );
+        assert_eq!(raw_garbled_messages, vec![garbled_message.to_vec()]);
+    }
+}
@@ -0,0 +1,311 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use mio::tcp::TcpListener;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{
+    accept_with_timeout, new_logon_message, TestStream, CLIENT_SENDER_COMP_ID,
+    CLIENT_TARGET_COMP_ID,
+};
+use fix_rs::dictionary::messages::{Heartbeat, Logon, Logout, Reject};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{ConnectionTerminatedReason, Engine, EngineEvent, MsgSeqNums};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::reconnect_policy::ReconnectPolicy;
+use fix_rs::fixt::session_config::ConnectionConfig;
+use fix_rs::fixt::session_options::SessionOptions;
+use fix_rs::message_version::MessageVersion;
+
+fn local_listener() -> (TcpListener, SocketAddr) {
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let listener = TcpListener::bind(&addr).unwrap();
+    let addr = listener.local_addr().unwrap();
+    (listener, addr)
+}
+
+//Address that refuses connections.
+fn closed_addr() -> SocketAddr {
+    let (_, addr) = local_listener();
+    addr
+}
+
+//Accept the Engine's connection and respond to its Logon.
+fn accept_logon(listener: &TcpListener, inbound_msg_seq_num: u64) -> (TestStream, Logon) {
+    define_dictionary!(Heartbeat, Logon, Logout, Reject,);
+
+    let stream = accept_with_timeout(listener, Duration::from_secs(5)).unwrap();
+    let mut test_server = TestStream::new(
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        stream,
+        build_dictionary(),
+    );
+    let message = test_server.recv_message::<Logon>();
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.msg_seq_num = inbound_msg_seq_num;
+    response_message.encrypt_method = message.encrypt_method.clone();
+    response_message.heart_bt_int = message.heart_bt_int;
+    response_message.default_appl_ver_id = message.default_appl_ver_id;
+    test_server.send_message(response_message);
+
+    (test_server, message)
+}
+
+#[test]
+fn test_reconnect_policy_delay() {
+    let reconnect_policy =
+        ReconnectPolicy::new(Duration::from_millis(100), Duration::from_millis(1000));
+    assert_eq!(reconnect_policy.delay(1), Duration::from_millis(100));
+    assert_eq!(reconnect_policy.delay(2), Duration::from_millis(200));
+    assert_eq!(reconnect_policy.delay(4), Duration::from_millis(800));
+    assert_eq!(reconnect_policy.delay(5), Duration::from_millis(1000));
+    assert_eq!(reconnect_policy.delay(100), Duration::from_millis(1000));
+
+    let reconnect_policy = reconnect_policy.with_jitter(0.5);
+    for _ in 0..100 {
+        let delay = reconnect_policy.delay(2);
+        assert!(delay >= Duration::from_millis(100));
+        assert!(delay <= Duration::from_millis(300));
+    }
+}
+
+#[test]
+fn test_reconnect_after_disconnect() {
+    define_dictionary!(Heartbeat, Logon, Logout, Reject,);
+
+    let (listener, addr) = local_listener();
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    let connection = client
+        .add_connection_with_config(
+            ConnectionConfig::new(
+                FIXVersion::FIXT_1_1,
+                MessageVersion::FIX50SP2,
+                CLIENT_SENDER_COMP_ID,
+                CLIENT_TARGET_COMP_ID,
+                addr,
+            )
+            .with_reconnect_policy(ReconnectPolicy::new(
+                Duration::from_millis(100),
+                Duration::from_secs(1),
+            ))
+            .with_logon(Box::new(new_logon_message())),
+        )
+        .unwrap();
+
+    let (mut test_server, message) = accept_logon(&listener, 1);
+    assert_eq!(message.msg_seq_num, 1);
+    engine_poll_event!(client,EngineEvent::ConnectionSucceeded(_) => {});
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    //Force the session to end with an error.
+    let mut message = new_fixt_message!(Heartbeat);
+    message.msg_seq_num = 2;
+    message.sender_comp_id = b"unknown".to_vec();
+    test_server.send_message(message);
+    let _ = test_server.recv_message::<Reject>();
+    let _ = test_server.recv_message::<Logout>();
+    engine_poll_event!(client,EngineEvent::MessageRejected(_,_) => {});
+    engine_poll_event!(client,EngineEvent::SessionEnded(ended_connection,ConnectionTerminatedReason::SenderCompIDWrongError,msg_seq_nums) => {
+        assert_eq!(ended_connection,connection);
+        assert_eq!(msg_seq_nums,MsgSeqNums::new(2,4));
+    });
+    engine_poll_event!(client,EngineEvent::Reconnecting(reconnecting_connection,attempt,delay) => {
+        assert_eq!(reconnecting_connection,connection);
+        assert_eq!(attempt,1);
+        assert_eq!(delay,Duration::from_millis(100));
+    });
+
+    //Same Connection logs on again and continues where the session left off.
+    let (_test_server, message) = accept_logon(&listener, 2);
+    assert_eq!(message.msg_seq_num, 4);
+    engine_poll_event!(client,EngineEvent::ConnectionSucceeded(succeeded_connection) => {
+        assert_eq!(succeeded_connection,connection);
+    });
+    engine_poll_event!(client,EngineEvent::SessionEstablished(established_connection) => {
+        assert_eq!(established_connection,connection);
+    });
+}
+
+#[test]
+fn test_reconnect_to_failover_address() {
+    define_dictionary!(Heartbeat, Logon, Logout, Reject,);
+
+    let (failover_listener, failover_addr) = local_listener();
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    let connection = client
+        .add_connection_with_config(
+            ConnectionConfig::new(
+                FIXVersion::FIXT_1_1,
+                MessageVersion::FIX50SP2,
+                CLIENT_SENDER_COMP_ID,
+                CLIENT_TARGET_COMP_ID,
+                closed_addr(),
+            )
+            .with_reconnect_policy(
+                ReconnectPolicy::new(Duration::from_millis(50), Duration::from_millis(50))
+                    .with_failover_address(failover_addr),
+            )
+            .with_logon(Box::new(new_logon_message())),
+        )
+        .unwrap();
+
+    //Primary address is down so the failover address is tried next.
+    engine_poll_event!(client,EngineEvent::SessionEnded(ended_connection,ConnectionTerminatedReason::SocketConnectError(_),_) => {
+        assert_eq!(ended_connection,connection);
+    });
+    engine_poll_event!(client,EngineEvent::Reconnecting(_,1,_) => {});
+    let (_test_server, message) = accept_logon(&failover_listener, 1);
+    assert_eq!(message.msg_seq_num, 1);
+    engine_poll_event!(client,EngineEvent::ConnectionSucceeded(_) => {});
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+}
+
+#[test]
+fn test_reconnect_gives_up_after_max_attempts() {
+    define_dictionary!(Logon,);
+
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    let connection = client
+        .add_connection_with_config(
+            ConnectionConfig::new(
+                FIXVersion::FIXT_1_1,
+                MessageVersion::FIX50SP2,
+                CLIENT_SENDER_COMP_ID,
+                CLIENT_TARGET_COMP_ID,
+                closed_addr(),
+            )
+            .with_reconnect_policy(
+                ReconnectPolicy::new(Duration::from_millis(50), Duration::from_millis(50))
+                    .with_max_attempts(2),
+            )
+            .with_logon(Box::new(new_logon_message())),
+        )
+        .unwrap();
+
+    for expected_attempt in 1..3 {
+        engine_poll_event!(client,EngineEvent::SessionEnded(_,ConnectionTerminatedReason::SocketConnectError(_),_) => {});
+        engine_poll_event!(client,EngineEvent::Reconnecting(_,attempt,_) => {
+            assert_eq!(attempt,expected_attempt);
+        });
+    }
+
+    //Connection is released once the attempts run out.
+    engine_poll_event!(client,EngineEvent::ConnectionFailed(failed_connection,_) => {
+        assert_eq!(failed_connection,connection);
+    });
+    engine_poll_no_event!(client);
+}
+
+#[test]
+fn test_logout_while_waiting_to_reconnect() {
+    define_dictionary!(Logon,);
+
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    let connection = client
+        .add_connection_with_config(
+            ConnectionConfig::new(
+                FIXVersion::FIXT_1_1,
+                MessageVersion::FIX50SP2,
+                CLIENT_SENDER_COMP_ID,
+                CLIENT_TARGET_COMP_ID,
+                closed_addr(),
+            )
+            .with_reconnect_policy(ReconnectPolicy::new(
+                Duration::from_secs(60),
+                Duration::from_secs(60),
+            ))
+            .with_logon(Box::new(new_logon_message())),
+        )
+        .unwrap();
+    engine_poll_event!(client,EngineEvent::SessionEnded(_,_,_) => {});
+    engine_poll_event!(client,EngineEvent::Reconnecting(_,1,_) => {});
+
+    client.logout(connection);
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,ConnectionTerminatedReason::LocalRequested,_) => {
+        assert_eq!(terminated_connection,connection);
+    });
+}
+
+#[test]
+fn test_connection_config_without_reconnect_policy() {
+    define_dictionary!(Heartbeat, Logon, Logout, Reject,);
+
+    //Connection that resumes an existing session, uses its own SessionOptions and sends Logon
+    //automatically but never reconnects.
+    let (listener, addr) = local_listener();
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    let connection = client
+        .add_connection_with_config(
+            ConnectionConfig::new(
+                FIXVersion::FIXT_1_1,
+                MessageVersion::FIX50SP2,
+                CLIENT_SENDER_COMP_ID,
+                CLIENT_TARGET_COMP_ID,
+                addr,
+            )
+            .with_msg_seq_nums(MsgSeqNums::new(5, 10))
+            .with_session_options(
+                SessionOptions::new().with_logout_response_timeout(Duration::from_millis(100)),
+            )
+            .with_logon(Box::new(new_logon_message())),
+        )
+        .unwrap();
+
+    let (mut test_server, message) = accept_logon(&listener, 5);
+    assert_eq!(message.msg_seq_num, 10);
+    engine_poll_event!(client,EngineEvent::ConnectionSucceeded(_) => {});
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    //Respond to the remote's Logout and hang up after the shorter logout response timeout. The
+    //Connection is released for good once the session ends.
+    let mut message = new_fixt_message!(Logout);
+    message.msg_seq_num = 6;
+    test_server.send_message(message);
+    let _ = engine_poll_message!(client, connection, Logout);
+    let _ = test_server.recv_message::<Logout>();
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,ConnectionTerminatedReason::LogoutNoHangUpError,_) => {
+        assert_eq!(terminated_connection,connection);
+    });
+    assert!(client.session_info(connection).is_none());
+}
+
+#[test]
+fn test_connection_config_requires_logon_to_reconnect() {
+    define_dictionary!(Logon,);
+
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    assert!(client
+        .add_connection_with_config(
+            ConnectionConfig::new(
+                FIXVersion::FIXT_1_1,
+                MessageVersion::FIX50SP2,
+                CLIENT_SENDER_COMP_ID,
+                CLIENT_TARGET_COMP_ID,
+                closed_addr(),
+            )
+            .with_reconnect_policy(ReconnectPolicy::new(
+                Duration::from_secs(1),
+                Duration::from_secs(1)
+            )),
+        )
+        .is_none());
+}
@@ -0,0 +1,190 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{new_logon_message, TestStream, CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID};
+use fix_rs::dictionary::messages::{Heartbeat, Logon, TestRequest};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{EngineEvent, MsgSeqNums, SessionID};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::seq_num_store::{MemorySeqNumStore, SeqNumStore};
+use fix_rs::message_version::MessageVersion;
+
+fn send_test_request_and_recv_heartbeat(
+    test_stream: &mut TestStream,
+    mut message: TestRequest,
+    msg_seq_num: u64,
+) -> Heartbeat {
+    message.msg_seq_num = msg_seq_num;
+    message.test_req_id = b"1".to_vec();
+    test_stream.send_message(message);
+    test_stream.recv_message::<Heartbeat>()
+}
+
+#[test]
+fn test_initiator_logon_with_reset_seq_num_flag() {
+    define_dictionary!(Heartbeat, Logon, TestRequest,);
+
+    //Session would normally resume where it left off.
+    let mut seq_num_store = MemorySeqNumStore::new();
+    seq_num_store
+        .save(
+            &SessionID::new(
+                FIXVersion::FIXT_1_1,
+                CLIENT_SENDER_COMP_ID,
+                CLIENT_TARGET_COMP_ID,
+            ),
+            MsgSeqNums::new(7, 9),
+        )
+        .unwrap();
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_with_ver_and_engine_setup(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            build_dictionary(),
+            |engine| {
+                engine.set_seq_num_store(Box::new(seq_num_store));
+            },
+        );
+
+    //Logon with ResetSeqNumFlag starts over at 1 instead.
+    let mut logon_message = new_logon_message();
+    logon_message.reset_seq_num_flag = true;
+    client.send_message(connection, logon_message);
+    let message = test_server.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 1);
+    assert!(message.reset_seq_num_flag);
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.msg_seq_num = 1;
+    response_message.encrypt_method = message.encrypt_method;
+    response_message.heart_bt_int = message.heart_bt_int;
+    response_message.reset_seq_num_flag = true;
+    response_message.default_appl_ver_id = message.default_appl_ver_id;
+    test_server.send_message(response_message);
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    let message =
+        send_test_request_and_recv_heartbeat(&mut test_server, new_fixt_message!(TestRequest), 2);
+    assert_eq!(message.msg_seq_num, 2);
+    let _ = engine_poll_message!(client, connection, TestRequest);
+}
+
+#[test]
+fn test_acceptor_echoes_reset_seq_num_flag() {
+    define_dictionary!(Heartbeat, Logon, TestRequest,);
+
+    let (mut test_client, mut engine, _listener, connection) =
+        TestStream::setup_test_client(build_dictionary());
+
+    let mut logon_message = new_logon_message();
+    logon_message.msg_seq_num = 1;
+    logon_message.reset_seq_num_flag = true;
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    test_client.send_message(logon_message);
+
+    //ResetSeqNumFlag overrides whatever MsgSeqNums the session would otherwise resume at.
+    engine_poll_event!(engine,EngineEvent::ConnectionLoggingOn(_,_,logon_message) => {
+        assert!(logon_message.reset_seq_num_flag);
+
+        let mut response_message = new_fixt_message!(Logon);
+        response_message.encrypt_method = logon_message.encrypt_method.clone();
+        response_message.heart_bt_int = logon_message.heart_bt_int;
+        response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+        engine.approve_new_connection_with_msg_seq_nums(connection,Box::new(response_message),MsgSeqNums::new(5,10));
+    });
+    let message = test_client.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 1);
+    assert!(message.reset_seq_num_flag);
+
+    let message = send_test_request_and_recv_heartbeat(
+        &mut test_client,
+        new_fixt_message!(FROM_CLIENT TestRequest),
+        2,
+    );
+    assert_eq!(message.msg_seq_num, 2);
+}
+
+#[test]
+fn test_reset_sequence_numbers_mid_session() {
+    define_dictionary!(Heartbeat, Logon, TestRequest,);
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+
+    let message =
+        send_test_request_and_recv_heartbeat(&mut test_server, new_fixt_message!(TestRequest), 2);
+    assert_eq!(message.msg_seq_num, 2);
+    let _ = engine_poll_message!(client, connection, TestRequest);
+
+    //Local side asks for the reset.
+    client.reset_sequence_numbers(connection);
+    let message = test_server.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 1);
+    assert!(message.reset_seq_num_flag);
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.msg_seq_num = 1;
+    response_message.encrypt_method = message.encrypt_method;
+    response_message.heart_bt_int = message.heart_bt_int;
+    response_message.reset_seq_num_flag = true;
+    response_message.default_appl_ver_id = message.default_appl_ver_id;
+    test_server.send_message(response_message);
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    let message =
+        send_test_request_and_recv_heartbeat(&mut test_server, new_fixt_message!(TestRequest), 2);
+    assert_eq!(message.msg_seq_num, 2);
+    let _ = engine_poll_message!(client, connection, TestRequest);
+    engine_poll_no_event!(client);
+}
+
+#[test]
+fn test_remote_resets_sequence_numbers_mid_session() {
+    define_dictionary!(Heartbeat, Logon, TestRequest,);
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+
+    let message =
+        send_test_request_and_recv_heartbeat(&mut test_server, new_fixt_message!(TestRequest), 2);
+    assert_eq!(message.msg_seq_num, 2);
+    let _ = engine_poll_message!(client, connection, TestRequest);
+
+    //Remote asks for the reset even though its MsgSeqNum would normally be too low.
+    let mut message = new_logon_message();
+    message.msg_seq_num = 1;
+    message.reset_seq_num_flag = true;
+    test_server.send_message(message);
+    let message = engine_poll_message!(client, connection, Logon);
+    assert!(message.reset_seq_num_flag);
+
+    //Reset is acknowledged with the same.
+    let message = test_server.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 1);
+    assert!(message.reset_seq_num_flag);
+    assert_eq!(message.heart_bt_int, 5);
+
+    let message =
+        send_test_request_and_recv_heartbeat(&mut test_server, new_fixt_message!(TestRequest), 2);
+    assert_eq!(message.msg_seq_num, 2);
+    let _ = engine_poll_message!(client, connection, TestRequest);
+    engine_poll_no_event!(client);
+}
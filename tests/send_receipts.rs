@@ -0,0 +1,237 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::TestStream;
+use fix_rs::dictionary::field_types::other::{OrdType, SecurityIDSource, Side};
+use fix_rs::dictionary::messages::{
+    Heartbeat, Logon, Logout, NewOrderSingle, Reject, ResendRequest, SequenceReset, TestRequest,
+};
+use fix_rs::fixt::engine::{Connection, Engine, EngineEvent, MessageId};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::throttle::Throttle;
+
+fn new_order_single(cl_ord_id: &[u8]) -> NewOrderSingle {
+    let mut new_order_single = new_fixt_message!(FROM_CLIENT NewOrderSingle);
+    new_order_single.cl_ord_id = cl_ord_id.to_vec();
+    new_order_single.symbol = b"TEST".to_vec();
+    new_order_single.security_id = b"0".to_vec();
+    new_order_single.security_id_source = Some(SecurityIDSource::CUSIP);
+    new_order_single.side = Side::Buy;
+    new_order_single.transact_time = new_order_single.sending_time;
+    new_order_single.order_qty = b"1".to_vec();
+    new_order_single.ord_type = OrdType::Market;
+
+    new_order_single
+}
+
+#[test]
+fn test_message_sent() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        NewOrderSingle,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+    client.set_send_receipts(true);
+
+    let first_message_id = client.send_message(connection, new_order_single(b"1"));
+    let second_message_id = client.send_message(connection, new_order_single(b"2"));
+    assert!(first_message_id < second_message_id);
+
+    for (message_id, cl_ord_id) in [(first_message_id, b"1"), (second_message_id, b"2")] {
+        let message = test_server.recv_message::<NewOrderSingle>();
+        assert_eq!(message.cl_ord_id, cl_ord_id);
+        engine_poll_event!(client,EngineEvent::MessageSent(sent_connection,sent_message_id,msg_seq_num,sending_time) => {
+            assert_eq!(sent_connection,connection);
+            assert_eq!(sent_message_id,message_id);
+            assert_eq!(msg_seq_num,message.msg_seq_num);
+            assert_eq!(sending_time,message.sending_time);
+        });
+    }
+
+    //Messages generated by the engine don't have receipts.
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 2;
+    message.test_req_id = b"1".to_vec();
+    test_server.send_message(message);
+    engine_poll_message!(client, connection, TestRequest);
+    let _ = test_server.recv_message::<Heartbeat>();
+    engine_poll_no_event!(client);
+}
+
+#[test]
+fn test_message_not_sent_on_logout() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        NewOrderSingle,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+    client.set_send_receipts(true);
+    client.set_throttle(connection, Throttle::new(1, Duration::from_secs(60)));
+
+    //Second message is held back by the throttle long enough to be dropped by the Logout.
+    let first_message_id = client.send_message(connection, new_order_single(b"1"));
+    let second_message_id = client.send_message(connection, new_order_single(b"2"));
+    let _ = test_server.recv_message::<NewOrderSingle>();
+    engine_poll_event!(client,EngineEvent::MessageSent(_,message_id,_,_) => {
+        assert_eq!(message_id,first_message_id);
+    });
+    engine_poll_event!(client, EngineEvent::Throttled(_, _) => {});
+
+    client.logout(connection);
+    let _ = test_server.recv_message::<Logout>();
+    engine_poll_event!(client,EngineEvent::MessageNotSent(not_sent_connection,message_id,message) => {
+        assert_eq!(not_sent_connection,connection);
+        assert_eq!(message_id,second_message_id);
+        let message = message.as_any().downcast_ref::<NewOrderSingle>().unwrap();
+        assert_eq!(message.cl_ord_id,b"2");
+    });
+}
+
+#[test]
+fn test_message_not_sent_on_disconnect() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        NewOrderSingle,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+    client.set_send_receipts(true);
+    client.set_throttle(connection, Throttle::new(1, Duration::from_secs(60)));
+
+    let _ = client.send_message(connection, new_order_single(b"1"));
+    let second_message_id = client.send_message(connection, new_order_single(b"2"));
+    let third_message_id = client.send_message(connection, new_order_single(b"3"));
+    let _ = test_server.recv_message::<NewOrderSingle>();
+    engine_poll_event!(client, EngineEvent::MessageSent(_, _, _, _) => {});
+    engine_poll_event!(client, EngineEvent::Throttled(_, _) => {});
+
+    //Unsent messages are reported in order before the connection is reported as terminated.
+    drop(test_server);
+    for expected_message_id in [second_message_id, third_message_id] {
+        engine_poll_event!(client,EngineEvent::MessageNotSent(not_sent_connection,message_id,_) => {
+            assert_eq!(not_sent_connection,connection);
+            assert_eq!(message_id,expected_message_id);
+        });
+    }
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,_,_) => {
+        assert_eq!(terminated_connection,connection);
+    });
+}
+
+#[test]
+fn test_message_not_sent_to_invalid_connection() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        NewOrderSingle,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (_test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+    client.set_send_receipts(true);
+
+    let invalid_connection = Connection(connection.0 + 100);
+    let message_id = client.send_message(invalid_connection, new_order_single(b"1"));
+    engine_poll_event!(client,EngineEvent::MessageNotSent(not_sent_connection,not_sent_message_id,_) => {
+        assert_eq!(not_sent_connection,invalid_connection);
+        assert_eq!(not_sent_message_id,message_id);
+    });
+
+    //Receipts can be turned back off.
+    client.set_send_receipts(false);
+    let _: MessageId = client.send_message(invalid_connection, new_order_single(b"2"));
+    engine_poll_no_event!(client);
+}
+
+#[test]
+fn test_message_refused_includes_message_id() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        NewOrderSingle,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (_test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+    client.set_send_receipts(true);
+    client.set_outbound_message_hook(Box::new(|_, message| {
+        !message.as_any().is::<NewOrderSingle>()
+    }));
+
+    //Refused message is the only event about it.
+    let message_id = client.send_message(connection, new_order_single(b"1"));
+    engine_poll_event!(client,EngineEvent::MessageRefused(refused_connection,refused_message_id,_,_) => {
+        assert_eq!(refused_connection,connection);
+        assert_eq!(refused_message_id,Some(message_id));
+    });
+    engine_poll_no_event!(client);
+
+    //Without receipts there's no MessageId to report.
+    client.set_send_receipts(false);
+    let _ = client.send_message(connection, new_order_single(b"2"));
+    engine_poll_event!(client,EngineEvent::MessageRefused(_,refused_message_id,_,_) => {
+        assert_eq!(refused_message_id,None);
+    });
+}
+
+#[test]
+fn test_send_receipts_enabled_by_default() {
+    define_dictionary!(Logon, NewOrderSingle,);
+
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    let invalid_connection = Connection(100);
+    let message_id = client.send_message(invalid_connection, new_order_single(b"1"));
+    engine_poll_event!(client,EngineEvent::MessageNotSent(not_sent_connection,not_sent_message_id,_) => {
+        assert_eq!(not_sent_connection,invalid_connection);
+        assert_eq!(not_sent_message_id,message_id);
+    });
+}
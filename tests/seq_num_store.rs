@@ -0,0 +1,244 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use mio::tcp::TcpListener;
+use std::env;
+use std::fs::{self, File};
+use std::io::Write;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{
+    accept_with_timeout, new_logon_message, TestStream, CLIENT_SENDER_COMP_ID,
+    CLIENT_TARGET_COMP_ID,
+};
+use fix_rs::dictionary::messages::{Heartbeat, Logon, Logout, ResendRequest, TestRequest};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{
+    ConnectionTerminatedReason, Engine, EngineEvent, MsgSeqNums, SessionID,
+};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::seq_num_store::{FileSeqNumStore, MemorySeqNumStore, SeqNumStore};
+use fix_rs::message_version::MessageVersion;
+
+fn client_session_id() -> SessionID {
+    SessionID::new(
+        FIXVersion::FIXT_1_1,
+        CLIENT_SENDER_COMP_ID,
+        CLIENT_TARGET_COMP_ID,
+    )
+}
+
+fn temp_directory(name: &str) -> std::path::PathBuf {
+    let mut path = env::temp_dir();
+    path.push(format!("fix-rs-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&path);
+    path
+}
+
+#[test]
+fn test_initiator_resumes_msg_seq_nums_from_store() {
+    define_dictionary!(Heartbeat, Logon, Logout, TestRequest,);
+
+    //Pretend the session already exchanged a few messages in a previous run.
+    let mut seq_num_store = MemorySeqNumStore::new();
+    seq_num_store
+        .save(&client_session_id(), MsgSeqNums::new(4, 7))
+        .unwrap();
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_with_ver_and_engine_setup(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            build_dictionary(),
+            |engine| {
+                engine.set_seq_num_store(Box::new(seq_num_store));
+            },
+        );
+
+    //Logon continues where the session left off.
+    client.send_message(connection, new_logon_message());
+    let message = test_server.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 7);
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.msg_seq_num = 4;
+    response_message.encrypt_method = message.encrypt_method;
+    response_message.heart_bt_int = message.heart_bt_int;
+    response_message.default_appl_ver_id = message.default_appl_ver_id;
+    test_server.send_message(response_message);
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 5;
+    message.test_req_id = b"1".to_vec();
+    test_server.send_message(message);
+    let _ = engine_poll_message!(client, connection, TestRequest);
+    let message = test_server.recv_message::<Heartbeat>();
+    assert_eq!(message.msg_seq_num, 8);
+
+    //Final MsgSeqNums are reported so the session can be resumed again.
+    client.logout(connection);
+    let message = test_server.recv_message::<Logout>();
+    assert_eq!(message.msg_seq_num, 9);
+    let mut message = new_fixt_message!(Logout);
+    message.msg_seq_num = 6;
+    test_server.send_message(message);
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,ConnectionTerminatedReason::LocalRequested,msg_seq_nums) => {
+        assert_eq!(terminated_connection,connection);
+        assert_eq!(msg_seq_nums,MsgSeqNums::new(7,10));
+    });
+}
+
+#[test]
+#[allow(deprecated)]
+fn test_add_connection_with_msg_seq_nums() {
+    define_dictionary!(Logon,);
+
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let listener = TcpListener::bind(&addr).unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    //Explicit MsgSeqNums take priority over anything in the SeqNumStore.
+    let mut seq_num_store = MemorySeqNumStore::new();
+    seq_num_store
+        .save(&client_session_id(), MsgSeqNums::new(4, 7))
+        .unwrap();
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    client.set_send_receipts(false);
+    client.set_seq_num_store(Box::new(seq_num_store));
+    let connection = client
+        .add_connection_with_msg_seq_nums(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            CLIENT_SENDER_COMP_ID,
+            CLIENT_TARGET_COMP_ID,
+            addr,
+            MsgSeqNums::new(30, 40),
+        )
+        .unwrap();
+    let stream = accept_with_timeout(&listener, Duration::from_secs(5)).unwrap();
+    engine_poll_event!(client,EngineEvent::ConnectionSucceeded(_) => {});
+    let mut test_server = TestStream::new(
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        stream,
+        build_dictionary(),
+    );
+
+    client.send_message(connection, new_logon_message());
+    let message = test_server.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 40);
+
+    //Remote Logon with a lower MsgSeqNum than expected ends the session.
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.msg_seq_num = 29;
+    response_message.encrypt_method = message.encrypt_method;
+    response_message.heart_bt_int = message.heart_bt_int;
+    response_message.default_appl_ver_id = message.default_appl_ver_id;
+    test_server.send_message(response_message);
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(_,_,msg_seq_nums) => {
+        assert_eq!(msg_seq_nums.inbound,30);
+    });
+}
+
+#[test]
+fn test_acceptor_approve_with_msg_seq_nums() {
+    define_dictionary!(Logon, ResendRequest,);
+
+    let (mut test_client, mut engine, _listener, connection) =
+        TestStream::setup_test_client(build_dictionary());
+
+    let mut logon_message = new_logon_message();
+    logon_message.msg_seq_num = 7;
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    test_client.send_message(logon_message);
+
+    engine_poll_event!(engine,EngineEvent::ConnectionLoggingOn(_,_,logon_message) => {
+        let mut response_message = new_fixt_message!(Logon);
+        response_message.encrypt_method = logon_message.encrypt_method.clone();
+        response_message.heart_bt_int = logon_message.heart_bt_int;
+        response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+        engine.approve_new_connection_with_msg_seq_nums(connection,Box::new(response_message),MsgSeqNums::new(5,10));
+    });
+
+    //Logon response resumes outbound MsgSeqNum and the missed messages are requested.
+    let message = test_client.recv_message::<Logon>();
+    assert_eq!(message.msg_seq_num, 10);
+    let message = test_client.recv_message::<ResendRequest>();
+    assert_eq!(message.msg_seq_num, 11);
+    assert_eq!(message.begin_seq_no, 5);
+}
+
+#[test]
+fn test_file_seq_num_store() {
+    define_dictionary!(Logon, Logout,);
+
+    let directory = temp_directory("seq-num-store");
+    let session_id = client_session_id();
+
+    //Unknown sessions have nothing stored.
+    let mut seq_num_store = FileSeqNumStore::new(directory.clone()).unwrap();
+    assert_eq!(seq_num_store.load(&session_id).unwrap(), None);
+
+    //Run a session until it's dropped and make sure the MsgSeqNums outlive the Engine.
+    {
+        let engine_directory = directory.clone();
+        let (mut test_server, mut client, connection) =
+            TestStream::setup_test_server_and_logon_with_engine_setup(
+                build_dictionary(),
+                move |engine| {
+                    engine.set_seq_num_store(Box::new(
+                        FileSeqNumStore::new(engine_directory).unwrap(),
+                    ));
+                },
+            );
+        client.logout(connection);
+        let _ = test_server.recv_message::<Logout>();
+        let mut message = new_fixt_message!(Logout);
+        message.msg_seq_num = 2;
+        test_server.send_message(message);
+        engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,ConnectionTerminatedReason::LocalRequested,msg_seq_nums) => {
+            assert_eq!(terminated_connection,connection);
+            assert_eq!(msg_seq_nums,MsgSeqNums::new(3,3));
+        });
+    }
+    assert_eq!(
+        seq_num_store.load(&session_id).unwrap(),
+        Some(MsgSeqNums::new(3, 3))
+    );
+
+    seq_num_store
+        .save(&session_id, MsgSeqNums::new(100, 200))
+        .unwrap();
+    let mut seq_num_store = FileSeqNumStore::new(directory.clone()).unwrap();
+    assert_eq!(
+        seq_num_store.load(&session_id).unwrap(),
+        Some(MsgSeqNums::new(100, 200))
+    );
+
+    //Corrupted files are reported instead of silently restarting the session at 1.
+    let mut path = directory.clone();
+    path.push(format!("{}.seqnums", session_id.file_stem()));
+    File::create(&path).unwrap().write_all(b"garbage").unwrap();
+    assert!(seq_num_store.load(&session_id).is_err());
+
+    let _ = fs::remove_dir_all(&directory);
+}
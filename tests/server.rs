@@ -52,7 +52,7 @@ fn test_wrong_target_comp_id_in_logon() {
     logon_message.target_comp_id = Vec::new();
     test_client.send_message(logon_message.clone());
 
-    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::TargetCompIDWrongError = reason { true } else { false });
     });
@@ -402,7 +402,7 @@ fn test_auto_disconnect_after_no_logon() {
     ));
 
     //Confirm connection was terminated.
-    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::LogonNeverReceivedError = reason { true } else { false });
     });
@@ -423,7 +423,7 @@ fn test_connection_terminated_when_disconnected_with_no_logon() {
     let _ = test_client.stream.shutdown(Shutdown::Both);
 
     //Confirm connection was terminated.
-    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::SocketWriteError(_) = reason { true } else { false });
     });
@@ -455,7 +455,7 @@ fn test_connection_terminated_while_approving_logon() {
         .expect("disconnect");
 
     //Confirm connection was terminated.
-    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
         assert_eq!(terminated_connection,connection);
         assert!(if let ConnectionTerminatedReason::SocketWriteError(_) = reason { true } else { false });
     });
@@ -483,7 +483,7 @@ fn test_heart_bt_int() {
         thread::sleep(Duration::from_millis(500));
         assert!(test_client.is_stream_closed(Duration::from_secs(5)));
 
-        engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason) => {
+        engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
             assert_eq!(terminated_connection,connection);
             assert!(if let ConnectionTerminatedReason::LogonHeartBtIntNegativeError = reason { true } else { false });
         });
@@ -0,0 +1,288 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use std::sync::mpsc;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{new_logon_message, TestStream, CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID};
+use fix_rs::dictionary::messages::{
+    Heartbeat, Logon, Logout, Reject, ResendRequest, SequenceReset, TestRequest,
+};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{Connection, EngineEvent, MsgSeqNums, SessionID};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::session_info::SessionState;
+use fix_rs::message_version::MessageVersion;
+
+macro_rules! engine_poll_session_state_changed {
+    ( $engine:ident, $connection:ident, $old_state:expr, $new_state:expr ) => {
+        engine_poll_event!($engine,EngineEvent::SessionStateChanged(changed_connection,old_state,new_state) => {
+            assert_eq!(changed_connection,$connection);
+            assert_eq!(old_state,$old_state);
+            assert_eq!(new_state,$new_state);
+        })
+    };
+}
+
+#[test]
+fn test_session_info_after_logon() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (test_server, client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+
+    let session_info = client.session_info(connection).unwrap();
+    assert_eq!(session_info.state, SessionState::Established);
+    assert_eq!(
+        session_info.session_id,
+        SessionID::new(
+            FIXVersion::FIXT_1_1,
+            CLIENT_SENDER_COMP_ID,
+            CLIENT_TARGET_COMP_ID
+        )
+    );
+    assert_eq!(session_info.default_appl_ver_id, MessageVersion::FIX50SP2);
+    assert_eq!(
+        session_info.next_msg_seq_nums,
+        MsgSeqNums {
+            inbound: 2,
+            outbound: 2,
+        }
+    );
+    assert_eq!(session_info.heart_bt_int, Some(Duration::from_secs(5)));
+    assert_eq!(
+        session_info.peer_addr,
+        Some(test_server.stream.local_addr().unwrap())
+    );
+
+    //Unknown connections don't have any info.
+    assert!(client
+        .session_info(Connection(connection.0 + 100))
+        .is_none());
+}
+
+#[test]
+fn test_session_state_changed_during_logon_and_logout() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server(build_dictionary());
+    assert_eq!(
+        client.session_info(connection).unwrap().state,
+        SessionState::LoggingOn
+    );
+    client.set_session_state_events(true);
+
+    //Logon.
+    client.send_message(connection, new_logon_message());
+    let message = test_server.recv_message::<Logon>();
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.encrypt_method = message.encrypt_method;
+    response_message.heart_bt_int = message.heart_bt_int;
+    response_message.default_appl_ver_id = message.default_appl_ver_id;
+    test_server.send_message(response_message);
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+    engine_poll_session_state_changed!(
+        client,
+        connection,
+        SessionState::LoggingOn,
+        SessionState::Established
+    );
+
+    //Logout.
+    client.logout(connection);
+    let _ = test_server.recv_message::<Logout>();
+    engine_poll_session_state_changed!(
+        client,
+        connection,
+        SessionState::Established,
+        SessionState::LoggingOut
+    );
+    assert_eq!(
+        client.session_info(connection).unwrap().state,
+        SessionState::LoggingOut
+    );
+
+    let mut message = new_fixt_message!(Logout);
+    message.msg_seq_num = 2;
+    test_server.send_message(message);
+    engine_poll_session_state_changed!(
+        client,
+        connection,
+        SessionState::LoggingOut,
+        SessionState::Disconnected
+    );
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,_,_) => {
+        assert_eq!(terminated_connection,connection);
+    });
+
+    //Terminated connections don't have any info.
+    assert!(client.session_info(connection).is_none());
+}
+
+#[test]
+fn test_session_state_changed_while_resending() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+    client.set_session_state_events(true);
+
+    //Skip ahead so the client has to ask for the missing messages.
+    let mut message = new_fixt_message!(Heartbeat);
+    message.msg_seq_num = 5;
+    test_server.send_message(message);
+    let message = test_server.recv_message::<ResendRequest>();
+    assert_eq!(message.begin_seq_no, 2);
+    engine_poll_session_state_changed!(
+        client,
+        connection,
+        SessionState::Established,
+        SessionState::Resending
+    );
+    assert_eq!(
+        client.session_info(connection).unwrap().state,
+        SessionState::Resending
+    );
+
+    //Gap fill the missing messages.
+    let mut message = new_fixt_message!(SequenceReset);
+    message.gap_fill_flag = true;
+    message.new_seq_no = 5;
+    message.msg_seq_num = 2;
+    test_server.send_message(message);
+    let _ = engine_poll_message!(client, connection, SequenceReset);
+    engine_poll_session_state_changed!(
+        client,
+        connection,
+        SessionState::Resending,
+        SessionState::Established
+    );
+}
+
+#[test]
+fn test_session_state_changed_for_accepted_connection() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_client, mut engine, _, connection) =
+        TestStream::setup_test_client(build_dictionary());
+    let session_info = engine.session_info(connection).unwrap();
+    assert_eq!(session_info.state, SessionState::AwaitingLogon);
+    assert_eq!(session_info.heart_bt_int, None);
+    assert_eq!(
+        session_info.peer_addr,
+        Some(test_client.stream.local_addr().unwrap())
+    );
+    engine.set_session_state_events(true);
+
+    //Logon waits on the application.
+    let mut logon_message = new_logon_message();
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    test_client.send_message(logon_message);
+    let logon_message = engine_poll_event!(engine,EngineEvent::ConnectionLoggingOn(_,_,logon_message) => {
+        logon_message
+    });
+    engine_poll_session_state_changed!(
+        engine,
+        connection,
+        SessionState::AwaitingLogon,
+        SessionState::ApprovingLogon
+    );
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.encrypt_method = logon_message.encrypt_method.clone();
+    response_message.heart_bt_int = logon_message.heart_bt_int;
+    response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+    engine.approve_new_connection(connection, Box::new(response_message), None);
+    let _ = test_client.recv_message::<Logon>();
+    engine_poll_session_state_changed!(
+        engine,
+        connection,
+        SessionState::ApprovingLogon,
+        SessionState::Established
+    );
+}
+
+#[test]
+fn test_session_info_while_engine_thread_is_busy() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+
+    //Hold the engine's thread inside the outbound message hook.
+    let (entered_tx, entered_rx) = mpsc::channel();
+    let (release_tx, release_rx) = mpsc::channel::<()>();
+    client.set_outbound_message_hook(Box::new(move |_, _| {
+        let _ = entered_tx.send(());
+        let _ = release_rx.recv();
+        true
+    }));
+    client.send_message(connection, new_fixt_message!(Heartbeat));
+    entered_rx.recv().unwrap();
+
+    //SessionInfo is still available without waiting on the engine's thread.
+    let session_info = client.session_info(connection).unwrap();
+    assert_eq!(session_info.state, SessionState::Established);
+    assert_eq!(session_info.next_msg_seq_nums.outbound, 3);
+
+    release_tx.send(()).unwrap();
+    let message = test_server.recv_message::<Heartbeat>();
+    assert_eq!(message.msg_seq_num, 2);
+}
@@ -0,0 +1,313 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate chrono;
+extern crate mio;
+
+use chrono::Duration as ChronoDuration;
+use mio::tcp::{TcpListener, TcpStream};
+use std::io::Write;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::{Duration, Instant};
+
+#[macro_use]
+mod common;
+use crate::common::{
+    accept_with_timeout, new_logon_message, TestStream, CLIENT_SENDER_COMP_ID,
+    CLIENT_TARGET_COMP_ID, SERVER_SENDER_COMP_ID,
+};
+use fix_rs::dictionary::field_types::other::SessionRejectReason;
+use fix_rs::dictionary::messages::{
+    Heartbeat, Logon, Logout, Reject, ResendRequest, SequenceReset, TestRequest,
+};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::acceptor_session::AcceptorSession;
+use fix_rs::fixt::engine::{Connection, ConnectionTerminatedReason, Engine, EngineEvent};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::session_config::ConnectionConfig;
+use fix_rs::fixt::session_options::SessionOptions;
+use fix_rs::message_version::MessageVersion;
+
+fn unused_addr() -> SocketAddr {
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let listener_socket = TcpListener::bind(&addr).unwrap();
+    listener_socket.local_addr().unwrap()
+}
+
+//Connect to a test server using session_options and send a Logon.
+fn setup_test_server_with_session_options(
+    session_options: SessionOptions,
+) -> (TestStream, Engine, Connection) {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let addr = unused_addr();
+    let listener = TcpListener::bind(&addr).unwrap();
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    client.set_send_receipts(false);
+    let connection = client
+        .add_connection_with_config(
+            ConnectionConfig::new(
+                FIXVersion::FIXT_1_1,
+                MessageVersion::FIX50SP2,
+                CLIENT_SENDER_COMP_ID,
+                CLIENT_TARGET_COMP_ID,
+                addr,
+            )
+            .with_session_options(session_options),
+        )
+        .unwrap();
+    let stream = accept_with_timeout(&listener, Duration::from_secs(5))
+        .expect("Could not accept connection");
+    engine_poll_event!(client, EngineEvent::ConnectionSucceeded(_) => {});
+
+    let mut test_server = TestStream::new(
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        stream,
+        build_dictionary(),
+    );
+    client.send_message(connection, new_logon_message());
+    let _ = test_server.recv_message::<Logon>();
+
+    (test_server, client, connection)
+}
+
+//Setup a listener using listener_session_options and connect to it.
+fn setup_listener_with_session_options(
+    listener_session_options: SessionOptions,
+    acceptor_session: Option<AcceptorSession>,
+) -> (TestStream, Engine, Connection) {
+    define_dictionary!(Logon, Logout,);
+
+    let addr = unused_addr();
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    engine.set_send_receipts(false);
+    let listener = engine
+        .add_listener_with_session_options(SERVER_SENDER_COMP_ID, addr, listener_session_options)
+        .unwrap()
+        .unwrap();
+    if let Some(acceptor_session) = acceptor_session {
+        assert!(engine.add_acceptor_session(listener, acceptor_session));
+    }
+
+    let stream = TcpStream::connect(&addr).unwrap();
+    let connection = engine_poll_event!(engine,EngineEvent::ConnectionAccepted(_,connection,_) => {
+        connection
+    });
+    let test_client = TestStream::new(
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        stream,
+        build_dictionary(),
+    );
+
+    (test_client, engine, connection)
+}
+
+fn new_client_logon_message(heart_bt_int: i64) -> Logon {
+    let mut logon_message = new_logon_message();
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    logon_message.heart_bt_int = heart_bt_int;
+    logon_message
+}
+
+#[test]
+fn test_initiator_heart_bt_int_out_of_range() {
+    let (mut test_server, mut client, connection) = setup_test_server_with_session_options(
+        SessionOptions::new().with_heart_bt_int_range(10, 30),
+    );
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.heart_bt_int = 5;
+    response_message.default_appl_ver_id = MessageVersion::FIX50SP2;
+    test_server.send_message(response_message);
+
+    let message = test_server.recv_message::<Logout>();
+    assert_eq!(message.text, b"HeartBtInt must be between 10 and 30");
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
+        assert_eq!(terminated_connection,connection);
+        assert!(matches!(reason,ConnectionTerminatedReason::LogonHeartBtIntOutOfRangeError));
+    });
+}
+
+#[test]
+fn test_listener_heart_bt_int_out_of_range() {
+    let (mut test_client, mut engine, connection) = setup_listener_with_session_options(
+        SessionOptions::new().with_heart_bt_int_range(10, 30),
+        None,
+    );
+
+    //Application never sees the Logon.
+    test_client.send_message(new_client_logon_message(5));
+    let message = test_client.recv_message::<Logout>();
+    assert_eq!(message.text, b"HeartBtInt must be between 10 and 30");
+    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
+        assert_eq!(terminated_connection,connection);
+        assert!(matches!(reason,ConnectionTerminatedReason::LogonHeartBtIntOutOfRangeError));
+    });
+}
+
+#[test]
+fn test_acceptor_session_options_replace_listener_options() {
+    let (mut test_client, mut engine, connection) = setup_listener_with_session_options(
+        SessionOptions::new().with_heart_bt_int_range(10, 30),
+        Some(
+            AcceptorSession::new(FIXVersion::FIXT_1_1, CLIENT_SENDER_COMP_ID)
+                .with_session_options(SessionOptions::new().with_heart_bt_int_range(1, 5)),
+        ),
+    );
+
+    test_client.send_message(new_client_logon_message(5));
+    engine_poll_event!(engine,EngineEvent::ConnectionLoggingOn(_,logging_on_connection,logon) => {
+        assert_eq!(logging_on_connection,connection);
+        assert_eq!(logon.heart_bt_int,5);
+    });
+}
+
+#[test]
+fn test_no_logon_timeout() {
+    let (_test_client, mut engine, connection) = setup_listener_with_session_options(
+        SessionOptions::new().with_no_logon_timeout(Duration::from_millis(500)),
+        None,
+    );
+
+    let now = Instant::now();
+    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
+        assert_eq!(terminated_connection,connection);
+        assert!(matches!(reason,ConnectionTerminatedReason::LogonNeverReceivedError));
+    });
+    assert!(now.elapsed() < Duration::from_secs(2));
+}
+
+#[test]
+fn test_sending_time_skew() {
+    let (mut test_server, mut client, connection) = setup_test_server_with_session_options(
+        SessionOptions::new().with_sending_time_skew(Duration::from_secs(5)),
+    );
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.heart_bt_int = 5;
+    response_message.default_appl_ver_id = MessageVersion::FIX50SP2;
+    test_server.send_message(response_message);
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    //SendingTime within the allowed skew is fine.
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 2;
+    message.test_req_id = b"1".to_vec();
+    message.sending_time -= ChronoDuration::seconds(2);
+    test_server.send_message(message);
+    let _ = engine_poll_message!(client, connection, TestRequest);
+    let _ = test_server.recv_message::<Heartbeat>();
+
+    //SendingTime outside of the allowed skew is rejected.
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 3;
+    message.test_req_id = b"2".to_vec();
+    message.sending_time -= ChronoDuration::minutes(1);
+    test_server.send_message(message);
+    engine_poll_event!(client,EngineEvent::MessageRejected(rejected_connection,rejected_message) => {
+        assert_eq!(rejected_connection,connection);
+        assert_eq!(rejected_message.msg_seq_num(),3);
+    });
+    let message = test_server.recv_message::<Reject>();
+    assert_eq!(message.ref_seq_num, 3);
+    assert_eq!(
+        message.session_reject_reason,
+        Some(SessionRejectReason::SendingTimeAccuracyProblem)
+    );
+}
+
+#[test]
+fn test_garbled_resend_request_retry_count() {
+    let (mut test_server, mut client, connection) = setup_test_server_with_session_options(
+        SessionOptions::new().with_garbled_resend_request_retry_count(1),
+    );
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.heart_bt_int = 5;
+    response_message.default_appl_ver_id = MessageVersion::FIX50SP2;
+    test_server.send_message(response_message);
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    //Skip ahead so the missing messages are requested.
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 5;
+    message.test_req_id = b"1".to_vec();
+    test_server.send_message(message);
+    let message = test_server.recv_message::<ResendRequest>();
+    assert_eq!(message.begin_seq_no, 2);
+    assert_eq!(message.end_seq_no, 0);
+
+    //Messages resent in response are garbled so they're requested once more.
+    let garbled_message = b"8=FIXT.1.1\x019=5\x0135=0\x0110=000\x01";
+    test_server.stream.write_all(garbled_message).unwrap();
+    engine_poll_event!(client,EngineEvent::MessageReceivedGarbled(garbled_connection,_) => {
+        assert_eq!(garbled_connection,connection);
+    });
+    let _ = test_server.recv_message::<Reject>();
+
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 6;
+    message.test_req_id = b"2".to_vec();
+    test_server.send_message(message);
+    engine_poll_event!(client,EngineEvent::ResendRequestRetried(retried_connection,range,attempt) => {
+        assert_eq!(retried_connection,connection);
+        assert_eq!(range,3..7);
+        assert_eq!(attempt,2);
+    });
+    let message = test_server.recv_message::<ResendRequest>();
+    assert_eq!(message.begin_seq_no, 3);
+
+    //Retries have run out when the messages come back garbled again.
+    test_server.stream.write_all(garbled_message).unwrap();
+    engine_poll_event!(client,EngineEvent::MessageReceivedGarbled(garbled_connection,_) => {
+        assert_eq!(garbled_connection,connection);
+    });
+    let _ = test_server.recv_message::<Reject>();
+
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 7;
+    message.test_req_id = b"3".to_vec();
+    test_server.send_message(message);
+    let message = test_server.recv_message::<Logout>();
+    assert_eq!(
+        message.text,
+        b"Detected garbled ResendRequest loop for BeginSeqNo 4".to_vec()
+    );
+
+    let mut message = new_fixt_message!(Logout);
+    message.msg_seq_num = 8;
+    test_server.send_message(message);
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,reason,_) => {
+        assert_eq!(terminated_connection,connection);
+        match reason {
+            ConnectionTerminatedReason::OutboundResendRequestLoopError { range, attempts } => {
+                assert_eq!(range,4..8);
+                assert_eq!(attempts,2);
+            },
+            _ => panic!("Wrong reason: {:?}", reason),
+        }
+    });
+}
@@ -0,0 +1,289 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+extern crate chrono;
+extern crate chrono_tz;
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use chrono::{Duration as ChronoDuration, NaiveTime, TimeZone, Utc, Weekday};
+use chrono_tz::America::New_York;
+use chrono_tz::UTC;
+use mio::tcp::{TcpListener, TcpStream};
+use std::env;
+use std::fs;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{
+    accept_with_timeout, new_logon_message, TestStream, CLIENT_SENDER_COMP_ID,
+    CLIENT_TARGET_COMP_ID, SERVER_SENDER_COMP_ID,
+};
+use fix_rs::dictionary::messages::{Logon, Logout};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{
+    ConnectionTerminatedReason, Engine, EngineEvent, MsgSeqNums, SessionID,
+};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::seq_num_store::{FileSeqNumStore, SeqNumStore};
+use fix_rs::fixt::session_schedule::{ScheduleTime, SessionSchedule};
+use fix_rs::message_version::MessageVersion;
+
+fn time_from_now(offset: ChronoDuration) -> NaiveTime {
+    (Utc::now() + offset).time()
+}
+
+fn local_addr() -> (TcpListener, SocketAddr) {
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let listener = TcpListener::bind(&addr).unwrap();
+    let addr = listener.local_addr().unwrap();
+    (listener, addr)
+}
+
+//Accept the Engine's connection and respond to its Logon.
+fn accept_logon(listener: &TcpListener) -> (TestStream, Logon) {
+    let stream = accept_with_timeout(listener, Duration::from_secs(5)).unwrap();
+    define_dictionary!(Logon, Logout,);
+    let mut test_server = TestStream::new(
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        stream,
+        build_dictionary(),
+    );
+    let message = test_server.recv_message::<Logon>();
+
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.msg_seq_num = 1;
+    response_message.encrypt_method = message.encrypt_method.clone();
+    response_message.heart_bt_int = message.heart_bt_int;
+    response_message.default_appl_ver_id = message.default_appl_ver_id;
+    test_server.send_message(response_message);
+
+    (test_server, message)
+}
+
+#[test]
+fn test_schedule_is_active() {
+    //Weekly schedule crossing a weekend in a time zone with daylight saving time.
+    let schedule = SessionSchedule::weekly(
+        Weekday::Sun,
+        NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        Weekday::Fri,
+        NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+        New_York,
+    );
+
+    //2017-03-10 is a Friday. Daylight saving time started on Sunday 2017-03-12.
+    let friday_before_close = Utc.with_ymd_and_hms(2017, 3, 10, 21, 59, 59).unwrap();
+    let friday_after_close = Utc.with_ymd_and_hms(2017, 3, 10, 22, 0, 0).unwrap();
+    let sunday_open = Utc.with_ymd_and_hms(2017, 3, 12, 21, 0, 0).unwrap();
+    assert!(schedule.is_active(&friday_before_close));
+    assert!(!schedule.is_active(&friday_after_close));
+    assert_eq!(schedule.next_transition(&friday_after_close), sunday_open);
+    assert!(schedule.is_active(&sunday_open));
+
+    //Daily schedule crossing midnight.
+    let schedule = SessionSchedule::daily(
+        NaiveTime::from_hms_opt(22, 0, 0).unwrap(),
+        NaiveTime::from_hms_opt(2, 0, 0).unwrap(),
+        UTC,
+    )
+    .with_reset_time(ScheduleTime::Daily(
+        NaiveTime::from_hms_opt(21, 0, 0).unwrap(),
+    ));
+    let midnight = Utc.with_ymd_and_hms(2017, 3, 10, 0, 0, 0).unwrap();
+    let noon = Utc.with_ymd_and_hms(2017, 3, 10, 12, 0, 0).unwrap();
+    assert!(schedule.is_active(&midnight));
+    assert!(!schedule.is_active(&noon));
+    assert_eq!(
+        schedule.next_reset(&noon),
+        Some(Utc.with_ymd_and_hms(2017, 3, 10, 21, 0, 0).unwrap())
+    );
+
+    //Same start and end means the session never closes.
+    let schedule = SessionSchedule::daily(
+        NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+        NaiveTime::from_hms_opt(0, 0, 0).unwrap(),
+        UTC,
+    );
+    assert!(schedule.is_active(&midnight));
+    assert!(schedule.is_active(&noon));
+}
+
+#[test]
+fn test_scheduled_connection_logs_on_and_out() {
+    define_dictionary!(Logon, Logout,);
+
+    let (listener, addr) = local_addr();
+    let schedule = SessionSchedule::daily(
+        time_from_now(ChronoDuration::seconds(1)),
+        time_from_now(ChronoDuration::seconds(3)),
+        UTC,
+    );
+
+    //Nothing happens until the session becomes active.
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    let connection = client
+        .add_scheduled_connection(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            CLIENT_SENDER_COMP_ID,
+            CLIENT_TARGET_COMP_ID,
+            addr,
+            schedule,
+            Box::new(new_logon_message()),
+        )
+        .unwrap();
+    assert!(listener.accept().is_err());
+
+    //Logon is sent automatically once the session starts.
+    let (mut test_server, message) = accept_logon(&listener);
+    assert_eq!(message.msg_seq_num, 1);
+    engine_poll_event!(client,EngineEvent::ConnectionSucceeded(succeeded_connection) => {
+        assert_eq!(succeeded_connection,connection);
+    });
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    //Logout is sent automatically once the session ends but the connection remains valid.
+    let message = test_server.recv_message::<Logout>();
+    assert_eq!(message.msg_seq_num, 2);
+    let mut message = new_fixt_message!(Logout);
+    message.msg_seq_num = 2;
+    test_server.send_message(message);
+    engine_poll_event!(client,EngineEvent::SessionEnded(ended_connection,ConnectionTerminatedReason::LocalRequested,msg_seq_nums) => {
+        assert_eq!(ended_connection,connection);
+        assert_eq!(msg_seq_nums,MsgSeqNums::new(3,3));
+    });
+
+    //Logging out stops the schedule and releases the connection.
+    client.logout(connection);
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,ConnectionTerminatedReason::LocalRequested,msg_seq_nums) => {
+        assert_eq!(terminated_connection,connection);
+        assert_eq!(msg_seq_nums,MsgSeqNums::new(3,3));
+    });
+}
+
+#[test]
+fn test_scheduled_connection_resets_msg_seq_nums() {
+    define_dictionary!(Logon, Logout,);
+
+    let mut directory = env::temp_dir();
+    directory.push(format!("fix-rs-session-schedule-{}", std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    let session_id = SessionID::new(
+        FIXVersion::FIXT_1_1,
+        CLIENT_SENDER_COMP_ID,
+        CLIENT_TARGET_COMP_ID,
+    );
+
+    //Session left off somewhere in a previous day.
+    let mut seq_num_store = FileSeqNumStore::new(directory.clone()).unwrap();
+    seq_num_store
+        .save(&session_id, MsgSeqNums::new(5, 5))
+        .unwrap();
+
+    let (listener, addr) = local_addr();
+    let schedule = SessionSchedule::daily(
+        time_from_now(ChronoDuration::milliseconds(1500)),
+        time_from_now(ChronoDuration::seconds(60)),
+        UTC,
+    )
+    .with_reset_time(ScheduleTime::Daily(time_from_now(
+        ChronoDuration::milliseconds(500),
+    )));
+
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    client.set_seq_num_store(Box::new(FileSeqNumStore::new(directory.clone()).unwrap()));
+    let connection = client
+        .add_scheduled_connection(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            CLIENT_SENDER_COMP_ID,
+            CLIENT_TARGET_COMP_ID,
+            addr,
+            schedule,
+            Box::new(new_logon_message()),
+        )
+        .unwrap();
+
+    //Reset happened before the session started so it starts over at 1.
+    let (mut test_server, message) = accept_logon(&listener);
+    assert_eq!(message.msg_seq_num, 1);
+    engine_poll_event!(client,EngineEvent::ConnectionSucceeded(_) => {});
+    engine_poll_event!(client,EngineEvent::SessionEstablished(_) => {});
+    let _ = engine_poll_message!(client, connection, Logon);
+
+    client.logout(connection);
+    let _ = test_server.recv_message::<Logout>();
+    let mut message = new_fixt_message!(Logout);
+    message.msg_seq_num = 2;
+    test_server.send_message(message);
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(_,ConnectionTerminatedReason::LocalRequested,msg_seq_nums) => {
+        assert_eq!(msg_seq_nums,MsgSeqNums::new(3,3));
+    });
+    assert_eq!(
+        seq_num_store.load(&session_id).unwrap(),
+        Some(MsgSeqNums::new(3, 3))
+    );
+
+    let _ = fs::remove_dir_all(&directory);
+}
+
+#[test]
+fn test_listener_rejects_logon_outside_schedule() {
+    define_dictionary!(Logon, Logout,);
+
+    //Session doesn't start for another hour.
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let listener_socket = TcpListener::bind(&addr).unwrap();
+    let addr = listener_socket.local_addr().unwrap();
+    drop(listener_socket);
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    let listener = engine
+        .add_listener(SERVER_SENDER_COMP_ID, addr)
+        .unwrap()
+        .unwrap();
+    engine.set_listener_session_schedule(
+        listener,
+        SessionSchedule::daily(
+            time_from_now(ChronoDuration::hours(1)),
+            time_from_now(ChronoDuration::hours(2)),
+            UTC,
+        ),
+    );
+
+    let stream = TcpStream::connect(&addr).unwrap();
+    let connection = engine_poll_event!(engine,EngineEvent::ConnectionAccepted(_,connection,_) => {
+        connection
+    });
+    let mut test_client = TestStream::new(
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        stream,
+        build_dictionary(),
+    );
+
+    //Logon is answered with a Logout and the connection is dropped without asking the
+    //application.
+    let mut logon_message = new_logon_message();
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    test_client.send_message(logon_message);
+    let message = test_client.recv_message::<Logout>();
+    assert_eq!(message.text, b"Session is not active".to_vec());
+    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,ConnectionTerminatedReason::LogonOutsideScheduleError,_) => {
+        assert_eq!(terminated_connection,connection);
+    });
+}
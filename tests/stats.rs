@@ -0,0 +1,217 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use std::env;
+use std::fs;
+use std::io::{Read, Write};
+use std::net::TcpStream;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{TestStream, CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID};
+use fix_rs::byte_buffer::ByteBuffer;
+use fix_rs::dictionary::messages::{
+    Heartbeat, Logon, Logout, Reject, ResendRequest, SequenceReset, TestRequest,
+};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{EngineEvent, ResendResponse, SessionID};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::stats::{MsgTypeStats, PrometheusExporter};
+use fix_rs::message::Message;
+use fix_rs::message_version::MessageVersion;
+
+#[test]
+fn test_stats_count_messages_by_msg_type() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+
+    //Write the TestRequest by hand so its exact size is known.
+    let mut message = new_fixt_message!(TestRequest);
+    message.msg_seq_num = 2;
+    message.test_req_id = b"1".to_vec();
+    let mut bytes = ByteBuffer::new();
+    message.read(FIXVersion::FIXT_1_1, MessageVersion::FIX50SP2, &mut bytes);
+    test_server.stream.write_all(bytes.bytes()).unwrap();
+    engine_poll_message!(client, connection, TestRequest);
+    let _ = test_server.recv_message::<Heartbeat>();
+
+    let stats = client.stats();
+    assert_eq!(stats.sessions.len(), 1);
+    let session_stats = &stats.sessions[&connection];
+    assert_eq!(
+        session_stats.session_id,
+        SessionID::new(
+            FIXVersion::FIXT_1_1,
+            CLIENT_SENDER_COMP_ID,
+            CLIENT_TARGET_COMP_ID
+        )
+    );
+    assert_eq!(session_stats.inbound.len(), 2);
+    assert_eq!(session_stats.inbound[&b"A".to_vec()].messages, 1);
+    assert_eq!(
+        session_stats.inbound[&b"1".to_vec()],
+        MsgTypeStats {
+            messages: 1,
+            bytes: bytes.len() as u64,
+        }
+    );
+    assert_eq!(session_stats.outbound.len(), 2);
+    assert_eq!(session_stats.outbound[&b"A".to_vec()].messages, 1);
+    assert_eq!(session_stats.outbound[&b"0".to_vec()].messages, 1);
+    assert_eq!(session_stats.messages_received(), 2);
+    assert_eq!(session_stats.messages_sent(), 2);
+    assert!(session_stats.bytes_received() > bytes.len() as u64);
+    assert!(session_stats.bytes_sent() > 0);
+    assert!(session_stats.parse_time > Duration::from_secs(0));
+    assert!(session_stats.parse_time >= session_stats.parse_time_max);
+    assert_eq!(session_stats.outbound_queue_depth, 0);
+}
+
+#[test]
+fn test_stats_count_rejects_resend_requests_and_garbled_messages() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+
+    //Garbled message is answered with a Reject.
+    test_server
+        .stream
+        .write_all(b"8=FIXT.1.1\x019=5\x0135=0\x0110=000\x01")
+        .unwrap();
+    engine_poll_event!(client,EngineEvent::MessageReceivedGarbled(garbled_connection,_) => {
+        assert_eq!(garbled_connection,connection);
+    });
+    let _ = test_server.recv_message::<Reject>();
+
+    //ResendRequest for the Reject is answered with a gap fill. The garbled message still used up
+    //MsgSeqNum 2.
+    let mut message = new_fixt_message!(ResendRequest);
+    message.msg_seq_num = 3;
+    message.begin_seq_no = 2;
+    message.end_seq_no = 2;
+    test_server.send_message(message);
+    engine_gap_fill_resend_request!(client, connection, 2..3);
+    let _ = test_server.recv_message::<SequenceReset>();
+
+    let mut message = new_fixt_message!(Reject);
+    message.msg_seq_num = 4;
+    message.ref_seq_num = 2;
+    test_server.send_message(message);
+    engine_poll_message!(client, connection, ResendRequest);
+    engine_poll_message!(client, connection, Reject);
+
+    let stats = client.stats();
+    let session_stats = &stats.sessions[&connection];
+    assert_eq!(session_stats.garbled_messages, 1);
+    assert_eq!(session_stats.rejects_sent, 1);
+    assert_eq!(session_stats.rejects_received, 1);
+    assert_eq!(session_stats.resend_requests_received, 1);
+    assert_eq!(session_stats.resend_requests_sent, 0);
+    assert_eq!(session_stats.inbound[&b"3".to_vec()].messages, 1);
+    assert_eq!(session_stats.outbound[&b"3".to_vec()].messages, 1);
+}
+
+#[test]
+fn test_stats_count_heartbeat_misses() {
+    define_dictionary!(Heartbeat, Logon, Logout, Reject, ResendRequest, TestRequest,);
+
+    let (mut test_server, client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+
+    //Stay quiet until the engine asks if we're still around.
+    loop {
+        let message = test_server
+            .try_recv_fixt_message(Duration::from_secs(10))
+            .expect("Did not receive TestRequest");
+        if message.as_any().is::<TestRequest>() {
+            break;
+        }
+    }
+
+    assert_eq!(client.stats().sessions[&connection].heartbeat_misses, 1);
+}
+
+#[test]
+fn test_stats_removed_when_connection_terminated() {
+    define_dictionary!(Heartbeat, Logon, Logout, Reject, ResendRequest, TestRequest,);
+
+    let (test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+    assert!(client.stats().sessions.contains_key(&connection));
+
+    drop(test_server);
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(terminated_connection,_,_) => {
+        assert_eq!(terminated_connection,connection);
+    });
+    assert!(client.stats().sessions.is_empty());
+}
+
+#[test]
+fn test_prometheus_exporter() {
+    define_dictionary!(Heartbeat, Logon, Logout, Reject, ResendRequest, TestRequest,);
+
+    let (_test_server, client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+    let expected_line = format!(
+        "fix_messages_total{{connection=\"{}\",session=\"FIXT.1.1:TEST->TX\",direction=\"in\",msg_type=\"A\"}} 1\n",
+        connection
+    );
+
+    //Served over HTTP.
+    let address = PrometheusExporter::new(&client)
+        .serve("127.0.0.1:0")
+        .unwrap();
+    let mut stream = TcpStream::connect(address).unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(response.contains("# TYPE fix_messages_total counter\n"));
+    assert!(response.contains(&expected_line));
+
+    //Written to a file.
+    let mut path = env::temp_dir();
+    path.push(format!("fix-rs-stats-{}.prom", std::process::id()));
+    PrometheusExporter::new(&client).write_file(&path).unwrap();
+    let contents = fs::read_to_string(&path).unwrap();
+    assert!(contents.contains(&expected_line));
+    assert!(contents.contains(&format!(
+        "fix_outbound_queue_depth{{connection=\"{}\",session=\"FIXT.1.1:TEST->TX\"}} 0\n",
+        connection
+    )));
+    let _ = fs::remove_file(&path);
+}
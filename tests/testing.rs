@@ -0,0 +1,173 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use mio::tcp::TcpListener;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{
+    new_logon_message, CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID, SERVER_SENDER_COMP_ID,
+    SERVER_TARGET_COMP_ID,
+};
+use fix_rs::dictionary::messages::{Heartbeat, Logon, Logout, ResendRequest, TestRequest};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::clock::ManualClock;
+use fix_rs::fixt::engine::{Connection, Engine, EngineEvent};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::transport::LoopbackAddr;
+use fix_rs::message_version::MessageVersion;
+use fix_rs::testing::{Counterparty, CounterpartyError, Script};
+
+define_dictionary!(Heartbeat, Logon, Logout, ResendRequest, TestRequest,);
+
+fn unused_addr() -> SocketAddr {
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let listener_socket = TcpListener::bind(&addr).unwrap();
+    listener_socket.local_addr().unwrap()
+}
+
+//Have engine connect to a new Counterparty and send a Logon.
+fn connect_to_counterparty(engine: &mut Engine) -> (Counterparty, Connection) {
+    engine.set_send_receipts(false);
+    let addr = unused_addr();
+    let listener = TcpListener::bind(&addr).unwrap();
+    let connection = engine
+        .add_connection(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            CLIENT_SENDER_COMP_ID,
+            CLIENT_TARGET_COMP_ID,
+            addr,
+        )
+        .unwrap();
+    let counterparty = Counterparty::accept(&listener, Duration::from_secs(5), build_dictionary())
+        .unwrap()
+        .with_comp_ids(SERVER_SENDER_COMP_ID, SERVER_TARGET_COMP_ID);
+    engine_poll_event!(engine,EngineEvent::ConnectionSucceeded(succeeded_connection) => {
+        assert_eq!(succeeded_connection,connection);
+    });
+    engine.send_message(connection, new_logon_message());
+
+    (counterparty, connection)
+}
+
+#[test]
+fn test_scripted_acceptor() {
+    let clock = ManualClock::new();
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    engine.set_clock(Arc::new(clock.clone()));
+    let (counterparty, connection) = connect_to_counterparty(&mut engine);
+    let mut counterparty = counterparty.with_clock(Arc::new(clock.clone()));
+
+    let mut logon_response = Logon::new();
+    logon_response.heart_bt_int = 5;
+    logon_response.default_appl_ver_id = MessageVersion::FIX50SP2;
+    let mut test_request = TestRequest::new();
+    test_request.test_req_id = b"1".to_vec();
+
+    let script = Script::new()
+        .expect::<Logon>()
+        .send(logon_response)
+        .then("advance clock past HeartBtInt", |_| {
+            engine_poll_event!(engine,EngineEvent::SessionEstablished(_) => {});
+            let _ = engine_poll_message!(engine, connection, Logon);
+            clock.advance(Duration::from_secs(5));
+            Ok(())
+        })
+        .expect_within::<Heartbeat>(Duration::from_secs(6))
+        .send_with_msg_seq_num(10, test_request)
+        .expect_matching(|message: &ResendRequest| {
+            message.begin_seq_no == 2 && message.end_seq_no == 0
+        });
+    counterparty.run(script).unwrap();
+    assert_eq!(counterparty.next_msg_seq_num(), 11);
+}
+
+#[test]
+fn test_script_reports_failed_step() {
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    let (mut counterparty, _) = connect_to_counterparty(&mut engine);
+
+    let script = Script::new()
+        .accept_logon()
+        .expect_within::<Heartbeat>(Duration::from_millis(100));
+    let error = counterparty.run(script).unwrap_err();
+    assert_eq!(error.step, 1);
+    assert!(matches!(
+        error.error,
+        CounterpartyError::Timeout("Heartbeat")
+    ));
+
+    //Receiving the wrong message is reported with the message.
+    let mut message = TestRequest::new();
+    message.test_req_id = b"1".to_vec();
+    counterparty.send(message).unwrap();
+    match counterparty.expect::<TestRequest>() {
+        Err(CounterpartyError::UnexpectedMessage { expected, received }) => {
+            assert_eq!(expected, "TestRequest");
+            assert_eq!(
+                received
+                    .as_any()
+                    .downcast_ref::<Heartbeat>()
+                    .unwrap()
+                    .test_req_id,
+                b"1"
+            );
+        }
+        _ => panic!("TestRequest was not reported as unexpected"),
+    }
+}
+
+#[test]
+fn test_scripted_initiator() {
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    let address = LoopbackAddr::new();
+    engine
+        .add_listener_with_transport(SERVER_SENDER_COMP_ID, address.clone())
+        .unwrap()
+        .unwrap();
+    let mut counterparty = Counterparty::connect_loopback(&address, build_dictionary())
+        .unwrap()
+        .with_comp_ids(CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID);
+    let connection = engine_poll_event!(engine,EngineEvent::ConnectionAccepted(_,connection,_) => {
+        connection
+    });
+
+    counterparty.send_logon(5).unwrap();
+    let logon_message = engine_poll_event!(engine,EngineEvent::ConnectionLoggingOn(_,logging_on_connection,logon_message) => {
+        assert_eq!(logging_on_connection,connection);
+        logon_message
+    });
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.encrypt_method = logon_message.encrypt_method.clone();
+    response_message.heart_bt_int = logon_message.heart_bt_int;
+    response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+    engine.approve_new_connection(connection, Box::new(response_message), None);
+
+    let script = Script::new()
+        .expect_matching(|message: &Logon| message.heart_bt_int == 5)
+        .send(Logout::new())
+        .expect::<Logout>()
+        .disconnect();
+    counterparty.run(script).unwrap();
+    assert!(counterparty.is_closed());
+    let _ = engine_poll_message!(engine, connection, Logout);
+    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,_,_) => {
+        assert_eq!(terminated_connection,connection);
+    });
+}
@@ -0,0 +1,162 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use std::time::{Duration, Instant};
+
+#[macro_use]
+mod common;
+use crate::common::TestStream;
+use fix_rs::dictionary::field_types::other::{
+    BusinessRejectReason, OrdType, SecurityIDSource, Side,
+};
+use fix_rs::dictionary::messages::{
+    BusinessMessageReject, Heartbeat, Logon, Logout, NewOrderSingle, Reject, ResendRequest,
+    SequenceReset, TestRequest,
+};
+use fix_rs::fixt::engine::{EngineEvent, MessageRefusedReason};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::throttle::{Throttle, ThrottleAction};
+
+fn new_order_single(cl_ord_id: &[u8]) -> NewOrderSingle {
+    let mut new_order_single = new_fixt_message!(FROM_CLIENT NewOrderSingle);
+    new_order_single.cl_ord_id = cl_ord_id.to_vec();
+    new_order_single.symbol = b"TEST".to_vec();
+    new_order_single.security_id = b"0".to_vec();
+    new_order_single.security_id_source = Some(SecurityIDSource::CUSIP);
+    new_order_single.side = Side::Buy;
+    new_order_single.transact_time = new_order_single.sending_time;
+    new_order_single.order_qty = b"1".to_vec();
+    new_order_single.ord_type = OrdType::Market;
+
+    new_order_single
+}
+
+#[test]
+fn test_throttle_delays_application_messages() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        NewOrderSingle,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+    client.set_throttle(connection, Throttle::new(2, Duration::from_secs(1)));
+
+    let now = Instant::now();
+    client.send_message(connection, new_order_single(b"1"));
+    client.send_message(connection, new_order_single(b"2"));
+    client.send_message(connection, new_order_single(b"3"));
+    assert_eq!(test_server.recv_message::<NewOrderSingle>().cl_ord_id, b"1");
+    assert_eq!(test_server.recv_message::<NewOrderSingle>().cl_ord_id, b"2");
+    engine_poll_event!(client,EngineEvent::Throttled(throttled_connection,duration) => {
+        assert_eq!(throttled_connection,connection);
+        assert!(duration > Duration::from_secs(0));
+        assert!(duration <= Duration::from_millis(500));
+    });
+
+    //Administrative messages skip ahead of held application messages.
+    let mut message = new_fixt_message!(TestRequest);
+    message.test_req_id = b"1".to_vec();
+    client.send_message(connection, message);
+    assert_eq!(test_server.recv_message::<TestRequest>().test_req_id, b"1");
+
+    //Held message goes out once the bucket refills enough.
+    assert_eq!(test_server.recv_message::<NewOrderSingle>().cl_ord_id, b"3");
+    assert!(now.elapsed() >= Duration::from_millis(400));
+}
+
+#[test]
+fn test_throttle_rejects_application_messages() {
+    define_dictionary!(
+        BusinessMessageReject,
+        Heartbeat,
+        Logon,
+        Logout,
+        NewOrderSingle,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    //Only NewOrderSingle is limited.
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+    client.set_throttle(
+        connection,
+        Throttle::unlimited()
+            .with_msg_type_limit(b"D", 1, Duration::from_secs(60))
+            .with_action(ThrottleAction::Reject),
+    );
+
+    client.send_message(connection, new_order_single(b"1"));
+    client.send_message(connection, new_order_single(b"2"));
+    let mut message = new_fixt_message!(BusinessMessageReject);
+    message.ref_msg_type = b"D".to_vec();
+    message.business_reject_reason = BusinessRejectReason::NotAuthorized;
+    client.send_message(connection, message);
+
+    assert_eq!(test_server.recv_message::<NewOrderSingle>().cl_ord_id, b"1");
+    engine_poll_event!(client,EngineEvent::MessageRefused(refused_connection,_,message,reason) => {
+        assert_eq!(refused_connection,connection);
+        let message = message.as_any().downcast_ref::<NewOrderSingle>().unwrap();
+        assert_eq!(message.cl_ord_id,b"2");
+        match reason {
+            MessageRefusedReason::Throttled(duration) => assert!(duration > Duration::from_secs(0)),
+            _ => panic!("Wrong refused reason: {:?}",reason),
+        }
+    });
+
+    //Refused message didn't use up a MsgSeqNum.
+    let message = test_server.recv_message::<BusinessMessageReject>();
+    assert_eq!(message.msg_seq_num, 3);
+}
+
+#[test]
+fn test_removing_throttle_sends_held_messages() {
+    define_dictionary!(
+        Heartbeat,
+        Logon,
+        Logout,
+        NewOrderSingle,
+        Reject,
+        ResendRequest,
+        SequenceReset,
+        TestRequest,
+    );
+
+    let (mut test_server, mut client, connection) =
+        TestStream::setup_test_server_and_logon(build_dictionary());
+    client.set_throttle(connection, Throttle::new(1, Duration::from_secs(60)));
+
+    client.send_message(connection, new_order_single(b"1"));
+    client.send_message(connection, new_order_single(b"2"));
+    assert_eq!(test_server.recv_message::<NewOrderSingle>().cl_ord_id, b"1");
+    engine_poll_event!(client,EngineEvent::Throttled(throttled_connection,_) => {
+        assert_eq!(throttled_connection,connection);
+    });
+    assert!(test_server
+        .try_recv_fixt_message(Duration::from_millis(500))
+        .is_none());
+
+    client.set_throttle(connection, None);
+    assert_eq!(test_server.recv_message::<NewOrderSingle>().cl_ord_id, b"2");
+}
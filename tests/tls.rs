@@ -0,0 +1,328 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use std::env;
+use std::fs;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4, TcpStream};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{new_logon_message, CLIENT_SENDER_COMP_ID, CLIENT_TARGET_COMP_ID};
+use fix_rs::dictionary::messages::{Heartbeat, Logon, Logout, TestRequest};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{Connection, ConnectionTerminatedReason, Engine, EngineEvent};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::session_config::ConnectionConfig;
+use fix_rs::fixt::tls::{TlsAcceptor, TlsConnector};
+use fix_rs::message_version::MessageVersion;
+
+//Kept separate from the ports used by common so both can run at the same time.
+static SOCKET_PORT: AtomicUsize = AtomicUsize::new(7900);
+
+struct Certificates {
+    server_cert: PathBuf,
+    server_key: PathBuf,
+    client_cert: PathBuf,
+    client_key: PathBuf,
+}
+
+//Write self-signed certificates for the server (valid for localhost) and the client.
+fn generate_certificates(name: &str) -> Certificates {
+    let mut directory = env::temp_dir();
+    directory.push(format!("fix-rs-tls-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&directory);
+    fs::create_dir_all(&directory).unwrap();
+
+    let write_cert = |name: &str, subject_alt_names: Vec<String>| {
+        let cert = rcgen::generate_simple_self_signed(subject_alt_names).unwrap();
+        let cert_path = directory.join(format!("{}.pem", name));
+        let key_path = directory.join(format!("{}.key", name));
+        fs::write(&cert_path, cert.serialize_pem().unwrap()).unwrap();
+        fs::write(&key_path, cert.serialize_private_key_pem()).unwrap();
+        (cert_path, key_path)
+    };
+
+    let (server_cert, server_key) = write_cert("server", vec!["localhost".to_string()]);
+    let (client_cert, client_key) = write_cert("client", vec!["client".to_string()]);
+    Certificates {
+        server_cert,
+        server_key,
+        client_cert,
+        client_key,
+    }
+}
+
+fn new_engine() -> Engine {
+    define_dictionary!(Heartbeat, Logon, Logout, TestRequest,);
+
+    let mut engine = Engine::new(build_dictionary(), 4096).unwrap();
+    engine.set_send_receipts(false);
+    engine
+}
+
+//Start a TLS listener on a server engine and connect a client engine to it.
+fn setup_tls_client_and_server(
+    tls_acceptor: TlsAcceptor,
+    tls_connector: TlsConnector,
+) -> (Engine, Connection, Engine, Connection) {
+    let addr = SocketAddr::V4(SocketAddrV4::new(
+        Ipv4Addr::new(127, 0, 0, 1),
+        SOCKET_PORT.fetch_add(1, Ordering::SeqCst) as u16,
+    ));
+
+    let mut server = new_engine();
+    server
+        .add_listener_with_tls(CLIENT_TARGET_COMP_ID, addr, tls_acceptor)
+        .unwrap()
+        .unwrap();
+
+    let mut client = new_engine();
+    let client_connection = client
+        .add_connection_with_config(
+            ConnectionConfig::new(
+                FIXVersion::FIXT_1_1,
+                MessageVersion::FIX50SP2,
+                CLIENT_SENDER_COMP_ID,
+                CLIENT_TARGET_COMP_ID,
+                addr,
+            )
+            .with_tls(tls_connector),
+        )
+        .unwrap();
+    engine_poll_event!(client,EngineEvent::ConnectionSucceeded(connection) => {
+        assert_eq!(connection,client_connection);
+    });
+    let server_connection = engine_poll_event!(server,EngineEvent::ConnectionAccepted(_,connection,_) => {
+        connection
+    });
+
+    (client, client_connection, server, server_connection)
+}
+
+fn logon(
+    client: &mut Engine,
+    client_connection: Connection,
+    server: &mut Engine,
+    server_connection: Connection,
+) {
+    client.send_message(client_connection, new_logon_message());
+
+    engine_poll_event!(server,EngineEvent::ConnectionLoggingOn(_,connection,logon_message) => {
+        assert_eq!(connection,server_connection);
+        assert_eq!(logon_message.sender_comp_id,CLIENT_SENDER_COMP_ID.to_vec());
+
+        let mut response_message = new_fixt_message!(Logon);
+        response_message.encrypt_method = logon_message.encrypt_method.clone();
+        response_message.heart_bt_int = logon_message.heart_bt_int;
+        response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+        server.approve_new_connection(connection,Box::new(response_message),None);
+    });
+
+    engine_poll_event!(client,EngineEvent::SessionEstablished(connection) => {
+        assert_eq!(connection,client_connection);
+    });
+    engine_poll_event!(client,EngineEvent::MessageReceived(connection,message) => {
+        assert_eq!(connection,client_connection);
+        assert!(message.as_any().is::<Logon>());
+    });
+}
+
+fn poll_terminated_reason(
+    engine: &mut Engine,
+    expected_connection: Connection,
+) -> ConnectionTerminatedReason {
+    loop {
+        match engine.poll(Duration::from_secs(5)) {
+            Some(EngineEvent::ConnectionTerminated(connection, reason, _)) => {
+                assert_eq!(connection, expected_connection);
+                return reason;
+            }
+            Some(_) => {}
+            None => panic!("Connection was never terminated"),
+        }
+    }
+}
+
+#[test]
+fn test_logon_over_tls() {
+    let certificates = generate_certificates("logon");
+    let tls_acceptor =
+        TlsAcceptor::from_pem_files(&certificates.server_cert, &certificates.server_key).unwrap();
+    let tls_connector =
+        TlsConnector::from_pem_files(&certificates.server_cert, "localhost").unwrap();
+
+    let (mut client, client_connection, mut server, server_connection) =
+        setup_tls_client_and_server(tls_acceptor, tls_connector);
+    logon(
+        &mut client,
+        client_connection,
+        &mut server,
+        server_connection,
+    );
+
+    //Messages keep flowing both ways after the handshake.
+    let mut message = new_fixt_message!(TestRequest);
+    message.test_req_id = b"1".to_vec();
+    client.send_message(client_connection, message);
+    engine_poll_event!(server,EngineEvent::MessageReceived(connection,message) => {
+        assert_eq!(connection,server_connection);
+        assert!(message.as_any().is::<TestRequest>());
+    });
+    let message = engine_poll_message!(client, client_connection, Heartbeat);
+    assert_eq!(message.test_req_id, b"1".to_vec());
+
+    //Clean logout still works through close_notify.
+    client.logout(client_connection);
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(connection,ConnectionTerminatedReason::LocalRequested,_) => {
+        assert_eq!(connection,client_connection);
+    });
+}
+
+#[test]
+fn test_server_certificate_hostname_mismatch() {
+    let certificates = generate_certificates("hostname");
+    let tls_acceptor =
+        TlsAcceptor::from_pem_files(&certificates.server_cert, &certificates.server_key).unwrap();
+    let tls_connector =
+        TlsConnector::from_pem_files(&certificates.server_cert, "example.com").unwrap();
+
+    let (mut client, client_connection, mut server, server_connection) =
+        setup_tls_client_and_server(tls_acceptor, tls_connector);
+    client.send_message(client_connection, new_logon_message());
+
+    let reason = poll_terminated_reason(&mut client, client_connection);
+    assert!(matches!(
+        reason,
+        ConnectionTerminatedReason::TlsCertificateError(_)
+    ));
+
+    //Server never receives a Logon.
+    let reason = poll_terminated_reason(&mut server, server_connection);
+    assert!(matches!(
+        reason,
+        ConnectionTerminatedReason::TlsCertificateError(_)
+    ));
+}
+
+#[test]
+fn test_server_certificate_untrusted() {
+    let certificates = generate_certificates("untrusted");
+    let tls_acceptor =
+        TlsAcceptor::from_pem_files(&certificates.server_cert, &certificates.server_key).unwrap();
+    let tls_connector =
+        TlsConnector::from_pem_files(&certificates.client_cert, "localhost").unwrap();
+
+    let (mut client, client_connection, _server, _) =
+        setup_tls_client_and_server(tls_acceptor, tls_connector);
+
+    let reason = poll_terminated_reason(&mut client, client_connection);
+    assert!(matches!(
+        reason,
+        ConnectionTerminatedReason::TlsCertificateError(_)
+    ));
+}
+
+#[test]
+fn test_client_certificate_required() {
+    let certificates = generate_certificates("client-auth");
+    let tls_acceptor = TlsAcceptor::from_pem_files_with_client_auth(
+        &certificates.server_cert,
+        &certificates.server_key,
+        &certificates.client_cert,
+    )
+    .unwrap();
+    let tls_connector =
+        TlsConnector::from_pem_files(&certificates.server_cert, "localhost").unwrap();
+
+    //Client doesn't present a certificate so the server drops it.
+    let (mut client, client_connection, mut server, server_connection) =
+        setup_tls_client_and_server(tls_acceptor, tls_connector);
+    client.send_message(client_connection, new_logon_message());
+
+    let reason = poll_terminated_reason(&mut server, server_connection);
+    assert!(matches!(
+        reason,
+        ConnectionTerminatedReason::TlsCertificateError(_)
+    ));
+
+    //With TLS 1.3, the client finishes its side of the handshake before the server checks its
+    //certificate so how the client sees the connection drop depends on timing.
+    let _ = poll_terminated_reason(&mut client, client_connection);
+}
+
+#[test]
+fn test_client_certificate_accepted() {
+    let certificates = generate_certificates("client-cert");
+    let tls_acceptor = TlsAcceptor::from_pem_files_with_client_auth(
+        &certificates.server_cert,
+        &certificates.server_key,
+        &certificates.client_cert,
+    )
+    .unwrap();
+    let tls_connector = TlsConnector::from_pem_files_with_client_auth(
+        &certificates.server_cert,
+        &certificates.client_cert,
+        &certificates.client_key,
+        "localhost",
+    )
+    .unwrap();
+
+    let (mut client, client_connection, mut server, server_connection) =
+        setup_tls_client_and_server(tls_acceptor, tls_connector);
+    logon(
+        &mut client,
+        client_connection,
+        &mut server,
+        server_connection,
+    );
+}
+
+#[test]
+fn test_failed_tls_accept_releases_connection() {
+    //Every connection fails to be wrapped because the fragment size is invalid.
+    let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let mut config = rustls::ServerConfig::builder()
+        .with_safe_defaults()
+        .with_no_client_auth()
+        .with_single_cert(
+            vec![rustls::Certificate(cert.serialize_der().unwrap())],
+            rustls::PrivateKey(cert.serialize_private_key_der()),
+        )
+        .unwrap();
+    config.max_fragment_size = Some(1);
+
+    let addr = SocketAddr::V4(SocketAddrV4::new(
+        Ipv4Addr::new(127, 0, 0, 1),
+        SOCKET_PORT.fetch_add(1, Ordering::SeqCst) as u16,
+    ));
+    let mut server = new_engine();
+    server
+        .add_listener_with_tls(
+            CLIENT_TARGET_COMP_ID,
+            addr,
+            TlsAcceptor::new(Arc::new(config)),
+        )
+        .unwrap()
+        .unwrap();
+    assert_eq!(server.connection_count(), 1);
+
+    let _stream = TcpStream::connect(addr).unwrap();
+    engine_poll_event!(server,EngineEvent::ConnectionDropped(_,_) => {});
+    assert_eq!(server.connection_count(), 1);
+}
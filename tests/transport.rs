@@ -0,0 +1,245 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate mio;
+
+use std::env;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::path::PathBuf;
+use std::process;
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{
+    new_logon_message, recv_bytes_with_timeout, send_message, CLIENT_SENDER_COMP_ID,
+    CLIENT_TARGET_COMP_ID, SERVER_SENDER_COMP_ID,
+};
+use fix_rs::dictionary::messages::{Heartbeat, Logon, Logout, TestRequest};
+use fix_rs::fix::Parser;
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{Connection, Engine, EngineEvent};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::session_config::ConnectionConfig;
+use fix_rs::fixt::transport::{LoopbackAddr, TransportAddr};
+use fix_rs::message_version::MessageVersion;
+
+define_dictionary!(Heartbeat, Logon, Logout, TestRequest,);
+
+fn unspecified_addr() -> SocketAddr {
+    SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(0, 0, 0, 0), 0))
+}
+
+fn unix_socket_path(name: &str) -> PathBuf {
+    env::temp_dir().join(format!("fix-rs-{}-{}.sock", name, process::id()))
+}
+
+//Connect client to server over address and log on. Returns the client's and server's connections.
+fn connect_and_logon(
+    client: &mut Engine,
+    server: &mut Engine,
+    address: TransportAddr,
+) -> (Connection, Connection) {
+    let client_connection = client
+        .add_connection_with_config(ConnectionConfig::new(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            CLIENT_SENDER_COMP_ID,
+            CLIENT_TARGET_COMP_ID,
+            address,
+        ))
+        .unwrap();
+    engine_poll_event!(client,EngineEvent::ConnectionSucceeded(connection) => {
+        assert_eq!(connection,client_connection);
+    });
+    let server_connection = engine_poll_event!(server,EngineEvent::ConnectionAccepted(_,connection,addr) => {
+        assert_eq!(addr,unspecified_addr());
+        connection
+    });
+
+    client.send_message(client_connection, new_logon_message());
+    let logon_message = engine_poll_event!(server,EngineEvent::ConnectionLoggingOn(_,connection,logon_message) => {
+        assert_eq!(connection,server_connection);
+        logon_message
+    });
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.encrypt_method = logon_message.encrypt_method.clone();
+    response_message.heart_bt_int = logon_message.heart_bt_int;
+    response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+    server.approve_new_connection(server_connection, Box::new(response_message), None);
+    engine_poll_event!(client,EngineEvent::SessionEstablished(connection) => {
+        assert_eq!(connection,client_connection);
+    });
+    let _ = engine_poll_message!(client, client_connection, Logon);
+
+    (client_connection, server_connection)
+}
+
+//Exchange a TestRequest and then logout cleanly.
+fn test_request_and_logout(
+    client: &mut Engine,
+    client_connection: Connection,
+    server: &mut Engine,
+    server_connection: Connection,
+) {
+    let mut message = new_fixt_message!(TestRequest);
+    message.test_req_id = b"1".to_vec();
+    client.send_message(client_connection, message);
+    let message = engine_poll_message!(server, server_connection, TestRequest);
+    assert_eq!(message.test_req_id, b"1");
+    let message = engine_poll_message!(client, client_connection, Heartbeat);
+    assert_eq!(message.test_req_id, b"1");
+
+    client.logout(client_connection);
+    let _ = engine_poll_message!(server, server_connection, Logout);
+    engine_poll_event!(client,EngineEvent::ConnectionTerminated(connection,_,_) => {
+        assert_eq!(connection,client_connection);
+    });
+    engine_poll_event!(server,EngineEvent::ConnectionTerminated(connection,_,_) => {
+        assert_eq!(connection,server_connection);
+    });
+}
+
+#[test]
+fn test_loopback_engines() {
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    client.set_send_receipts(false);
+    let mut server = Engine::new(build_dictionary(), 4096).unwrap();
+    server.set_send_receipts(false);
+    let address = LoopbackAddr::new();
+    server
+        .add_listener_with_transport(SERVER_SENDER_COMP_ID, address.clone())
+        .unwrap()
+        .unwrap();
+
+    let (client_connection, server_connection) =
+        connect_and_logon(&mut client, &mut server, TransportAddr::from(address));
+    assert_eq!(
+        client.session_info(client_connection).unwrap().peer_addr,
+        None
+    );
+    test_request_and_logout(
+        &mut client,
+        client_connection,
+        &mut server,
+        server_connection,
+    );
+}
+
+#[test]
+fn test_loopback_connection_refused() {
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    let client_connection = client
+        .add_connection_with_config(ConnectionConfig::new(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            CLIENT_SENDER_COMP_ID,
+            CLIENT_TARGET_COMP_ID,
+            LoopbackAddr::new(),
+        ))
+        .unwrap();
+    engine_poll_event!(client,EngineEvent::ConnectionFailed(connection,_) => {
+        assert_eq!(connection,client_connection);
+    });
+}
+
+#[test]
+fn test_loopback_address_in_use() {
+    let mut server = Engine::new(build_dictionary(), 4096).unwrap();
+    let address = LoopbackAddr::new();
+    server
+        .add_listener_with_transport(SERVER_SENDER_COMP_ID, address.clone())
+        .unwrap()
+        .unwrap();
+    assert!(server
+        .add_listener_with_transport(SERVER_SENDER_COMP_ID, address)
+        .is_err());
+}
+
+#[test]
+fn test_loopback_test_driver() {
+    let mut server = Engine::new(build_dictionary(), 4096).unwrap();
+    let address = LoopbackAddr::new();
+    server
+        .add_listener_with_transport(SERVER_SENDER_COMP_ID, address.clone())
+        .unwrap()
+        .unwrap();
+
+    //Drive the other side directly without a second Engine.
+    let mut stream = address.connect().unwrap();
+    let server_connection = engine_poll_event!(server,EngineEvent::ConnectionAccepted(_,connection,_) => {
+        connection
+    });
+
+    let mut logon_message = new_logon_message();
+    logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+    logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+    send_message(
+        &mut stream,
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        Box::new(logon_message),
+    );
+    let logon_message = engine_poll_event!(server,EngineEvent::ConnectionLoggingOn(_,connection,logon_message) => {
+        assert_eq!(connection,server_connection);
+        logon_message
+    });
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.encrypt_method = logon_message.encrypt_method.clone();
+    response_message.heart_bt_int = logon_message.heart_bt_int;
+    response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+    server.approve_new_connection(server_connection, Box::new(response_message), None);
+
+    let bytes = recv_bytes_with_timeout(&mut stream, Duration::from_secs(5))
+        .expect("Did not receive Logon");
+    let mut parser = Parser::new(build_dictionary(), 4096);
+    let (bytes_parsed, result) = parser.parse(&bytes[..]);
+    assert!(result.is_ok());
+    assert_eq!(bytes_parsed, bytes.len());
+    let message = parser.messages.remove(0);
+    let message = message.as_any().downcast_ref::<Logon>().unwrap();
+    assert_eq!(message.sender_comp_id, SERVER_SENDER_COMP_ID);
+
+    //Closing the stream is seen the same as a closed socket.
+    drop(stream);
+    engine_poll_event!(server,EngineEvent::ConnectionTerminated(connection,_,_) => {
+        assert_eq!(connection,server_connection);
+    });
+}
+
+#[test]
+fn test_unix_socket_engines() {
+    let path = unix_socket_path("engines");
+    let mut client = Engine::new(build_dictionary(), 4096).unwrap();
+    client.set_send_receipts(false);
+    let mut server = Engine::new(build_dictionary(), 4096).unwrap();
+    server.set_send_receipts(false);
+    server
+        .add_listener_with_transport(SERVER_SENDER_COMP_ID, TransportAddr::Unix(path.clone()))
+        .unwrap()
+        .unwrap();
+    assert!(path.exists());
+
+    let (client_connection, server_connection) =
+        connect_and_logon(&mut client, &mut server, TransportAddr::Unix(path.clone()));
+    test_request_and_logout(
+        &mut client,
+        client_connection,
+        &mut server,
+        server_connection,
+    );
+
+    //Socket file is cleaned up with the listener.
+    drop(server);
+    assert!(!path.exists());
+}
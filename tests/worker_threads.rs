@@ -0,0 +1,252 @@
+// Copyright 2017 James Bendig. See the COPYRIGHT file at the top-level
+// directory of this distribution.
+//
+// Licensed under:
+//   the MIT license
+//     <LICENSE-MIT or https://opensource.org/licenses/MIT>
+//   or the Apache License, Version 2.0
+//     <LICENSE-APACHE or https://www.apache.org/licenses/LICENSE-2.0>,
+// at your option. This file may not be copied, modified, or distributed
+// except according to those terms.
+
+#[macro_use]
+extern crate fix_rs;
+extern crate chrono;
+extern crate mio;
+
+use chrono::{DateTime, Utc};
+use mio::tcp::{TcpListener, TcpStream};
+use std::io;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+#[macro_use]
+mod common;
+use crate::common::{
+    accept_with_timeout, new_logon_message, TestStream, CLIENT_SENDER_COMP_ID,
+    CLIENT_TARGET_COMP_ID, SERVER_SENDER_COMP_ID,
+};
+use fix_rs::dictionary::messages::{Heartbeat, Logon, Logout, TestRequest};
+use fix_rs::fix_version::FIXVersion;
+use fix_rs::fixt::engine::{Connection, Engine, EngineEvent, SessionID, WorkerPolicy};
+use fix_rs::fixt::message::FIXTMessage;
+use fix_rs::fixt::message_log::{MessageDirection, MessageLog};
+use fix_rs::fixt::session_info::SessionState;
+use fix_rs::message_version::MessageVersion;
+
+define_dictionary!(Heartbeat, Logon, Logout, TestRequest,);
+
+fn unused_addr() -> SocketAddr {
+    let addr = SocketAddr::V4(SocketAddrV4::new(Ipv4Addr::new(127, 0, 0, 1), 0));
+    let listener_socket = TcpListener::bind(&addr).unwrap();
+    listener_socket.local_addr().unwrap()
+}
+
+//Add a connection to a new test server and log it on.
+fn add_connection_and_logon(engine: &mut Engine) -> (TestStream, Connection) {
+    let addr = unused_addr();
+    let listener = TcpListener::bind(&addr).unwrap();
+    let connection = engine
+        .add_connection(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            CLIENT_SENDER_COMP_ID,
+            CLIENT_TARGET_COMP_ID,
+            addr,
+        )
+        .unwrap();
+    let stream = accept_with_timeout(&listener, Duration::from_secs(5))
+        .expect("Could not accept connection");
+    engine_poll_event!(engine,EngineEvent::ConnectionSucceeded(succeeded_connection) => {
+        assert_eq!(succeeded_connection,connection);
+    });
+
+    let mut test_server = TestStream::new(
+        FIXVersion::FIXT_1_1,
+        MessageVersion::FIX50SP2,
+        stream,
+        build_dictionary(),
+    );
+    engine.send_message(connection, new_logon_message());
+    let message = test_server.recv_message::<Logon>();
+    let mut response_message = new_fixt_message!(Logon);
+    response_message.encrypt_method = message.encrypt_method;
+    response_message.heart_bt_int = message.heart_bt_int;
+    response_message.default_appl_ver_id = message.default_appl_ver_id;
+    test_server.send_message(response_message);
+    engine_poll_event!(engine,EngineEvent::SessionEstablished(established_connection) => {
+        assert_eq!(established_connection,connection);
+    });
+    let _ = engine_poll_message!(engine, connection, Logon);
+
+    (test_server, connection)
+}
+
+#[test]
+fn test_round_robin_worker_policy() {
+    let mut engine = Engine::new_with_worker_threads(build_dictionary(), 4096, 3).unwrap();
+    engine.set_send_receipts(false);
+    assert_eq!(engine.worker_threads(), 3);
+
+    let mut sessions = Vec::new();
+    for expected_worker in [0, 1, 2, 0].iter() {
+        let (test_server, connection) = add_connection_and_logon(&mut engine);
+        assert_eq!(engine.connection_worker(connection), Some(*expected_worker));
+        sessions.push((test_server, connection));
+    }
+
+    //Every session still works independently no matter which worker thread handles it.
+    for (index, &mut (ref mut test_server, connection)) in sessions.iter_mut().enumerate() {
+        let mut message = new_fixt_message!(TestRequest);
+        message.msg_seq_num = 2;
+        message.test_req_id = index.to_string().into_bytes();
+        test_server.send_message(message);
+        let message = engine_poll_message!(engine, connection, TestRequest);
+        assert_eq!(message.test_req_id, index.to_string().into_bytes());
+
+        let message = test_server.recv_message::<Heartbeat>();
+        assert_eq!(message.test_req_id, index.to_string().into_bytes());
+    }
+}
+
+#[test]
+fn test_pinned_worker_policy() {
+    let mut engine = Engine::new_with_worker_threads(build_dictionary(), 4096, 3).unwrap();
+    engine.set_send_receipts(false);
+
+    //Pinned wraps around past the last worker thread.
+    engine.set_worker_policy(WorkerPolicy::Pinned(4));
+    let (_test_server1, connection1) = add_connection_and_logon(&mut engine);
+    let (_test_server2, connection2) = add_connection_and_logon(&mut engine);
+    assert_eq!(engine.connection_worker(connection1), Some(1));
+    assert_eq!(engine.connection_worker(connection2), Some(1));
+
+    //Unknown connections aren't handled by any worker thread.
+    assert_eq!(
+        engine.connection_worker(Connection(connection2.0 + 100)),
+        None
+    );
+}
+
+#[test]
+fn test_terminated_connection_has_no_worker() {
+    let mut engine = Engine::new_with_worker_threads(build_dictionary(), 4096, 2).unwrap();
+    engine.set_send_receipts(false);
+    engine.set_worker_policy(WorkerPolicy::Pinned(1));
+    let (mut test_server, connection) = add_connection_and_logon(&mut engine);
+    assert_eq!(engine.connection_worker(connection), Some(1));
+
+    engine.logout(connection);
+    let _ = test_server.recv_message::<Logout>();
+    let mut message = new_fixt_message!(Logout);
+    message.msg_seq_num = 2;
+    test_server.send_message(message);
+    engine_poll_event!(engine,EngineEvent::ConnectionTerminated(terminated_connection,_,_) => {
+        assert_eq!(terminated_connection,connection);
+    });
+    assert_eq!(engine.connection_worker(connection), None);
+}
+
+#[test]
+fn test_accepted_connections_use_listener_worker() {
+    let mut engine = Engine::new_with_worker_threads(build_dictionary(), 4096, 2).unwrap();
+    engine.set_worker_policy(WorkerPolicy::Pinned(1));
+
+    let addr = unused_addr();
+    let listener = engine
+        .add_listener(SERVER_SENDER_COMP_ID, addr)
+        .unwrap()
+        .unwrap();
+
+    //Policy only applies to connections and listeners added afterwards.
+    engine.set_worker_policy(WorkerPolicy::Pinned(0));
+
+    let mut test_clients = Vec::new();
+    for _ in 0..2 {
+        let stream = TcpStream::connect(&addr).unwrap();
+        let connection = engine_poll_event!(engine,EngineEvent::ConnectionAccepted(accepted_listener,connection,_) => {
+            assert_eq!(accepted_listener,listener);
+            connection
+        });
+        assert_eq!(engine.connection_worker(connection), Some(1));
+
+        let test_client = TestStream::new(
+            FIXVersion::FIXT_1_1,
+            MessageVersion::FIX50SP2,
+            stream,
+            build_dictionary(),
+        );
+        test_clients.push((test_client, connection));
+    }
+
+    //Approving the Logon has to reach the listener's worker thread.
+    for &mut (ref mut test_client, connection) in test_clients.iter_mut() {
+        let mut logon_message = new_logon_message();
+        logon_message.sender_comp_id = CLIENT_SENDER_COMP_ID.to_vec();
+        logon_message.target_comp_id = CLIENT_TARGET_COMP_ID.to_vec();
+        test_client.send_message(logon_message);
+        let logon_message = engine_poll_event!(engine,EngineEvent::ConnectionLoggingOn(_,logging_on_connection,logon_message) => {
+            assert_eq!(logging_on_connection,connection);
+            logon_message
+        });
+
+        let mut response_message = new_fixt_message!(Logon);
+        response_message.encrypt_method = logon_message.encrypt_method.clone();
+        response_message.heart_bt_int = logon_message.heart_bt_int;
+        response_message.default_appl_ver_id = logon_message.default_appl_ver_id;
+        engine.approve_new_connection(connection, Box::new(response_message), None);
+        let _ = test_client.recv_message::<Logon>();
+        assert_eq!(
+            engine.session_info(connection).unwrap().state,
+            SessionState::Established
+        );
+    }
+}
+
+#[test]
+fn test_message_log_per_worker() {
+    //Each MessageLog records which worker thread's instance logged what.
+    struct WorkerMessageLog {
+        instance: usize,
+        entries: Arc<Mutex<Vec<usize>>>,
+    }
+
+    impl MessageLog for WorkerMessageLog {
+        fn log(
+            &mut self,
+            _session_id: &SessionID,
+            _direction: MessageDirection,
+            _timestamp: &DateTime<Utc>,
+            _bytes: &[u8],
+        ) -> io::Result<()> {
+            self.entries.lock().unwrap().push(self.instance);
+            Ok(())
+        }
+    }
+
+    let mut engine = Engine::new_with_worker_threads(build_dictionary(), 4096, 2).unwrap();
+    engine.set_send_receipts(false);
+    let entries = Arc::new(Mutex::new(Vec::new()));
+    let mut instance_count = 0;
+    engine.set_message_log_per_worker(|| {
+        instance_count += 1;
+        Box::new(WorkerMessageLog {
+            instance: instance_count,
+            entries: entries.clone(),
+        })
+    });
+    assert_eq!(instance_count, 2);
+
+    //Sessions on different worker threads are logged by different instances.
+    let (_test_server1, connection1) = add_connection_and_logon(&mut engine);
+    let (_test_server2, connection2) = add_connection_and_logon(&mut engine);
+    assert_ne!(
+        engine.connection_worker(connection1),
+        engine.connection_worker(connection2)
+    );
+    let mut instances = entries.lock().unwrap().clone();
+    instances.sort();
+    instances.dedup();
+    assert_eq!(instances, vec![1, 2]);
+}